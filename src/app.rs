@@ -1,5 +1,10 @@
 use crate::nodes::{self, SimNode, SimViewer};
 use crate::simulation::SimConfig;
+use crate::simulation::identify::{self, FitConfig, FitParam, FitResult, Measurement};
+use crate::simulation::monte_carlo::{
+    self, Distribution, MetricSummary, MonteCarloConfig, Tolerance, TraceMetric,
+};
+use crate::simulation::sweep::{self, RunScalars, SweepConfig, SweepMode};
 use egui_snarl::Snarl;
 use egui_snarl::ui::SnarlWidget;
 
@@ -34,6 +39,18 @@ fn create_tree() -> egui_tiles::Tree<Pane> {
 struct TreeBehavior<'a> {
     snarl: &'a mut Snarl<SimNode>,
     sim_status: &'a str,
+    sweep: &'a mut SweepConfig,
+    sweep_scalars: &'a [(f64, RunScalars)],
+    /// Set when the sweep button was clicked this frame.
+    run_sweep: bool,
+    monte_carlo: &'a mut MonteCarloConfig,
+    mc_summary: &'a [MetricSummary],
+    /// Set when the Monte Carlo button was clicked this frame.
+    run_monte_carlo: bool,
+    fit: &'a mut FitConfig,
+    fit_result: Option<&'a FitResult>,
+    /// Set when the fit button was clicked this frame.
+    run_fit: bool,
 }
 
 impl egui_tiles::Behavior<Pane> for TreeBehavior<'_> {
@@ -63,6 +80,13 @@ impl egui_tiles::Behavior<Pane> for TreeBehavior<'_> {
             }
             Pane::Right => {
                 ui.label(format!("Status: {}", self.sim_status));
+                ui.separator();
+                self.run_sweep |= show_sweep_editor(ui, self.snarl, self.sweep, self.sweep_scalars);
+                ui.separator();
+                self.run_monte_carlo |=
+                    show_monte_carlo_editor(ui, self.snarl, self.monte_carlo, self.mc_summary);
+                ui.separator();
+                self.run_fit |= show_fit_editor(ui, self.snarl, self.fit, self.fit_result);
             }
         }
 
@@ -95,8 +119,20 @@ pub struct TemplateApp {
     tree: egui_tiles::Tree<Pane>,
     snarl: Snarl<SimNode>,
     sim_config: SimConfig,
+    sweep: SweepConfig,
+    monte_carlo: MonteCarloConfig,
+    fit: FitConfig,
     #[serde(skip)]
     sim_status: String,
+    /// Named scalars of the runs of the last sweep.
+    #[serde(skip)]
+    sweep_scalars: Vec<(f64, RunScalars)>,
+    /// Metric summaries of the last Monte Carlo study.
+    #[serde(skip)]
+    mc_summary: Vec<MetricSummary>,
+    /// Outcome of the last parameter fit.
+    #[serde(skip)]
+    fit_result: Option<FitResult>,
 }
 
 impl Default for TemplateApp {
@@ -105,7 +141,13 @@ impl Default for TemplateApp {
             tree: create_tree(),
             snarl: Snarl::new(),
             sim_config: SimConfig::default(),
+            sweep: SweepConfig::default(),
+            monte_carlo: MonteCarloConfig::default(),
+            fit: FitConfig::default(),
             sim_status: "Ready".to_owned(),
+            sweep_scalars: Vec::new(),
+            mc_summary: Vec::new(),
+            fit_result: None,
         }
     }
 }
@@ -119,6 +161,67 @@ impl TemplateApp {
             Self::default()
         }
     }
+
+    /// Renders the time span, steady-start option and the trim and simulate
+    /// buttons in the top bar.
+    fn show_sim_controls(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.label("t₀:");
+        ui.add(
+            egui::DragValue::new(&mut self.sim_config.t_start)
+                .speed(0.01)
+                .range(0.0..=self.sim_config.t_end),
+        );
+        ui.label("t₁:");
+        ui.add(
+            egui::DragValue::new(&mut self.sim_config.t_end)
+                .speed(0.01)
+                .range(self.sim_config.t_start..=100.0),
+        );
+        ui.label("Δt:");
+        ui.add(
+            egui::DragValue::new(&mut self.sim_config.output_dt)
+                .speed(0.0001)
+                .range(0.0001..=1.0)
+                .suffix(" s")
+                .max_decimals(4),
+        );
+        ui.checkbox(&mut self.sim_config.start_at_steady_state, "Steady start")
+            .on_hover_text("Start from the steady state for the inputs at t₀");
+        if ui
+            .button("⚖ Trim")
+            .on_hover_text(
+                "Write the steady-state i_d, i_q, ω_m and shaft twist into the initial conditions",
+            )
+            .clicked()
+        {
+            match crate::simulation::solver::trim(&mut self.snarl, &self.sim_config) {
+                Ok(point) => {
+                    point.apply(&mut self.snarl);
+                    self.sim_status = format!(
+                        "Trimmed: i_d = {:.3} A, i_q = {:.3} A, ω_m = {:.2} rad/s",
+                        point.i_d, point.i_q, point.omega_m
+                    );
+                }
+                Err(e) => {
+                    self.sim_status = format!("Error: {e}");
+                    log::error!("Trim failed: {e}");
+                }
+            }
+        }
+
+        if ui.button("▶ Simulate").clicked() {
+            match crate::simulation::solver::run_simulation(&mut self.snarl, &self.sim_config) {
+                Ok(()) => {
+                    self.sim_status = "Simulation complete".to_owned();
+                }
+                Err(e) => {
+                    self.sim_status = format!("Error: {e}");
+                    log::error!("Simulation failed: {e}");
+                }
+            }
+        }
+    }
 }
 
 impl eframe::App for TemplateApp {
@@ -141,43 +244,7 @@ impl eframe::App for TemplateApp {
                     ui.add_space(16.0);
                 }
 
-                // Simulation controls
-                ui.separator();
-                ui.label("t₀:");
-                ui.add(
-                    egui::DragValue::new(&mut self.sim_config.t_start)
-                        .speed(0.01)
-                        .range(0.0..=self.sim_config.t_end),
-                );
-                ui.label("t₁:");
-                ui.add(
-                    egui::DragValue::new(&mut self.sim_config.t_end)
-                        .speed(0.01)
-                        .range(self.sim_config.t_start..=100.0),
-                );
-                ui.label("Δt:");
-                ui.add(
-                    egui::DragValue::new(&mut self.sim_config.output_dt)
-                        .speed(0.0001)
-                        .range(0.0001..=1.0)
-                        .suffix(" s")
-                        .max_decimals(4),
-                );
-
-                if ui.button("▶ Simulate").clicked() {
-                    match crate::simulation::solver::run_simulation(
-                        &mut self.snarl,
-                        &self.sim_config,
-                    ) {
-                        Ok(()) => {
-                            self.sim_status = "Simulation complete".to_owned();
-                        }
-                        Err(e) => {
-                            self.sim_status = format!("Error: {e}");
-                            log::error!("Simulation failed: {e}");
-                        }
-                    }
-                }
+                self.show_sim_controls(ui);
 
                 ui.add_space(8.0);
                 egui::widgets::global_theme_preference_buttons(ui);
@@ -188,8 +255,59 @@ impl eframe::App for TemplateApp {
             let mut behavior = TreeBehavior {
                 snarl: &mut self.snarl,
                 sim_status: &self.sim_status,
+                sweep: &mut self.sweep,
+                sweep_scalars: &self.sweep_scalars,
+                run_sweep: false,
+                monte_carlo: &mut self.monte_carlo,
+                mc_summary: &self.mc_summary,
+                run_monte_carlo: false,
+                fit: &mut self.fit,
+                fit_result: self.fit_result.as_ref(),
+                run_fit: false,
             };
             self.tree.ui(&mut behavior, ui);
+            let run_monte_carlo = behavior.run_monte_carlo;
+            let run_fit = behavior.run_fit;
+            if behavior.run_sweep {
+                match sweep::run_sweep(&mut self.snarl, &self.sim_config, &self.sweep) {
+                    Ok(scalars) => {
+                        self.sweep_scalars = scalars;
+                        self.sim_status = "Sweep complete".to_owned();
+                    }
+                    Err(e) => {
+                        self.sim_status = format!("Error: {e}");
+                        log::error!("Sweep failed: {e}");
+                    }
+                }
+            }
+            if run_monte_carlo {
+                match monte_carlo::run_monte_carlo(
+                    &mut self.snarl,
+                    &self.sim_config,
+                    &self.monte_carlo,
+                ) {
+                    Ok(summary) => {
+                        self.mc_summary = summary;
+                        self.sim_status = "Monte Carlo complete".to_owned();
+                    }
+                    Err(e) => {
+                        self.sim_status = format!("Error: {e}");
+                        log::error!("Monte Carlo failed: {e}");
+                    }
+                }
+            }
+            if run_fit {
+                match identify::run_fit(&self.snarl, &self.sim_config, &self.fit) {
+                    Ok(result) => {
+                        self.fit_result = Some(result);
+                        self.sim_status = "Fit complete".to_owned();
+                    }
+                    Err(e) => {
+                        self.sim_status = format!("Error: {e}");
+                        log::error!("Parameter fit failed: {e}");
+                    }
+                }
+            }
         });
     }
 }
@@ -217,12 +335,72 @@ fn show_node_library(ui: &mut egui::Ui, snarl: &mut Snarl<SimNode>) {
             "Mechanical Dynamics",
             SimNode::Mechanical(nodes::mechanical::MechanicalNode::default()),
         ),
+        (
+            "Two-Mass Drivetrain",
+            SimNode::TwoMass(nodes::two_mass::TwoMassNode::default()),
+        ),
         (
             "Inverse Park",
             SimNode::InversePark(nodes::park::InverseParkNode::default()),
         ),
         ("Park", SimNode::Park(nodes::park::ParkNode::default())),
+        ("SVPWM", SimNode::Svpwm(nodes::svpwm::SvpwmNode::default())),
+        (
+            "Inverter",
+            SimNode::Inverter(nodes::inverter::InverterNode::default()),
+        ),
+        (
+            "DC Link",
+            SimNode::DcLink(nodes::dc_link::DcLinkNode::default()),
+        ),
+        ("PMSM (abc)", SimNode::AbcMachine(Box::default())),
+        (
+            "Induction Machine",
+            SimNode::Induction(nodes::induction::InductionMachineNode::default()),
+        ),
+        (
+            "DC Motor",
+            SimNode::DcMotor(nodes::dc_motor::DcMotorNode::default()),
+        ),
+        (
+            "BLDC Motor",
+            SimNode::Bldc(nodes::bldc::BldcNode::default()),
+        ),
+        (
+            "Thermal Network",
+            SimNode::Thermal(nodes::thermal::ThermalNode::default()),
+        ),
+        (
+            "Position Sensor",
+            SimNode::Sensor(nodes::sensor::PositionSensorNode::default()),
+        ),
+        (
+            "Measurement",
+            SimNode::Measurement(nodes::measurement::MeasurementNode::default()),
+        ),
+        (
+            "Observer",
+            SimNode::Observer(nodes::observer::ObserverNode::default()),
+        ),
+        ("EKF", SimNode::Ekf(nodes::ekf::EkfNode::default())),
         ("Plot", SimNode::Plot(nodes::plot::PlotNode::default())),
+        ("Bode", SimNode::Bode(nodes::bode::BodeNode::default())),
+        (
+            "Spectrum",
+            SimNode::Spectrum(nodes::spectrum::SpectrumNode::default()),
+        ),
+        (
+            "Metrics",
+            SimNode::Metrics(nodes::metrics::MetricsNode::default()),
+        ),
+        (
+            "Power Flow",
+            SimNode::PowerFlow(nodes::power::PowerFlowNode::default()),
+        ),
+        (
+            "Efficiency Map",
+            SimNode::EfficiencyMap(nodes::efficiency_map::EfficiencyMapNode::default()),
+        ),
     ];
 
     for (label, template) in entries {
@@ -231,3 +409,306 @@ fn show_node_library(ui: &mut egui::Ui, snarl: &mut Snarl<SimNode>) {
         }
     }
 }
+
+/// Node and parameter combo boxes selecting a numeric node parameter.
+fn show_param_picker(
+    ui: &mut egui::Ui,
+    id: impl std::hash::Hash + Copy,
+    snarl: &Snarl<SimNode>,
+    node: &mut Option<egui_snarl::NodeId>,
+    param: &mut String,
+) {
+    let node_label = |id: egui_snarl::NodeId| {
+        snarl.get_node(id).map_or_else(
+            || "?".to_owned(),
+            |node| format!("{} #{}", node.title(), id.0),
+        )
+    };
+    egui::ComboBox::from_id_salt((id, "node"))
+        .selected_text(node.map_or_else(|| "Node…".to_owned(), node_label))
+        .show_ui(ui, |ui| {
+            for (id, n) in snarl.node_ids() {
+                if !matches!(n, SimNode::Plot(_)) {
+                    ui.selectable_value(node, Some(id), node_label(id));
+                }
+            }
+        });
+    let params = node
+        .and_then(|id| snarl.get_node(id))
+        .map(sweep::numeric_params)
+        .unwrap_or_default();
+    egui::ComboBox::from_id_salt((id, "param"))
+        .selected_text(if param.is_empty() {
+            "Parameter…"
+        } else {
+            param.as_str()
+        })
+        .show_ui(ui, |ui| {
+            for p in params {
+                let label = p.clone();
+                ui.selectable_value(param, p, label);
+            }
+        });
+}
+
+/// Renders the parameter sweep setup and the Metrics scalars of the last
+/// sweep in the right pane; returns whether the sweep should run.
+fn show_sweep_editor(
+    ui: &mut egui::Ui,
+    snarl: &Snarl<SimNode>,
+    sweep: &mut SweepConfig,
+    scalars: &[(f64, RunScalars)],
+) -> bool {
+    ui.heading("Sweep");
+    show_param_picker(ui, "sweep", snarl, &mut sweep.node, &mut sweep.param);
+    egui::ComboBox::from_label("Values")
+        .selected_text(sweep.mode.label())
+        .show_ui(ui, |ui| {
+            for mode in SweepMode::ALL {
+                ui.selectable_value(&mut sweep.mode, mode, mode.label());
+            }
+        });
+    match sweep.mode {
+        SweepMode::List => {
+            ui.add(egui::TextEdit::singleline(&mut sweep.list).hint_text("0.5, 1, 2"));
+        }
+        SweepMode::Linear | SweepMode::Logarithmic => {
+            egui::Grid::new("sweep_range")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Start");
+                    ui.add(egui::DragValue::new(&mut sweep.start).speed(0.001));
+                    ui.end_row();
+                    ui.label("End");
+                    ui.add(egui::DragValue::new(&mut sweep.end).speed(0.001));
+                    ui.end_row();
+                    ui.label("Count");
+                    ui.add(egui::DragValue::new(&mut sweep.count).range(1..=64));
+                    ui.end_row();
+                });
+        }
+    }
+    let run = ui.button("▶ Run sweep").clicked();
+
+    // One row per scalar, one column per swept value
+    let names: Vec<&str> = scalars
+        .first()
+        .map(|(_, run)| run.iter().map(|(name, _)| name.as_str()).collect())
+        .unwrap_or_default();
+    if !names.is_empty() {
+        egui::ScrollArea::horizontal()
+            .id_salt("sweep_scalars")
+            .show(ui, |ui| {
+                egui::Grid::new("sweep_scalars_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong(sweep.param.as_str());
+                        for (value, _) in scalars {
+                            ui.strong(format!("{value}"));
+                        }
+                        ui.end_row();
+                        for (k, name) in names.iter().enumerate() {
+                            ui.label(*name);
+                            for (_, run) in scalars {
+                                ui.label(
+                                    run.get(k)
+                                        .map_or_else(String::new, |(_, v)| format!("{v:.4}")),
+                                );
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+    run
+}
+
+/// Renders the Monte Carlo setup and the summary of the last study in the
+/// right pane; returns whether the study should run.
+fn show_monte_carlo_editor(
+    ui: &mut egui::Ui,
+    snarl: &Snarl<SimNode>,
+    mc: &mut MonteCarloConfig,
+    summary: &[MetricSummary],
+) -> bool {
+    ui.heading("Monte Carlo");
+    let mut remove = None;
+    for (k, tol) in mc.tolerances.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            show_param_picker(ui, ("tolerance", k), snarl, &mut tol.node, &mut tol.param);
+            if ui.small_button("−").clicked() {
+                remove = Some(k);
+            }
+        });
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt(("tolerance_dist", k))
+                .selected_text(tol.distribution.label())
+                .show_ui(ui, |ui| {
+                    for dist in Distribution::ALL {
+                        ui.selectable_value(&mut tol.distribution, dist, dist.label());
+                    }
+                });
+            ui.label("±");
+            ui.add(egui::DragValue::new(&mut tol.spread).speed(0.001));
+        });
+    }
+    if let Some(k) = remove {
+        mc.tolerances.remove(k);
+    }
+    if ui.small_button("+ Tolerance").clicked() {
+        mc.tolerances.push(Tolerance {
+            spread: 0.1,
+            ..Tolerance::default()
+        });
+    }
+    egui::Grid::new("monte_carlo_params")
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Runs");
+            ui.add(egui::DragValue::new(&mut mc.runs).range(1..=1000));
+            ui.end_row();
+            ui.label("Seed");
+            ui.add(egui::DragValue::new(&mut mc.seed));
+            ui.end_row();
+            ui.label("Band (%)");
+            ui.add(egui::DragValue::new(&mut mc.percentile).range(0.0..=50.0));
+            ui.end_row();
+        });
+    ui.horizontal_wrapped(|ui| {
+        for metric in TraceMetric::ALL {
+            let mut on = mc.metrics.contains(&metric);
+            if ui.checkbox(&mut on, metric.label()).changed() {
+                if on {
+                    mc.metrics.push(metric);
+                } else {
+                    mc.metrics.retain(|&m| m != metric);
+                }
+            }
+        }
+    });
+    let run = ui.button("▶ Run Monte Carlo").clicked();
+
+    if !summary.is_empty() {
+        egui::Grid::new("monte_carlo_summary")
+            .num_columns(7)
+            .striped(true)
+            .show(ui, |ui| {
+                for header in ["Trace", "Metric", "Mean", "σ", "Min", "Band", "Max"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for s in summary {
+                    ui.label(s.trace.as_str());
+                    ui.label(s.metric.label());
+                    ui.label(format!("{:.4}", s.mean));
+                    ui.label(format!("{:.4}", s.std));
+                    ui.label(format!("{:.4}", s.min));
+                    ui.label(format!("{:.4} … {:.4}", s.p_low, s.p_high));
+                    ui.label(format!("{:.4}", s.max));
+                    ui.end_row();
+                }
+            });
+    }
+    run
+}
+
+/// Renders the parameter fit setup and the last fit in the right pane;
+/// returns whether the fit should run. "Apply fitted values" writes the
+/// result back into the nodes.
+fn show_fit_editor(
+    ui: &mut egui::Ui,
+    snarl: &mut Snarl<SimNode>,
+    fit: &mut FitConfig,
+    result: Option<&FitResult>,
+) -> bool {
+    ui.heading("Parameter fit");
+    ui.collapsing("Measurement (CSV)", |ui| {
+        ui.add(
+            egui::TextEdit::multiline(&mut fit.csv)
+                .code_editor()
+                .desired_rows(4)
+                .hint_text("t,i_d,i_q,omega_m"),
+        );
+        if ui.button("Load").clicked() {
+            match Measurement::from_csv(&fit.csv) {
+                Ok(measurement) => {
+                    fit.measurement = Some(measurement);
+                    fit.csv_error = None;
+                }
+                Err(e) => fit.csv_error = Some(e.to_string()),
+            }
+        }
+        if let Some(error) = &fit.csv_error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+    });
+    if let Some(measurement) = &fit.measurement {
+        let (t_first, t_last) = measurement.span();
+        let channels: Vec<&str> = measurement
+            .channels
+            .iter()
+            .map(|(channel, _)| channel.label())
+            .collect();
+        ui.label(format!(
+            "{} samples, {t_first:.3}–{t_last:.3} s: {}",
+            measurement.times.len(),
+            channels.join(", ")
+        ));
+    }
+    ui.horizontal_wrapped(|ui| {
+        for param in FitParam::ALL {
+            let mut on = fit.params.contains(&param);
+            if ui.checkbox(&mut on, param.label()).changed() {
+                if on {
+                    fit.params.push(param);
+                } else {
+                    fit.params.retain(|&p| p != param);
+                }
+            }
+        }
+    });
+    ui.horizontal(|ui| {
+        ui.label("Iterations");
+        ui.add(egui::DragValue::new(&mut fit.max_iterations).range(1..=200));
+    });
+    let run = ui.button("▶ Fit parameters").clicked();
+
+    if let Some(result) = result {
+        egui::Grid::new("fit_result")
+            .num_columns(5)
+            .striped(true)
+            .show(ui, |ui| {
+                for header in ["Parameter", "Initial", "Fitted", "±σ", "95 % interval"] {
+                    ui.strong(header);
+                }
+                ui.end_row();
+                for p in &result.params {
+                    let (low, high) = p.interval();
+                    ui.label(p.param.label());
+                    ui.label(format!("{:.4e}", p.initial));
+                    ui.label(format!("{:.4e}", p.value));
+                    ui.label(format!("{:.2e}", p.std_error()));
+                    ui.label(format!("{low:.4e} … {high:.4e}"));
+                    ui.end_row();
+                }
+            });
+        for (channel, rms) in &result.rms {
+            ui.label(format!("RMS error {}: {rms:.4}", channel.label()));
+        }
+        ui.label(format!(
+            "{} iterations, {}",
+            result.iterations,
+            if result.converged {
+                "converged"
+            } else {
+                "iteration limit reached"
+            }
+        ));
+        if ui.button("Apply fitted values").clicked()
+            && let Err(e) = result.apply(snarl)
+        {
+            log::error!("Applying the fit failed: {e}");
+        }
+    }
+    run
+}
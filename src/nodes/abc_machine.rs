@@ -0,0 +1,463 @@
+//! Three-phase PMSM in the stationary abc frame, with fault injection.
+//!
+//! Unlike the d/q model every winding is a circuit branch of its own, so
+//! asymmetric conditions — an open phase, a short between two terminals or a
+//! partial short across the turns of one phase — can be represented. The
+//! winding inductances vary with `θ_e` through the rotor saliency:
+//!
+//! ```text
+//! L_jk(θ_e)   = L_ls·δ_jk + L_A·cos(φ_j − φ_k) − L_B·cos(2θ_e − φ_j − φ_k)
+//! ψ_pm,k(θ_e) = λ_m·cos(θ_e − φ_k)
+//! L_A = (L_d + L_q − 2·L_ls) / 3,   L_B = (L_q − L_d) / 3,   φ_k = k·2π/3
+//! ```
+//!
+//! which reduces to `L_d`, `L_q`, `λ_m` under the amplitude-invariant Park
+//! transform. The windings are fed from the `v_abc` sources through a line
+//! resistance; [`AbcMachineNode::network`] describes the resulting circuit.
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// Smallest resistance used for line, neutral and fault branches (Ω).
+const R_MIN: f64 = 1e-6;
+
+/// Circuit node of the star point.
+pub const NODE_NEUTRAL: usize = 3;
+
+/// How the three phase windings are connected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum WindingConnection {
+    /// Windings from each terminal to a common star point.
+    #[default]
+    Star,
+    /// Windings between terminal pairs a→b, b→c, c→a.
+    Delta,
+}
+
+impl WindingConnection {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 2] = [Self::Star, Self::Delta];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Star => "Star",
+            Self::Delta => "Delta",
+        }
+    }
+}
+
+/// Whether the star point is tied to the reference of the `v_abc` sources.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum NeutralConnection {
+    /// Isolated star point: the phase currents sum to zero.
+    #[default]
+    Floating,
+    /// Star point returned to the source reference through the line resistance.
+    Connected,
+}
+
+impl NeutralConnection {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 2] = [Self::Floating, Self::Connected];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Floating => "Floating",
+            Self::Connected => "Connected",
+        }
+    }
+}
+
+/// Fault applied from [`Fault::t_fault`] onwards.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum FaultKind {
+    /// Healthy machine.
+    #[default]
+    None,
+    /// The line to the faulted terminal is disconnected.
+    OpenPhase,
+    /// The faulted terminal is shorted to the next one through `r_f`.
+    PhaseShort,
+    /// A fraction of the faulted winding's turns is shorted through `r_f`.
+    InterTurn,
+}
+
+impl FaultKind {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 4] = [
+        Self::None,
+        Self::OpenPhase,
+        Self::PhaseShort,
+        Self::InterTurn,
+    ];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::OpenPhase => "Open phase",
+            Self::PhaseShort => "Phase short",
+            Self::InterTurn => "Inter-turn",
+        }
+    }
+}
+
+/// Fault configuration.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Fault {
+    /// Fault type.
+    pub kind: FaultKind,
+    /// Faulted phase (0 = a, 1 = b, 2 = c); a phase short joins it to the next.
+    pub phase: usize,
+    /// Fault onset time (s).
+    pub t_fault: f64,
+    /// Fault resistance (Ω).
+    pub r_f: f64,
+    /// Fraction of the winding's turns shorted by an inter-turn fault.
+    pub fraction: f64,
+}
+
+impl Default for Fault {
+    fn default() -> Self {
+        Self {
+            kind: FaultKind::None,
+            phase: 0,
+            t_fault: 0.1,
+            r_f: 0.01,
+            fraction: 0.1,
+        }
+    }
+}
+
+/// Far end of a resistive branch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Far {
+    /// Another circuit node.
+    Node(usize),
+    /// The source voltage of a phase (`v_abc` component).
+    Source(usize),
+    /// The reference potential of the sources.
+    Reference,
+}
+
+/// Resistive branch from a circuit node.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Resistor {
+    /// Circuit node at the near end.
+    pub node: usize,
+    /// Far end of the branch.
+    pub far: Far,
+    /// Resistance (Ω).
+    pub r: f64,
+}
+
+/// Inductive branch: all or part of one phase winding.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Coil {
+    /// Phase winding the coil belongs to.
+    pub phase: usize,
+    /// Fraction of the winding's turns.
+    pub turns: f64,
+    /// Node the positive current leaves.
+    pub from: usize,
+    /// Node the positive current enters.
+    pub to: usize,
+}
+
+/// Circuit of the machine windings with their supply and fault branches.
+///
+/// Nodes 0–2 are the terminals a, b, c; a star connection adds
+/// [`NODE_NEUTRAL`], and an inter-turn fault adds the tap node last.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Network {
+    /// Number of circuit nodes.
+    pub n_nodes: usize,
+    /// Winding coils, whose currents are the ODE states.
+    pub coils: Vec<Coil>,
+    /// Line, neutral and fault resistors.
+    pub resistors: Vec<Resistor>,
+    /// Index into `resistors` of the fault branch, if any.
+    pub fault: Option<usize>,
+}
+
+/// Three-phase PMSM modelled in phase quantities.
+///
+/// Input: source voltages `v_abc` (Vector), referred to the source reference.\
+/// Outputs: line currents `i_abc` (Vector), torque `T_e` and fault-branch
+/// current `i_f` (Signal). The pole-pair count comes from the Mechanical node.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AbcMachineNode {
+    /// Phase winding resistance (Ω).
+    pub r_s: f64,
+    /// d-axis inductance (H).
+    pub l_d: f64,
+    /// q-axis inductance (H).
+    pub l_q: f64,
+    /// Leakage (zero-sequence) inductance (H).
+    pub l_ls: f64,
+    /// Permanent magnet flux linkage (Wb).
+    pub lambda_m: f64,
+    /// Winding connection.
+    pub connection: WindingConnection,
+    /// Star-point connection (ignored for delta).
+    pub neutral: NeutralConnection,
+    /// Resistance of each supply line and of the neutral return (Ω).
+    pub r_line: f64,
+    /// Fault configuration.
+    pub fault: Fault,
+    /// Line-current time-series produced after simulation.
+    #[serde(skip)]
+    pub output_i_abc: Option<PortValue>,
+    /// Torque time-series produced after simulation.
+    #[serde(skip)]
+    pub output_t_e: Option<PortValue>,
+    /// Fault-branch current time-series produced after simulation.
+    #[serde(skip)]
+    pub output_i_f: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for AbcMachineNode {
+    fn default() -> Self {
+        Self {
+            r_s: 1.2,
+            l_d: 0.008,
+            l_q: 0.008,
+            l_ls: 0.0008,
+            lambda_m: 0.175,
+            connection: WindingConnection::Star,
+            neutral: NeutralConnection::Floating,
+            r_line: 0.01,
+            fault: Fault::default(),
+            output_i_abc: None,
+            output_t_e: None,
+            output_i_f: None,
+            custom_size: None,
+        }
+    }
+}
+
+/// Axis angle `φ_k = k·2π/3` of phase winding `k`.
+fn axis(k: usize) -> f64 {
+    k as f64 * std::f64::consts::TAU / 3.0
+}
+
+impl AbcMachineNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "PMSM (abc)"
+    }
+
+    /// Input port descriptors: source voltages.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[("v_abc", PortType::Vector)]
+    }
+
+    /// Output port descriptors: line currents, torque and fault current.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("i_abc", PortType::Vector),
+            ("T_e", PortType::Signal),
+            ("i_f", PortType::Signal),
+        ]
+    }
+
+    /// Header colour (same family as the d/q electrical node).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0xB0, 0x50, 0x30)
+    }
+
+    /// Phase inductance matrix `L(θ_e)` and its derivative `∂L/∂θ_e`.
+    pub fn inductance(&self, theta_e: f64) -> ([[f64; 3]; 3], [[f64; 3]; 3]) {
+        let l_a = (self.l_d + self.l_q - 2.0 * self.l_ls) / 3.0;
+        let l_b = (self.l_q - self.l_d) / 3.0;
+        let l = std::array::from_fn(|j| {
+            std::array::from_fn(|k| {
+                let leak = if j == k { self.l_ls } else { 0.0 };
+                leak + l_a * (axis(j) - axis(k)).cos()
+                    - l_b * (2.0 * theta_e - axis(j) - axis(k)).cos()
+            })
+        });
+        let dl = std::array::from_fn(|j| {
+            std::array::from_fn(|k| 2.0 * l_b * (2.0 * theta_e - axis(j) - axis(k)).sin())
+        });
+        (l, dl)
+    }
+
+    /// Phase PM flux linkages `ψ_pm(θ_e)` and their derivative `∂ψ_pm/∂θ_e`.
+    pub fn pm_flux(&self, theta_e: f64) -> ([f64; 3], [f64; 3]) {
+        (
+            std::array::from_fn(|k| self.lambda_m * (theta_e - axis(k)).cos()),
+            std::array::from_fn(|k| -self.lambda_m * (theta_e - axis(k)).sin()),
+        )
+    }
+
+    /// Winding circuit, healthy or with the configured fault applied.
+    pub fn network(&self, faulted: bool) -> Network {
+        let star = self.connection == WindingConnection::Star;
+        let mut n_nodes = if star { NODE_NEUTRAL + 1 } else { 3 };
+        let end = |k: usize| if star { NODE_NEUTRAL } else { (k + 1) % 3 };
+        let fault = if faulted {
+            self.fault.kind
+        } else {
+            FaultKind::None
+        };
+        let phase = self.fault.phase.min(2);
+        let r_line = self.r_line.max(R_MIN);
+        let r_f = self.fault.r_f.max(R_MIN);
+
+        let mut coils: Vec<Coil> = (0..3)
+            .map(|k| Coil {
+                phase: k,
+                turns: 1.0,
+                from: k,
+                to: end(k),
+            })
+            .collect();
+        let mut resistors: Vec<Resistor> = (0..3)
+            .filter(|&k| !(fault == FaultKind::OpenPhase && k == phase))
+            .map(|k| Resistor {
+                node: k,
+                far: Far::Source(k),
+                r: r_line,
+            })
+            .collect();
+        if star && self.neutral == NeutralConnection::Connected {
+            resistors.push(Resistor {
+                node: NODE_NEUTRAL,
+                far: Far::Reference,
+                r: r_line,
+            });
+        }
+
+        let mut fault_branch = None;
+        match fault {
+            FaultKind::None | FaultKind::OpenPhase => {}
+            FaultKind::PhaseShort => {
+                fault_branch = Some(resistors.len());
+                resistors.push(Resistor {
+                    node: phase,
+                    far: Far::Node((phase + 1) % 3),
+                    r: r_f,
+                });
+            }
+            FaultKind::InterTurn => {
+                // Split the winding at a tap: healthy turns, then shorted turns
+                let mu = self.fault.fraction.clamp(0.01, 0.99);
+                let tap = n_nodes;
+                n_nodes += 1;
+                if let Some(coil) = coils.get_mut(phase) {
+                    coil.turns = 1.0 - mu;
+                    coil.to = tap;
+                }
+                coils.push(Coil {
+                    phase,
+                    turns: mu,
+                    from: tap,
+                    to: end(phase),
+                });
+                fault_branch = Some(resistors.len());
+                resistors.push(Resistor {
+                    node: tap,
+                    far: Far::Node(end(phase)),
+                    r: r_f,
+                });
+            }
+        }
+
+        Network {
+            n_nodes,
+            coils,
+            resistors,
+            fault: fault_branch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AbcMachineNode, FaultKind, NeutralConnection};
+
+    /// Park-transforming the phase inductance matrix yields `L_d`, `L_q`.
+    #[test]
+    fn inductance_matches_dq_parameters() {
+        let m = AbcMachineNode {
+            l_d: 0.006,
+            l_q: 0.011,
+            ..AbcMachineNode::default()
+        };
+        let theta = 0.4;
+        let (l, _) = m.inductance(theta);
+        let third = std::f64::consts::TAU / 3.0;
+        let offsets = [0.0, third, -third];
+        // ψ_abc for a pure d-axis (then q-axis) current, projected back onto d/q
+        for (axis, expected) in [(0, m.l_d), (1, m.l_q)] {
+            let i: Vec<f64> = offsets
+                .iter()
+                .map(|o| {
+                    let angle = theta - o;
+                    if axis == 0 { angle.cos() } else { -angle.sin() }
+                })
+                .collect();
+            let psi: Vec<f64> = l
+                .iter()
+                .map(|row| row.iter().zip(&i).map(|(a, b)| a * b).sum())
+                .collect();
+            let project = |f: fn(f64) -> f64, sign: f64| {
+                sign * 2.0 / 3.0
+                    * psi
+                        .iter()
+                        .zip(&offsets)
+                        .map(|(p, o)| p * f(theta - o))
+                        .sum::<f64>()
+            };
+            let (psi_d, psi_q) = (project(f64::cos, 1.0), project(f64::sin, -1.0));
+            let (on, off) = if axis == 0 {
+                (psi_d, psi_q)
+            } else {
+                (psi_q, psi_d)
+            };
+            assert!(
+                (on - expected).abs() < 1e-12,
+                "axis {axis}: {on} vs {expected}"
+            );
+            assert!(off.abs() < 1e-12, "axis {axis} cross-coupling: {off}");
+        }
+    }
+
+    /// Faults change the circuit topology as described.
+    #[test]
+    fn network_reflects_fault() {
+        let mut m = AbcMachineNode::default();
+        let healthy = m.network(false);
+        assert_eq!(healthy.coils.len(), 3);
+        assert_eq!(healthy.resistors.len(), 3, "three lines, floating neutral");
+
+        m.neutral = NeutralConnection::Connected;
+        m.fault.kind = FaultKind::OpenPhase;
+        m.fault.phase = 1;
+        let open = m.network(true);
+        assert_eq!(open.resistors.len(), 3, "two lines plus the neutral return");
+        assert!(open.resistors.iter().all(|r| r.node != 1), "line b removed");
+
+        m.fault.kind = FaultKind::InterTurn;
+        let inter = m.network(true);
+        assert_eq!(inter.n_nodes, 5, "tap node added");
+        assert_eq!(inter.coils.len(), 4, "winding b split in two");
+        let turns: f64 = inter
+            .coils
+            .iter()
+            .filter(|c| c.phase == 1)
+            .map(|c| c.turns)
+            .sum();
+        assert!((turns - 1.0).abs() < 1e-12, "split keeps all turns");
+        assert!(inter.fault.is_some(), "fault branch recorded");
+    }
+}
@@ -0,0 +1,254 @@
+//! BLDC motor node — trapezoidal back-EMF with Hall-sensor six-step drive.
+//!
+//! Each phase has resistance `R_s`, inductance `L_s` (self minus mutual) and
+//! back-EMF `e_k = k_e·ω_m·F(θ_e − k·2π/3)`, where `F` is a unit trapezoid
+//! with 120° flat tops. The star point floats, so
+//!
+//! ```text
+//! L_s·di_k/dt = v_k − v_n − R_s·i_k − e_k,   v_n = (Σv_k − Σe_k) / 3
+//! T_e = k_e·Σ F(θ_e − k·2π/3)·i_k
+//! ```
+//!
+//! Ideal Hall sensors switch every 60° electrical; the decoded sector selects
+//! one phase tied to the averaged PWM voltage `d·V_dc` and one to the negative
+//! rail. The third leg is off: its freewheeling diodes clamp it to a rail
+//! while its current decays, after which it floats.
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// Resistance that an off leg presents between the rails' midpoint and its
+/// phase while neither diode conducts (Ω).
+const R_OFF: f64 = 1.0e4;
+
+/// Drive state of one inverter leg.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Leg {
+    /// Averaged PWM high side: `d·V_dc`.
+    High,
+    /// Low side on: negative rail.
+    Low,
+    /// Both switches off, neither diode conducting.
+    Off,
+    /// Both switches off, upper diode clamping the phase to `V_dc`.
+    UpperDiode,
+    /// Both switches off, lower diode clamping the phase to the negative rail.
+    LowerDiode,
+}
+
+/// BLDC motor with Hall-sensor six-step commutation.
+///
+/// Inputs: DC-link voltage `V_dc` and PWM duty `d` (Signal; an unconnected
+/// duty reads as 1).\
+/// Outputs: phase currents `i_abc`, Hall states `hall` (0/1 per sensor, as a
+/// Vector) and torque `T_e` (Signal). Speed and angle come from the
+/// Mechanical node, which receives `T_e` internally.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct BldcNode {
+    /// Phase resistance (Ω).
+    pub r_s: f64,
+    /// Phase inductance, self minus mutual (H).
+    pub l_s: f64,
+    /// Per-phase back-EMF constant at the trapezoid's flat top (V·s/rad).
+    pub k_e: f64,
+    /// Hall-sensor placement error (deg electrical); positive advances
+    /// commutation.
+    pub hall_offset: f64,
+    /// Phase current time-series produced after simulation.
+    #[serde(skip)]
+    pub output_i_abc: Option<PortValue>,
+    /// Hall-state time-series produced after simulation.
+    #[serde(skip)]
+    pub output_hall: Option<PortValue>,
+    /// Torque time-series produced after simulation.
+    #[serde(skip)]
+    pub output_t_e: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for BldcNode {
+    fn default() -> Self {
+        Self {
+            r_s: 0.5,
+            l_s: 0.001,
+            k_e: 0.05,
+            hall_offset: 0.0,
+            output_i_abc: None,
+            output_hall: None,
+            output_t_e: None,
+            custom_size: None,
+        }
+    }
+}
+
+/// Unit trapezoid of electrical angle `x` (rad): 0 at 0, +1 over 30°–150°,
+/// −1 over 210°–330°, linear in between.
+pub fn trapezoid(x: f64) -> f64 {
+    let deg = x.to_degrees().rem_euclid(360.0);
+    if deg < 30.0 {
+        deg / 30.0
+    } else if deg < 150.0 {
+        1.0
+    } else if deg < 210.0 {
+        1.0 - (deg - 150.0) / 30.0
+    } else if deg < 330.0 {
+        -1.0
+    } else {
+        -1.0 + (deg - 330.0) / 30.0
+    }
+}
+
+/// Phase-axis offset `k·2π/3` of phase `k`.
+fn axis(k: usize) -> f64 {
+    k as f64 * std::f64::consts::TAU / 3.0
+}
+
+impl BldcNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "BLDC Motor"
+    }
+
+    /// Input port descriptors: DC-link voltage and PWM duty.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[("V_dc", PortType::Signal), ("d", PortType::Signal)]
+    }
+
+    /// Output port descriptors: phase currents, Hall states and torque.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("i_abc", PortType::Vector),
+            ("hall", PortType::Vector),
+            ("T_e", PortType::Signal),
+        ]
+    }
+
+    /// Header colour (same family as the other machine nodes).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x90, 0x50, 0x60)
+    }
+
+    /// Back-EMF shape `F(θ_e − k·2π/3)` of the three phases.
+    pub fn emf_shape(theta_e: f64) -> [f64; 3] {
+        std::array::from_fn(|k| trapezoid(theta_e - axis(k)))
+    }
+
+    /// Hall-sensor states at electrical angle `theta_e`.
+    ///
+    /// Sensor `k` is high for the half turn that starts 30° after phase `k`'s
+    /// back-EMF zero crossing, so the code changes at the commutation angles.
+    pub fn hall(&self, theta_e: f64) -> [bool; 3] {
+        let shifted = theta_e + self.hall_offset.to_radians() - 30_f64.to_radians();
+        std::array::from_fn(|k| {
+            (shifted - axis(k)).rem_euclid(std::f64::consts::TAU) < std::f64::consts::PI
+        })
+    }
+
+    /// Leg drive states for a Hall code; all legs are off for the invalid
+    /// codes 000 and 111.
+    ///
+    /// Sector `s` spans `30° + s·60°` to `90° + s·60°`: the phase whose
+    /// back-EMF is at its positive flat top is driven high and the one at its
+    /// negative flat top low.
+    pub fn commutation(hall: [bool; 3]) -> [Leg; 3] {
+        let probe = Self::default();
+        (0..6)
+            .map(|s| (60.0 + 60.0 * f64::from(s)).to_radians())
+            .find(|&centre| probe.hall(centre) == hall)
+            .map_or([Leg::Off; 3], |centre| {
+                let shape = Self::emf_shape(centre);
+                shape.map(|f| {
+                    if f > 0.99 {
+                        Leg::High
+                    } else if f < -0.99 {
+                        Leg::Low
+                    } else {
+                        Leg::Off
+                    }
+                })
+            })
+    }
+
+    /// Resolve the off legs among the commanded `legs` into conducting-diode
+    /// states for bus voltage `v_dc` and phase currents `i`.
+    ///
+    /// An off leg sits at the rails' midpoint behind [`R_OFF`] until that
+    /// voltage leaves the rails, at which point a diode clamps it.
+    pub fn conduction(legs: [Leg; 3], v_dc: f64, i: [f64; 3]) -> [Leg; 3] {
+        std::array::from_fn(|k| match legs.get(k).copied().unwrap_or(Leg::Off) {
+            Leg::Off | Leg::UpperDiode | Leg::LowerDiode => {
+                let floating = 0.5 * v_dc - R_OFF * i.get(k).copied().unwrap_or(0.0);
+                if floating > v_dc {
+                    Leg::UpperDiode
+                } else if floating < 0.0 {
+                    Leg::LowerDiode
+                } else {
+                    Leg::Off
+                }
+            }
+            leg => leg,
+        })
+    }
+
+    /// Leg output voltages (V, from the negative rail) for leg states `legs`
+    /// (resolved by [`Self::conduction`]), averaged high-side voltage
+    /// `v_high`, bus voltage `v_dc` and phase currents `i`.
+    pub fn leg_voltages(legs: [Leg; 3], v_high: f64, v_dc: f64, i: [f64; 3]) -> [f64; 3] {
+        std::array::from_fn(|k| match legs.get(k).copied().unwrap_or(Leg::Off) {
+            Leg::High => v_high,
+            Leg::Low | Leg::LowerDiode => 0.0,
+            Leg::UpperDiode => v_dc,
+            Leg::Off => 0.5 * v_dc - R_OFF * i.get(k).copied().unwrap_or(0.0),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BldcNode, Leg};
+
+    /// Walking one electrical turn visits six distinct Hall codes, and each
+    /// drives high the phase at its positive flat top.
+    #[test]
+    fn hall_sequence_commutates_on_flat_tops() {
+        let m = BldcNode::default();
+        let mut codes = Vec::new();
+        for s in 0..6 {
+            let theta = (45.0 + 60.0 * f64::from(s)).to_radians();
+            let code = m.hall(theta);
+            assert!(!codes.contains(&code), "sector {s} repeats a Hall code");
+            codes.push(code);
+            let legs = BldcNode::commutation(code);
+            let shape = BldcNode::emf_shape(theta);
+            for (leg, f) in legs.iter().zip(shape) {
+                match leg {
+                    Leg::High => assert!(f > 0.99, "sector {s}: high phase on +flat top"),
+                    Leg::Low => assert!(f < -0.99, "sector {s}: low phase on −flat top"),
+                    _ => assert!(f.abs() < 1.0, "sector {s}: off phase is ramping"),
+                }
+            }
+        }
+        assert_eq!(
+            BldcNode::commutation([true; 3]),
+            [Leg::Off; 3],
+            "invalid code turns all legs off"
+        );
+    }
+
+    /// The back-EMF shapes stay within ±1 and sum to zero at a phase's zero
+    /// crossing.
+    #[test]
+    fn trapezoid_is_bounded_and_balanced() {
+        for step in 0..360 {
+            let theta = f64::from(step).to_radians();
+            let shape = BldcNode::emf_shape(theta);
+            assert!(shape.iter().all(|f| f.abs() <= 1.0), "bounded at {step}°");
+        }
+        let sum: f64 = BldcNode::emf_shape(0.0).iter().sum();
+        assert!(sum.abs() < 1e-12, "balanced at the zero crossing: {sum}");
+    }
+}
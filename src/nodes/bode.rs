@@ -0,0 +1,232 @@
+//! Bode node — small-signal frequency response and poles of the PMSM
+//! linearised about its steady state.
+//!
+//! The input `u` is wired from the source output whose value is perturbed,
+//! the output `y` from a signal output of the machine: its currents and
+//! losses, drivetrain, torque, DC link, temperatures or power flow. A signal
+//! source is linearised about its value at `t_start`; a source that depends
+//! on the machine state, or a `y` the machine does not drive, leaves the
+//! node without a model and with an error. Before each simulation the
+//! solver trims the machine for the inputs at `t_start` and extracts the
+//! linear model
+//!
+//! ```text
+//! ẋ = A·x + B·u,   y = C·x + D·u
+//! ```
+//!
+//! with `A` from the analytic Jacobian of the ODE and `B`, `C`, `D` from
+//! central differences. `θ_e` is a pure integrator of `ω_m` and is left out.
+
+use std::f64::consts::TAU;
+
+use egui::Color32;
+use nalgebra::{Complex, DMatrix, DVector, RowDVector};
+
+use crate::port::PortType;
+
+/// Single-input single-output state-space model `ẋ = A·x + B·u`,
+/// `y = C·x + D·u`.
+#[derive(Clone, Debug)]
+pub struct LinearModel {
+    /// Names of the states, in row order of `A`.
+    pub states: Vec<String>,
+    /// State matrix `A = ∂f/∂x`.
+    pub a: DMatrix<f64>,
+    /// Input matrix `B = ∂f/∂u`.
+    pub b: DVector<f64>,
+    /// Output matrix `C = ∂y/∂x`.
+    pub c: RowDVector<f64>,
+    /// Feedthrough `D = ∂y/∂u`.
+    pub d: f64,
+}
+
+/// One eigenvalue `λ = re + j·im` of the state matrix.
+#[derive(Clone, Copy, Debug)]
+pub struct Pole {
+    /// Real part (1/s).
+    pub re: f64,
+    /// Imaginary part (rad/s).
+    pub im: f64,
+}
+
+impl Pole {
+    /// Natural frequency `|λ| / 2π` (Hz).
+    pub fn frequency(self) -> f64 {
+        self.re.hypot(self.im) / TAU
+    }
+
+    /// Damping ratio `ζ = −re / |λ|`; `NaN` for a pole at the origin.
+    pub fn damping(self) -> f64 {
+        -self.re / self.re.hypot(self.im)
+    }
+}
+
+impl LinearModel {
+    /// Frequency response `G(j2πf) = C·(j2πf·I − A)⁻¹·B + D`, or `None`
+    /// where `j2πf` is an eigenvalue of `A`.
+    pub fn response(&self, f: f64) -> Option<Complex<f64>> {
+        let s = Complex::new(0.0, TAU * f);
+        let n = self.a.nrows();
+        let m = DMatrix::from_diagonal_element(n, n, s) - self.a.map(Complex::from);
+        let x = m.lu().solve(&self.b.map(Complex::from))?;
+        let cx: Complex<f64> = self.c.iter().zip(x.iter()).map(|(&c, &x)| x * c).sum();
+        Some(cx + self.d)
+    }
+
+    /// Magnitude (dB) and unwrapped phase (°) against frequency (Hz) at
+    /// `points` logarithmically spaced frequencies from `f_min` to `f_max`.
+    pub fn bode(&self, f_min: f64, f_max: f64, points: usize) -> BodeSeries {
+        let n = points.max(2);
+        let ratio = (f_max / f_min).ln();
+        let mut series = BodeSeries::default();
+        let mut previous: Option<f64> = None;
+        for k in 0..n {
+            let f = f_min * (ratio * k as f64 / (n - 1) as f64).exp();
+            let Some(g) = self.response(f) else {
+                continue;
+            };
+            let mut phase = g.arg().to_degrees();
+            // Unwrap against the previous point so the phase is continuous
+            if let Some(last) = previous {
+                phase -= 360.0 * ((phase - last) / 360.0).round();
+            }
+            previous = Some(phase);
+            series.magnitude.push([f, 20.0 * g.norm().log10()]);
+            series.phase.push([f, phase]);
+        }
+        series
+    }
+
+    /// Eigenvalues of `A`, slowest first.
+    pub fn poles(&self) -> Vec<Pole> {
+        let mut poles: Vec<Pole> = self
+            .a
+            .complex_eigenvalues()
+            .iter()
+            .map(|z| Pole { re: z.re, im: z.im })
+            .collect();
+        poles.sort_by(|a, b| a.frequency().total_cmp(&b.frequency()));
+        poles
+    }
+}
+
+/// Bode magnitude and phase traces.
+#[derive(Clone, Debug, Default)]
+pub struct BodeSeries {
+    /// `[f (Hz), |G| (dB)]` points.
+    pub magnitude: Vec<[f64; 2]>,
+    /// `[f (Hz), ∠G (°)]` points.
+    pub phase: Vec<[f64; 2]>,
+}
+
+/// A sink node that plots the small-signal response from `u` to `y`.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct BodeNode {
+    /// Lowest plotted frequency (Hz).
+    pub f_min: f64,
+    /// Highest plotted frequency (Hz).
+    pub f_max: f64,
+    /// Number of logarithmically spaced frequencies.
+    pub points: usize,
+    /// Linear model of the last run.
+    #[serde(skip)]
+    pub model: Option<LinearModel>,
+    /// Bode traces of [`Self::model`].
+    #[serde(skip)]
+    pub series: BodeSeries,
+    /// Eigenvalues of [`Self::model`].
+    #[serde(skip)]
+    pub poles: Vec<Pole>,
+    /// Why the last run could not linearise the wiring.
+    #[serde(skip)]
+    pub error: Option<String>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for BodeNode {
+    fn default() -> Self {
+        Self {
+            f_min: 1.0,
+            f_max: 10_000.0,
+            points: 200,
+            model: None,
+            series: BodeSeries::default(),
+            poles: Vec::new(),
+            error: None,
+            custom_size: None,
+        }
+    }
+}
+
+impl BodeNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "Bode"
+    }
+
+    /// Input ports: `u` from the perturbed source, `y` from a machine output.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[("u", PortType::Signal), ("y", PortType::Signal)]
+    }
+
+    /// Output port list — empty; this is a sink node.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[]
+    }
+
+    /// Header background color, shared with the Plot node.
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x40, 0xB0, 0x40)
+    }
+
+    /// Store a linear model with its Bode traces and poles, or clear them.
+    pub fn set_model(&mut self, model: Option<LinearModel>) {
+        self.series = model
+            .as_ref()
+            .map(|m| m.bode(self.f_min, self.f_max, self.points))
+            .unwrap_or_default();
+        self.poles = model.as_ref().map(LinearModel::poles).unwrap_or_default();
+        self.model = model;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{DMatrix, DVector, RowDVector};
+
+    use super::LinearModel;
+
+    /// A first-order lag `1 / (τs + 1)` is −3 dB and −45° at its corner and
+    /// has one real pole at `−1/τ`.
+    #[test]
+    fn first_order_lag_response() {
+        let tau = 0.01;
+        let model = LinearModel {
+            states: vec!["x".to_owned()],
+            a: DMatrix::from_element(1, 1, -1.0 / tau),
+            b: DVector::from_element(1, 1.0 / tau),
+            c: RowDVector::from_element(1, 1.0),
+            d: 0.0,
+        };
+        let corner = 1.0 / (std::f64::consts::TAU * tau);
+        let series = model.bode(corner, corner * 10.0, 2);
+        let [[_, mag], _] = series.magnitude[..] else {
+            panic!("expected two points");
+        };
+        let [[_, phase], _] = series.phase[..] else {
+            panic!("expected two points");
+        };
+        assert!((mag + 10.0 * 2.0_f64.log10()).abs() < 1e-9, "−3 dB: {mag}");
+        assert!((phase + 45.0).abs() < 1e-9, "−45°: {phase}");
+
+        let [pole] = model.poles()[..] else {
+            panic!("expected one pole");
+        };
+        assert!((pole.re + 1.0 / tau).abs() < 1e-9 && pole.im.abs() < 1e-12);
+        assert!((pole.damping() - 1.0).abs() < 1e-12);
+        assert!((pole.frequency() - corner).abs() < 1e-9);
+    }
+}
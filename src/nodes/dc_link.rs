@@ -0,0 +1,215 @@
+//! DC-link node — a battery feeding the inverter bus through a capacitor bank.
+//!
+//! The capacitor voltage `v_C` is an ODE state. The inverter draws the motor's
+//! electrical power `P_e = (3/2)·(v_d·i_d + v_q·i_q)` (pmsm.md §9) from the
+//! bus, so its input current is `i_dc = P_e / V_dc`. An optional brake chopper
+//! dissipates regenerated energy once the bus rises above a threshold.
+//!
+//! ```text
+//!   V_oc ──R_bat──┬──────────┬─────────┬──▶ i_dc (inverter)
+//!                 │          │         │
+//!                ESR       R_chop     V_dc
+//!                 │          │
+//!                 C         ─┴─
+//!                ─┴─
+//! ```
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// Smallest resistance used for the battery and ESR branches (Ω).
+///
+/// Keeps the bus equation well-posed when a parameter is set to zero.
+const R_MIN: f64 = 1e-6;
+
+/// Branch currents of the DC link at one instant (A).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DcLinkCurrents {
+    /// Battery current, positive when discharging.
+    pub i_bat: f64,
+    /// Capacitor current, positive when charging.
+    pub i_cap: f64,
+    /// Inverter input current `P_e / V_dc`.
+    pub i_dc: f64,
+    /// Brake chopper current.
+    pub i_chop: f64,
+}
+
+/// DC-link capacitor bank supplied by a battery, with optional brake chopper.
+///
+/// Has no inputs: the load is computed from the motor states.\
+/// Outputs: bus voltage `V_dc`, battery current `i_bat` and inverter input
+/// current `i_dc` (all Signal).
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DcLinkNode {
+    /// DC-link capacitance (F).
+    pub capacitance: f64,
+    /// Capacitor equivalent series resistance (Ω).
+    pub esr: f64,
+    /// Battery open-circuit voltage (V); also the initial capacitor voltage.
+    pub v_oc: f64,
+    /// Battery internal resistance (Ω).
+    pub r_bat: f64,
+    /// Whether the brake chopper is fitted.
+    pub chopper: bool,
+    /// Bus voltage at which the chopper is fully on (V).
+    pub v_chop: f64,
+    /// Voltage band below `v_chop` over which the chopper duty ramps from 0 to 1 (V).
+    pub chop_band: f64,
+    /// Brake resistor (Ω).
+    pub r_chop: f64,
+    /// Bus voltage time-series produced after simulation.
+    #[serde(skip)]
+    pub output_v_dc: Option<PortValue>,
+    /// Battery current time-series produced after simulation.
+    #[serde(skip)]
+    pub output_i_bat: Option<PortValue>,
+    /// Inverter input current time-series produced after simulation.
+    #[serde(skip)]
+    pub output_i_dc: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for DcLinkNode {
+    fn default() -> Self {
+        Self {
+            capacitance: 1e-3,
+            esr: 0.01,
+            v_oc: 48.0,
+            r_bat: 0.05,
+            chopper: false,
+            v_chop: 56.0,
+            chop_band: 2.0,
+            r_chop: 10.0,
+            output_v_dc: None,
+            output_i_bat: None,
+            output_i_dc: None,
+            custom_size: None,
+        }
+    }
+}
+
+impl DcLinkNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "DC Link"
+    }
+
+    /// Input port descriptors (none: the load comes from the motor states).
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[]
+    }
+
+    /// Output port descriptors: bus voltage, battery and inverter currents.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("V_dc", PortType::Signal),
+            ("i_bat", PortType::Signal),
+            ("i_dc", PortType::Signal),
+        ]
+    }
+
+    /// Header colour (teal, matching other power-electronics nodes).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x20, 0x90, 0x90)
+    }
+
+    /// Brake chopper conductance (S) at capacitor voltage `v_c`.
+    ///
+    /// The chopper duty ramps linearly over `[v_chop − chop_band, v_chop]`.
+    /// Keying it on the capacitor state rather than the bus voltage avoids an
+    /// algebraic loop and keeps the RHS continuous.
+    pub fn chopper_conductance(&self, v_c: f64) -> f64 {
+        if !self.chopper || self.r_chop <= 0.0 {
+            return 0.0;
+        }
+        let band = self.chop_band.max(1e-3);
+        let duty = ((v_c - (self.v_chop - band)) / band).clamp(0.0, 1.0);
+        duty / self.r_chop
+    }
+
+    /// Bus voltage `V_dc` for capacitor voltage `v_c` and inverter power `p_e`.
+    ///
+    /// Kirchhoff's current law at the bus with a constant-power load gives
+    /// `G·V² − I_s·V + P_e = 0`, where `G` is the total branch conductance and
+    /// `I_s` the short-circuit current of the battery and capacitor branches.
+    /// The upper root is the stable operating point. If the load exceeds what
+    /// the source can deliver the discriminant is clamped, which holds the bus
+    /// at its maximum-power point instead of failing.
+    pub fn bus_voltage(&self, v_c: f64, p_e: f64) -> f64 {
+        let g_bat = 1.0 / self.r_bat.max(R_MIN);
+        let g_cap = 1.0 / self.esr.max(R_MIN);
+        let g = g_bat + g_cap + self.chopper_conductance(v_c);
+        let i_s = self.v_oc * g_bat + v_c * g_cap;
+        let disc = (i_s * i_s - 4.0 * g * p_e).max(0.0);
+        (i_s + disc.sqrt()) / (2.0 * g)
+    }
+
+    /// Branch currents for capacitor voltage `v_c` and bus voltage `v_dc`.
+    pub fn currents(&self, v_c: f64, v_dc: f64, p_e: f64) -> DcLinkCurrents {
+        DcLinkCurrents {
+            i_bat: (self.v_oc - v_dc) / self.r_bat.max(R_MIN),
+            i_cap: (v_dc - v_c) / self.esr.max(R_MIN),
+            i_dc: if v_dc.abs() > f64::EPSILON {
+                p_e / v_dc
+            } else {
+                0.0
+            },
+            i_chop: self.chopper_conductance(v_c) * v_dc,
+        }
+    }
+
+    /// Capacitor voltage derivative `dv_C/dt = i_cap / C`.
+    pub fn dv_c(&self, v_c: f64, v_dc: f64) -> f64 {
+        (v_dc - v_c) / (self.esr.max(R_MIN) * self.capacitance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DcLinkNode;
+
+    /// The bus solution satisfies Kirchhoff's current law and sags under load.
+    #[test]
+    fn bus_voltage_balances_currents() {
+        let link = DcLinkNode::default();
+        let v_c = 47.0;
+        let p_e = 500.0;
+        let v_dc = link.bus_voltage(v_c, p_e);
+        let i = link.currents(v_c, v_dc, p_e);
+        assert!(
+            (i.i_bat - i.i_cap - i.i_dc - i.i_chop).abs() < 1e-9,
+            "KCL residual: {i:?}"
+        );
+        assert!(v_dc < link.v_oc, "bus should sag under load: {v_dc}");
+        assert!(
+            (link.bus_voltage(link.v_oc, 0.0) - link.v_oc).abs() < 1e-12,
+            "no-load bus equals the open-circuit voltage"
+        );
+    }
+
+    /// The chopper only conducts above its threshold band.
+    #[test]
+    fn chopper_ramps_over_band() {
+        let link = DcLinkNode {
+            chopper: true,
+            ..DcLinkNode::default()
+        };
+        assert!(
+            link.chopper_conductance(50.0).abs() < 1e-15,
+            "off below band"
+        );
+        assert!(
+            (link.chopper_conductance(55.0) - 0.5 / link.r_chop).abs() < 1e-12,
+            "half duty mid-band"
+        );
+        assert!(
+            (link.chopper_conductance(60.0) - 1.0 / link.r_chop).abs() < 1e-12,
+            "fully on above threshold"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Brushed DC motor node — armature circuit with a constant-flux field.
+//!
+//! ```text
+//! di_a/dt = (v_a − R_a·i_a − k_e·ω_m) / L_a
+//! T_e     = k_t·i_a
+//! ```
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// Brushed DC motor (permanent-magnet or separately excited at fixed field).
+///
+/// Input: armature voltage `v_a` (Signal).\
+/// Outputs: armature current `i_a` and torque `T_e` (Signal). Speed comes from
+/// the Mechanical node, which receives `T_e` internally.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DcMotorNode {
+    /// Armature resistance (Ω).
+    pub r_a: f64,
+    /// Armature inductance (H).
+    pub l_a: f64,
+    /// Back-EMF constant (V·s/rad).
+    pub k_e: f64,
+    /// Torque constant (N·m/A).
+    pub k_t: f64,
+    /// Initial armature current (A).
+    pub i_a_0: f64,
+    /// Armature current time-series produced after simulation.
+    #[serde(skip)]
+    pub output_i_a: Option<PortValue>,
+    /// Torque time-series produced after simulation.
+    #[serde(skip)]
+    pub output_t_e: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for DcMotorNode {
+    fn default() -> Self {
+        Self {
+            r_a: 1.0,
+            l_a: 0.002,
+            k_e: 0.05,
+            k_t: 0.05,
+            i_a_0: 0.0,
+            output_i_a: None,
+            output_t_e: None,
+            custom_size: None,
+        }
+    }
+}
+
+impl DcMotorNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "DC Motor"
+    }
+
+    /// Input port descriptors: armature voltage.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[("v_a", PortType::Signal)]
+    }
+
+    /// Output port descriptors: armature current and torque.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[("i_a", PortType::Signal), ("T_e", PortType::Signal)]
+    }
+
+    /// Header colour (same family as the other machine nodes).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x90, 0x60, 0x50)
+    }
+}
@@ -0,0 +1,577 @@
+//! Efficiency Map node — torque–speed capability envelope and efficiency map
+//! of the d/q PMSM from its steady-state equations.
+//!
+//! At every speed `ω_m` and shaft torque demand `T` of a grid the node looks
+//! for the currents that deliver the torque with the least loss within the
+//! current and voltage limits. In steady state the d/q equations reduce to
+//!
+//! ```text
+//! v_d = R_s·i_d − ω_e·ψ_q,   v_q = R_s·i_q + ω_e·ψ_d,   ω_e = N_p·ω_m
+//! T_e(i_d, i_q) = T + B·ω_m + T_f(ω_m) + T_fe(ω_m, |ψ|)
+//! |i| ≤ I_max,   |v| ≤ V_dc / √3
+//! ```
+//!
+//! with `ψ_d = L_d·i_d + λ_m`, `ψ_q = L_q·i_q` (or the flux map) and `T_e`
+//! from the Torque node's parameters. Along the constant-torque curve `i_d`
+//! is scanned from 0 to `−I_max` (field weakening) and the feasible point with
+//! the least copper plus iron loss is refined by golden-section search. The
+//! efficiency is `η = T·ω_m / P_e` with `P_e = (3/2)·(v_d·i_d + v_q·i_q)`, and
+//! the envelope is the largest feasible torque at each speed.
+
+use egui::Color32;
+
+use super::electrical::{ElectricalNode, IronLoss};
+use super::flux_map::FluxMap;
+use super::mechanical::Friction;
+use super::torque::TorqueNode;
+use crate::port::PortType;
+
+/// Number of `i_d` values scanned along a constant-torque curve.
+const SCAN_POINTS: usize = 100;
+
+/// Bisection and golden-section iterations.
+const ITERATIONS: usize = 48;
+
+/// Steady-state equations of the d/q PMSM and its rotor losses.
+#[derive(Clone)]
+pub struct SteadyStateModel {
+    /// Stator resistance at the winding temperature (Ω).
+    pub r_s: f64,
+    /// d-axis inductance (H).
+    pub l_d: f64,
+    /// q-axis inductance (H).
+    pub l_q: f64,
+    /// PM flux linkage (Wb).
+    pub lambda_m: f64,
+    /// Pole pairs of the voltage equations.
+    pub n_p: f64,
+    /// Torque-equation parameters `(N_p, λ_m, L_d, L_q)`.
+    pub torque: TorqueNode,
+    /// Flux-linkage map replacing `L_d`, `L_q`, `λ_m` when present.
+    pub flux_map: Option<FluxMap>,
+    /// Core-loss model.
+    pub iron_loss: IronLoss,
+    /// Viscous friction coefficient (N·m·s/rad).
+    pub b: f64,
+    /// Dry friction of the rotor.
+    pub friction: Friction,
+}
+
+/// Current and voltage limits of the drive.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// Peak phase current (A).
+    pub i_max: f64,
+    /// Peak phase voltage (V).
+    pub v_max: f64,
+}
+
+/// Steady-state operating point of the machine.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MapPoint {
+    /// Mechanical speed `ω_m` (rad/s).
+    pub omega_m: f64,
+    /// Shaft torque `T` (N·m).
+    pub torque: f64,
+    /// d-axis current (A).
+    pub i_d: f64,
+    /// q-axis current (A).
+    pub i_q: f64,
+    /// Phase voltage magnitude `|v|` (V).
+    pub v_s: f64,
+    /// Electrical input power (W).
+    pub p_e: f64,
+    /// Copper loss (W).
+    pub p_cu: f64,
+    /// Iron loss (W).
+    pub p_fe: f64,
+    /// Viscous and dry friction loss (W).
+    pub p_fric: f64,
+    /// Efficiency `T·ω_m / P_e` (%).
+    pub efficiency: f64,
+}
+
+impl SteadyStateModel {
+    /// Model of the Electrical node at its winding temperature, with the
+    /// torque equation of `torque` (the Electrical node's parameters when
+    /// `None`) and the rotor friction `b`, `friction`.
+    pub fn new(
+        electrical: &ElectricalNode,
+        torque: Option<&TorqueNode>,
+        b: f64,
+        friction: Friction,
+    ) -> Self {
+        let torque = torque.cloned().unwrap_or_else(|| TorqueNode {
+            n_p: electrical.n_p,
+            lambda_m: electrical.lambda_m,
+            l_d: electrical.l_d,
+            l_q: electrical.l_q,
+            ..TorqueNode::default()
+        });
+        Self {
+            r_s: electrical.r_s_at(electrical.t_winding),
+            l_d: electrical.l_d,
+            l_q: electrical.l_q,
+            lambda_m: electrical.lambda_m,
+            n_p: electrical.n_p,
+            torque,
+            flux_map: electrical.flux_map.as_deref().cloned(),
+            iron_loss: electrical.iron_loss,
+            b,
+            friction,
+        }
+    }
+
+    /// Stator flux linkages `[ψ_d, ψ_q]` at the currents.
+    fn flux(&self, i_d: f64, i_q: f64) -> [f64; 2] {
+        match &self.flux_map {
+            Some(map) => {
+                let point = map.eval(i_d, i_q);
+                [point.psi_d, point.psi_q]
+            }
+            None => [self.l_d * i_d + self.lambda_m, self.l_q * i_q],
+        }
+    }
+
+    /// Electromagnetic torque at the currents.
+    fn electromagnetic_torque(&self, i_d: f64, i_q: f64) -> f64 {
+        match &self.flux_map {
+            Some(_) => {
+                let [psi_d, psi_q] = self.flux(i_d, i_q);
+                1.5 * self.torque.n_p * (psi_d * i_q - psi_q * i_d)
+            }
+            None => self.torque.compute(i_d, i_q),
+        }
+    }
+
+    /// Friction and iron-loss drag on the rotor at speed `omega_m`.
+    fn drag(&self, omega_m: f64, i_d: f64, i_q: f64) -> f64 {
+        let [psi_d, psi_q] = self.flux(i_d, i_q);
+        self.b * omega_m
+            + self.friction.torque(omega_m).torque
+            + self
+                .iron_loss
+                .torque(self.n_p, omega_m, psi_d.hypot(psi_q))
+                .torque
+    }
+
+    /// Operating point at speed `omega_m` and currents `(i_d, i_q)`, with
+    /// the shaft torque left after the drag.
+    pub fn point(&self, omega_m: f64, i_d: f64, i_q: f64) -> MapPoint {
+        let omega_e = self.n_p * omega_m;
+        let [psi_d, psi_q] = self.flux(i_d, i_q);
+        let (v_d, v_q) = (
+            self.r_s * i_d - omega_e * psi_q,
+            self.r_s * i_q + omega_e * psi_d,
+        );
+        let torque = self.electromagnetic_torque(i_d, i_q) - self.drag(omega_m, i_d, i_q);
+        let p_e = 1.5 * (v_d * i_d + v_q * i_q);
+        let p_m = torque * omega_m;
+        MapPoint {
+            omega_m,
+            torque,
+            i_d,
+            i_q,
+            v_s: v_d.hypot(v_q),
+            p_e,
+            p_cu: 1.5 * self.r_s * (i_d * i_d + i_q * i_q),
+            p_fe: self.iron_loss.power(self.n_p, omega_m, psi_d.hypot(psi_q)),
+            p_fric: (self.b * omega_m + self.friction.torque(omega_m).torque) * omega_m,
+            efficiency: if p_e > 0.0 && p_m > 0.0 {
+                100.0 * p_m / p_e
+            } else {
+                0.0
+            },
+        }
+    }
+
+    /// The `i_q ≥ 0` delivering shaft torque `torque` at `i_d` within the
+    /// current limit, by bisection, or `None` when the limit is reached first.
+    fn q_current(&self, omega_m: f64, torque: f64, i_d: f64, i_max: f64) -> Option<f64> {
+        let excess = |i_q: f64| {
+            self.electromagnetic_torque(i_d, i_q) - self.drag(omega_m, i_d, i_q) - torque
+        };
+        let mut high = (i_max * i_max - i_d * i_d).max(0.0).sqrt();
+        if excess(high) < 0.0 {
+            return None;
+        }
+        let mut low = 0.0;
+        if excess(low) >= 0.0 {
+            return Some(low);
+        }
+        for _ in 0..ITERATIONS {
+            let mid = 0.5 * (low + high);
+            if excess(mid) < 0.0 {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Some(high)
+    }
+
+    /// Operating point at `i_d` delivering `torque`, if within the limits.
+    fn feasible(&self, omega_m: f64, torque: f64, i_d: f64, limits: Limits) -> Option<MapPoint> {
+        let i_q = self.q_current(omega_m, torque, i_d, limits.i_max)?;
+        let point = self.point(omega_m, i_d, i_q);
+        (point.v_s <= limits.v_max).then_some(point)
+    }
+
+    /// Least-loss operating point delivering shaft torque `torque` at speed
+    /// `omega_m`, or `None` when the limits do not allow it.
+    pub fn operate(&self, omega_m: f64, torque: f64, limits: Limits) -> Option<MapPoint> {
+        let i_d = |k: usize| -limits.i_max * k as f64 / SCAN_POINTS as f64;
+        let loss = |p: &MapPoint| p.p_cu + p.p_fe;
+        let (k_best, best) = (0..=SCAN_POINTS)
+            .filter_map(|k| Some((k, self.feasible(omega_m, torque, i_d(k), limits)?)))
+            .min_by(|(_, a), (_, b)| loss(a).total_cmp(&loss(b)))?;
+
+        // Golden-section search between the neighbours of the best scan point
+        let cost = |x: f64| {
+            self.feasible(omega_m, torque, x, limits)
+                .map_or(f64::INFINITY, |p| loss(&p))
+        };
+        let ratio = 0.5 * (5.0_f64.sqrt() - 1.0);
+        let (mut a, mut b) = (i_d(k_best + 1), i_d(k_best.saturating_sub(1)));
+        let (mut c, mut d) = (b - ratio * (b - a), a + ratio * (b - a));
+        let (mut f_c, mut f_d) = (cost(c), cost(d));
+        for _ in 0..ITERATIONS {
+            if f_c < f_d {
+                (b, d, f_d) = (d, c, f_c);
+                c = b - ratio * (b - a);
+                f_c = cost(c);
+            } else {
+                (a, c, f_c) = (c, d, f_d);
+                d = a + ratio * (b - a);
+                f_d = cost(d);
+            }
+        }
+        let refined = self.feasible(omega_m, torque, 0.5 * (a + b), limits);
+        Some(match refined {
+            Some(p) if loss(&p) < loss(&best) => p,
+            _ => best,
+        })
+    }
+
+    /// Largest shaft torque the limits allow at speed `omega_m`, by
+    /// bisection up to `torque_max`; zero when even no load is out of reach.
+    pub fn max_torque(&self, omega_m: f64, torque_max: f64, limits: Limits) -> f64 {
+        let reachable = |torque: f64| {
+            (0..=SCAN_POINTS).any(|k| {
+                let i_d = -limits.i_max * k as f64 / SCAN_POINTS as f64;
+                self.feasible(omega_m, torque, i_d, limits).is_some()
+            })
+        };
+        if !reachable(0.0) {
+            return 0.0;
+        }
+        if reachable(torque_max) {
+            return torque_max;
+        }
+        let (mut low, mut high) = (0.0, torque_max);
+        for _ in 0..ITERATIONS {
+            let mid = 0.5 * (low + high);
+            if reachable(mid) {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+}
+
+/// Operating points over a speed × torque grid and the capability envelope.
+#[derive(Clone, Debug, Default)]
+pub struct EfficiencyMap {
+    /// Speed grid (rad/s).
+    pub speeds: Vec<f64>,
+    /// Shaft torque grid (N·m).
+    pub torques: Vec<f64>,
+    /// Operating points, speed-major: index `k_ω · n_T + k_T`; `None` where
+    /// the limits do not allow the demand.
+    pub points: Vec<Option<MapPoint>>,
+    /// `[ω_m, T_max]` at each grid speed.
+    pub envelope: Vec<[f64; 2]>,
+}
+
+impl EfficiencyMap {
+    /// Map of `model` over `speed_points` speeds from 0 to `speed_max` and
+    /// `torque_points` torques from 0 to `torque_max`.
+    pub fn compute(
+        model: &SteadyStateModel,
+        limits: Limits,
+        (speed_max, speed_points): (f64, usize),
+        (torque_max, torque_points): (f64, usize),
+    ) -> Self {
+        let grid = |max: f64, n: usize| -> Vec<f64> {
+            let n = n.max(2);
+            (0..n).map(|k| max * k as f64 / (n - 1) as f64).collect()
+        };
+        let speeds = grid(speed_max, speed_points);
+        let torques = grid(torque_max, torque_points);
+        let points = speeds
+            .iter()
+            .flat_map(|&omega_m| {
+                torques
+                    .iter()
+                    .map(move |&torque| model.operate(omega_m, torque, limits))
+            })
+            .collect();
+        let envelope = speeds
+            .iter()
+            .map(|&omega_m| [omega_m, model.max_torque(omega_m, torque_max, limits)])
+            .collect();
+        Self {
+            speeds,
+            torques,
+            points,
+            envelope,
+        }
+    }
+
+    /// Operating point at speed index `k_w` and torque index `k_t`.
+    pub fn point(&self, k_w: usize, k_t: usize) -> Option<&MapPoint> {
+        self.points
+            .get(k_w * self.torques.len() + k_t)
+            .and_then(Option::as_ref)
+    }
+
+    /// Feasible operating points as CSV, one row per point.
+    pub fn csv(&self) -> String {
+        let mut csv = String::from("omega_m,torque,i_d,i_q,v_s,p_e,p_cu,p_fe,p_fric,efficiency\n");
+        for p in self.points.iter().flatten() {
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{},{},{},{}\n",
+                p.omega_m,
+                p.torque,
+                p.i_d,
+                p.i_q,
+                p.v_s,
+                p.p_e,
+                p.p_cu,
+                p.p_fe,
+                p.p_fric,
+                p.efficiency
+            ));
+        }
+        csv
+    }
+
+    /// Capability envelope as CSV.
+    pub fn envelope_csv(&self) -> String {
+        let mut csv = String::from("omega_m,torque_max\n");
+        for [omega_m, torque] in &self.envelope {
+            csv.push_str(&format!("{omega_m},{torque}\n"));
+        }
+        csv
+    }
+
+    /// Highest efficiency on the map, with its point.
+    pub fn peak(&self) -> Option<&MapPoint> {
+        self.points
+            .iter()
+            .flatten()
+            .max_by(|a, b| a.efficiency.total_cmp(&b.efficiency))
+    }
+
+    /// Line segments `[[ω_m, T]; 2]` of the iso-efficiency contour at `level`
+    /// (%), by marching squares over the cells with four feasible corners.
+    pub fn contour(&self, level: f64) -> Vec<[[f64; 2]; 2]> {
+        let mut segments = Vec::new();
+        for (k_w, speeds) in self.speeds.windows(2).enumerate() {
+            for (k_t, torques) in self.torques.windows(2).enumerate() {
+                let (&[w_0, w_1], &[t_0, t_1]) = (speeds, torques) else {
+                    continue;
+                };
+                // Corners counter-clockwise from (ω₀, T₀)
+                let corners = [
+                    ([w_0, t_0], self.point(k_w, k_t)),
+                    ([w_1, t_0], self.point(k_w + 1, k_t)),
+                    ([w_1, t_1], self.point(k_w + 1, k_t + 1)),
+                    ([w_0, t_1], self.point(k_w, k_t + 1)),
+                ];
+                let Some(values) = corners
+                    .iter()
+                    .map(|&(at, point)| point.map(|p| (at, p.efficiency)))
+                    .collect::<Option<Vec<_>>>()
+                else {
+                    continue;
+                };
+                let crossings: Vec<[f64; 2]> = (0..4)
+                    .filter_map(|k| {
+                        let (&(a, e_a), &(b, e_b)) = (values.get(k)?, values.get((k + 1) % 4)?);
+                        if (e_a < level) == (e_b < level) {
+                            return None;
+                        }
+                        let s = (level - e_a) / (e_b - e_a);
+                        Some([a[0] + s * (b[0] - a[0]), a[1] + s * (b[1] - a[1])])
+                    })
+                    .collect();
+                segments.extend(crossings.chunks_exact(2).filter_map(|pair| match pair {
+                    &[a, b] => Some([a, b]),
+                    _ => None,
+                }));
+            }
+        }
+        segments
+    }
+}
+
+/// A sink node that maps the efficiency and capability of the PMSM.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct EfficiencyMapNode {
+    /// Highest mapped speed (rad/s).
+    pub speed_max: f64,
+    /// Highest mapped shaft torque (N·m).
+    pub torque_max: f64,
+    /// Number of speeds on the grid.
+    pub speed_points: usize,
+    /// Number of torques on the grid.
+    pub torque_points: usize,
+    /// Peak phase current limit (A).
+    pub i_max: f64,
+    /// DC bus voltage (V); the phase voltage is limited to `V_dc/√3`.
+    pub v_dc: f64,
+    /// Map of the last run.
+    #[serde(skip)]
+    pub map: Option<EfficiencyMap>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for EfficiencyMapNode {
+    fn default() -> Self {
+        Self {
+            speed_max: 80.0,
+            torque_max: 12.0,
+            speed_points: 30,
+            torque_points: 30,
+            i_max: 10.0,
+            v_dc: 48.0,
+            map: None,
+            custom_size: None,
+        }
+    }
+}
+
+impl EfficiencyMapNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "Efficiency Map"
+    }
+
+    /// Input port list — empty; the map is computed from the machine nodes.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[]
+    }
+
+    /// Output port list — empty; this is a sink node.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[]
+    }
+
+    /// Header background color, shared with the Plot node.
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x40, 0xB0, 0x40)
+    }
+
+    /// Current and voltage limits: `I_max` and `V_dc/√3` (linear SVPWM).
+    pub fn limits(&self) -> Limits {
+        Limits {
+            i_max: self.i_max,
+            v_max: self.v_dc / 3.0_f64.sqrt(),
+        }
+    }
+
+    /// Map `model` over the node's grid.
+    pub fn evaluate(&mut self, model: &SteadyStateModel) {
+        self.map = Some(EfficiencyMap::compute(
+            model,
+            self.limits(),
+            (self.speed_max, self.speed_points),
+            (self.torque_max, self.torque_points),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EfficiencyMap, Limits, SteadyStateModel};
+    use crate::nodes::electrical::{ElectricalNode, IronLoss, IronLossModel};
+    use crate::nodes::mechanical::Friction;
+
+    /// The default surface-magnet machine delivers `(3/2)·N_p·λ_m·I_max` up to
+    /// base speed, loses torque in field weakening and none beyond its top
+    /// speed; every point balances its powers and stays inside the limits.
+    #[test]
+    fn envelope_and_power_balance() {
+        let electrical = ElectricalNode {
+            iron_loss: IronLoss {
+                model: IronLossModel::Resistance,
+                ..IronLoss::default()
+            },
+            ..ElectricalNode::default()
+        };
+        let model = SteadyStateModel::new(&electrical, None, 1e-3, Friction::default());
+        let limits = Limits {
+            i_max: 10.0,
+            v_max: 48.0 / 3.0_f64.sqrt(),
+        };
+        let rated = 1.5 * 4.0 * 0.175 * 10.0;
+        let low = model.max_torque(5.0, 20.0, limits);
+        assert!(
+            low < rated && low > 0.98 * rated,
+            "constant-torque region: {low} vs {rated}"
+        );
+        let weakened = model.max_torque(60.0, 20.0, limits);
+        assert!(
+            weakened > 0.0 && weakened < 0.5 * low,
+            "field weakening: {weakened}"
+        );
+        assert!(
+            model.max_torque(100.0, 20.0, limits).abs() < f64::EPSILON,
+            "beyond top speed"
+        );
+
+        let map = EfficiencyMap::compute(&model, limits, (80.0, 9), (12.0, 7));
+        let feasible: Vec<_> = map.points.iter().flatten().collect();
+        assert!(feasible.len() > 20, "feasible points: {}", feasible.len());
+        for p in &feasible {
+            let out = p.torque * p.omega_m + p.p_cu + p.p_fe + p.p_fric;
+            assert!(
+                (p.p_e - out).abs() < 1e-6 * p.p_e.abs().max(1.0),
+                "P_e {} vs {out} at ({}, {})",
+                p.p_e,
+                p.omega_m,
+                p.torque
+            );
+            assert!(p.i_d.hypot(p.i_q) <= limits.i_max + 1e-9, "current limit");
+            assert!(p.v_s <= limits.v_max + 1e-9, "voltage limit");
+        }
+        // Below base speed the least-loss point trades a little i_d for
+        // iron loss, beating i_d = 0
+        let best = map.point(1, 3).expect("feasible at low speed");
+        let aligned = model
+            .feasible(best.omega_m, best.torque, 0.0, limits)
+            .expect("i_d = 0 is feasible");
+        assert!(
+            best.i_d < 0.0 && best.i_d > -0.1 * best.i_q,
+            "slightly negative i_d: {}",
+            best.i_d
+        );
+        assert!(
+            best.p_cu + best.p_fe < aligned.p_cu + aligned.p_fe,
+            "loss {} vs {} at i_d = 0",
+            best.p_cu + best.p_fe,
+            aligned.p_cu + aligned.p_fe
+        );
+        assert_eq!(map.csv().lines().count(), feasible.len() + 1, "CSV rows");
+
+        let peak = map.peak().expect("feasible points").efficiency;
+        assert!(peak > 80.0 && peak < 100.0, "peak η {peak}");
+        assert!(!map.contour(0.5 * peak).is_empty(), "contour at half peak");
+    }
+}
@@ -0,0 +1,442 @@
+//! Extended Kalman filter node — PMSM states estimated from measured phase
+//! voltages and currents.
+//!
+//! The filter predicts with the current-state rotor-frame equations and
+//! analytic Jacobian that the machine solve also uses (see
+//! [`super::pmsm`]), on its own machine constants and without spatial
+//! harmonics, core loss or a flux map:
+//!
+//! ```text
+//! di_d/dt = (v_d − R_s·i_d + N_p·ω_m·L_q·i_q) / L_d
+//! di_q/dt = (v_q − R_s·i_q − N_p·ω_m·(L_d·i_d + λ_m)) / L_q
+//! dω_m/dt = (T_e − T_L − B·ω_m) / J,   T_e = (3/2)·N_p·(λ_m·i_q + (L_d − L_q)·i_d·i_q)
+//! dθ_e/dt = N_p·ω_m
+//! ```
+//!
+//! where `v_dq` are the measured phase voltages rotated by the estimated
+//! angle, so `∂v_d/∂θ_e = v_q` and `∂v_q/∂θ_e = −v_d`. A fifth state, the
+//! load torque `T_L` or the stator resistance `R_s`, can be estimated as a
+//! random walk. The measurement is the αβ current, the estimated dq current
+//! rotated forward by `θ_e`.
+//!
+//! Every sampling period `T_s` the state is predicted by one forward-Euler
+//! step with `Φ = I + F·T_s` and `P ← Φ·P·Φᵀ + Q·T_s`, then corrected with
+//! the sampled currents, updating `P` in Joseph form.
+
+use egui::Color32;
+use nalgebra::{Matrix2, Matrix2x5, Matrix5, Vector2, Vector5};
+
+use super::harmonics::SpatialHarmonics;
+use super::pmsm::{PmsmParams, RotorFrame, RotorJacobian};
+use crate::port::{PortType, PortValue};
+
+/// Index of the fifth, augmented state.
+const X_AUG: usize = 4;
+
+/// Optional fifth state estimated alongside the machine states.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum EkfAugment {
+    /// Machine states only; the load torque is taken as zero.
+    #[default]
+    None,
+    /// Load torque `T_L`.
+    LoadTorque,
+    /// Stator resistance `R_s`.
+    StatorResistance,
+}
+
+impl EkfAugment {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 3] = [Self::None, Self::LoadTorque, Self::StatorResistance];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::LoadTorque => "Load torque",
+            Self::StatorResistance => "Stator resistance",
+        }
+    }
+}
+
+/// Extended Kalman filter for the PMSM states.
+///
+/// Inputs: phase voltages `v_abc` and currents `i_abc` (Vector), and the true
+/// electrical angle `θ_e` (Signal, optional, for the error output).\
+/// Outputs: estimated `i_d`, `i_q`, `ω_m`, `θ_e` and augmented state `p`,
+/// angle error `θ_err` wrapped to `(−π, π]`, and the standard deviations
+/// `σ_ω`, `σ_θ`, `σ_p` from the covariance diagonal (all Signal).
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct EkfNode {
+    /// Augmented fifth state.
+    pub augment: EkfAugment,
+    /// Model stator resistance; the initial estimate when augmented (Ω).
+    pub r_s: f64,
+    /// Model d-axis inductance (H).
+    pub l_d: f64,
+    /// Model q-axis inductance (H).
+    pub l_q: f64,
+    /// Model PM flux linkage (Wb).
+    pub lambda_m: f64,
+    /// Number of pole pairs.
+    pub n_p: f64,
+    /// Model rotor inertia (kg·m²).
+    pub j: f64,
+    /// Model viscous friction (N·m·s/rad).
+    pub b: f64,
+    /// Filter sampling frequency (Hz).
+    pub f_sample: f64,
+    /// Initial angle estimate (rad).
+    pub theta_0: f64,
+    /// Process noise intensities `[i_d, i_q, ω_m, θ_e, p]`, the diagonal of
+    /// `Q` per second.
+    pub q: [f64; 5],
+    /// Variance of each measured phase current (A²).
+    pub r: f64,
+    /// Initial covariance diagonal `[i_d, i_q, ω_m, θ_e, p]`.
+    pub p_0: [f64; 5],
+    /// Estimated d-axis current time-series produced after simulation.
+    #[serde(skip)]
+    pub output_i_d: Option<PortValue>,
+    /// Estimated q-axis current time-series produced after simulation.
+    #[serde(skip)]
+    pub output_i_q: Option<PortValue>,
+    /// Estimated speed time-series produced after simulation.
+    #[serde(skip)]
+    pub output_omega_m: Option<PortValue>,
+    /// Estimated angle time-series produced after simulation.
+    #[serde(skip)]
+    pub output_theta_e: Option<PortValue>,
+    /// Augmented state time-series produced after simulation.
+    #[serde(skip)]
+    pub output_param: Option<PortValue>,
+    /// Angle error time-series produced after simulation.
+    #[serde(skip)]
+    pub output_error: Option<PortValue>,
+    /// Speed standard deviation time-series produced after simulation.
+    #[serde(skip)]
+    pub output_sigma_omega: Option<PortValue>,
+    /// Angle standard deviation time-series produced after simulation.
+    #[serde(skip)]
+    pub output_sigma_theta: Option<PortValue>,
+    /// Augmented state standard deviation time-series produced after
+    /// simulation.
+    #[serde(skip)]
+    pub output_sigma_param: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for EkfNode {
+    /// Model of the default Electrical and Mechanical nodes.
+    fn default() -> Self {
+        Self {
+            augment: EkfAugment::None,
+            r_s: 1.2,
+            l_d: 0.008,
+            l_q: 0.008,
+            lambda_m: 0.175,
+            n_p: 4.0,
+            j: 0.0008,
+            b: 0.001,
+            f_sample: 20_000.0,
+            theta_0: 0.0,
+            q: [10.0, 10.0, 1e4, 1e-2, 10.0],
+            r: 1e-2,
+            p_0: [1.0, 1.0, 100.0, 1.0, 1.0],
+            output_i_d: None,
+            output_i_q: None,
+            output_omega_m: None,
+            output_theta_e: None,
+            output_param: None,
+            output_error: None,
+            output_sigma_omega: None,
+            output_sigma_theta: None,
+            output_sigma_param: None,
+            custom_size: None,
+        }
+    }
+}
+
+/// Filter estimates at the sampling instants.
+#[derive(Clone, Debug, Default)]
+pub struct EkfSeries {
+    /// Estimated d-axis current.
+    pub i_d: Vec<[f64; 2]>,
+    /// Estimated q-axis current.
+    pub i_q: Vec<[f64; 2]>,
+    /// Estimated mechanical speed.
+    pub omega_m: Vec<[f64; 2]>,
+    /// Estimated electrical angle (unwrapped).
+    pub theta_e: Vec<[f64; 2]>,
+    /// Estimated augmented state.
+    pub param: Vec<[f64; 2]>,
+    /// Standard deviation of the speed estimate.
+    pub sigma_omega: Vec<[f64; 2]>,
+    /// Standard deviation of the angle estimate.
+    pub sigma_theta: Vec<[f64; 2]>,
+    /// Standard deviation of the augmented state.
+    pub sigma_param: Vec<[f64; 2]>,
+}
+
+impl EkfSeries {
+    /// Append the estimate `x` with covariance `p` at time `t`.
+    fn push(&mut self, t: f64, x: &Vector5<f64>, p: &Matrix5<f64>) {
+        let sigma = |k: usize| p[(k, k)].max(0.0).sqrt();
+        self.i_d.push([t, x[0]]);
+        self.i_q.push([t, x[1]]);
+        self.omega_m.push([t, x[2]]);
+        self.theta_e.push([t, x[3]]);
+        self.param.push([t, x[X_AUG]]);
+        self.sigma_omega.push([t, sigma(2)]);
+        self.sigma_theta.push([t, sigma(3)]);
+        self.sigma_param.push([t, sigma(X_AUG)]);
+    }
+}
+
+impl EkfNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "EKF"
+    }
+
+    /// Input port descriptors: phase voltages, phase currents and the true
+    /// angle.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("v_abc", PortType::Vector),
+            ("i_abc", PortType::Vector),
+            ("θ_e", PortType::Signal),
+        ]
+    }
+
+    /// Output port descriptors: state estimates, angle error and standard
+    /// deviations.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("i_d", PortType::Signal),
+            ("i_q", PortType::Signal),
+            ("ω_m", PortType::Signal),
+            ("θ_e", PortType::Signal),
+            ("p", PortType::Signal),
+            ("θ_err", PortType::Signal),
+            ("σ_ω", PortType::Signal),
+            ("σ_θ", PortType::Signal),
+            ("σ_p", PortType::Signal),
+        ]
+    }
+
+    /// Header colour (teal, shared by the measurement nodes).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x30, 0x80, 0x80)
+    }
+
+    /// Stator resistance and load torque for state `x`.
+    fn r_s_and_t_l(&self, x: &Vector5<f64>) -> (f64, f64) {
+        match self.augment {
+            EkfAugment::None => (self.r_s, 0.0),
+            EkfAugment::LoadTorque => (self.r_s, x[X_AUG]),
+            EkfAugment::StatorResistance => (x[X_AUG], 0.0),
+        }
+    }
+
+    /// State derivative `f(x, v)` and its Jacobian `F = ∂f/∂x` for measured
+    /// voltages `v_ab` (αβ).
+    fn model(&self, x: &Vector5<f64>, [v_a, v_b]: [f64; 2]) -> (Vector5<f64>, Matrix5<f64>) {
+        let rotor = [x[0], x[1], x[2], x[3]];
+        let (r_s, t_l) = self.r_s_and_t_l(x);
+        let (l_d, l_q, j) = (
+            self.l_d.max(f64::EPSILON),
+            self.l_q.max(f64::EPSILON),
+            self.j.max(f64::EPSILON),
+        );
+        let harmonics = SpatialHarmonics::default();
+        let model = RotorFrame {
+            params: PmsmParams {
+                r_s,
+                l_d,
+                l_q,
+                lambda_m: self.lambda_m,
+                n_p: self.n_p,
+                j,
+                b: self.b,
+                torque_n_p: self.n_p,
+                torque_lambda_m: self.lambda_m,
+                torque_l_d: l_d,
+                torque_l_q: l_q,
+            },
+            harmonics: &harmonics,
+        };
+        let (sin, cos) = x[3].sin_cos();
+        let v_d = v_a * cos + v_b * sin;
+        let v_q = -v_a * sin + v_b * cos;
+
+        let [f_d, f_q, f_wm, f_te] = model.derivatives(rotor, [v_d, v_q], t_l);
+        let f = Vector5::new(f_d, f_q, f_wm, f_te, 0.0);
+
+        // The measured voltages are rotated by the estimated angle, and the
+        // augmented state adds ∂f/∂p
+        let RotorJacobian {
+            dx: [row_d, row_q, row_wm, row_te],
+            dv: [dv_d, dv_q],
+        } = model.jacobian(rotor);
+        let (did_dp, diq_dp, dwm_dp) = match self.augment {
+            EkfAugment::None => (0.0, 0.0, 0.0),
+            EkfAugment::LoadTorque => (0.0, 0.0, -1.0 / j),
+            EkfAugment::StatorResistance => (-x[0] / l_d, -x[1] / l_q, 0.0),
+        };
+        #[rustfmt::skip]
+        let jacobian = Matrix5::new(
+            row_d[0], row_d[1], row_d[2], row_d[3] + dv_d * v_q, did_dp,
+            row_q[0], row_q[1], row_q[2], row_q[3] - dv_q * v_d, diq_dp,
+            row_wm[0], row_wm[1], row_wm[2], row_wm[3], dwm_dp,
+            row_te[0], row_te[1], row_te[2], row_te[3], 0.0,
+            0.0, 0.0, 0.0, 0.0, 0.0,
+        );
+        (f, jacobian)
+    }
+
+    /// Predicted αβ current `h(x)` and its Jacobian `H = ∂h/∂x`.
+    fn measurement(x: &Vector5<f64>) -> (Vector2<f64>, Matrix2x5<f64>) {
+        let (i_d, i_q) = (x[0], x[1]);
+        let (sin, cos) = x[3].sin_cos();
+        let i_a = i_d * cos - i_q * sin;
+        let i_b = i_d * sin + i_q * cos;
+        #[rustfmt::skip]
+        let jacobian = Matrix2x5::new(
+            cos, -sin, 0.0, -i_b, 0.0,
+            sin, cos, 0.0, i_a, 0.0,
+        );
+        (Vector2::new(i_a, i_b), jacobian)
+    }
+
+    /// Run the filter from `t0` to `t1` on the measured αβ voltages `v_ab`
+    /// and currents `i_ab`.
+    pub fn estimate(
+        &self,
+        t0: f64,
+        t1: f64,
+        v_ab: impl Fn(f64) -> [f64; 2],
+        i_ab: impl Fn(f64) -> [f64; 2],
+    ) -> EkfSeries {
+        let t_s = 1.0 / self.f_sample.max(f64::EPSILON);
+        let augmented = self.augment != EkfAugment::None;
+        // Without augmentation the fifth state has no variance and stays put
+        let aug_only = |value: f64| if augmented { value } else { 0.0 };
+        let [q_id, q_iq, q_wm, q_te, q_p] = self.q;
+        let q = Matrix5::from_diagonal(&Vector5::new(q_id, q_iq, q_wm, q_te, aug_only(q_p))) * t_s;
+        let [p_id, p_iq, p_wm, p_te, p_p] = self.p_0;
+        let r = Matrix2::from_diagonal_element(self.r);
+
+        // Start at rest with the measured currents seen at the initial angle
+        let [m_a, m_b] = i_ab(t0);
+        let (sin, cos) = self.theta_0.sin_cos();
+        let param_0 = match self.augment {
+            EkfAugment::StatorResistance => self.r_s,
+            EkfAugment::None | EkfAugment::LoadTorque => 0.0,
+        };
+        let mut x = Vector5::new(
+            m_a * cos + m_b * sin,
+            -m_a * sin + m_b * cos,
+            0.0,
+            self.theta_0,
+            param_0,
+        );
+        let mut p = Matrix5::from_diagonal(&Vector5::new(p_id, p_iq, p_wm, p_te, aug_only(p_p)));
+
+        let mut series = EkfSeries::default();
+        series.push(t0, &x, &p);
+        let steps = ((t1 - t0) / t_s).floor().max(0.0) as usize;
+        for k in 1..=steps {
+            let t_prev = t0 + (k - 1) as f64 * t_s;
+            let t = t0 + k as f64 * t_s;
+
+            // Predict over one period with the voltage held from its start
+            let (f, jacobian) = self.model(&x, v_ab(t_prev));
+            let phi = Matrix5::identity() + jacobian * t_s;
+            x += f * t_s;
+            p = phi * p * phi.transpose() + q;
+
+            // Correct with the sampled currents
+            let (h, jac_h) = Self::measurement(&x);
+            let s = jac_h * p * jac_h.transpose() + r;
+            if let Some(s_inv) = s.try_inverse() {
+                let gain = p * jac_h.transpose() * s_inv;
+                let [m_a, m_b] = i_ab(t);
+                x += gain * (Vector2::new(m_a, m_b) - h);
+                let i_kh = Matrix5::identity() - gain * jac_h;
+                p = i_kh * p * i_kh.transpose() + gain * r * gain.transpose();
+                p = (p + p.transpose()) * 0.5;
+            }
+            series.push(t, &x, &p);
+        }
+        series
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EkfAugment, EkfNode};
+
+    /// Measured αβ voltages and currents of a machine turning at a constant
+    /// electrical speed `omega_e` with steady dq currents, in the
+    /// steady state of the filter's model.
+    fn machine(
+        ekf: &EkfNode,
+        omega_e: f64,
+        [i_d, i_q]: [f64; 2],
+    ) -> impl Fn(f64) -> ([f64; 2], [f64; 2]) {
+        let v_d = ekf.r_s * i_d - omega_e * ekf.l_q * i_q;
+        let v_q = ekf.r_s * i_q + omega_e * (ekf.l_d * i_d + ekf.lambda_m);
+        move |t: f64| {
+            let (sin, cos) = (omega_e * t).sin_cos();
+            let rotate = |d: f64, q: f64| [d * cos - q * sin, d * sin + q * cos];
+            (rotate(v_d, v_q), rotate(i_d, i_q))
+        }
+    }
+
+    /// From rest at the true initial angle, the filter converges onto a
+    /// spinning rotor's angle, speed and currents, and onto the load torque
+    /// that holds the speed.
+    #[test]
+    fn tracks_rotating_rotor() {
+        let ekf = EkfNode {
+            augment: EkfAugment::LoadTorque,
+            ..EkfNode::default()
+        };
+        let omega_e = 400.0;
+        let feed = machine(&ekf, omega_e, [-0.5, 3.0]);
+        let series = ekf.estimate(0.0, 0.2, |t| feed(t).0, |t| feed(t).1);
+        let last = |s: &[[f64; 2]]| s.last().expect("samples")[1];
+        let theta = last(&series.theta_e);
+        let error = (theta - omega_e * 0.2 + std::f64::consts::PI)
+            .rem_euclid(std::f64::consts::TAU)
+            - std::f64::consts::PI;
+        assert!(error.abs() < 0.02, "angle error {error}");
+        let omega = last(&series.omega_m) * ekf.n_p;
+        assert!((omega - omega_e).abs() < 0.01 * omega_e, "speed {omega}");
+        assert!((last(&series.i_q) - 3.0).abs() < 0.05, "i_q");
+        assert!(last(&series.sigma_theta) < 0.05, "angle variance settles");
+        let t_l = 1.5 * ekf.n_p * ekf.lambda_m * 3.0 - ekf.b * omega_e / ekf.n_p;
+        let t_l_hat = last(&series.param);
+        assert!((t_l_hat - t_l).abs() < 0.05 * t_l, "load torque {t_l_hat}");
+    }
+
+    /// With the stator resistance augmented, a wrong initial guess is
+    /// pulled towards the machine's resistance.
+    #[test]
+    fn estimates_stator_resistance() {
+        let truth = EkfNode::default();
+        let ekf = EkfNode {
+            augment: EkfAugment::StatorResistance,
+            r_s: 0.8,
+            ..EkfNode::default()
+        };
+        let feed = machine(&truth, 400.0, [-1.0, 3.0]);
+        let series = ekf.estimate(0.0, 0.5, |t| feed(t).0, |t| feed(t).1);
+        let r_s = series.param.last().expect("samples")[1];
+        assert!((r_s - truth.r_s).abs() < 0.1, "R_s estimate {r_s}");
+    }
+}
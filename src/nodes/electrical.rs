@@ -2,8 +2,121 @@
 
 use egui::Color32;
 
+use super::flux_map::FluxMap;
+use super::harmonics::SpatialHarmonics;
 use crate::port::{PortType, PortValue};
 
+/// Rotor speed (rad/s) over which the hysteresis drag changes sign.
+///
+/// Hysteresis loss is proportional to `|f_e|`, so its torque is a signum of
+/// speed; smoothing it over a small band keeps the RHS continuous at standstill.
+const HYSTERESIS_OMEGA: f64 = 0.1;
+
+/// Core-loss model of the stator iron.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum IronLossModel {
+    /// No core loss.
+    #[default]
+    None,
+    /// Equivalent resistance across the back-EMF: `P_fe = (3/2)·ω_e²·|ψ|² / R_fe`.
+    Resistance,
+    /// Steinmetz split: `P_fe = k_h·|f_e|·|ψ|^β + k_e·f_e²·|ψ|²`.
+    Steinmetz,
+}
+
+impl IronLossModel {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 3] = [Self::None, Self::Resistance, Self::Steinmetz];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Resistance => "R_fe",
+            Self::Steinmetz => "Steinmetz",
+        }
+    }
+}
+
+/// Core-loss parameters.
+///
+/// The loss is drawn from the rotor as a drag torque `T_fe = P_fe / ω_m`, so it
+/// appears in the mechanical equation rather than in the stator currents.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct IronLoss {
+    /// Which loss model is active.
+    pub model: IronLossModel,
+    /// Equivalent iron-loss resistance (Ω).
+    pub r_fe: f64,
+    /// Hysteresis coefficient `k_h` (W/(Hz·Wb^β)).
+    pub k_h: f64,
+    /// Eddy-current coefficient `k_e` (W/(Hz²·Wb²)).
+    pub k_e: f64,
+    /// Steinmetz flux exponent `β`.
+    pub beta: f64,
+}
+
+impl Default for IronLoss {
+    fn default() -> Self {
+        Self {
+            model: IronLossModel::None,
+            r_fe: 200.0,
+            k_h: 3.0,
+            k_e: 0.02,
+            beta: 2.0,
+        }
+    }
+}
+
+/// Core-loss drag torque and its partial derivatives.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct IronLossTorque {
+    /// Drag torque `T_fe` (N·m), opposing the rotation.
+    pub torque: f64,
+    /// `∂T_fe/∂ω_m`.
+    pub d_omega: f64,
+    /// `∂T_fe/∂|ψ|`.
+    pub d_psi: f64,
+}
+
+impl IronLoss {
+    /// Drag torque at rotor speed `omega_m` and stator flux magnitude `psi`.
+    pub fn torque(&self, n_p: f64, omega_m: f64, psi: f64) -> IronLossTorque {
+        match self.model {
+            IronLossModel::None => IronLossTorque::default(),
+            IronLossModel::Resistance => {
+                // P/ω_m = (3/2)·N_p²·ω_m·|ψ|² / R_fe
+                let k = 1.5 * n_p * n_p / self.r_fe.max(f64::EPSILON);
+                IronLossTorque {
+                    torque: k * omega_m * psi * psi,
+                    d_omega: k * psi * psi,
+                    d_psi: 2.0 * k * omega_m * psi,
+                }
+            }
+            IronLossModel::Steinmetz => {
+                // f_e = N_p·ω_m / 2π
+                let f_per_omega = n_p / std::f64::consts::TAU;
+                let sign = (omega_m / HYSTERESIS_OMEGA).tanh();
+                let d_sign = (1.0 - sign * sign) / HYSTERESIS_OMEGA;
+                let hyst = self.k_h * f_per_omega * psi.powf(self.beta);
+                let eddy = self.k_e * f_per_omega * f_per_omega * psi * psi;
+                IronLossTorque {
+                    torque: hyst * sign + eddy * omega_m,
+                    d_omega: hyst * d_sign + eddy,
+                    d_psi: self.k_h * f_per_omega * self.beta * psi.powf(self.beta - 1.0) * sign
+                        + 2.0 * self.k_e * f_per_omega * f_per_omega * psi * omega_m,
+                }
+            }
+        }
+    }
+
+    /// Core-loss power `P_fe = T_fe·ω_m` (W).
+    pub fn power(&self, n_p: f64, omega_m: f64, psi: f64) -> f64 {
+        self.torque(n_p, omega_m, psi).torque * omega_m
+    }
+}
+
 /// Node representing the PMSM electrical subsystem.
 ///
 /// Implements the d/q-axis stator voltage equations:
@@ -11,6 +124,22 @@ use crate::port::{PortType, PortValue};
 /// L_d * di_d/dt = v_d - R_s * i_d + N_p * ω_m * L_q * i_q
 /// L_q * di_q/dt = v_q - R_s * i_q - N_p * ω_m * (L_d * i_d + λ_m)
 /// ```
+///
+/// With a flux map loaded, the states become the flux linkages and saturation
+/// and cross-coupling are taken from the map instead of `L_d`, `L_q`, `λ_m`:
+/// ```text
+/// dψ_d/dt = v_d - R_s * i_d + N_p * ω_m * ψ_q
+/// dψ_q/dt = v_q - R_s * i_q - N_p * ω_m * ψ_d
+/// (i_d, i_q) = ψ⁻¹(ψ_d, ψ_q)
+/// ```
+///
+/// `R_s` is scaled to the winding temperature, and the optional core loss
+/// (see [`IronLoss`]) loads the rotor. Copper and iron losses are exported as
+/// the `P_cu` and `P_fe` outputs.
+///
+/// Optional back-EMF harmonics add a rotor-angle dependent term `Δψ(θ_e)` to
+/// the PM flux, and a cogging profile adds torque ripple; see
+/// [`SpatialHarmonics`].
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct ElectricalNode {
@@ -28,12 +157,40 @@ pub struct ElectricalNode {
     pub i_d_0: f64,
     /// Initial q-axis current (A)
     pub i_q_0: f64,
+    /// Winding temperature (°C); a thermal network feeding back `R_s`
+    /// replaces it with its winding temperature
+    pub t_winding: f64,
+    /// Reference temperature at which `r_s` is specified (°C)
+    pub t_ref: f64,
+    /// Temperature coefficient of the winding resistance (1/K)
+    pub alpha_cu: f64,
+    /// Temperature coefficient of the PM flux linkage (1/K), negative for
+    /// magnets that weaken when hot
+    pub alpha_pm: f64,
+    /// Core-loss model
+    pub iron_loss: IronLoss,
+    /// Back-EMF harmonics and cogging torque
+    pub harmonics: Box<SpatialHarmonics>,
+    /// Flux-linkage map; replaces the linear inductance model when present
+    pub flux_map: Option<Box<FluxMap>>,
+    /// CSV text being edited in the flux-map import box
+    #[serde(skip)]
+    pub flux_csv: String,
+    /// Error from the last flux-map import, shown in the node body
+    #[serde(skip)]
+    pub flux_csv_error: Option<String>,
     /// Output signal for d-axis current, populated after simulation
     #[serde(skip)]
     pub output_i_d: Option<PortValue>,
     /// Output signal for q-axis current, populated after simulation
     #[serde(skip)]
     pub output_i_q: Option<PortValue>,
+    /// Output signal for copper loss, populated after simulation
+    #[serde(skip)]
+    pub output_p_cu: Option<PortValue>,
+    /// Output signal for iron loss, populated after simulation
+    #[serde(skip)]
+    pub output_p_fe: Option<PortValue>,
     /// User-defined node size override (width, height).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub custom_size: Option<[f32; 2]>,
@@ -49,8 +206,19 @@ impl Default for ElectricalNode {
             n_p: 4.0,
             i_d_0: 0.0,
             i_q_0: 0.0,
+            t_winding: 20.0,
+            t_ref: 20.0,
+            alpha_cu: 0.00393,
+            alpha_pm: -0.0012,
+            iron_loss: IronLoss::default(),
+            harmonics: Box::default(),
+            flux_map: None,
+            flux_csv: String::new(),
+            flux_csv_error: None,
             output_i_d: None,
             output_i_q: None,
+            output_p_cu: None,
+            output_p_fe: None,
             custom_size: None,
         }
     }
@@ -71,13 +239,85 @@ impl ElectricalNode {
         ]
     }
 
-    /// Output port descriptors: d/q current waveforms as time-series signals.
+    /// Output port descriptors: d/q currents and copper/iron losses as time-series signals.
     pub fn output_ports() -> &'static [(&'static str, PortType)] {
-        &[("i_d", PortType::Signal), ("i_q", PortType::Signal)]
+        &[
+            ("i_d", PortType::Signal),
+            ("i_q", PortType::Signal),
+            ("P_cu", PortType::Signal),
+            ("P_fe", PortType::Signal),
+        ]
     }
 
     /// Header color for this node in the graph canvas.
     pub fn header_color() -> Color32 {
         Color32::from_rgb(0xB0, 0x40, 0x40)
     }
+
+    /// Stator resistance at winding temperature `temp` (°C).
+    pub fn r_s_at(&self, temp: f64) -> f64 {
+        self.r_s * (1.0 + self.alpha_cu * (temp - self.t_ref))
+    }
+
+    /// PM flux linkage at magnet temperature `temp` (°C).
+    pub fn lambda_m_at(&self, temp: f64) -> f64 {
+        self.lambda_m * (1.0 + self.alpha_pm * (temp - self.t_ref))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ElectricalNode, IronLoss, IronLossModel};
+
+    /// The analytic partials of the drag torque match finite differences.
+    #[test]
+    fn iron_loss_partials_match_finite_differences() {
+        let (n_p, omega, psi, h) = (4.0, 0.3, 0.2, 1e-6);
+        for model in [IronLossModel::Resistance, IronLossModel::Steinmetz] {
+            let loss = IronLoss {
+                model,
+                beta: 1.8,
+                ..IronLoss::default()
+            };
+            let at = loss.torque(n_p, omega, psi);
+            let d_omega = (loss.torque(n_p, omega + h, psi).torque
+                - loss.torque(n_p, omega - h, psi).torque)
+                / (2.0 * h);
+            let d_psi = (loss.torque(n_p, omega, psi + h).torque
+                - loss.torque(n_p, omega, psi - h).torque)
+                / (2.0 * h);
+            assert!(
+                (at.d_omega - d_omega).abs() < 1e-6 * d_omega.abs().max(1.0),
+                "{model:?} ∂T/∂ω: {} vs {d_omega}",
+                at.d_omega
+            );
+            assert!(
+                (at.d_psi - d_psi).abs() < 1e-6 * d_psi.abs().max(1.0),
+                "{model:?} ∂T/∂ψ: {} vs {d_psi}",
+                at.d_psi
+            );
+            assert!(
+                loss.power(n_p, -omega, psi) > 0.0,
+                "{model:?} loss must be positive in reverse"
+            );
+        }
+    }
+
+    /// Winding resistance and PM flux follow their linear temperature
+    /// coefficients.
+    #[test]
+    fn resistance_scales_with_temperature() {
+        let e = ElectricalNode::default();
+        assert!((e.r_s_at(e.t_ref) - e.r_s).abs() < 1e-15, "r_s at T_ref");
+        let hot = e.r_s_at(e.t_ref + 100.0);
+        assert!(
+            (hot / e.r_s - 1.393).abs() < 1e-9,
+            "100 K rise adds 39.3 %: {hot}"
+        );
+        let weak = e.lambda_m_at(e.t_ref + 100.0);
+        assert!(
+            (weak / e.lambda_m - 0.88).abs() < 1e-9,
+            "100 K rise weakens the magnet by 12 %: {weak}"
+        );
+    }
 }
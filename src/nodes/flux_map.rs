@@ -0,0 +1,414 @@
+//! Flux-linkage maps `ψ_d(i_d, i_q)`, `ψ_q(i_d, i_q)` for saturated machines.
+//!
+//! A map is a rectangular table over an `i_d × i_q` grid, typically exported
+//! from FEA. Lookups return the flux linkages together with the incremental
+//! inductances `∂ψ/∂i`, which the solver needs both to invert the map
+//! (flux-based states → currents) and for its analytic Jacobian.
+
+/// Interpolation scheme used between table points.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum MapInterpolation {
+    /// Piecewise bilinear: exact at the grid, derivatives jump across cells.
+    Bilinear,
+    /// Tensor-product cubic Hermite with finite-difference slopes (C¹).
+    #[default]
+    Spline,
+}
+
+impl MapInterpolation {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 2] = [Self::Spline, Self::Bilinear];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Bilinear => "Bilinear",
+            Self::Spline => "Spline",
+        }
+    }
+}
+
+/// Errors raised while importing a flux map.
+#[derive(Debug, PartialEq, Eq)]
+pub enum FluxMapError {
+    /// A data line does not hold four numeric columns.
+    Parse {
+        /// 1-based line number in the input.
+        line: usize,
+        /// Offending line content.
+        content: String,
+    },
+    /// The rows do not cover every `(i_d, i_q)` grid point exactly once.
+    NotAGrid,
+    /// Fewer than two distinct values along one axis.
+    TooSmall,
+}
+
+impl std::fmt::Display for FluxMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse { line, content } => {
+                write!(
+                    f,
+                    "line {line}: expected `i_d, i_q, ψ_d, ψ_q`, got {content:?}"
+                )
+            }
+            Self::NotAGrid => write!(f, "rows do not form a complete i_d × i_q grid"),
+            Self::TooSmall => write!(f, "need at least two i_d and two i_q values"),
+        }
+    }
+}
+
+impl std::error::Error for FluxMapError {}
+
+/// Flux linkages and incremental inductances at one operating point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FluxPoint {
+    /// d-axis flux linkage `ψ_d` (Wb).
+    pub psi_d: f64,
+    /// q-axis flux linkage `ψ_q` (Wb).
+    pub psi_q: f64,
+    /// `∂ψ_d/∂i_d` (H).
+    pub l_dd: f64,
+    /// `∂ψ_d/∂i_q` (H).
+    pub l_dq: f64,
+    /// `∂ψ_q/∂i_d` (H).
+    pub l_qd: f64,
+    /// `∂ψ_q/∂i_q` (H).
+    pub l_qq: f64,
+}
+
+/// Tabulated flux-linkage map over a rectangular current grid.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct FluxMap {
+    /// Strictly increasing d-axis current grid (A).
+    i_d: Vec<f64>,
+    /// Strictly increasing q-axis current grid (A).
+    i_q: Vec<f64>,
+    /// `ψ_d` samples, row-major: index `k_d · n_q + k_q` (Wb).
+    psi_d: Vec<f64>,
+    /// `ψ_q` samples, same layout as `psi_d` (Wb).
+    psi_q: Vec<f64>,
+    /// Interpolation between grid points.
+    pub interpolation: MapInterpolation,
+}
+
+impl FluxMap {
+    /// Parse a map from CSV text with columns `i_d, i_q, ψ_d, ψ_q`.
+    ///
+    /// One row per grid point, in any order. Commas, semicolons, tabs or
+    /// spaces separate columns; blank lines, `#` comments and non-numeric
+    /// header lines before the first data row are skipped.
+    ///
+    /// # Errors
+    ///
+    /// - [`FluxMapError::Parse`] — a data line is malformed.
+    /// - [`FluxMapError::TooSmall`] — an axis has fewer than two values.
+    /// - [`FluxMapError::NotAGrid`] — grid points are missing or repeated.
+    pub fn from_csv(text: &str) -> Result<Self, FluxMapError> {
+        let mut rows: Vec<[f64; 4]> = Vec::new();
+        for (n, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<f64> = line
+                .split([',', ';', '\t', ' '])
+                .filter(|f| !f.is_empty())
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .unwrap_or_default();
+            match fields.as_slice() {
+                &[i_d, i_q, psi_d, psi_q] => rows.push([i_d, i_q, psi_d, psi_q]),
+                // A header is only allowed before the data.
+                _ if rows.is_empty() && line.chars().any(char::is_alphabetic) => {}
+                _ => {
+                    return Err(FluxMapError::Parse {
+                        line: n + 1,
+                        content: line.to_owned(),
+                    });
+                }
+            }
+        }
+
+        let axis = |col: usize| {
+            let mut values: Vec<f64> = rows.iter().filter_map(|r| r.get(col).copied()).collect();
+            values.sort_by(f64::total_cmp);
+            values.dedup();
+            values
+        };
+        let (i_d, i_q) = (axis(0), axis(1));
+        if i_d.len() < 2 || i_q.len() < 2 {
+            return Err(FluxMapError::TooSmall);
+        }
+        if rows.len() != i_d.len() * i_q.len() {
+            return Err(FluxMapError::NotAGrid);
+        }
+
+        let mut psi_d = vec![f64::NAN; rows.len()];
+        let mut psi_q = vec![f64::NAN; rows.len()];
+        for &[d, q, pd, pq] in &rows {
+            let k_d = i_d.partition_point(|&v| v < d);
+            let k_q = i_q.partition_point(|&v| v < q);
+            let k = k_d * i_q.len() + k_q;
+            match (psi_d.get_mut(k), psi_q.get_mut(k)) {
+                (Some(slot_d), Some(slot_q)) if slot_d.is_nan() => {
+                    *slot_d = pd;
+                    *slot_q = pq;
+                }
+                _ => return Err(FluxMapError::NotAGrid),
+            }
+        }
+
+        Ok(Self {
+            i_d,
+            i_q,
+            psi_d,
+            psi_q,
+            interpolation: MapInterpolation::default(),
+        })
+    }
+
+    /// Build the map of a linear machine, `ψ_d = L_d·i_d + λ_m`, `ψ_q = L_q·i_q`.
+    ///
+    /// Useful as a starting point and to cross-check the map-based model.
+    pub fn linear(l_d: f64, l_q: f64, lambda_m: f64, i_max: f64, points: usize) -> Self {
+        let points = points.max(2);
+        let grid: Vec<f64> = (0..points)
+            .map(|k| -i_max + 2.0 * i_max * k as f64 / (points - 1) as f64)
+            .collect();
+        let mut psi_d = Vec::with_capacity(points * points);
+        let mut psi_q = Vec::with_capacity(points * points);
+        for &d in &grid {
+            for &q in &grid {
+                psi_d.push(l_d * d + lambda_m);
+                psi_q.push(l_q * q);
+            }
+        }
+        Self {
+            i_d: grid.clone(),
+            i_q: grid,
+            psi_d,
+            psi_q,
+            interpolation: MapInterpolation::default(),
+        }
+    }
+
+    /// Grid size `(n_d, n_q)`.
+    pub fn shape(&self) -> (usize, usize) {
+        (self.i_d.len(), self.i_q.len())
+    }
+
+    /// Flux linkages and incremental inductances at `(i_d, i_q)`.
+    ///
+    /// Outside the table the map is extended linearly with the boundary slope.
+    pub fn eval(&self, i_d: f64, i_q: f64) -> FluxPoint {
+        let (psi_d, l_dd, l_dq) = self.lookup(&self.psi_d, i_d, i_q);
+        let (psi_q, l_qd, l_qq) = self.lookup(&self.psi_q, i_d, i_q);
+        FluxPoint {
+            psi_d,
+            psi_q,
+            l_dd,
+            l_dq,
+            l_qd,
+            l_qq,
+        }
+    }
+
+    /// Currents `(i_d, i_q)` producing the flux linkages `(ψ_d, ψ_q)`.
+    ///
+    /// Solved by Newton iteration on the map, starting from `guess`. The
+    /// incremental inductance matrix is positive definite for physical maps,
+    /// so a handful of iterations suffice. Returns `None` if the iteration
+    /// does not converge.
+    pub fn currents(&self, psi_d: f64, psi_q: f64, guess: (f64, f64)) -> Option<(f64, f64)> {
+        let (mut i_d, mut i_q) = guess;
+        for _ in 0..50 {
+            let p = self.eval(i_d, i_q);
+            let (r_d, r_q) = (p.psi_d - psi_d, p.psi_q - psi_q);
+            let det = p.l_dd * p.l_qq - p.l_dq * p.l_qd;
+            if det.abs() < f64::MIN_POSITIVE {
+                return None;
+            }
+            let delta_d = (p.l_qq * r_d - p.l_dq * r_q) / det;
+            let delta_q = (p.l_dd * r_q - p.l_qd * r_d) / det;
+            i_d -= delta_d;
+            i_q -= delta_q;
+            if delta_d.abs() + delta_q.abs() < 1e-12 * (1.0 + i_d.abs() + i_q.abs()) {
+                return Some((i_d, i_q));
+            }
+        }
+        None
+    }
+
+    /// Currents of the map linearised at zero current, `(ψ − λ_m)/L` with
+    /// `λ_m` and `L` read from the map: a starting guess for
+    /// [`Self::currents`] when no nearby solution is known, exact for a
+    /// linear machine.
+    pub fn linear_currents(&self, psi_d: f64, psi_q: f64) -> (f64, f64) {
+        let p = self.eval(0.0, 0.0);
+        let (r_d, r_q) = (psi_d - p.psi_d, psi_q - p.psi_q);
+        let det = p.l_dd * p.l_qq - p.l_dq * p.l_qd;
+        if det.abs() < f64::MIN_POSITIVE {
+            return (0.0, 0.0);
+        }
+        (
+            (p.l_qq * r_d - p.l_dq * r_q) / det,
+            (p.l_dd * r_q - p.l_qd * r_d) / det,
+        )
+    }
+
+    /// Interpolate one table: returns `(ψ, ∂ψ/∂i_d, ∂ψ/∂i_q)`.
+    fn lookup(&self, table: &[f64], i_d: f64, i_q: f64) -> (f64, f64, f64) {
+        let n_q = self.i_q.len();
+        let row = |k_d: usize| move |k_q: usize| table.get(k_d * n_q + k_q).copied().unwrap_or(0.0);
+        let kind = self.interpolation;
+        // Interpolation is linear in the samples, so ∂/∂i_q commutes with the
+        // i_d pass: interpolate the row values and the row slopes separately.
+        let (psi, dpsi_did) = interp_1d(kind, &self.i_d, i_d, |k_d| {
+            interp_1d(kind, &self.i_q, i_q, row(k_d)).0
+        });
+        let (dpsi_diq, _) = interp_1d(kind, &self.i_d, i_d, |k_d| {
+            interp_1d(kind, &self.i_q, i_q, row(k_d)).1
+        });
+        (psi, dpsi_did, dpsi_diq)
+    }
+}
+
+/// Interpolate samples `y(k)` on the grid `xs` at `x`; returns `(y, dy/dx)`.
+///
+/// `xs` must hold at least two strictly increasing points.
+fn interp_1d(kind: MapInterpolation, xs: &[f64], x: f64, y: impl Fn(usize) -> f64) -> (f64, f64) {
+    let n = xs.len();
+    let at = |k: usize| xs.get(k).copied().unwrap_or(0.0);
+    // Cell k spans [xs[k], xs[k+1]].
+    let k = xs.partition_point(|&v| v <= x).clamp(1, n - 1) - 1;
+    let (x0, x1) = (at(k), at(k + 1));
+    let h = x1 - x0;
+    let (y0, y1) = (y(k), y(k + 1));
+
+    match kind {
+        MapInterpolation::Bilinear => {
+            let slope = (y1 - y0) / h;
+            (y0 + slope * (x - x0), slope)
+        }
+        MapInterpolation::Spline => {
+            // Finite-difference slope at grid point j (one-sided at the ends).
+            let slope = |j: usize| {
+                let lo = j.saturating_sub(1);
+                let hi = (j + 1).min(n - 1);
+                (y(hi) - y(lo)) / (at(hi) - at(lo))
+            };
+            let (m0, m1) = (slope(k), slope(k + 1));
+            if x < x0 {
+                return (y0 + m0 * (x - x0), m0);
+            }
+            if x > x1 {
+                return (y1 + m1 * (x - x1), m1);
+            }
+            let s = (x - x0) / h;
+            let (s2, s3) = (s * s, s * s * s);
+            let value = (2.0 * s3 - 3.0 * s2 + 1.0) * y0
+                + (s3 - 2.0 * s2 + s) * h * m0
+                + (-2.0 * s3 + 3.0 * s2) * y1
+                + (s3 - s2) * h * m1;
+            let deriv = (6.0 * s2 - 6.0 * s) * y0 / h
+                + (3.0 * s2 - 4.0 * s + 1.0) * m0
+                + (-6.0 * s2 + 6.0 * s) * y1 / h
+                + (3.0 * s2 - 2.0 * s) * m1;
+            (value, deriv)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FluxMap, FluxMapError, MapInterpolation};
+
+    /// A saturating map sampled on a coarse grid, with d-q cross-coupling.
+    fn saturating_csv() -> String {
+        let mut csv = String::from("i_d,i_q,psi_d,psi_q\n");
+        for k_d in 0..7 {
+            for k_q in 0..7 {
+                let i_d = -30.0 + 10.0 * f64::from(k_d);
+                let i_q = -30.0 + 10.0 * f64::from(k_q);
+                let psi_d = 0.175 + 0.05 * (0.16 * i_d).atan() - 1e-5 * i_q * i_q;
+                let psi_q = 0.06 * (0.2 * i_q).atan() - 1e-4 * i_d * i_q;
+                csv.push_str(&format!("{i_d},{i_q},{psi_d},{psi_q}\n"));
+            }
+        }
+        csv
+    }
+
+    /// Both schemes reproduce a linear machine exactly, including its slopes.
+    #[test]
+    fn linear_map_is_reproduced() {
+        for kind in MapInterpolation::ALL {
+            let map = FluxMap {
+                interpolation: kind,
+                ..FluxMap::linear(0.008, 0.012, 0.175, 40.0, 5)
+            };
+            let p = map.eval(7.3, -12.1);
+            let eps = 1e-12;
+            assert!(
+                (p.psi_d - (0.008 * 7.3 + 0.175)).abs() < eps,
+                "{kind:?}: ψ_d"
+            );
+            assert!((p.psi_q - 0.012 * -12.1).abs() < eps, "{kind:?}: ψ_q");
+            assert!((p.l_dd - 0.008).abs() < eps, "{kind:?}: L_dd");
+            assert!((p.l_qq - 0.012).abs() < eps, "{kind:?}: L_qq");
+            assert!(p.l_dq.abs() < eps && p.l_qd.abs() < eps, "{kind:?}: cross");
+        }
+    }
+
+    /// Map derivatives agree with finite differences and inversion round-trips.
+    #[test]
+    fn derivatives_and_inverse_are_consistent() {
+        let map = FluxMap::from_csv(&saturating_csv()).expect("valid csv");
+        assert_eq!(map.shape(), (7, 7), "grid shape");
+        let (i_d, i_q) = (-4.2, 13.7);
+        let p = map.eval(i_d, i_q);
+        let h = 1e-6;
+        let fd_d = (map.eval(i_d + h, i_q).psi_d - map.eval(i_d - h, i_q).psi_d) / (2.0 * h);
+        let fd_q = (map.eval(i_d, i_q + h).psi_q - map.eval(i_d, i_q - h).psi_q) / (2.0 * h);
+        let fd_dq = (map.eval(i_d, i_q + h).psi_d - map.eval(i_d, i_q - h).psi_d) / (2.0 * h);
+        assert!((p.l_dd - fd_d).abs() < 1e-6, "L_dd {} vs {fd_d}", p.l_dd);
+        assert!((p.l_qq - fd_q).abs() < 1e-6, "L_qq {} vs {fd_q}", p.l_qq);
+        assert!((p.l_dq - fd_dq).abs() < 1e-6, "L_dq {} vs {fd_dq}", p.l_dq);
+
+        let (d, q) = map
+            .currents(p.psi_d, p.psi_q, map.linear_currents(p.psi_d, p.psi_q))
+            .expect("Newton converges");
+        assert!(
+            (d - i_d).abs() < 1e-9 && (q - i_q).abs() < 1e-9,
+            "inverse ({d}, {q})"
+        );
+        assert_eq!(
+            FluxMap::linear(0.0, 0.0, 0.1, 10.0, 3).currents(0.2, 0.0, (0.0, 0.0)),
+            None,
+            "a flat map cannot be inverted"
+        );
+    }
+
+    /// Incomplete grids and malformed rows are rejected.
+    #[test]
+    fn malformed_csv_is_rejected() {
+        assert_eq!(
+            FluxMap::from_csv("0,0,0.1,0\n1,0,0.2,0\n0,1,0.1,0.1\n"),
+            Err(FluxMapError::NotAGrid),
+            "missing grid point"
+        );
+        assert!(
+            matches!(
+                FluxMap::from_csv("0,0,0.1,0\n1,0,oops,0\n"),
+                Err(FluxMapError::Parse { line: 2, .. })
+            ),
+            "non-numeric data row"
+        );
+        assert_eq!(
+            FluxMap::from_csv("0,0,0.1,0\n0,1,0.1,0.1\n"),
+            Err(FluxMapError::TooSmall),
+            "single i_d value"
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Spatial harmonics of the PMSM: non-sinusoidal back-EMF and cogging torque.
+//!
+//! Back-EMF harmonics are given on the phase PM flux linkage in electrical
+//! angle, `λ_a(θ_e) = λ_m·[cos θ_e + Σ a_h·cos(h·θ_e + φ_h)]`. Order `h` forms a
+//! positive-sequence set when `h ≡ 1 (mod 3)` and a negative-sequence set when
+//! `h ≡ 2 (mod 3)`; in the rotor frame they appear at `(h − 1)·θ_e` and
+//! `(h + 1)·θ_e` respectively, so the 5th and 7th both produce the familiar
+//! 6th-harmonic torque ripple. Triplen orders are zero-sequence and do not
+//! couple into a star-connected winding, so they are ignored.
+//!
+//! Cogging torque depends on mechanical angle only and is given either as a
+//! Fourier series or as a periodic table.
+
+/// One term of a harmonic series.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Harmonic {
+    /// Harmonic order.
+    pub order: u32,
+    /// Amplitude: per unit of `λ_m` for back-EMF, N·m for cogging.
+    pub amplitude: f64,
+    /// Phase (rad).
+    pub phase: f64,
+}
+
+/// Cogging-torque representation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CoggingModel {
+    /// No cogging torque.
+    #[default]
+    None,
+    /// `T_cog(θ_m) = Σ A_k·sin(k·θ_m + φ_k)`.
+    Fourier,
+    /// Linear interpolation in a table that repeats every `cogging_period`.
+    Table,
+}
+
+impl CoggingModel {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 3] = [Self::None, Self::Fourier, Self::Table];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Fourier => "Fourier",
+            Self::Table => "Table",
+        }
+    }
+}
+
+/// Errors raised while importing a cogging table.
+#[derive(Debug, PartialEq, Eq)]
+pub enum CoggingTableError {
+    /// A data line does not hold two numeric columns.
+    Parse {
+        /// 1-based line number in the input.
+        line: usize,
+        /// Offending line content.
+        content: String,
+    },
+    /// Fewer than two points.
+    TooSmall,
+}
+
+impl std::fmt::Display for CoggingTableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse { line, content } => {
+                write!(f, "line {line}: expected `θ_m, T_cog`, got {content:?}")
+            }
+            Self::TooSmall => write!(f, "need at least two points"),
+        }
+    }
+}
+
+impl std::error::Error for CoggingTableError {}
+
+/// Rotor-frame harmonic PM flux `Δψ_dq(θ_e)` and its first two derivatives.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PmFlux {
+    /// `[Δψ_d, Δψ_q]` (Wb), on top of the fundamental `λ_m` on the d-axis.
+    pub psi: [f64; 2],
+    /// `∂Δψ/∂θ_e`.
+    pub dpsi: [f64; 2],
+    /// `∂²Δψ/∂θ_e²`.
+    pub d2psi: [f64; 2],
+}
+
+/// Back-EMF harmonics and cogging torque of a machine.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SpatialHarmonics {
+    /// Back-EMF (PM flux) harmonics in electrical angle.
+    pub emf: Vec<Harmonic>,
+    /// Active cogging representation.
+    pub cogging: CoggingModel,
+    /// Cogging Fourier terms in mechanical angle (orders are per revolution).
+    pub cogging_fourier: Vec<Harmonic>,
+    /// Cogging table rows `[θ_m (deg), T_cog (N·m)]`, sorted by angle.
+    pub cogging_table: Vec<[f64; 2]>,
+    /// Mechanical period of the cogging table (deg).
+    pub cogging_period: f64,
+    /// CSV text being edited in the cogging-table import box.
+    #[serde(skip)]
+    pub cogging_csv: String,
+    /// Error from the last cogging-table import, shown in the node body.
+    #[serde(skip)]
+    pub cogging_csv_error: Option<String>,
+}
+
+impl Default for SpatialHarmonics {
+    fn default() -> Self {
+        Self {
+            emf: Vec::new(),
+            cogging: CoggingModel::None,
+            cogging_fourier: Vec::new(),
+            cogging_table: Vec::new(),
+            cogging_period: 15.0,
+            cogging_csv: String::new(),
+            cogging_csv_error: None,
+        }
+    }
+}
+
+impl SpatialHarmonics {
+    /// Harmonic PM flux in the rotor frame at electrical angle `theta_e`.
+    pub fn pm_flux(&self, lambda_m: f64, theta_e: f64) -> PmFlux {
+        let mut out = PmFlux::default();
+        for h in &self.emf {
+            // Rotor-frame order and direction of the space vector
+            let (n, sign) = match h.order % 3 {
+                1 => (f64::from(h.order) - 1.0, 1.0),
+                2 => (f64::from(h.order) + 1.0, -1.0),
+                _ => continue,
+            };
+            let amp = lambda_m * h.amplitude;
+            let (sin, cos) = (n * theta_e + h.phase).sin_cos();
+            out.psi[0] += amp * cos;
+            out.psi[1] += sign * amp * sin;
+            out.dpsi[0] -= amp * n * sin;
+            out.dpsi[1] += sign * amp * n * cos;
+            out.d2psi[0] -= amp * n * n * cos;
+            out.d2psi[1] -= sign * amp * n * n * sin;
+        }
+        out
+    }
+
+    /// Cogging torque (N·m) and `∂T_cog/∂θ_m` at mechanical angle `theta_m` (rad).
+    pub fn cogging(&self, theta_m: f64) -> (f64, f64) {
+        match self.cogging {
+            CoggingModel::None => (0.0, 0.0),
+            CoggingModel::Fourier => self.cogging_fourier.iter().fold((0.0, 0.0), |(t, dt), h| {
+                let k = f64::from(h.order);
+                let (sin, cos) = (k * theta_m + h.phase).sin_cos();
+                (t + h.amplitude * sin, dt + h.amplitude * k * cos)
+            }),
+            CoggingModel::Table => self.cogging_table_at(theta_m.to_degrees()),
+        }
+    }
+
+    /// Periodic linear interpolation in the cogging table at `deg` (mechanical).
+    fn cogging_table_at(&self, deg: f64) -> (f64, f64) {
+        let (Some(first), Some(last)) = (self.cogging_table.first(), self.cogging_table.last())
+        else {
+            return (0.0, 0.0);
+        };
+        let period = self.cogging_period.max(f64::EPSILON);
+        let x = first[0] + (deg - first[0]).rem_euclid(period);
+        // The segment from the last point wraps round to the first one
+        let wrap = [first[0] + period, first[1]];
+        let (a, b) = self
+            .cogging_table
+            .windows(2)
+            .filter_map(|w| w.first().zip(w.get(1)))
+            .find(|(_, b)| x < b[0])
+            .map_or((*last, wrap), |(a, b)| (*a, *b));
+        let width = b[0] - a[0];
+        if width <= 0.0 {
+            return (a[1], 0.0);
+        }
+        let slope = (b[1] - a[1]) / width;
+        (a[1] + slope * (x - a[0]), slope.to_degrees())
+    }
+
+    /// Parse a cogging table from CSV rows `θ_m (deg), T_cog (N·m)`.
+    ///
+    /// Header lines and lines starting with `#` are skipped. Rows are sorted by
+    /// angle; the period is kept separately in [`Self::cogging_period`].
+    ///
+    /// # Errors
+    ///
+    /// - [`CoggingTableError::Parse`] — a data line is malformed.
+    /// - [`CoggingTableError::TooSmall`] — fewer than two rows.
+    pub fn parse_cogging_table(text: &str) -> Result<Vec<[f64; 2]>, CoggingTableError> {
+        let mut rows = Vec::new();
+        for (k, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let values: Vec<Option<f64>> = line
+                .split([',', ';', '\t'])
+                .map(|v| v.trim().parse().ok())
+                .collect();
+            match values.as_slice() {
+                [Some(angle), Some(torque)] => rows.push([*angle, *torque]),
+                // A non-numeric first line is a header
+                _ if rows.is_empty() && k == 0 => {}
+                _ => {
+                    return Err(CoggingTableError::Parse {
+                        line: k + 1,
+                        content: line.to_owned(),
+                    });
+                }
+            }
+        }
+        if rows.len() < 2 {
+            return Err(CoggingTableError::TooSmall);
+        }
+        rows.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CoggingModel, Harmonic, SpatialHarmonics};
+
+    /// 5th and 7th back-EMF harmonics map to the 6th in the rotor frame, and
+    /// the returned derivatives match finite differences.
+    #[test]
+    fn pm_flux_harmonics_appear_at_sixth_order() {
+        let h = SpatialHarmonics {
+            emf: vec![
+                Harmonic {
+                    order: 5,
+                    amplitude: 0.04,
+                    phase: 0.3,
+                },
+                Harmonic {
+                    order: 7,
+                    amplitude: 0.02,
+                    phase: -0.2,
+                },
+                Harmonic {
+                    order: 3,
+                    amplitude: 0.1,
+                    phase: 0.0,
+                },
+            ],
+            ..SpatialHarmonics::default()
+        };
+        let lambda_m = 0.175;
+        let theta = 0.7;
+        let period = std::f64::consts::TAU / 6.0;
+        let a = h.pm_flux(lambda_m, theta);
+        let b = h.pm_flux(lambda_m, theta + period);
+        for (x, y) in a.psi.iter().zip(&b.psi) {
+            assert!((x - y).abs() < 1e-12, "Δψ repeats every 60° electrical");
+        }
+        let step = 1e-6;
+        let hi = h.pm_flux(lambda_m, theta + step);
+        let lo = h.pm_flux(lambda_m, theta - step);
+        let fd = |f: fn(&super::PmFlux) -> [f64; 2]| {
+            let (hi, lo) = (f(&hi), f(&lo));
+            [
+                (hi[0] - lo[0]) / (2.0 * step),
+                (hi[1] - lo[1]) / (2.0 * step),
+            ]
+        };
+        let (dpsi, d2psi) = (fd(|p| p.psi), fd(|p| p.dpsi));
+        for (exact, approx) in a.dpsi.iter().zip(&dpsi) {
+            assert!((exact - approx).abs() < 1e-7, "∂Δψ/∂θ: {exact} vs {approx}");
+        }
+        for (exact, approx) in a.d2psi.iter().zip(&d2psi) {
+            assert!(
+                (exact - approx).abs() < 1e-6,
+                "∂²Δψ/∂θ²: {exact} vs {approx}"
+            );
+        }
+    }
+
+    /// The cogging table interpolates linearly and wraps at its period.
+    #[test]
+    fn cogging_table_is_periodic() {
+        let table = SpatialHarmonics::parse_cogging_table("theta,T\n0,0\n5,0.2\n10,-0.2\n")
+            .expect("valid table");
+        let h = SpatialHarmonics {
+            cogging: CoggingModel::Table,
+            cogging_table: table,
+            cogging_period: 15.0,
+            ..SpatialHarmonics::default()
+        };
+        let (t, _) = h.cogging(2.5_f64.to_radians());
+        assert!((t - 0.1).abs() < 1e-12, "midpoint of first segment: {t}");
+        let (t, dt) = h.cogging(12.5_f64.to_radians());
+        assert!((t + 0.1).abs() < 1e-12, "wrap segment back to zero: {t}");
+        assert!(
+            (dt - (0.2 / 5.0_f64).to_degrees()).abs() < 1e-9,
+            "slope per radian: {dt}"
+        );
+        let (t_next, _) = h.cogging(17.5_f64.to_radians());
+        let (t_first, _) = h.cogging(2.5_f64.to_radians());
+        assert!((t_next - t_first).abs() < 1e-12, "period of 15°");
+    }
+}
@@ -0,0 +1,273 @@
+//! Squirrel-cage induction machine in a two-axis reference frame.
+//!
+//! The model uses the stator and rotor flux linkages as states, in a frame
+//! rotating at `ω_k`:
+//!
+//! ```text
+//! dψ_sd/dt = v_sd − R_s·i_sd + ω_k·ψ_sq
+//! dψ_sq/dt = v_sq − R_s·i_sq − ω_k·ψ_sd
+//! dψ_rd/dt =      − R_r·i_rd + (ω_k − ω_r)·ψ_rq
+//! dψ_rq/dt =      − R_r·i_rq − (ω_k − ω_r)·ψ_rd
+//! ψ_s = L_s·i_s + L_m·i_r,   ψ_r = L_m·i_s + L_r·i_r
+//! T_e = (3/2)·N_p·(ψ_sd·i_sq − ψ_sq·i_sd)
+//! ```
+//!
+//! with `L_s = L_ls + L_m`, `L_r = L_lr + L_m` and `ω_r = N_p·ω_m`. The frame is
+//! stationary (`α`/`β`, `ω_k = 0`), synchronous (`ω_k = 2π·f_sync`) or fixed
+//! to the rotor (`ω_k = ω_r`).
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// Reference frame of the induction machine's inputs, states and outputs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum InductionFrame {
+    /// Stator-fixed `α`/`β` axes.
+    Stationary,
+    /// Axes rotating at the supply frequency `f_sync`.
+    #[default]
+    Synchronous,
+    /// Axes fixed to the rotor.
+    Rotor,
+}
+
+impl InductionFrame {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 3] = [Self::Stationary, Self::Synchronous, Self::Rotor];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Stationary => "Stationary (αβ)",
+            Self::Synchronous => "Synchronous",
+            Self::Rotor => "Rotor",
+        }
+    }
+}
+
+/// Induction machine with stator and rotor flux-linkage states.
+///
+/// Inputs: stator voltages `v_d`, `v_q` (Signal) in the selected frame.\
+/// Outputs: stator currents `i_sd`, `i_sq`, rotor flux `ψ_rd`, `ψ_rq` and
+/// torque `T_e` (Signal), all in the selected frame. Speed and angle come
+/// from the Mechanical node, which receives `T_e` internally.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct InductionMachineNode {
+    /// Stator resistance (Ω).
+    pub r_s: f64,
+    /// Rotor resistance referred to the stator (Ω).
+    pub r_r: f64,
+    /// Stator leakage inductance (H).
+    pub l_ls: f64,
+    /// Rotor leakage inductance referred to the stator (H).
+    pub l_lr: f64,
+    /// Magnetizing inductance (H).
+    pub l_m: f64,
+    /// Number of pole pairs.
+    pub n_p: f64,
+    /// Reference frame.
+    pub frame: InductionFrame,
+    /// Supply frequency that the synchronous frame rotates at (Hz).
+    pub f_sync: f64,
+    /// Stator d-axis current time-series produced after simulation.
+    #[serde(skip)]
+    pub output_i_sd: Option<PortValue>,
+    /// Stator q-axis current time-series produced after simulation.
+    #[serde(skip)]
+    pub output_i_sq: Option<PortValue>,
+    /// Rotor d-axis flux time-series produced after simulation.
+    #[serde(skip)]
+    pub output_psi_rd: Option<PortValue>,
+    /// Rotor q-axis flux time-series produced after simulation.
+    #[serde(skip)]
+    pub output_psi_rq: Option<PortValue>,
+    /// Torque time-series produced after simulation.
+    #[serde(skip)]
+    pub output_t_e: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for InductionMachineNode {
+    fn default() -> Self {
+        Self {
+            r_s: 1.4,
+            r_r: 1.2,
+            l_ls: 0.006,
+            l_lr: 0.006,
+            l_m: 0.2,
+            n_p: 2.0,
+            frame: InductionFrame::Synchronous,
+            f_sync: 50.0,
+            output_i_sd: None,
+            output_i_sq: None,
+            output_psi_rd: None,
+            output_psi_rq: None,
+            output_t_e: None,
+            custom_size: None,
+        }
+    }
+}
+
+impl InductionMachineNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "Induction Machine"
+    }
+
+    /// Input port descriptors: stator voltages in the selected frame.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[("v_d", PortType::Signal), ("v_q", PortType::Signal)]
+    }
+
+    /// Output port descriptors: stator currents, rotor flux and torque.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("i_sd", PortType::Signal),
+            ("i_sq", PortType::Signal),
+            ("ψ_rd", PortType::Signal),
+            ("ψ_rq", PortType::Signal),
+            ("T_e", PortType::Signal),
+        ]
+    }
+
+    /// Header colour (same family as the other machine nodes).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0xA0, 0x60, 0x40)
+    }
+
+    /// Speed of the reference frame (rad/s) at rotor speed `omega_m`.
+    pub fn frame_speed(&self, omega_m: f64) -> f64 {
+        match self.frame {
+            InductionFrame::Stationary => 0.0,
+            InductionFrame::Synchronous => std::f64::consts::TAU * self.f_sync,
+            InductionFrame::Rotor => self.n_p * omega_m,
+        }
+    }
+
+    /// Angle of the reference frame relative to the stator `α` axis at time
+    /// `t`, with rotor electrical angle `theta_e`.
+    pub fn frame_angle(&self, t: f64, theta_e: f64) -> f64 {
+        match self.frame {
+            InductionFrame::Stationary => 0.0,
+            InductionFrame::Synchronous => std::f64::consts::TAU * self.f_sync * t,
+            InductionFrame::Rotor => theta_e,
+        }
+    }
+
+    /// Stator and rotor currents `[i_sd, i_sq, i_rd, i_rq]` from the flux
+    /// linkages `[ψ_sd, ψ_sq, ψ_rd, ψ_rq]`.
+    pub fn currents(&self, psi: [f64; 4]) -> [f64; 4] {
+        let [psi_sd, psi_sq, psi_rd, psi_rq] = psi;
+        let (l_s, l_r) = (self.l_ls + self.l_m, self.l_lr + self.l_m);
+        let det = l_s * l_r - self.l_m * self.l_m;
+        [
+            (l_r * psi_sd - self.l_m * psi_rd) / det,
+            (l_r * psi_sq - self.l_m * psi_rq) / det,
+            (l_s * psi_rd - self.l_m * psi_sd) / det,
+            (l_s * psi_rq - self.l_m * psi_sq) / det,
+        ]
+    }
+
+    /// Electromagnetic torque (N·m) for flux linkages `psi`.
+    pub fn torque(&self, psi: [f64; 4]) -> f64 {
+        let [psi_sd, psi_sq, ..] = psi;
+        let [i_sd, i_sq, ..] = self.currents(psi);
+        1.5 * self.n_p * (psi_sd * i_sq - psi_sq * i_sd)
+    }
+
+    /// Flux-linkage derivatives for stator voltages `v`, frame speed
+    /// `omega_k` and rotor electrical speed `omega_r`.
+    pub fn flux_derivatives(
+        &self,
+        psi: [f64; 4],
+        v: [f64; 2],
+        omega_k: f64,
+        omega_r: f64,
+    ) -> [f64; 4] {
+        let [psi_sd, psi_sq, psi_rd, psi_rq] = psi;
+        let [i_sd, i_sq, i_rd, i_rq] = self.currents(psi);
+        let slip = omega_k - omega_r;
+        [
+            v[0] - self.r_s * i_sd + omega_k * psi_sq,
+            v[1] - self.r_s * i_sq - omega_k * psi_sd,
+            -self.r_r * i_rd + slip * psi_rq,
+            -self.r_r * i_rq - slip * psi_rd,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InductionMachineNode;
+
+    /// Rotate the `[d, q]` pairs of `x` by `-angle` (into a frame at `angle`).
+    fn to_frame(x: [f64; 4], angle: f64) -> [f64; 4] {
+        let (sin, cos) = angle.sin_cos();
+        [
+            cos * x[0] + sin * x[1],
+            -sin * x[0] + cos * x[1],
+            cos * x[2] + sin * x[3],
+            -sin * x[2] + cos * x[3],
+        ]
+    }
+
+    /// The frame-speed terms are exactly those of a rotating transformation:
+    /// derivatives in a frame at angle `ρ` equal the rotated stationary ones
+    /// minus `ω_k × ψ`.
+    #[test]
+    fn flux_equations_are_frame_invariant() {
+        let m = InductionMachineNode::default();
+        let psi = [0.6, -0.3, 0.55, -0.25];
+        let v = [120.0, 250.0];
+        let (omega_k, omega_r, rho) = (314.0, 290.0, 0.8);
+
+        let stationary = m.flux_derivatives(psi, v, 0.0, omega_r);
+        let rotated = to_frame(psi, rho);
+        let v_rot = to_frame([v[0], v[1], 0.0, 0.0], rho);
+        let moving = m.flux_derivatives(rotated, [v_rot[0], v_rot[1]], omega_k, omega_r);
+        let expected = to_frame(stationary, rho);
+        let coriolis = [
+            rotated[1] * omega_k,
+            -rotated[0] * omega_k,
+            rotated[3] * omega_k,
+            -rotated[2] * omega_k,
+        ];
+        for ((got, want), extra) in moving.iter().zip(&expected).zip(&coriolis) {
+            assert!(
+                (got - (want + extra)).abs() < 1e-9,
+                "frame derivative {got} vs {}",
+                want + extra
+            );
+        }
+        assert!(
+            (m.torque(psi) - m.torque(rotated)).abs() < 1e-12,
+            "torque does not depend on the frame"
+        );
+    }
+
+    /// The currents invert the inductance matrix, and a stator flux aligned
+    /// with the stator current produces no torque.
+    #[test]
+    fn currents_invert_flux_linkages() {
+        let m = InductionMachineNode::default();
+        let i = [3.0, -2.0, -1.5, 1.0];
+        let (l_s, l_r) = (m.l_ls + m.l_m, m.l_lr + m.l_m);
+        let psi = [
+            l_s * i[0] + m.l_m * i[2],
+            l_s * i[1] + m.l_m * i[3],
+            m.l_m * i[0] + l_r * i[2],
+            m.l_m * i[1] + l_r * i[3],
+        ];
+        for (got, want) in m.currents(psi).iter().zip(&i) {
+            assert!((got - want).abs() < 1e-9, "current {got} vs {want}");
+        }
+        assert!(
+            m.torque([0.5, 0.0, 0.0, 0.0]).abs() < 1e-12,
+            "no torque from aligned flux"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! Switched two-level inverter node — compares duty cycles against a
+//! centre-aligned triangular carrier and produces switched phase voltages.
+//!
+//! Duty cycles are sampled once per carrier period (regular sampling), so all
+//! switching instants of a period are known at its start. The solver uses
+//! [`InverterNode::period_segments`] to stop exactly at every edge instead of
+//! smearing the discontinuities across BDF steps.
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// Conduction state of one inverter leg.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LegState {
+    /// Upper switch on: pole voltage `+V_dc/2`.
+    Upper,
+    /// Lower switch on: pole voltage `−V_dc/2`.
+    Lower,
+    /// Dead time: both switches off, a freewheeling diode conducts.
+    Dead,
+}
+
+/// Switched two-level voltage-source inverter.
+///
+/// Inputs: duty cycles `d_abc` (Vector), DC-link voltage `V_dc` (Signal) and
+/// phase currents `i_abc` (Vector, used for diode conduction in dead time).\
+/// Output: switched phase-to-neutral voltages `v_abc` (Vector).
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct InverterNode {
+    /// Carrier (switching) frequency (Hz).
+    pub f_sw: f64,
+    /// Dead time inserted before each switch turn-on (s).
+    pub t_dead: f64,
+    /// Freewheeling diode forward voltage drop (V).
+    pub v_diode: f64,
+    /// Switched phase voltage time-series produced after simulation.
+    #[serde(skip)]
+    pub output_v_abc: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for InverterNode {
+    fn default() -> Self {
+        Self {
+            f_sw: 10_000.0,
+            t_dead: 1e-6,
+            v_diode: 0.7,
+            output_v_abc: None,
+            custom_size: None,
+        }
+    }
+}
+
+impl InverterNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "Inverter"
+    }
+
+    /// Input port descriptors: duty cycles, DC-link voltage and phase currents.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("d_abc", PortType::Vector),
+            ("V_dc", PortType::Signal),
+            ("i_abc", PortType::Vector),
+        ]
+    }
+
+    /// Output port descriptors: switched phase voltages.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[("v_abc", PortType::Vector)]
+    }
+
+    /// Header colour (teal, matching other power-electronics nodes).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x20, 0x90, 0x90)
+    }
+
+    /// Carrier period (s).
+    pub fn period(&self) -> f64 {
+        1.0 / self.f_sw.max(1.0)
+    }
+
+    /// Split one carrier period into intervals of constant leg states.
+    ///
+    /// The carrier rises from 0 to 1 over the first half period and falls back
+    /// over the second; the upper gate is on while the carrier is below the
+    /// duty. Each turn-on is delayed by the dead time. Duties at 0 or 1 keep
+    /// the leg clamped for the whole period.
+    ///
+    /// # Arguments
+    /// * `t_start` — start of the carrier period (s)
+    /// * `duty`    — per-phase duty cycles sampled at `t_start`
+    ///
+    /// # Returns
+    /// Consecutive `(t_begin, t_end, states)` intervals covering the period.
+    pub fn period_segments(&self, t_start: f64, duty: [f64; 3]) -> Vec<(f64, f64, [LegState; 3])> {
+        let period = self.period();
+        let t_dead = self.t_dead.clamp(0.0, 0.5 * period);
+
+        // Candidate boundaries (relative to t_start) for every leg.
+        let mut bounds = vec![0.0, period];
+        for &d in &duty {
+            if d > 0.0 && d < 1.0 {
+                let t_off = 0.5 * d * period;
+                let t_on = period - t_off;
+                bounds.extend([t_off, t_off + t_dead, t_on, t_on + t_dead]);
+            }
+        }
+        bounds.retain(|&b| (0.0..=period).contains(&b));
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup_by(|a, b| (*a - *b).abs() < 1e-15);
+
+        bounds
+            .windows(2)
+            .filter_map(|w| match *w {
+                [a, b] if b > a => {
+                    let mid = 0.5 * (a + b);
+                    let states = duty.map(|d| self.leg_state(d, mid, t_dead));
+                    Some((t_start + a, t_start + b, states))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// State of one leg at time `tau` into the carrier period.
+    fn leg_state(&self, duty: f64, tau: f64, t_dead: f64) -> LegState {
+        if duty >= 1.0 {
+            return LegState::Upper;
+        }
+        if duty <= 0.0 {
+            return LegState::Lower;
+        }
+        let period = self.period();
+        let t_off = 0.5 * duty * period;
+        let t_on = period - t_off;
+        if tau < t_off || tau >= t_on + t_dead {
+            LegState::Upper
+        } else if tau >= t_off + t_dead && tau < t_on {
+            LegState::Lower
+        } else {
+            LegState::Dead
+        }
+    }
+
+    /// Phase-to-neutral voltages for given leg states (floating star point).
+    ///
+    /// During dead time the pole is clamped by the diode that carries the
+    /// phase current: positive current (out of the inverter) flows through the
+    /// lower diode, negative current through the upper one.
+    ///
+    /// # Arguments
+    /// * `states` — conduction state of each leg
+    /// * `v_dc`   — DC-link voltage (V)
+    /// * `i_abc`  — phase currents (A), positive out of the inverter
+    pub fn phase_voltages(&self, states: [LegState; 3], v_dc: f64, i_abc: [f64; 3]) -> [f64; 3] {
+        let half = 0.5 * v_dc;
+        let mut pole = [0.0; 3];
+        for ((v, state), i) in pole.iter_mut().zip(states).zip(i_abc) {
+            *v = match (state, i > 0.0) {
+                (LegState::Upper, _) => half,
+                (LegState::Lower, _) => -half,
+                (LegState::Dead, true) => -half - self.v_diode,
+                (LegState::Dead, false) => half + self.v_diode,
+            };
+        }
+        let v_n = (pole[0] + pole[1] + pole[2]) / 3.0;
+        pole.map(|v| v - v_n)
+    }
+
+    /// Generate the switched waveform from pre-computed input series.
+    ///
+    /// Each edge is emitted as two samples at the same instant so that plots
+    /// show vertical transitions. Inputs are evaluated with the supplied
+    /// interpolators.
+    ///
+    /// # Arguments
+    /// * `t_start`, `t_end` — simulated time span (s)
+    /// * `duty`  — duty cycles at time `t`
+    /// * `v_dc`  — DC-link voltage at time `t`
+    /// * `i_abc` — phase currents at time `t`
+    ///
+    /// # Returns
+    /// A vector of `[t, v_a, v_b, v_c]` entries.
+    pub fn compute(
+        &self,
+        t_start: f64,
+        t_end: f64,
+        duty: impl Fn(f64) -> [f64; 3],
+        v_dc: impl Fn(f64) -> f64,
+        i_abc: impl Fn(f64) -> [f64; 3],
+    ) -> Vec<[f64; 4]> {
+        let period = self.period();
+        let mut out = Vec::new();
+        let mut t_period = t_start;
+        while t_period < t_end {
+            for (a, b, states) in self.period_segments(t_period, duty(t_period)) {
+                if a >= t_end {
+                    break;
+                }
+                let b = b.min(t_end);
+                for t in [a, b] {
+                    let [v_a, v_b, v_c] = self.phase_voltages(states, v_dc(t), i_abc(t));
+                    out.push([t, v_a, v_b, v_c]);
+                }
+            }
+            t_period += period;
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InverterNode, LegState};
+
+    /// Segments tile the period and the upper-switch time matches the duty.
+    #[test]
+    fn period_segments_match_duty() {
+        let inv = InverterNode {
+            t_dead: 0.0,
+            ..InverterNode::default()
+        };
+        let period = inv.period();
+        let duty = [0.25, 0.5, 1.0];
+        let segments = inv.period_segments(1.0, duty);
+
+        let eps = 1e-12;
+        assert!((segments.first().expect("segments").0 - 1.0).abs() < eps);
+        assert!((segments.last().expect("segments").1 - (1.0 + period)).abs() < eps);
+        for (k, &d) in duty.iter().enumerate() {
+            let upper: f64 = segments
+                .iter()
+                .filter(|(_, _, s)| s.get(k) == Some(&LegState::Upper))
+                .map(|(a, b, _)| b - a)
+                .sum();
+            assert!(
+                (upper - d * period).abs() < eps,
+                "phase {k}: upper on-time {upper}, expected {}",
+                d * period
+            );
+        }
+    }
+
+    /// Dead time removes volt-seconds in the direction of the current.
+    #[test]
+    fn dead_time_depends_on_current_sign() {
+        let inv = InverterNode {
+            v_diode: 0.0,
+            ..InverterNode::default()
+        };
+        let dead = [LegState::Dead, LegState::Lower, LegState::Lower];
+        let v_pos = inv.phase_voltages(dead, 100.0, [1.0, -0.5, -0.5]);
+        let v_neg = inv.phase_voltages(dead, 100.0, [-1.0, 0.5, 0.5]);
+        assert!(
+            v_pos[0].abs() < 1e-12,
+            "positive current clamps phase a low"
+        );
+        assert!(v_neg[0] > 0.0, "negative current clamps phase a high");
+        assert!(
+            (v_neg.iter().sum::<f64>()).abs() < 1e-12,
+            "zero-sequence removed"
+        );
+    }
+}
@@ -1,13 +1,262 @@
 //! Mechanical dynamics node for the PMSM simulation graph.
 //!
 //! Models the rotor mechanical subsystem via two coupled ODEs:
-//! - `dω_m/dt = (1/J) · (T_e − T_L − B·ω_m)`
+//! - `dω_m/dt = (1/J) · (T_e − T_L − T_f(ω_m) − T_load(ω_m) − B·ω_m)`
 //! - `dθ_e/dt = N_p · ω_m`
+//!
+//! `T_f` is the dry friction and `T_load` a built-in speed-dependent load;
+//! both are evaluated from the speed state inside the ODE right-hand side.
 
 use egui::Color32;
 
 use crate::port::{PortType, PortValue};
 
+/// Standard gravity (m/s²).
+const GRAVITY: f64 = 9.81;
+
+/// Dry-friction model acting on the rotor, on top of the viscous term `B·ω_m`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum FrictionModel {
+    /// Viscous friction only.
+    #[default]
+    None,
+    /// Constant-magnitude Coulomb friction: `T_C·tanh(ω_m/ω_ε)`.
+    Coulomb,
+    /// Static breakaway decaying to Coulomb friction:
+    /// `(T_C + (T_S − T_C)·exp(−(ω_m/ω_S)²))·tanh(ω_m/ω_ε)`.
+    Stribeck,
+}
+
+impl FrictionModel {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 3] = [Self::None, Self::Coulomb, Self::Stribeck];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "Viscous only",
+            Self::Coulomb => "Coulomb",
+            Self::Stribeck => "Stribeck",
+        }
+    }
+}
+
+/// Dry-friction parameters.
+///
+/// The sign change at standstill is smoothed with `tanh(ω_m/ω_ε)` so the RHS
+/// stays continuous; a small `ω_ε` approaches the ideal discontinuous model
+/// at the cost of stiffness.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Friction {
+    /// Which friction model is active.
+    pub model: FrictionModel,
+    /// Coulomb (sliding) friction torque `T_C` (N·m).
+    pub t_c: f64,
+    /// Static (breakaway) friction torque `T_S` (N·m).
+    pub t_s: f64,
+    /// Stribeck speed `ω_S` (rad/s).
+    pub omega_s: f64,
+    /// Smoothing speed `ω_ε` of the sign change (rad/s).
+    pub omega_eps: f64,
+}
+
+impl Default for Friction {
+    fn default() -> Self {
+        Self {
+            model: FrictionModel::None,
+            t_c: 0.01,
+            t_s: 0.015,
+            omega_s: 5.0,
+            omega_eps: 0.1,
+        }
+    }
+}
+
+/// Speed-dependent torque and its slope.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpeedTorque {
+    /// Torque opposing positive rotation (N·m).
+    pub torque: f64,
+    /// `∂T/∂ω_m`.
+    pub d_omega: f64,
+}
+
+impl std::ops::Add for SpeedTorque {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            torque: self.torque + rhs.torque,
+            d_omega: self.d_omega + rhs.d_omega,
+        }
+    }
+}
+
+impl Friction {
+    /// Friction torque at rotor speed `omega_m`.
+    pub fn torque(&self, omega_m: f64) -> SpeedTorque {
+        let level = |omega: f64| match self.model {
+            FrictionModel::None => (0.0, 0.0),
+            FrictionModel::Coulomb => (self.t_c, 0.0),
+            FrictionModel::Stribeck => {
+                let omega_s = self.omega_s.max(f64::EPSILON);
+                let decay = (-(omega / omega_s).powi(2)).exp();
+                let level = self.t_c + (self.t_s - self.t_c) * decay;
+                let slope = (self.t_s - self.t_c) * decay * (-2.0 * omega / (omega_s * omega_s));
+                (level, slope)
+            }
+        };
+        let (level, d_level) = level(omega_m);
+        let omega_eps = self.omega_eps.max(f64::EPSILON);
+        let sign = (omega_m / omega_eps).tanh();
+        let d_sign = (1.0 - sign * sign) / omega_eps;
+        SpeedTorque {
+            torque: level * sign,
+            d_omega: d_level * sign + level * d_sign,
+        }
+    }
+}
+
+/// Built-in load-torque model, added to the `T_L` input.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum LoadModel {
+    /// `T_L` input only.
+    #[default]
+    None,
+    /// Fan or pump: `k·ω_m·|ω_m|`.
+    Fan,
+    /// Constant power `P / ω_m`, linear through standstill below `ω_min`.
+    ConstantPower,
+    /// Vehicle road load (grade, rolling resistance and aerodynamic drag)
+    /// seen through a fixed gear and the wheel radius.
+    Vehicle,
+}
+
+impl LoadModel {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 4] = [Self::None, Self::Fan, Self::ConstantPower, Self::Vehicle];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "T_L input only",
+            Self::Fan => "Fan / pump",
+            Self::ConstantPower => "Constant power",
+            Self::Vehicle => "Vehicle",
+        }
+    }
+}
+
+/// Built-in load parameters.
+///
+/// The vehicle load at the wheel is
+/// `F = m·g·sin(atan(grade)) + C_rr·m·g·cos(atan(grade))·tanh(v/v_ε) + ½·ρ·C_d·A·v·|v|`
+/// with `v = ω_m·r / N`, giving `T_L = F·r / N` at the motor. The vehicle
+/// mass adds `m·(r/N)²` to the rotor inertia.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Load {
+    /// Which load model is active.
+    pub model: LoadModel,
+    /// Fan/pump coefficient `k` (N·m·s²/rad²).
+    pub k_fan: f64,
+    /// Constant load power `P` (W).
+    pub power: f64,
+    /// Speed below which the constant-power load falls off linearly (rad/s).
+    pub omega_min: f64,
+    /// Vehicle mass `m` (kg).
+    pub mass: f64,
+    /// Road grade (%, positive uphill).
+    pub grade: f64,
+    /// Rolling-resistance coefficient `C_rr`.
+    pub c_rr: f64,
+    /// Drag area `C_d·A` (m²).
+    pub c_d_a: f64,
+    /// Air density `ρ` (kg/m³).
+    pub rho: f64,
+    /// Wheel radius `r` (m).
+    pub wheel_radius: f64,
+    /// Gear ratio `N`: motor turns per wheel turn.
+    pub ratio: f64,
+}
+
+impl Default for Load {
+    fn default() -> Self {
+        Self {
+            model: LoadModel::None,
+            k_fan: 1e-5,
+            power: 50.0,
+            omega_min: 10.0,
+            mass: 1500.0,
+            grade: 0.0,
+            c_rr: 0.01,
+            c_d_a: 0.6,
+            rho: 1.2,
+            wheel_radius: 0.3,
+            ratio: 10.0,
+        }
+    }
+}
+
+impl Load {
+    /// Load torque at rotor speed `omega_m`.
+    pub fn torque(&self, omega_m: f64) -> SpeedTorque {
+        match self.model {
+            LoadModel::None => SpeedTorque::default(),
+            LoadModel::Fan => SpeedTorque {
+                torque: self.k_fan * omega_m * omega_m.abs(),
+                d_omega: 2.0 * self.k_fan * omega_m.abs(),
+            },
+            LoadModel::ConstantPower => {
+                let omega_min = self.omega_min.max(f64::EPSILON);
+                if omega_m.abs() >= omega_min {
+                    SpeedTorque {
+                        torque: self.power / omega_m,
+                        d_omega: -self.power / (omega_m * omega_m),
+                    }
+                } else {
+                    SpeedTorque {
+                        torque: self.power * omega_m / (omega_min * omega_min),
+                        d_omega: self.power / (omega_min * omega_min),
+                    }
+                }
+            }
+            LoadModel::Vehicle => {
+                // Wheel speed → road speed; r/N converts force to motor torque
+                let lever = self.wheel_radius / self.ratio;
+                let v = omega_m * lever;
+                let slope = (self.grade / 100.0).atan();
+                let weight = self.mass * GRAVITY;
+                // Rolling resistance changes sign through standstill over ~0.1 m/s
+                let v_eps = 0.1;
+                let sign = (v / v_eps).tanh();
+                let d_sign = (1.0 - sign * sign) / v_eps;
+                let rolling = self.c_rr * weight * slope.cos();
+                let drag = 0.5 * self.rho * self.c_d_a;
+                let force = weight * slope.sin() + rolling * sign + drag * v * v.abs();
+                let d_force = rolling * d_sign + 2.0 * drag * v.abs();
+                SpeedTorque {
+                    torque: force * lever,
+                    d_omega: d_force * lever * lever,
+                }
+            }
+        }
+    }
+
+    /// Inertia the load adds at the rotor (kg·m²): `m·(r/N)²` for a vehicle,
+    /// zero otherwise.
+    pub fn inertia(&self) -> f64 {
+        match self.model {
+            LoadModel::Vehicle => {
+                let lever = self.wheel_radius / self.ratio;
+                self.mass * lever * lever
+            }
+            LoadModel::None | LoadModel::Fan | LoadModel::ConstantPower => 0.0,
+        }
+    }
+}
+
 /// ODE node representing the mechanical dynamics of a PMSM rotor.
 ///
 /// Inputs: electromagnetic torque `T_e` (Signal) and load torque `T_L` (Signal).\
@@ -25,6 +274,10 @@ pub struct MechanicalNode {
     pub omega_m_0: f64,
     /// Initial electrical angle (rad).
     pub theta_e_0: f64,
+    /// Dry friction on top of the viscous term.
+    pub friction: Friction,
+    /// Built-in speed-dependent load, added to the `T_L` input.
+    pub load: Load,
     /// Mechanical speed time-series produced after simulation.
     #[serde(skip)]
     pub output_omega_m: Option<PortValue>,
@@ -44,6 +297,8 @@ impl Default for MechanicalNode {
             n_p: 4.0,
             omega_m_0: 0.0,
             theta_e_0: 0.0,
+            friction: Friction::default(),
+            load: Load::default(),
             output_omega_m: None,
             output_theta_e: None,
             custom_size: None,
@@ -77,4 +332,82 @@ impl MechanicalNode {
     pub fn header_color() -> Color32 {
         Color32::from_rgb(0x40, 0x40, 0xB0)
     }
+
+    /// Friction plus built-in load torque at rotor speed `omega_m`; the
+    /// viscous term `B·ω_m` and the `T_L` input are not included.
+    pub fn speed_torque(&self, omega_m: f64) -> SpeedTorque {
+        self.friction.torque(omega_m) + self.load.torque(omega_m)
+    }
+
+    /// Total inertia at the rotor: `J` plus the inertia of the built-in load.
+    pub fn inertia(&self) -> f64 {
+        self.j + self.load.inertia()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Friction, FrictionModel, Load, LoadModel};
+
+    /// Analytic slopes match central differences for every model, away from
+    /// the constant-power knee.
+    #[test]
+    fn speed_torque_slopes_match_finite_differences() {
+        let frictions = FrictionModel::ALL.map(|model| Friction {
+            model,
+            ..Friction::default()
+        });
+        let loads = LoadModel::ALL.map(|model| Load {
+            model,
+            grade: 5.0,
+            ..Load::default()
+        });
+        let h = 1e-6;
+        for omega in [-30.0, -0.05, 0.02, 3.0, 40.0] {
+            let models = frictions
+                .iter()
+                .map(|f| (f.torque(omega), f.torque(omega + h), f.torque(omega - h)))
+                .chain(
+                    loads
+                        .iter()
+                        .map(|l| (l.torque(omega), l.torque(omega + h), l.torque(omega - h))),
+                );
+            for (at, plus, minus) in models {
+                let numeric = (plus.torque - minus.torque) / (2.0 * h);
+                assert!(
+                    (at.d_omega - numeric).abs() < 1e-4 * (1.0 + numeric.abs()),
+                    "slope at {omega}: {} vs {numeric}",
+                    at.d_omega
+                );
+            }
+        }
+    }
+
+    /// Stribeck friction breaks away at `T_S` and settles to `T_C`; a
+    /// constant-power load draws `P` above `ω_min`.
+    #[test]
+    fn friction_and_load_levels() {
+        let stribeck = Friction {
+            model: FrictionModel::Stribeck,
+            omega_eps: 0.01,
+            ..Friction::default()
+        };
+        let near_zero = stribeck.torque(10.0 * stribeck.omega_eps).torque;
+        let fast = stribeck.torque(20.0 * stribeck.omega_s).torque;
+        assert!(
+            (near_zero - stribeck.t_s).abs() < 1e-3 * stribeck.t_s,
+            "breakaway {near_zero}"
+        );
+        assert!((fast - stribeck.t_c).abs() < 1e-9, "sliding {fast}");
+
+        let power = Load {
+            model: LoadModel::ConstantPower,
+            ..Load::default()
+        };
+        let omega = 4.0 * power.omega_min;
+        assert!(
+            (power.torque(omega).torque * omega - power.power).abs() < 1e-9,
+            "constant power above ω_min"
+        );
+    }
 }
@@ -0,0 +1,272 @@
+//! Metrics node — scalar statistics and step-response figures of a Signal
+//! over a chosen time window.
+//!
+//! Over the samples of the window `[t_from, t_to]` the node reports
+//!
+//! ```text
+//! mean = (1/T)·∫x dt,   RMS = √((1/T)·∫x² dt),   ripple = 100·(max − min) / |mean|
+//! ```
+//!
+//! with trapezoidal integrals, and treats the window as a step response
+//! from its first value `x₀` to its last value `x_∞`: rise time from 10 % to
+//! 90 % of the step, overshoot beyond `x_∞` in percent of the step, and
+//! settling time after the window start until `x` stays within the band
+//! around `x_∞`. When `x_∞ ≈ x₀` there is no step and these are `NaN`.
+//!
+//! Each figure is published as a named scalar `<name>.<metric>`, e.g.
+//! `speed.settling_time`, for sweeps and regression tests.
+
+use egui::Color32;
+
+use crate::port::PortType;
+
+/// Statistics of one window of a Signal.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SignalMetrics {
+    /// Root mean square over time.
+    pub rms: f64,
+    /// Time average.
+    pub mean: f64,
+    /// Largest minus smallest value.
+    pub peak_to_peak: f64,
+    /// Peak-to-peak in percent of the mean magnitude.
+    pub ripple: f64,
+    /// Time from 10 % to 90 % of the step (s).
+    pub rise_time: f64,
+    /// Largest excursion beyond the final value, in percent of the step.
+    pub overshoot: f64,
+    /// Time from the window start until the value stays within the band (s).
+    pub settling_time: f64,
+}
+
+impl SignalMetrics {
+    /// Scalar names, display labels and values, in display order.
+    pub fn table(&self) -> [(&'static str, &'static str, f64); 7] {
+        [
+            ("rms", "RMS", self.rms),
+            ("mean", "Mean", self.mean),
+            ("peak_to_peak", "Peak-peak", self.peak_to_peak),
+            ("ripple", "Ripple (%)", self.ripple),
+            ("rise_time", "Rise time (s)", self.rise_time),
+            ("overshoot", "Overshoot (%)", self.overshoot),
+            ("settling_time", "Settling time (s)", self.settling_time),
+        ]
+    }
+
+    /// Metrics of `[t, value]` samples, or `None` when there are none.
+    pub fn of(samples: &[[f64; 2]], band: f64) -> Option<Self> {
+        let &[t_0, x_0] = samples.first()?;
+        let &[_, x_end] = samples.last()?;
+        let (mean, rms) = time_averages(samples)?;
+        let (min, max) = samples
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &[_, x]| {
+                (lo.min(x), hi.max(x))
+            });
+        let peak_to_peak = max - min;
+        let ripple = if mean == 0.0 {
+            f64::NAN
+        } else {
+            100.0 * peak_to_peak / mean.abs()
+        };
+
+        // Step figures on the normalised response s = (x − x₀)/(x_∞ − x₀)
+        let step = x_end - x_0;
+        let (rise_time, overshoot, settling_time) =
+            if step.abs() <= f64::EPSILON.sqrt() * peak_to_peak {
+                (f64::NAN, f64::NAN, f64::NAN)
+            } else {
+                let s = |x: f64| (x - x_0) / step;
+                let reach = |level: f64| {
+                    samples.windows(2).find_map(|window| match window {
+                        &[[ta, a], [tb, b]] if s(b) >= level => {
+                            Some(ta + (tb - ta) * ((level - s(a)) / (s(b) - s(a))).clamp(0.0, 1.0))
+                        }
+                        _ => None,
+                    })
+                };
+                let rise = reach(0.9)
+                    .zip(reach(0.1))
+                    .map_or(f64::NAN, |(t_90, t_10)| t_90 - t_10);
+                let peak = samples
+                    .iter()
+                    .map(|&[_, x]| s(x))
+                    .fold(f64::NEG_INFINITY, f64::max);
+                // Last exit from the band, interpolated onto its edge
+                let tolerance = band / 100.0;
+                let settled = samples
+                    .windows(2)
+                    .rev()
+                    .find_map(|window| match window {
+                        &[[ta, a], [tb, b]] if (s(a) - 1.0).abs() > tolerance => {
+                            let (ea, eb) = ((s(a) - 1.0).abs(), (s(b) - 1.0).abs());
+                            Some(ta + (tb - ta) * ((ea - tolerance) / (ea - eb)).clamp(0.0, 1.0))
+                        }
+                        _ => None,
+                    })
+                    .unwrap_or(t_0);
+                (rise, 100.0 * (peak - 1.0).max(0.0), settled - t_0)
+            };
+
+        Some(Self {
+            rms,
+            mean,
+            peak_to_peak,
+            ripple,
+            rise_time,
+            overshoot,
+            settling_time,
+        })
+    }
+}
+
+/// Trapezoidal time average and RMS of `[t, value]` samples, or `None` when
+/// there are none. Samples spanning no time average to the first value.
+pub fn time_averages(samples: &[[f64; 2]]) -> Option<(f64, f64)> {
+    let &[_, x_0] = samples.first()?;
+    let (mut area, mut square, mut span) = (0.0, 0.0, 0.0);
+    for window in samples.windows(2) {
+        if let &[[ta, a], [tb, b]] = window {
+            area += 0.5 * (a + b) * (tb - ta);
+            square += 0.5 * (a * a + b * b) * (tb - ta);
+            span += tb - ta;
+        }
+    }
+    Some(if span > 0.0 {
+        (area / span, (square / span).sqrt())
+    } else {
+        (x_0, x_0.abs())
+    })
+}
+
+/// A sink node that reports the metrics of a Signal over a time window.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MetricsNode {
+    /// Prefix of the published scalar names.
+    pub name: String,
+    /// Window start (s).
+    pub t_from: f64,
+    /// Window end (s); at or before `t_from` the window runs to the end.
+    pub t_to: f64,
+    /// Settling band around the final value, in percent of the step.
+    pub band: f64,
+    /// Metrics of the last run.
+    #[serde(skip)]
+    pub metrics: Option<SignalMetrics>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for MetricsNode {
+    fn default() -> Self {
+        Self {
+            name: "metrics".to_owned(),
+            t_from: 0.0,
+            t_to: 0.0,
+            band: 2.0,
+            metrics: None,
+            custom_size: None,
+        }
+    }
+}
+
+impl MetricsNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "Metrics"
+    }
+
+    /// Input ports: the analysed Signal `x`.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[("x", PortType::Signal)]
+    }
+
+    /// Output port list — empty; this is a sink node.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[]
+    }
+
+    /// Header background color, shared with the Plot node.
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x40, 0xB0, 0x40)
+    }
+
+    /// Metrics of the samples of `signal` inside the window.
+    pub fn evaluate(&self, signal: &[[f64; 2]]) -> Option<SignalMetrics> {
+        let to_end = self.t_to <= self.t_from;
+        let window: Vec<[f64; 2]> = signal
+            .iter()
+            .copied()
+            .filter(|&[t, _]| t >= self.t_from && (to_end || t <= self.t_to))
+            .collect();
+        SignalMetrics::of(&window, self.band)
+    }
+
+    /// Named scalars `<name>.<metric>` of the last run.
+    pub fn scalars(&self) -> Vec<(String, f64)> {
+        self.metrics
+            .iter()
+            .flat_map(SignalMetrics::table)
+            .map(|(key, _, value)| (format!("{}.{key}", self.name), value))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SignalMetrics;
+
+    /// A ramp from 0 to 1 over 1 s, a 20 % overshoot decaying by 2.5 s and a
+    /// flat tail give the analytic step figures; a sine on an offset gives its
+    /// RMS and ripple but no step.
+    #[test]
+    fn step_and_ripple_figures() {
+        let step: Vec<[f64; 2]> = (0..=400)
+            .map(|k| {
+                let t = k as f64 * 0.01;
+                let x = if t <= 1.0 {
+                    t
+                } else if t <= 1.5 {
+                    1.0 + 0.4 * (t - 1.0)
+                } else if t <= 2.5 {
+                    1.2 - 0.2 * (t - 1.5)
+                } else {
+                    1.0
+                };
+                [t, x]
+            })
+            .collect();
+        let m = SignalMetrics::of(&step, 2.0).expect("samples");
+        assert!((m.rise_time - 0.8).abs() < 1e-9, "rise {}", m.rise_time);
+        assert!(
+            (m.overshoot - 20.0).abs() < 1e-9,
+            "overshoot {}",
+            m.overshoot
+        );
+        // Back within 2 % once 1.2 − 0.2·(t − 1.5) ≤ 1.02
+        assert!(
+            (m.settling_time - 2.4).abs() < 1e-9,
+            "settling {}",
+            m.settling_time
+        );
+        assert!((m.peak_to_peak - 1.2).abs() < 1e-12);
+
+        let sine: Vec<[f64; 2]> = (0..=1000)
+            .map(|k| {
+                let t = k as f64 / 1000.0;
+                [t, 10.0 + (std::f64::consts::TAU * 5.0 * t).sin()]
+            })
+            .collect();
+        let m = SignalMetrics::of(&sine, 2.0).expect("samples");
+        assert!((m.mean - 10.0).abs() < 1e-6, "mean {}", m.mean);
+        assert!(
+            (m.rms - (100.0_f64 + 0.5).sqrt()).abs() < 1e-4,
+            "rms {}",
+            m.rms
+        );
+        assert!((m.ripple - 20.0).abs() < 1e-3, "ripple {}", m.ripple);
+        assert!(m.overshoot.is_nan(), "no step over whole periods");
+    }
+}
@@ -5,12 +5,33 @@
 //! [`SnarlViewer`](egui_snarl::ui::SnarlViewer) to render the graph
 //! and enforce typed-port connection rules.
 
+pub mod abc_machine;
+pub mod bldc;
+pub mod bode;
 pub mod constant;
+pub mod dc_link;
+pub mod dc_motor;
+pub mod efficiency_map;
+pub mod ekf;
 pub mod electrical;
+pub mod flux_map;
+pub mod harmonics;
+pub mod induction;
+pub mod inverter;
+pub mod measurement;
 pub mod mechanical;
+pub mod metrics;
+pub mod observer;
 pub mod park;
 pub mod plot;
+pub mod pmsm;
+pub mod power;
+pub mod sensor;
+pub mod spectrum;
+pub mod svpwm;
+pub mod thermal;
 pub mod torque;
+pub mod two_mass;
 
 use egui::{Color32, Ui};
 use egui_snarl::ui::{
@@ -21,13 +42,31 @@ use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
 
 use crate::port::{PortType, PortValue};
 
+use self::abc_machine::AbcMachineNode;
+use self::bldc::BldcNode;
+use self::bode::BodeNode;
 use self::constant::ConstantNode;
+use self::dc_link::DcLinkNode;
+use self::dc_motor::DcMotorNode;
+use self::efficiency_map::EfficiencyMapNode;
+use self::ekf::EkfNode;
 use self::electrical::ElectricalNode;
+use self::induction::InductionMachineNode;
+use self::inverter::InverterNode;
+use self::measurement::MeasurementNode;
 use self::mechanical::MechanicalNode;
+use self::metrics::MetricsNode;
+use self::observer::ObserverNode;
 use self::park::InverseParkNode;
 use self::park::ParkNode;
 use self::plot::PlotNode;
+use self::power::PowerFlowNode;
+use self::sensor::PositionSensorNode;
+use self::spectrum::SpectrumNode;
+use self::svpwm::SvpwmNode;
+use self::thermal::ThermalNode;
 use self::torque::TorqueNode;
+use self::two_mass::TwoMassNode;
 
 /// All node types that can appear in the simulation graph.
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
@@ -46,6 +85,42 @@ pub enum SimNode {
     Park(ParkNode),
     /// Time-series plot (sink).
     Plot(PlotNode),
+    /// Space-vector PWM modulator (algebraic).
+    Svpwm(SvpwmNode),
+    /// Switched two-level inverter with dead time.
+    Inverter(InverterNode),
+    /// Battery-fed DC-link capacitor (ODE).
+    DcLink(DcLinkNode),
+    /// Three-phase abc-frame PMSM with fault injection (ODE).
+    AbcMachine(Box<AbcMachineNode>),
+    /// Induction machine in a selectable two-axis frame (ODE).
+    Induction(InductionMachineNode),
+    /// Brushed DC motor armature circuit (ODE).
+    DcMotor(DcMotorNode),
+    /// BLDC motor with trapezoidal back-EMF and six-step drive (ODE).
+    Bldc(BldcNode),
+    /// Two-inertia drivetrain with compliant shaft and backlash (ODE).
+    TwoMass(TwoMassNode),
+    /// Lumped-parameter thermal network (ODE).
+    Thermal(ThermalNode),
+    /// Encoder, resolver or Hall position sensor (post-processing).
+    Sensor(PositionSensorNode),
+    /// Filter, ADC and sensor-error chain (post-processing).
+    Measurement(MeasurementNode),
+    /// Sensorless angle and speed observer (post-processing ODE).
+    Observer(ObserverNode),
+    /// Extended Kalman filter for the PMSM states (post-processing).
+    Ekf(EkfNode),
+    /// Small-signal Bode plot and poles of the linearised PMSM (sink).
+    Bode(BodeNode),
+    /// Harmonic spectrum, THD and fundamental of a Signal or Vector (sink).
+    Spectrum(SpectrumNode),
+    /// RMS, mean, ripple and step-response figures of a Signal (sink).
+    Metrics(MetricsNode),
+    /// Power flow, efficiency and energy balance of the d/q PMSM (algebraic).
+    PowerFlow(PowerFlowNode),
+    /// Torque–speed envelope and efficiency map of the d/q PMSM (sink).
+    EfficiencyMap(EfficiencyMapNode),
 }
 
 impl SimNode {
@@ -59,6 +134,24 @@ impl SimNode {
             Self::InversePark(_) => InverseParkNode::title(),
             Self::Park(_) => ParkNode::title(),
             Self::Plot(_) => PlotNode::title(),
+            Self::Svpwm(_) => SvpwmNode::title(),
+            Self::Inverter(_) => InverterNode::title(),
+            Self::DcLink(_) => DcLinkNode::title(),
+            Self::AbcMachine(_) => AbcMachineNode::title(),
+            Self::Induction(_) => InductionMachineNode::title(),
+            Self::DcMotor(_) => DcMotorNode::title(),
+            Self::Bldc(_) => BldcNode::title(),
+            Self::TwoMass(_) => TwoMassNode::title(),
+            Self::Thermal(_) => ThermalNode::title(),
+            Self::Sensor(_) => PositionSensorNode::title(),
+            Self::Measurement(_) => MeasurementNode::title(),
+            Self::Observer(_) => ObserverNode::title(),
+            Self::Ekf(_) => EkfNode::title(),
+            Self::Bode(_) => BodeNode::title(),
+            Self::Spectrum(_) => SpectrumNode::title(),
+            Self::Metrics(_) => MetricsNode::title(),
+            Self::PowerFlow(_) => PowerFlowNode::title(),
+            Self::EfficiencyMap(_) => EfficiencyMapNode::title(),
         }
     }
 
@@ -94,6 +187,45 @@ impl SimNode {
                 .collect(),
             Self::Park(_) => ParkNode::input_ports().iter().map(|(_, t)| *t).collect(),
             Self::Plot(p) => p.input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Svpwm(s) => s.input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Inverter(_) => InverterNode::input_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::DcLink(_) => DcLinkNode::input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::AbcMachine(_) => AbcMachineNode::input_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::Induction(_) => InductionMachineNode::input_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::DcMotor(_) => DcMotorNode::input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Bldc(_) => BldcNode::input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::TwoMass(_) => TwoMassNode::input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Thermal(_) => ThermalNode::input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Sensor(_) => PositionSensorNode::input_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::Measurement(m) => m.input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Observer(_) => ObserverNode::input_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::Ekf(_) => EkfNode::input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Bode(_) => BodeNode::input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Spectrum(s) => s.input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Metrics(_) => MetricsNode::input_ports().iter().map(|(_, t)| *t).collect(),
+            Self::PowerFlow(_) => PowerFlowNode::input_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::EfficiencyMap(_) => EfficiencyMapNode::input_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
         }
     }
 
@@ -116,6 +248,60 @@ impl SimNode {
                 .collect(),
             Self::Park(_) => ParkNode::output_ports().iter().map(|(_, t)| *t).collect(),
             Self::Plot(_) => PlotNode::output_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Svpwm(_) => SvpwmNode::output_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Inverter(_) => InverterNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::DcLink(_) => DcLinkNode::output_ports().iter().map(|(_, t)| *t).collect(),
+            Self::AbcMachine(_) => AbcMachineNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::Induction(_) => InductionMachineNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::DcMotor(_) => DcMotorNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::Bldc(_) => BldcNode::output_ports().iter().map(|(_, t)| *t).collect(),
+            Self::TwoMass(_) => TwoMassNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::Thermal(_) => ThermalNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::Sensor(_) => PositionSensorNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::Measurement(m) => m.output_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Observer(_) => ObserverNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::Ekf(_) => EkfNode::output_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Bode(_) => BodeNode::output_ports().iter().map(|(_, t)| *t).collect(),
+            Self::Spectrum(_) => SpectrumNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::Metrics(_) => MetricsNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::PowerFlow(_) => PowerFlowNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
+            Self::EfficiencyMap(_) => EfficiencyMapNode::output_ports()
+                .iter()
+                .map(|(_, t)| *t)
+                .collect(),
         }
     }
 
@@ -147,6 +333,46 @@ impl SimNode {
                 .map_or("?", |(n, _)| n),
             Self::Park(_) => ParkNode::input_ports().get(input).map_or("?", |(n, _)| n),
             Self::Plot(_) => "data",
+            Self::Svpwm(s) => s.input_ports().get(input).map_or("?", |(n, _)| *n),
+            Self::Inverter(_) => InverterNode::input_ports()
+                .get(input)
+                .map_or("?", |(n, _)| n),
+            Self::DcLink(_) => DcLinkNode::input_ports().get(input).map_or("?", |(n, _)| n),
+            Self::AbcMachine(_) => AbcMachineNode::input_ports()
+                .get(input)
+                .map_or("?", |(n, _)| n),
+            Self::Induction(_) => InductionMachineNode::input_ports()
+                .get(input)
+                .map_or("?", |(n, _)| n),
+            Self::DcMotor(_) => DcMotorNode::input_ports()
+                .get(input)
+                .map_or("?", |(n, _)| n),
+            Self::Bldc(_) => BldcNode::input_ports().get(input).map_or("?", |(n, _)| n),
+            Self::TwoMass(_) => TwoMassNode::input_ports()
+                .get(input)
+                .map_or("?", |(n, _)| n),
+            Self::Thermal(_) => ThermalNode::input_ports()
+                .get(input)
+                .map_or("?", |(n, _)| n),
+            Self::Sensor(_) => PositionSensorNode::input_ports()
+                .get(input)
+                .map_or("?", |(n, _)| n),
+            Self::Measurement(m) => m.input_ports().get(input).map_or("?", |(n, _)| *n),
+            Self::Observer(_) => ObserverNode::input_ports()
+                .get(input)
+                .map_or("?", |(n, _)| n),
+            Self::Ekf(_) => EkfNode::input_ports().get(input).map_or("?", |(n, _)| n),
+            Self::Bode(_) => BodeNode::input_ports().get(input).map_or("?", |(n, _)| n),
+            Self::Spectrum(s) => s.input_ports().get(input).map_or("?", |(n, _)| *n),
+            Self::Metrics(_) => MetricsNode::input_ports()
+                .get(input)
+                .map_or("?", |(n, _)| n),
+            Self::PowerFlow(_) => PowerFlowNode::input_ports()
+                .get(input)
+                .map_or("?", |(n, _)| n),
+            Self::EfficiencyMap(_) => EfficiencyMapNode::input_ports()
+                .get(input)
+                .map_or("?", |(n, _)| n),
         }
     }
 
@@ -168,6 +394,52 @@ impl SimNode {
                 .map_or("?", |(n, _)| n),
             Self::Park(_) => ParkNode::output_ports().get(output).map_or("?", |(n, _)| n),
             Self::Plot(_) => PlotNode::output_ports().get(output).map_or("?", |(n, _)| n),
+            Self::Svpwm(_) => SvpwmNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::Inverter(_) => InverterNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::DcLink(_) => DcLinkNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::AbcMachine(_) => AbcMachineNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::Induction(_) => InductionMachineNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::DcMotor(_) => DcMotorNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::Bldc(_) => BldcNode::output_ports().get(output).map_or("?", |(n, _)| n),
+            Self::TwoMass(_) => TwoMassNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::Thermal(_) => ThermalNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::Sensor(_) => PositionSensorNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::Measurement(m) => m.output_ports().get(output).map_or("?", |(n, _)| *n),
+            Self::Observer(_) => ObserverNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::Ekf(_) => EkfNode::output_ports().get(output).map_or("?", |(n, _)| n),
+            Self::Bode(_) => BodeNode::output_ports().get(output).map_or("?", |(n, _)| n),
+            Self::Spectrum(_) => SpectrumNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::Metrics(_) => MetricsNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::PowerFlow(_) => PowerFlowNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
+            Self::EfficiencyMap(_) => EfficiencyMapNode::output_ports()
+                .get(output)
+                .map_or("?", |(n, _)| n),
         }
     }
 
@@ -181,6 +453,24 @@ impl SimNode {
             Self::InversePark(_) => InverseParkNode::header_color(),
             Self::Park(_) => ParkNode::header_color(),
             Self::Plot(_) => PlotNode::header_color(),
+            Self::Svpwm(_) => SvpwmNode::header_color(),
+            Self::Inverter(_) => InverterNode::header_color(),
+            Self::DcLink(_) => DcLinkNode::header_color(),
+            Self::AbcMachine(_) => AbcMachineNode::header_color(),
+            Self::Induction(_) => InductionMachineNode::header_color(),
+            Self::DcMotor(_) => DcMotorNode::header_color(),
+            Self::Bldc(_) => BldcNode::header_color(),
+            Self::TwoMass(_) => TwoMassNode::header_color(),
+            Self::Thermal(_) => ThermalNode::header_color(),
+            Self::Sensor(_) => PositionSensorNode::header_color(),
+            Self::Measurement(_) => MeasurementNode::header_color(),
+            Self::Observer(_) => ObserverNode::header_color(),
+            Self::Ekf(_) => EkfNode::header_color(),
+            Self::Bode(_) => BodeNode::header_color(),
+            Self::Spectrum(_) => SpectrumNode::header_color(),
+            Self::Metrics(_) => MetricsNode::header_color(),
+            Self::PowerFlow(_) => PowerFlowNode::header_color(),
+            Self::EfficiencyMap(_) => EfficiencyMapNode::header_color(),
         }
     }
 
@@ -189,6 +479,8 @@ impl SimNode {
         match (self, output) {
             (Self::Electrical(e), 0) => e.output_i_d.as_ref(),
             (Self::Electrical(e), 1) => e.output_i_q.as_ref(),
+            (Self::Electrical(e), 2) => e.output_p_cu.as_ref(),
+            (Self::Electrical(e), 3) => e.output_p_fe.as_ref(),
             (Self::Torque(t), 0) => t.output_t_e.as_ref(),
             (Self::Mechanical(m), 0) => m.output_omega_m.as_ref(),
             (Self::Mechanical(m), 1) => m.output_theta_e.as_ref(),
@@ -196,6 +488,57 @@ impl SimNode {
             (Self::Park(p), 0) => p.output_f_d.as_ref(),
             (Self::Park(p), 1) => p.output_f_q.as_ref(),
             (Self::Constant(c), 0) => c.output_port_value.as_ref(),
+            (Self::Svpwm(s), 0) => s.output_duty.as_ref(),
+            (Self::Svpwm(s), 1) => s.output_v_abc.as_ref(),
+            (Self::Inverter(inv), 0) => inv.output_v_abc.as_ref(),
+            (Self::DcLink(d), 0) => d.output_v_dc.as_ref(),
+            (Self::DcLink(d), 1) => d.output_i_bat.as_ref(),
+            (Self::DcLink(d), 2) => d.output_i_dc.as_ref(),
+            (Self::AbcMachine(m), 0) => m.output_i_abc.as_ref(),
+            (Self::AbcMachine(m), 1) => m.output_t_e.as_ref(),
+            (Self::AbcMachine(m), 2) => m.output_i_f.as_ref(),
+            (Self::Induction(m), 0) => m.output_i_sd.as_ref(),
+            (Self::Induction(m), 1) => m.output_i_sq.as_ref(),
+            (Self::Induction(m), 2) => m.output_psi_rd.as_ref(),
+            (Self::Induction(m), 3) => m.output_psi_rq.as_ref(),
+            (Self::Induction(m), 4) => m.output_t_e.as_ref(),
+            (Self::DcMotor(m), 0) => m.output_i_a.as_ref(),
+            (Self::DcMotor(m), 1) => m.output_t_e.as_ref(),
+            (Self::Bldc(m), 0) => m.output_i_abc.as_ref(),
+            (Self::Bldc(m), 1) => m.output_hall.as_ref(),
+            (Self::Bldc(m), 2) => m.output_t_e.as_ref(),
+            (Self::TwoMass(m), 0) => m.output_omega_m.as_ref(),
+            (Self::TwoMass(m), 1) => m.output_theta_e.as_ref(),
+            (Self::TwoMass(m), 2) => m.output_omega_l.as_ref(),
+            (Self::TwoMass(m), 3) => m.output_t_sh.as_ref(),
+            (Self::Thermal(th), 0) => th.output_t_w.as_ref(),
+            (Self::Thermal(th), 1) => th.output_t_pm.as_ref(),
+            (Self::Thermal(th), 2) => th.output_t.as_ref(),
+            (Self::Sensor(s), 0) => s.output_theta_e.as_ref(),
+            (Self::Sensor(s), 1) => s.output_omega_m.as_ref(),
+            (Self::Sensor(s), 2) => s.output_sector.as_ref(),
+            (Self::Sensor(s), 3) => s.output_raw.as_ref(),
+            (Self::Measurement(m), 0) => m.output_value.as_ref(),
+            (Self::Observer(o), 0) => o.output_theta_e.as_ref(),
+            (Self::Observer(o), 1) => o.output_omega_m.as_ref(),
+            (Self::Observer(o), 2) => o.output_error.as_ref(),
+            (Self::Observer(o), 3) => o.output_emf.as_ref(),
+            (Self::Ekf(e), 0) => e.output_i_d.as_ref(),
+            (Self::Ekf(e), 1) => e.output_i_q.as_ref(),
+            (Self::Ekf(e), 2) => e.output_omega_m.as_ref(),
+            (Self::Ekf(e), 3) => e.output_theta_e.as_ref(),
+            (Self::Ekf(e), 4) => e.output_param.as_ref(),
+            (Self::Ekf(e), 5) => e.output_error.as_ref(),
+            (Self::Ekf(e), 6) => e.output_sigma_omega.as_ref(),
+            (Self::Ekf(e), 7) => e.output_sigma_theta.as_ref(),
+            (Self::Ekf(e), 8) => e.output_sigma_param.as_ref(),
+            (Self::PowerFlow(pf), 0) => pf.output_p_e.as_ref(),
+            (Self::PowerFlow(pf), 1) => pf.output_p_cu.as_ref(),
+            (Self::PowerFlow(pf), 2) => pf.output_p_fe.as_ref(),
+            (Self::PowerFlow(pf), 3) => pf.output_p_m.as_ref(),
+            (Self::PowerFlow(pf), 4) => pf.output_p_fric.as_ref(),
+            (Self::PowerFlow(pf), 5) => pf.output_eta.as_ref(),
+            (Self::PowerFlow(pf), 6) => pf.output_e_in.as_ref(),
             _ => None,
         }
     }
@@ -210,6 +553,24 @@ impl SimNode {
             Self::InversePark(n) => n.custom_size,
             Self::Park(n) => n.custom_size,
             Self::Plot(n) => n.custom_size,
+            Self::Svpwm(n) => n.custom_size,
+            Self::Inverter(n) => n.custom_size,
+            Self::DcLink(n) => n.custom_size,
+            Self::AbcMachine(n) => n.custom_size,
+            Self::Induction(n) => n.custom_size,
+            Self::DcMotor(n) => n.custom_size,
+            Self::Bldc(n) => n.custom_size,
+            Self::TwoMass(n) => n.custom_size,
+            Self::Thermal(n) => n.custom_size,
+            Self::Sensor(n) => n.custom_size,
+            Self::Measurement(n) => n.custom_size,
+            Self::Observer(n) => n.custom_size,
+            Self::Ekf(n) => n.custom_size,
+            Self::Bode(n) => n.custom_size,
+            Self::Spectrum(n) => n.custom_size,
+            Self::Metrics(n) => n.custom_size,
+            Self::PowerFlow(n) => n.custom_size,
+            Self::EfficiencyMap(n) => n.custom_size,
         };
         raw.map(|[w, h]| egui::vec2(w, h))
     }
@@ -225,6 +586,24 @@ impl SimNode {
             Self::InversePark(n) => n.custom_size = val,
             Self::Park(n) => n.custom_size = val,
             Self::Plot(n) => n.custom_size = val,
+            Self::Svpwm(n) => n.custom_size = val,
+            Self::Inverter(n) => n.custom_size = val,
+            Self::DcLink(n) => n.custom_size = val,
+            Self::AbcMachine(n) => n.custom_size = val,
+            Self::Induction(n) => n.custom_size = val,
+            Self::DcMotor(n) => n.custom_size = val,
+            Self::Bldc(n) => n.custom_size = val,
+            Self::TwoMass(n) => n.custom_size = val,
+            Self::Thermal(n) => n.custom_size = val,
+            Self::Sensor(n) => n.custom_size = val,
+            Self::Measurement(n) => n.custom_size = val,
+            Self::Observer(n) => n.custom_size = val,
+            Self::Ekf(n) => n.custom_size = val,
+            Self::Bode(n) => n.custom_size = val,
+            Self::Spectrum(n) => n.custom_size = val,
+            Self::Metrics(n) => n.custom_size = val,
+            Self::PowerFlow(n) => n.custom_size = val,
+            Self::EfficiencyMap(n) => n.custom_size = val,
         }
     }
 
@@ -238,6 +617,24 @@ impl SimNode {
             Self::InversePark(n) => n.custom_size = None,
             Self::Park(n) => n.custom_size = None,
             Self::Plot(n) => n.custom_size = None,
+            Self::Svpwm(n) => n.custom_size = None,
+            Self::Inverter(n) => n.custom_size = None,
+            Self::DcLink(n) => n.custom_size = None,
+            Self::AbcMachine(n) => n.custom_size = None,
+            Self::Induction(n) => n.custom_size = None,
+            Self::DcMotor(n) => n.custom_size = None,
+            Self::Bldc(n) => n.custom_size = None,
+            Self::TwoMass(n) => n.custom_size = None,
+            Self::Thermal(n) => n.custom_size = None,
+            Self::Sensor(n) => n.custom_size = None,
+            Self::Measurement(n) => n.custom_size = None,
+            Self::Observer(n) => n.custom_size = None,
+            Self::Ekf(n) => n.custom_size = None,
+            Self::Bode(n) => n.custom_size = None,
+            Self::Spectrum(n) => n.custom_size = None,
+            Self::Metrics(n) => n.custom_size = None,
+            Self::PowerFlow(n) => n.custom_size = None,
+            Self::EfficiencyMap(n) => n.custom_size = None,
         }
     }
 }
@@ -292,6 +689,25 @@ pub struct SimViewer;
 
 impl SnarlViewer<SimNode> for SimViewer {
     fn connect(&mut self, from: &OutPin, to: &InPin, snarl: &mut Snarl<SimNode>) {
+        // A Measurement node adopts the Signal or Vector type wired into it;
+        // its output wires no longer fit once the type changes.
+        if let Some(out_t) = snarl[from.id.node].output_port_type(from.id.output)
+            && let SimNode::Measurement(m) = &mut snarl[to.id.node]
+            && m.adapt_port_type(out_t)
+        {
+            snarl.drop_outputs(OutPinId {
+                node: to.id.node,
+                output: 0,
+            });
+        }
+        // So does the analysed input of a Spectrum node.
+        if let Some(out_t) = snarl[from.id.node].output_port_type(from.id.output)
+            && to.id.input == 0
+            && let SimNode::Spectrum(s) = &mut snarl[to.id.node]
+        {
+            s.adapt_port_type(out_t);
+        }
+
         let in_type = snarl[to.id.node].input_port_type(to.id.input);
 
         // If the source is a Constant, adapt its output type to match the destination.
@@ -401,6 +817,7 @@ impl SnarlViewer<SimNode> for SimViewer {
         true
     }
 
+    #[expect(clippy::too_many_lines, reason = "one parameter grid per node type")]
     fn show_body(
         &mut self,
         node: NodeId,
@@ -441,7 +858,39 @@ impl SnarlViewer<SimNode> for SimViewer {
                         ui.end_row();
                         param_row(ui, "i_d\u{2080} (A)", &mut e.i_d_0);
                         param_row(ui, "i_q\u{2080} (A)", &mut e.i_q_0);
+                        ui.separator();
+                        ui.end_row();
+                        param_row(ui, "T_w (\u{00b0}C)", &mut e.t_winding);
+                        param_row(ui, "T_ref (\u{00b0}C)", &mut e.t_ref);
+                        param_row(ui, "\u{03b1}_Cu (1/K)", &mut e.alpha_cu);
+                        param_row(ui, "\u{03b1}_PM (1/K)", &mut e.alpha_pm);
+                        ui.label("Iron loss");
+                        egui::ComboBox::from_id_salt(ui.id().with("elec_iron_loss"))
+                            .selected_text(e.iron_loss.model.label())
+                            .show_ui(ui, |ui| {
+                                for model in electrical::IronLossModel::ALL {
+                                    ui.selectable_value(
+                                        &mut e.iron_loss.model,
+                                        model,
+                                        model.label(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+                        match e.iron_loss.model {
+                            electrical::IronLossModel::None => {}
+                            electrical::IronLossModel::Resistance => {
+                                param_row(ui, "R_fe (\u{03a9})", &mut e.iron_loss.r_fe);
+                            }
+                            electrical::IronLossModel::Steinmetz => {
+                                param_row(ui, "k_h", &mut e.iron_loss.k_h);
+                                param_row(ui, "k_e", &mut e.iron_loss.k_e);
+                                param_row(ui, "\u{03b2}", &mut e.iron_loss.beta);
+                            }
+                        }
                     });
+                show_flux_map_editor(ui, e);
+                show_harmonics_editor(ui, &mut e.harmonics);
             }
             SimNode::Mechanical(m) => {
                 egui::Grid::new(ui.id().with("mech_params"))
@@ -454,8 +903,90 @@ impl SnarlViewer<SimNode> for SimViewer {
                         ui.end_row();
                         param_row(ui, "\u{03c9}_m\u{2080} (rad/s)", &mut m.omega_m_0);
                         param_row(ui, "\u{03b8}_e\u{2080} (rad)", &mut m.theta_e_0);
+                        ui.separator();
+                        ui.end_row();
+                        ui.label("Friction");
+                        egui::ComboBox::from_id_salt(ui.id().with("mech_friction"))
+                            .selected_text(m.friction.model.label())
+                            .show_ui(ui, |ui| {
+                                for model in mechanical::FrictionModel::ALL {
+                                    ui.selectable_value(
+                                        &mut m.friction.model,
+                                        model,
+                                        model.label(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+                        let f = &mut m.friction;
+                        match f.model {
+                            mechanical::FrictionModel::None => {}
+                            mechanical::FrictionModel::Coulomb => {
+                                param_row(ui, "T_C (N\u{00b7}m)", &mut f.t_c);
+                                param_row(ui, "\u{03c9}_\u{03b5} (rad/s)", &mut f.omega_eps);
+                            }
+                            mechanical::FrictionModel::Stribeck => {
+                                param_row(ui, "T_C (N\u{00b7}m)", &mut f.t_c);
+                                param_row(ui, "T_S (N\u{00b7}m)", &mut f.t_s);
+                                param_row(ui, "\u{03c9}_S (rad/s)", &mut f.omega_s);
+                                param_row(ui, "\u{03c9}_\u{03b5} (rad/s)", &mut f.omega_eps);
+                            }
+                        }
+                        ui.label("Load");
+                        egui::ComboBox::from_id_salt(ui.id().with("mech_load"))
+                            .selected_text(m.load.model.label())
+                            .show_ui(ui, |ui| {
+                                for model in mechanical::LoadModel::ALL {
+                                    ui.selectable_value(&mut m.load.model, model, model.label());
+                                }
+                            });
+                        ui.end_row();
+                        let l = &mut m.load;
+                        match l.model {
+                            mechanical::LoadModel::None => {}
+                            mechanical::LoadModel::Fan => {
+                                param_row(ui, "k (N\u{00b7}m\u{00b7}s\u{00b2})", &mut l.k_fan);
+                            }
+                            mechanical::LoadModel::ConstantPower => {
+                                param_row(ui, "P (W)", &mut l.power);
+                                param_row(ui, "\u{03c9}_min (rad/s)", &mut l.omega_min);
+                            }
+                            mechanical::LoadModel::Vehicle => {
+                                param_row(ui, "m (kg)", &mut l.mass);
+                                param_row(ui, "Grade (%)", &mut l.grade);
+                                param_row(ui, "C_rr", &mut l.c_rr);
+                                param_row(ui, "C_d\u{00b7}A (m\u{00b2})", &mut l.c_d_a);
+                                param_row(ui, "\u{03c1} (kg/m\u{00b3})", &mut l.rho);
+                                param_row(ui, "r_w (m)", &mut l.wheel_radius);
+                                param_row(ui, "N (gear)", &mut l.ratio);
+                                ui.label("J_veh (kg\u{00b7}m\u{00b2})");
+                                ui.label(format!("{:.4}", l.inertia()));
+                                ui.end_row();
+                            }
+                        }
+                    });
+            }
+            SimNode::TwoMass(m) => {
+                egui::Grid::new(ui.id().with("two_mass_params"))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        param_row(ui, "J_m (kg\u{00b7}m\u{00b2})", &mut m.j_m);
+                        param_row(ui, "B_m (N\u{00b7}m\u{00b7}s/rad)", &mut m.b_m);
+                        param_row(ui, "J_L (kg\u{00b7}m\u{00b2})", &mut m.j_l);
+                        param_row(ui, "B_L (N\u{00b7}m\u{00b7}s/rad)", &mut m.b_l);
+                        param_row(ui, "k (N\u{00b7}m/rad)", &mut m.k_s);
+                        param_row(ui, "c (N\u{00b7}m\u{00b7}s/rad)", &mut m.c_s);
+                        param_row(ui, "Gear ratio N", &mut m.ratio);
+                        param_row(ui, "Backlash (rad)", &mut m.backlash);
+                        param_row(ui, "N_p", &mut m.n_p);
+                        ui.separator();
+                        ui.end_row();
+                        param_row(ui, "\u{03c9}_m\u{2080} (rad/s)", &mut m.omega_m_0);
+                        param_row(ui, "\u{03c6}\u{2080} (rad)", &mut m.twist_0);
+                        param_row(ui, "\u{03b8}_e\u{2080} (rad)", &mut m.theta_e_0);
                     });
             }
+            SimNode::Thermal(th) => show_thermal_editor(ui, th),
             SimNode::Torque(t) => {
                 egui::Grid::new(ui.id().with("torque_params"))
                     .num_columns(2)
@@ -466,6 +997,165 @@ impl SnarlViewer<SimNode> for SimViewer {
                         param_row(ui, "L_q (H)", &mut t.l_q);
                     });
             }
+            SimNode::Svpwm(s) => {
+                egui::Grid::new(ui.id().with("svpwm_params"))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Frame");
+                        egui::ComboBox::from_id_salt(ui.id().with("svpwm_frame"))
+                            .selected_text(s.frame.label())
+                            .show_ui(ui, |ui| {
+                                for frame in svpwm::ReferenceFrame::ALL {
+                                    ui.selectable_value(&mut s.frame, frame, frame.label());
+                                }
+                            });
+                        ui.end_row();
+                        ui.label("Overmod.");
+                        egui::ComboBox::from_id_salt(ui.id().with("svpwm_overmod"))
+                            .selected_text(s.overmodulation.label())
+                            .show_ui(ui, |ui| {
+                                for mode in svpwm::OvermodulationMode::ALL {
+                                    ui.selectable_value(&mut s.overmodulation, mode, mode.label());
+                                }
+                            });
+                        ui.end_row();
+                        ui.label("f_sw (Hz)");
+                        ui.add(
+                            egui::DragValue::new(&mut s.f_sw)
+                                .speed(100.0)
+                                .range(1.0..=1e6),
+                        );
+                        ui.end_row();
+                        param_row(ui, "t_min (s)", &mut s.t_min);
+                    });
+            }
+            SimNode::Inverter(inv) => {
+                egui::Grid::new(ui.id().with("inverter_params"))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("f_sw (Hz)");
+                        ui.add(
+                            egui::DragValue::new(&mut inv.f_sw)
+                                .speed(100.0)
+                                .range(1.0..=1e6),
+                        );
+                        ui.end_row();
+                        param_row(ui, "t_dead (s)", &mut inv.t_dead);
+                        param_row(ui, "V_diode (V)", &mut inv.v_diode);
+                    });
+            }
+            SimNode::DcLink(d) => {
+                egui::Grid::new(ui.id().with("dc_link_params"))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        param_row(ui, "C (F)", &mut d.capacitance);
+                        param_row(ui, "ESR (\u{03a9})", &mut d.esr);
+                        param_row(ui, "V_oc (V)", &mut d.v_oc);
+                        param_row(ui, "R_bat (\u{03a9})", &mut d.r_bat);
+                        ui.label("Chopper");
+                        ui.checkbox(&mut d.chopper, "");
+                        ui.end_row();
+                        if d.chopper {
+                            param_row(ui, "V_chop (V)", &mut d.v_chop);
+                            param_row(ui, "Band (V)", &mut d.chop_band);
+                            param_row(ui, "R_chop (\u{03a9})", &mut d.r_chop);
+                        }
+                    });
+            }
+            SimNode::AbcMachine(m) => show_abc_machine_params(ui, m),
+            SimNode::Induction(m) => {
+                egui::Grid::new(ui.id().with("induction_params"))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        param_row(ui, "R_s (\u{03a9})", &mut m.r_s);
+                        param_row(ui, "R_r (\u{03a9})", &mut m.r_r);
+                        param_row(ui, "L_ls (H)", &mut m.l_ls);
+                        param_row(ui, "L_lr (H)", &mut m.l_lr);
+                        param_row(ui, "L_m (H)", &mut m.l_m);
+                        param_row(ui, "N_p", &mut m.n_p);
+                        ui.label("Frame");
+                        egui::ComboBox::from_id_salt(ui.id().with("induction_frame"))
+                            .selected_text(m.frame.label())
+                            .show_ui(ui, |ui| {
+                                for frame in induction::InductionFrame::ALL {
+                                    ui.selectable_value(&mut m.frame, frame, frame.label());
+                                }
+                            });
+                        ui.end_row();
+                        if m.frame == induction::InductionFrame::Synchronous {
+                            param_row(ui, "f_sync (Hz)", &mut m.f_sync);
+                        }
+                    });
+            }
+            SimNode::DcMotor(m) => {
+                egui::Grid::new(ui.id().with("dc_motor_params"))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        param_row(ui, "R_a (\u{03a9})", &mut m.r_a);
+                        param_row(ui, "L_a (H)", &mut m.l_a);
+                        param_row(ui, "k_e (V\u{00b7}s/rad)", &mut m.k_e);
+                        param_row(ui, "k_t (N\u{00b7}m/A)", &mut m.k_t);
+                        param_row(ui, "i_a\u{2080} (A)", &mut m.i_a_0);
+                    });
+            }
+            SimNode::Bldc(m) => {
+                egui::Grid::new(ui.id().with("bldc_params"))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        param_row(ui, "R_s (\u{03a9})", &mut m.r_s);
+                        param_row(ui, "L_s (H)", &mut m.l_s);
+                        param_row(ui, "k_e (V\u{00b7}s/rad)", &mut m.k_e);
+                        param_row(ui, "Hall offset (\u{00b0})", &mut m.hall_offset);
+                    });
+            }
+            SimNode::Sensor(s) => {
+                egui::Grid::new(ui.id().with("sensor_params"))
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Type");
+                        egui::ComboBox::from_id_salt(ui.id().with("sensor_model"))
+                            .selected_text(s.model.label())
+                            .show_ui(ui, |ui| {
+                                for model in sensor::SensorModel::ALL {
+                                    ui.selectable_value(&mut s.model, model, model.label());
+                                }
+                            });
+                        ui.end_row();
+                        param_row(ui, "N_p", &mut s.n_p);
+                        param_row(ui, "Offset (\u{00b0})", &mut s.offset);
+                        param_row(ui, "Latency (s)", &mut s.latency);
+                        match s.model {
+                            sensor::SensorModel::Encoder => {
+                                ui.label("Lines");
+                                ui.add(egui::DragValue::new(&mut s.lines).range(1..=1_000_000));
+                                ui.end_row();
+                                param_row(ui, "Speed window (s)", &mut s.speed_window);
+                            }
+                            sensor::SensorModel::Resolver => {
+                                param_row(ui, "Pole pairs", &mut s.resolver_pp);
+                                ui.label("f_ex (Hz)");
+                                ui.add(
+                                    egui::DragValue::new(&mut s.f_excitation)
+                                        .speed(100.0)
+                                        .range(1.0..=1e6),
+                                );
+                                ui.end_row();
+                                param_row(ui, "Gain error", &mut s.amplitude_error);
+                                param_row(ui, "Phase error (\u{00b0})", &mut s.phase_error);
+                                param_row(ui, "Speed window (s)", &mut s.speed_window);
+                            }
+                            sensor::SensorModel::Hall => {}
+                        }
+                    });
+            }
+            SimNode::Measurement(m) => show_measurement_params(ui, m),
+            SimNode::Observer(o) => show_observer_params(ui, o),
+            SimNode::Ekf(e) => show_ekf_params(ui, e),
+            SimNode::Bode(b) => show_bode(ui, b),
+            SimNode::Spectrum(s) => show_spectrum(ui, s),
+            SimNode::Metrics(m) => show_metrics(ui, m),
+            SimNode::PowerFlow(pf) => show_power_flow(ui, pf),
+            SimNode::EfficiencyMap(m) => show_efficiency_map(ui, m),
             SimNode::Constant(c) if c.output_type == PortType::Vector => {
                 egui::Grid::new(ui.id().with("const_vec"))
                     .num_columns(2)
@@ -519,7 +1209,13 @@ impl SnarlViewer<SimNode> for SimViewer {
                 };
                 let out_type = snarl[src_pin.node].output_port_type(src_pin.output);
 
-                for (label, template) in node_palette() {
+                for (label, mut template) in node_palette() {
+                    if let (SimNode::Measurement(m), Some(out_t)) = (&mut template, out_type) {
+                        m.adapt_port_type(out_t);
+                    }
+                    if let (SimNode::Spectrum(s), Some(out_t)) = (&mut template, out_type) {
+                        s.adapt_port_type(out_t);
+                    }
                     // Check if any input of this template is compatible.
                     // Plot accepts any plottable data (Signal or Vector).
                     let compatible_input = template
@@ -702,12 +1398,45 @@ fn node_palette() -> Vec<(&'static str, SimNode)> {
             "Mechanical Dynamics",
             SimNode::Mechanical(MechanicalNode::default()),
         ),
+        (
+            "Two-Mass Drivetrain",
+            SimNode::TwoMass(TwoMassNode::default()),
+        ),
         (
             "Inverse Park",
             SimNode::InversePark(InverseParkNode::default()),
         ),
         ("Park", SimNode::Park(ParkNode::default())),
+        ("SVPWM", SimNode::Svpwm(SvpwmNode::default())),
+        ("Inverter", SimNode::Inverter(InverterNode::default())),
+        ("DC Link", SimNode::DcLink(DcLinkNode::default())),
+        ("PMSM (abc)", SimNode::AbcMachine(Box::default())),
+        (
+            "Induction Machine",
+            SimNode::Induction(InductionMachineNode::default()),
+        ),
+        ("DC Motor", SimNode::DcMotor(DcMotorNode::default())),
+        ("BLDC Motor", SimNode::Bldc(BldcNode::default())),
+        ("Thermal Network", SimNode::Thermal(ThermalNode::default())),
+        (
+            "Position Sensor",
+            SimNode::Sensor(PositionSensorNode::default()),
+        ),
+        (
+            "Measurement",
+            SimNode::Measurement(MeasurementNode::default()),
+        ),
+        ("Observer", SimNode::Observer(ObserverNode::default())),
+        ("EKF", SimNode::Ekf(EkfNode::default())),
         ("Plot", SimNode::Plot(PlotNode::default())),
+        ("Bode", SimNode::Bode(BodeNode::default())),
+        ("Spectrum", SimNode::Spectrum(SpectrumNode::default())),
+        ("Metrics", SimNode::Metrics(MetricsNode::default())),
+        ("Power Flow", SimNode::PowerFlow(PowerFlowNode::default())),
+        (
+            "Efficiency Map",
+            SimNode::EfficiencyMap(EfficiencyMapNode::default()),
+        ),
     ]
 }
 
@@ -718,58 +1447,882 @@ fn param_row(ui: &mut Ui, label: &str, value: &mut f64) {
     ui.end_row();
 }
 
+/// Parameter grid for an abc-frame machine, including its fault settings.
+fn show_abc_machine_params(ui: &mut Ui, m: &mut AbcMachineNode) {
+    egui::Grid::new(ui.id().with("abc_params"))
+        .num_columns(2)
+        .show(ui, |ui| {
+            param_row(ui, "R_s (\u{03a9})", &mut m.r_s);
+            param_row(ui, "L_d (H)", &mut m.l_d);
+            param_row(ui, "L_q (H)", &mut m.l_q);
+            param_row(ui, "L_ls (H)", &mut m.l_ls);
+            param_row(ui, "\u{03bb}_m (Wb)", &mut m.lambda_m);
+            param_row(ui, "R_line (\u{03a9})", &mut m.r_line);
+            ui.label("Winding");
+            egui::ComboBox::from_id_salt(ui.id().with("abc_connection"))
+                .selected_text(m.connection.label())
+                .show_ui(ui, |ui| {
+                    for c in abc_machine::WindingConnection::ALL {
+                        ui.selectable_value(&mut m.connection, c, c.label());
+                    }
+                });
+            ui.end_row();
+            if m.connection == abc_machine::WindingConnection::Star {
+                ui.label("Neutral");
+                egui::ComboBox::from_id_salt(ui.id().with("abc_neutral"))
+                    .selected_text(m.neutral.label())
+                    .show_ui(ui, |ui| {
+                        for n in abc_machine::NeutralConnection::ALL {
+                            ui.selectable_value(&mut m.neutral, n, n.label());
+                        }
+                    });
+                ui.end_row();
+            }
+            ui.separator();
+            ui.end_row();
+            ui.label("Fault");
+            egui::ComboBox::from_id_salt(ui.id().with("abc_fault"))
+                .selected_text(m.fault.kind.label())
+                .show_ui(ui, |ui| {
+                    for kind in abc_machine::FaultKind::ALL {
+                        ui.selectable_value(&mut m.fault.kind, kind, kind.label());
+                    }
+                });
+            ui.end_row();
+            if m.fault.kind != abc_machine::FaultKind::None {
+                ui.label("Phase");
+                egui::ComboBox::from_id_salt(ui.id().with("abc_fault_phase"))
+                    .selected_text(PHASE_LABELS.get(m.fault.phase).copied().unwrap_or("?"))
+                    .show_ui(ui, |ui| {
+                        for (k, label) in PHASE_LABELS.iter().enumerate() {
+                            ui.selectable_value(&mut m.fault.phase, k, *label);
+                        }
+                    });
+                ui.end_row();
+                param_row(ui, "t_fault (s)", &mut m.fault.t_fault);
+                if m.fault.kind != abc_machine::FaultKind::OpenPhase {
+                    param_row(ui, "R_f (\u{03a9})", &mut m.fault.r_f);
+                }
+                if m.fault.kind == abc_machine::FaultKind::InterTurn {
+                    param_row(ui, "Shorted turns", &mut m.fault.fraction);
+                }
+            }
+        });
+}
+
+/// Phase names used in fault selectors.
+const PHASE_LABELS: [&str; 3] = ["a", "b", "c"];
+
+/// Flux-map status, interpolation choice and CSV import for an Electrical node.
+fn show_flux_map_editor(ui: &mut Ui, e: &mut electrical::ElectricalNode) {
+    ui.horizontal(|ui| {
+        if let Some(map) = &mut e.flux_map {
+            let (n_d, n_q) = map.shape();
+            ui.label(format!("Flux map {n_d}\u{00d7}{n_q}"));
+            egui::ComboBox::from_id_salt(ui.id().with("flux_interp"))
+                .selected_text(map.interpolation.label())
+                .show_ui(ui, |ui| {
+                    for kind in flux_map::MapInterpolation::ALL {
+                        ui.selectable_value(&mut map.interpolation, kind, kind.label());
+                    }
+                });
+            if ui.small_button("Clear").clicked() {
+                e.flux_map = None;
+            }
+        } else {
+            ui.label("Linear L_d, L_q, \u{03bb}_m");
+        }
+    });
+    ui.collapsing("Import flux map (CSV)", |ui| {
+        ui.label("Columns: i_d, i_q, \u{03c8}_d, \u{03c8}_q");
+        ui.add(
+            egui::TextEdit::multiline(&mut e.flux_csv)
+                .desired_rows(4)
+                .code_editor(),
+        );
+        if ui.button("Load").clicked() {
+            match flux_map::FluxMap::from_csv(&e.flux_csv) {
+                Ok(map) => {
+                    e.flux_map = Some(Box::new(map));
+                    e.flux_csv_error = None;
+                }
+                Err(err) => e.flux_csv_error = Some(err.to_string()),
+            }
+        }
+        if let Some(err) = &e.flux_csv_error {
+            ui.colored_label(ui.visuals().error_fg_color, err);
+        }
+    });
+}
+
+/// Back-EMF harmonic and cogging-torque editor for an Electrical node.
+fn show_harmonics_editor(ui: &mut Ui, h: &mut harmonics::SpatialHarmonics) {
+    ui.collapsing("Spatial harmonics", |ui| {
+        ui.label("Back-EMF (order, a_h p.u., \u{03c6}_h rad)");
+        harmonic_rows(ui, "emf_harmonics", &mut h.emf);
+        ui.horizontal(|ui| {
+            ui.label("Cogging");
+            egui::ComboBox::from_id_salt(ui.id().with("cogging_model"))
+                .selected_text(h.cogging.label())
+                .show_ui(ui, |ui| {
+                    for model in harmonics::CoggingModel::ALL {
+                        ui.selectable_value(&mut h.cogging, model, model.label());
+                    }
+                });
+        });
+        match h.cogging {
+            harmonics::CoggingModel::None => {}
+            harmonics::CoggingModel::Fourier => {
+                ui.label("Order per rev., A_k (N\u{00b7}m), \u{03c6}_k (rad)");
+                harmonic_rows(ui, "cogging_harmonics", &mut h.cogging_fourier);
+            }
+            harmonics::CoggingModel::Table => {
+                ui.horizontal(|ui| {
+                    ui.label(format!(
+                        "{} points, period (\u{00b0})",
+                        h.cogging_table.len()
+                    ));
+                    ui.add(
+                        egui::DragValue::new(&mut h.cogging_period)
+                            .speed(0.1)
+                            .range(0.1..=360.0),
+                    );
+                });
+                ui.label("Columns: \u{03b8}_m (\u{00b0}), T_cog (N\u{00b7}m)");
+                ui.add(
+                    egui::TextEdit::multiline(&mut h.cogging_csv)
+                        .desired_rows(4)
+                        .code_editor(),
+                );
+                if ui.button("Load").clicked() {
+                    match harmonics::SpatialHarmonics::parse_cogging_table(&h.cogging_csv) {
+                        Ok(table) => {
+                            h.cogging_table = table;
+                            h.cogging_csv_error = None;
+                        }
+                        Err(err) => h.cogging_csv_error = Some(err.to_string()),
+                    }
+                }
+                if let Some(err) = &h.cogging_csv_error {
+                    ui.colored_label(ui.visuals().error_fg_color, err);
+                }
+            }
+        }
+    });
+}
+
+/// Parameter grid for a measurement chain; a Vector has per-phase offset
+/// and gain error.
+fn show_measurement_params(ui: &mut Ui, m: &mut measurement::MeasurementNode) {
+    egui::Grid::new(ui.id().with("measurement_params"))
+        .num_columns(2)
+        .show(ui, |ui| {
+            param_row(ui, "f_c (Hz)", &mut m.f_cutoff);
+            ui.label("f_s (Hz)");
+            ui.add(
+                egui::DragValue::new(&mut m.f_sample)
+                    .speed(100.0)
+                    .range(1.0..=1e7),
+            );
+            ui.end_row();
+            ui.label("Bits");
+            ui.add(egui::DragValue::new(&mut m.bits).range(1..=32));
+            ui.end_row();
+            param_row(ui, "Range (\u{00b1})", &mut m.range);
+            let phases: &[&str] = if m.port_type == PortType::Vector {
+                &["a", "b", "c"]
+            } else {
+                &[""]
+            };
+            for ((phase, offset), gain) in phases.iter().zip(&mut m.offset).zip(&mut m.gain_error) {
+                param_row(ui, &format!("Offset {phase}"), offset);
+                param_row(ui, &format!("Gain error {phase}"), gain);
+            }
+            param_row(ui, "Noise \u{03c3}", &mut m.noise_std);
+            ui.label("Seed");
+            ui.add(egui::DragValue::new(&mut m.seed));
+            ui.end_row();
+        });
+}
+
+/// Parameter grid for a sensorless observer; only the gains of the chosen
+/// estimator and tracker are shown.
+fn show_observer_params(ui: &mut Ui, o: &mut observer::ObserverNode) {
+    egui::Grid::new(ui.id().with("observer_params"))
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Estimator");
+            egui::ComboBox::from_id_salt(ui.id().with("observer_estimator"))
+                .selected_text(o.estimator.label())
+                .show_ui(ui, |ui| {
+                    for estimator in observer::EmfEstimator::ALL {
+                        ui.selectable_value(&mut o.estimator, estimator, estimator.label());
+                    }
+                });
+            ui.end_row();
+            ui.label("Angle");
+            egui::ComboBox::from_id_salt(ui.id().with("observer_tracker"))
+                .selected_text(o.tracker.label())
+                .show_ui(ui, |ui| {
+                    for tracker in observer::AngleTracker::ALL {
+                        ui.selectable_value(&mut o.tracker, tracker, tracker.label());
+                    }
+                });
+            ui.end_row();
+            param_row(ui, "R_s (\u{03a9})", &mut o.r_s);
+            param_row(ui, "L (H)", &mut o.l_s);
+            param_row(ui, "\u{03bb}_m (Wb)", &mut o.lambda_m);
+            param_row(ui, "N_p", &mut o.n_p);
+            match o.estimator {
+                observer::EmfEstimator::BackEmf => {
+                    param_row(ui, "\u{03c9}_o (rad/s)", &mut o.bandwidth);
+                }
+                observer::EmfEstimator::SlidingMode => {
+                    param_row(ui, "k (V)", &mut o.k_smo);
+                    param_row(ui, "\u{03c6} (A)", &mut o.boundary);
+                    param_row(ui, "\u{03c9}_c (rad/s)", &mut o.omega_c);
+                }
+            }
+            if o.tracker == observer::AngleTracker::Pll {
+                param_row(ui, "\u{03c9}_PLL (rad/s)", &mut o.pll_bandwidth);
+            }
+        });
+}
+
+/// Parameter grid for the EKF: model, augmentation and the noise
+/// covariance diagonals.
+fn show_ekf_params(ui: &mut Ui, e: &mut ekf::EkfNode) {
+    egui::Grid::new(ui.id().with("ekf_params"))
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Augment");
+            egui::ComboBox::from_id_salt(ui.id().with("ekf_augment"))
+                .selected_text(e.augment.label())
+                .show_ui(ui, |ui| {
+                    for augment in ekf::EkfAugment::ALL {
+                        ui.selectable_value(&mut e.augment, augment, augment.label());
+                    }
+                });
+            ui.end_row();
+            param_row(ui, "R_s (\u{03a9})", &mut e.r_s);
+            param_row(ui, "L_d (H)", &mut e.l_d);
+            param_row(ui, "L_q (H)", &mut e.l_q);
+            param_row(ui, "\u{03bb}_m (Wb)", &mut e.lambda_m);
+            param_row(ui, "N_p", &mut e.n_p);
+            param_row(ui, "J (kg\u{00b7}m\u{00b2})", &mut e.j);
+            param_row(ui, "B (N\u{00b7}m\u{00b7}s)", &mut e.b);
+            param_row(ui, "f_s (Hz)", &mut e.f_sample);
+            param_row(ui, "\u{03b8}\u{2080} (rad)", &mut e.theta_0);
+            param_row(ui, "R (A\u{00b2})", &mut e.r);
+        });
+    let augmented = e.augment != ekf::EkfAugment::None;
+    let states = ["i_d", "i_q", "\u{03c9}_m", "\u{03b8}_e", "p"];
+    ui.label("Q (per s) and P\u{2080} diagonals");
+    egui::Grid::new(ui.id().with("ekf_covariances"))
+        .num_columns(3)
+        .show(ui, |ui| {
+            for ((name, q), p_0) in states.iter().zip(&mut e.q).zip(&mut e.p_0) {
+                if *name == "p" && !augmented {
+                    continue;
+                }
+                ui.label(*name);
+                ui.add(egui::DragValue::new(q).speed(0.01));
+                ui.add(egui::DragValue::new(p_0).speed(0.01));
+                ui.end_row();
+            }
+        });
+}
+
+/// Thermal network editor: bodies, links, ambient and feedback roles.
+fn show_thermal_editor(ui: &mut Ui, th: &mut thermal::ThermalNode) {
+    egui::Grid::new(ui.id().with("thermal_params"))
+        .num_columns(2)
+        .show(ui, |ui| {
+            param_row(ui, "T_amb (\u{00b0}C)", &mut th.t_ambient);
+        });
+    let names: Vec<String> = th.bodies.iter().map(|b| b.name.clone()).collect();
+    let body_name = |k: usize| names.get(k).map_or("?", String::as_str);
+
+    ui.label("Bodies (name, C J/K, T\u{2080} \u{00b0}C, P_cu share, P_fe share)");
+    let mut remove = None;
+    egui::Grid::new(ui.id().with("thermal_bodies"))
+        .num_columns(6)
+        .show(ui, |ui| {
+            for (k, body) in th.bodies.iter_mut().enumerate() {
+                ui.add(egui::TextEdit::singleline(&mut body.name).desired_width(70.0));
+                ui.add(egui::DragValue::new(&mut body.c).speed(1.0));
+                ui.add(egui::DragValue::new(&mut body.t_0).speed(0.1));
+                ui.add(egui::DragValue::new(&mut body.cu_share).speed(0.01));
+                ui.add(egui::DragValue::new(&mut body.fe_share).speed(0.01));
+                if ui.small_button("\u{2212}").clicked() {
+                    remove = Some(k);
+                }
+                ui.end_row();
+            }
+        });
+    if let Some(k) = remove {
+        th.remove_body(k);
+    }
+    if ui.small_button("+ body").clicked() {
+        th.bodies.push(thermal::ThermalBody::default());
+    }
+
+    ui.label("Links (from, to, R K/W)");
+    let mut remove = None;
+    egui::Grid::new(ui.id().with("thermal_links"))
+        .num_columns(4)
+        .show(ui, |ui| {
+            for (k, link) in th.links.iter_mut().enumerate() {
+                egui::ComboBox::from_id_salt(ui.id().with(("link_from", k)))
+                    .selected_text(body_name(link.from))
+                    .show_ui(ui, |ui| {
+                        for (b, name) in names.iter().enumerate() {
+                            ui.selectable_value(&mut link.from, b, name);
+                        }
+                    });
+                egui::ComboBox::from_id_salt(ui.id().with(("link_to", k)))
+                    .selected_text(link.to.map_or("Ambient", body_name))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut link.to, None, "Ambient");
+                        for (b, name) in names.iter().enumerate() {
+                            ui.selectable_value(&mut link.to, Some(b), name);
+                        }
+                    });
+                ui.add(egui::DragValue::new(&mut link.r).speed(0.01));
+                if ui.small_button("\u{2212}").clicked() {
+                    remove = Some(k);
+                }
+                ui.end_row();
+            }
+        });
+    if let Some(k) = remove {
+        th.links.remove(k);
+    }
+    if ui.small_button("+ link").clicked() {
+        th.links.push(thermal::ThermalLink {
+            from: 0,
+            to: None,
+            r: 1.0,
+        });
+    }
+
+    egui::Grid::new(ui.id().with("thermal_roles"))
+        .num_columns(3)
+        .show(ui, |ui| {
+            for (label, role, feedback, salt) in [
+                ("Winding", &mut th.winding, &mut th.feedback_r_s, "R_s"),
+                (
+                    "Magnet",
+                    &mut th.magnet,
+                    &mut th.feedback_lambda_m,
+                    "\u{03bb}_m",
+                ),
+            ] {
+                ui.label(label);
+                egui::ComboBox::from_id_salt(ui.id().with(label))
+                    .selected_text(body_name(*role))
+                    .show_ui(ui, |ui| {
+                        for (b, name) in names.iter().enumerate() {
+                            ui.selectable_value(role, b, name);
+                        }
+                    });
+                ui.checkbox(feedback, format!("sets {salt}"));
+                ui.end_row();
+            }
+        });
+}
+
+/// Editable list of harmonic terms with add/remove buttons.
+fn harmonic_rows(ui: &mut Ui, salt: &str, terms: &mut Vec<harmonics::Harmonic>) {
+    let mut remove = None;
+    egui::Grid::new(ui.id().with(salt))
+        .num_columns(4)
+        .show(ui, |ui| {
+            for (k, term) in terms.iter_mut().enumerate() {
+                ui.add(egui::DragValue::new(&mut term.order).range(1..=200));
+                ui.add(egui::DragValue::new(&mut term.amplitude).speed(0.001));
+                ui.add(egui::DragValue::new(&mut term.phase).speed(0.01));
+                if ui.small_button("\u{2212}").clicked() {
+                    remove = Some(k);
+                }
+                ui.end_row();
+            }
+        });
+    if let Some(k) = remove {
+        terms.remove(k);
+    }
+    if ui.small_button("+ harmonic").clicked() {
+        terms.push(harmonics::Harmonic {
+            order: 6,
+            amplitude: 0.0,
+            phase: 0.0,
+        });
+    }
+}
+
+/// Plot lines `(name, points)` for the output `remote` wired to Plot input
+/// `i`: one line for a Signal, one per phase for a Vector.
+pub fn plot_lines(
+    snarl: &Snarl<SimNode>,
+    remote: OutPinId,
+    i: usize,
+) -> Vec<(String, Vec<[f64; 2]>)> {
+    let mut lines = Vec::new();
+    let Some(source_node) = snarl.get_node(remote.node) else {
+        return lines;
+    };
+    match source_node.output_value(remote.output) {
+        Some(PortValue::Signal(data)) => {
+            lines.push((format!("signal_{i}"), data.clone()));
+        }
+        Some(PortValue::Vector(data)) => {
+            for (phase, &name) in ["a", "b", "c"].iter().enumerate() {
+                let pts: Vec<[f64; 2]> = data
+                    .iter()
+                    .map(|row| {
+                        // row is [t, a, b, c] — index 0 is time, 1..3 are phases
+                        [row[0], row.get(phase + 1).copied().unwrap_or(0.0)]
+                    })
+                    .collect();
+                lines.push((format!("{name}_{i}"), pts));
+            }
+        }
+        _ => {}
+    }
+    lines
+}
+
+/// Frequency range, Bode magnitude and phase against logarithmic frequency,
+/// the pole table and the state-space matrices of a Bode node.
+fn show_bode(ui: &mut Ui, b: &mut bode::BodeNode) {
+    use egui_plot::{GridMark, Line, Plot, PlotPoints};
+
+    egui::Grid::new(ui.id().with("bode_params"))
+        .num_columns(2)
+        .show(ui, |ui| {
+            param_row(ui, "f_min (Hz)", &mut b.f_min);
+            param_row(ui, "f_max (Hz)", &mut b.f_max);
+            ui.label("Points");
+            ui.add(egui::DragValue::new(&mut b.points).range(2..=5000));
+            ui.end_row();
+        });
+    if let Some(err) = &b.error {
+        ui.colored_label(ui.visuals().error_fg_color, err);
+    }
+    let Some(model) = &b.model else {
+        ui.label("Wire u from a Constant and y from i_d, i_q, \u{03c9}_m or T_e");
+        return;
+    };
+
+    // Frequencies are plotted as log\u{2081}\u{2080}(f) with the ticks labelled in Hz
+    let height = b.custom_size.map_or(100.0, |[_, h]| (h / 3.0).max(60.0));
+    let hz = |mark: GridMark, _: &std::ops::RangeInclusive<f64>| {
+        let f = 10.0_f64.powf(mark.value);
+        if f >= 10.0 {
+            format!("{f:.0}")
+        } else {
+            format!("{f:.2}")
+        }
+    };
+    let traces = [
+        ("bode_magnitude", "|G| (dB)", &b.series.magnitude),
+        ("bode_phase", "\u{2220}G (\u{00b0})", &b.series.phase),
+    ];
+    for (id, label, data) in traces {
+        let points: PlotPoints<'_> = data.iter().map(|&[f, v]| [f.log10(), v]).collect();
+        Plot::new(ui.id().with(id))
+            .height(height)
+            .allow_drag(false)
+            .allow_scroll(false)
+            .allow_zoom(false)
+            .y_axis_label(label)
+            .x_axis_formatter(hz)
+            .show(ui, |plot_ui| plot_ui.line(Line::new(label, points)));
+    }
+
+    egui::Grid::new(ui.id().with("bode_poles"))
+        .striped(true)
+        .show(ui, |ui| {
+            for heading in ["Re (1/s)", "Im (rad/s)", "\u{03b6}", "f_n (Hz)"] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+            for pole in &b.poles {
+                ui.label(format!("{:.4}", pole.re));
+                ui.label(format!("{:.4}", pole.im));
+                ui.label(format!("{:.4}", pole.damping()));
+                ui.label(format!("{:.4}", pole.frequency()));
+                ui.end_row();
+            }
+        });
+
+    egui::CollapsingHeader::new("A, B, C, D")
+        .id_salt(ui.id().with("bode_matrices"))
+        .show(ui, |ui| {
+            let row = |values: &mut dyn Iterator<Item = f64>| {
+                values.map(|v| format!("{v:>11.3e}")).collect::<String>()
+            };
+            let mut text = String::new();
+            for ((name, a), b) in model.states.iter().zip(model.a.row_iter()).zip(&model.b) {
+                text += &format!(
+                    "{name:>4} \u{2502}{} \u{2502}{b:>11.3e}\n",
+                    row(&mut a.iter().copied())
+                );
+            }
+            text += &format!(
+                "{:>4} \u{2502}{} \u{2502}{:>11.3e}",
+                "y",
+                row(&mut model.c.iter().copied()),
+                model.d
+            );
+            ui.monospace(text);
+        });
+}
+
+/// Grid and limits, then the iso-efficiency contours under the capability
+/// envelope and the CSV export of the last map.
+fn show_efficiency_map(ui: &mut Ui, m: &mut efficiency_map::EfficiencyMapNode) {
+    use egui_plot::{Legend, Line, Plot, PlotPoints};
+
+    /// Contour levels (%).
+    const LEVELS: [f64; 9] = [50.0, 70.0, 80.0, 85.0, 88.0, 90.0, 92.0, 94.0, 96.0];
+
+    egui::Grid::new(ui.id().with("map_params"))
+        .num_columns(2)
+        .show(ui, |ui| {
+            param_row(ui, "\u{03c9}_max (rad/s)", &mut m.speed_max);
+            param_row(ui, "T_max (N\u{00b7}m)", &mut m.torque_max);
+            ui.label("Speeds");
+            ui.add(egui::DragValue::new(&mut m.speed_points).range(2..=200));
+            ui.end_row();
+            ui.label("Torques");
+            ui.add(egui::DragValue::new(&mut m.torque_points).range(2..=200));
+            ui.end_row();
+            param_row(ui, "I_max (A)", &mut m.i_max);
+            param_row(ui, "V_dc (V)", &mut m.v_dc);
+        });
+    let Some(map) = &m.map else {
+        ui.label("Run a simulation to compute the map");
+        return;
+    };
+
+    let height = m.custom_size.map_or(200.0, |[_, h]| (h / 2.0).max(120.0));
+    Plot::new(ui.id().with("map_plot"))
+        .height(height)
+        .allow_drag(false)
+        .allow_scroll(false)
+        .allow_zoom(false)
+        .legend(Legend::default())
+        .x_axis_label("\u{03c9}_m (rad/s)")
+        .y_axis_label("T (N\u{00b7}m)")
+        .show(ui, |plot_ui| {
+            let envelope: PlotPoints<'_> = map.envelope.iter().copied().collect();
+            plot_ui.line(Line::new("T_max", envelope).width(2.0));
+            // Low efficiencies blue, high ones red
+            let (low, high) = (
+                Color32::from_rgb(0x56, 0x9C, 0xD6),
+                Color32::from_rgb(0xC0, 0x50, 0x50),
+            );
+            for (k, level) in LEVELS.into_iter().enumerate() {
+                let name = format!("{level:.0} %");
+                let color = low.lerp_to_gamma(high, k as f32 / (LEVELS.len() - 1) as f32);
+                for [a, b] in map.contour(level) {
+                    plot_ui.line(Line::new(&name, PlotPoints::from(vec![a, b])).color(color));
+                }
+            }
+        });
+
+    if let Some(peak) = map.peak() {
+        ui.label(format!(
+            "Peak \u{03b7} {:.2} % at {:.1} rad/s, {:.2} N\u{00b7}m",
+            peak.efficiency, peak.omega_m, peak.torque
+        ));
+    }
+    ui.horizontal(|ui| {
+        if ui.button("Copy map CSV").clicked() {
+            ui.ctx().copy_text(map.csv());
+        }
+        if ui.button("Copy envelope CSV").clicked() {
+            ui.ctx().copy_text(map.envelope_csv());
+        }
+    });
+}
+
+/// Energy balance of the last run: the energies, the residual against the
+/// solver tolerance and the mean efficiency.
+fn show_power_flow(ui: &mut Ui, pf: &power::PowerFlowNode) {
+    let Some(balance) = &pf.balance else {
+        ui.label("Run a simulation for the energy balance");
+        return;
+    };
+    egui::Grid::new(ui.id().with("power_balance"))
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            let rows = [
+                ("E_in", balance.e_in),
+                ("E_cu", balance.e_cu),
+                ("E_fe", balance.e_fe),
+                ("E_fric", balance.e_fric),
+                ("E_load", balance.e_load),
+                ("\u{0394}W_mag", balance.d_w_mag),
+                ("\u{0394}W_kin", balance.d_w_kin),
+                ("\u{0394}W_cog", balance.d_w_cog),
+            ];
+            for (label, energy) in rows {
+                ui.label(format!("{label} (J)"));
+                ui.monospace(format!("{energy:.6}"));
+                ui.end_row();
+            }
+            ui.label("Residual (J)");
+            let residual = format!("{:.3e}", balance.residual());
+            if balance.closes() {
+                ui.monospace(residual)
+                    .on_hover_text(format!("within {:.1e} J", balance.tolerance));
+            } else {
+                ui.colored_label(ui.visuals().warn_fg_color, residual)
+                    .on_hover_text(format!(
+                        "exceeds {:.1e} J: tighten the tolerances or check the Torque node",
+                        balance.tolerance
+                    ));
+            }
+            ui.end_row();
+            ui.label("\u{03b7} (%)");
+            ui.monospace(format!("{:.2}", balance.efficiency()));
+            ui.end_row();
+        });
+}
+
+/// Scalar name, window and settling band, and the metrics of the last run.
+fn show_metrics(ui: &mut Ui, m: &mut metrics::MetricsNode) {
+    egui::Grid::new(ui.id().with("metrics_params"))
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Name")
+                .on_hover_text("Prefix of the scalars published to sweeps");
+            ui.add(egui::TextEdit::singleline(&mut m.name).desired_width(90.0));
+            ui.end_row();
+            ui.label("From (s)");
+            ui.add(
+                egui::DragValue::new(&mut m.t_from)
+                    .speed(0.001)
+                    .range(0.0..=f64::MAX),
+            );
+            ui.end_row();
+            ui.label("To (s)")
+                .on_hover_text("At or before the start: to the end of the run");
+            ui.add(
+                egui::DragValue::new(&mut m.t_to)
+                    .speed(0.001)
+                    .range(0.0..=f64::MAX),
+            );
+            ui.end_row();
+            ui.label("Band (%)");
+            ui.add(
+                egui::DragValue::new(&mut m.band)
+                    .speed(0.1)
+                    .range(0.01..=100.0),
+            );
+            ui.end_row();
+        });
+    let Some(values) = &m.metrics else {
+        return;
+    };
+    ui.separator();
+    egui::Grid::new(ui.id().with("metrics_values"))
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            for (_, label, value) in values.table() {
+                ui.label(label);
+                if value.is_nan() {
+                    ui.label("\u{2014}");
+                } else {
+                    ui.monospace(format!("{value:.5}"));
+                }
+                ui.end_row();
+            }
+        });
+}
+
+/// Window settings, the amplitude spectrum as bars against harmonic order or
+/// frequency, and the fundamental, phase and THD of each channel.
+fn show_spectrum(ui: &mut Ui, s: &mut spectrum::SpectrumNode) {
+    use egui_plot::{Bar, BarChart, Legend, Plot};
+
+    egui::Grid::new(ui.id().with("spectrum_params"))
+        .num_columns(2)
+        .show(ui, |ui| {
+            ui.label("Periods");
+            ui.add(egui::DragValue::new(&mut s.periods).range(1..=1000));
+            ui.end_row();
+            ui.label("f\u{2081} (Hz)")
+                .on_hover_text("Fundamental frequency when \u{03b8}_e is not wired");
+            ui.add(egui::DragValue::new(&mut s.f_fundamental).range(1e-3..=1e6));
+            ui.end_row();
+            ui.label("Samples/period");
+            ui.add(egui::DragValue::new(&mut s.samples_per_period).range(4..=4096));
+            ui.end_row();
+            ui.label("Max order");
+            ui.add(egui::DragValue::new(&mut s.max_order).range(1..=1000));
+            ui.end_row();
+            ui.label("Window");
+            egui::ComboBox::from_id_salt(ui.id().with("spectrum_window"))
+                .selected_text(s.window.label())
+                .show_ui(ui, |ui| {
+                    for window in spectrum::WindowFunction::ALL {
+                        ui.selectable_value(&mut s.window, window, window.label());
+                    }
+                });
+            ui.end_row();
+            ui.label("Axis");
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut s.axis, spectrum::SpectrumAxis::Order, "Order");
+                ui.selectable_value(&mut s.axis, spectrum::SpectrumAxis::Frequency, "Hz");
+            });
+            ui.end_row();
+        });
+    let Some(window) = &s.analysis else {
+        ui.label("No window: the run is shorter than the chosen periods");
+        return;
+    };
+
+    let scale = match s.axis {
+        spectrum::SpectrumAxis::Order => 1.0,
+        spectrum::SpectrumAxis::Frequency => window.f_1,
+    };
+    let width = scale / s.periods.max(1) as f64;
+    let height = s.custom_size.map_or(150.0, |[_, h]| (h / 2.0).max(80.0));
+    Plot::new(ui.id().with("spectrum_plot"))
+        .height(height)
+        .allow_drag(false)
+        .allow_scroll(false)
+        .allow_zoom(false)
+        .legend(Legend::default())
+        .x_axis_label(match s.axis {
+            spectrum::SpectrumAxis::Order => "order",
+            spectrum::SpectrumAxis::Frequency => "f (Hz)",
+        })
+        .show(ui, |plot_ui| {
+            for channel in &s.channels {
+                let bars = channel
+                    .bins
+                    .iter()
+                    .map(|&[order, amplitude]| Bar::new(order * scale, amplitude).width(width))
+                    .collect();
+                plot_ui.bar_chart(BarChart::new(channel.name.as_str(), bars));
+            }
+        });
+
+    egui::Grid::new(ui.id().with("spectrum_results"))
+        .striped(true)
+        .show(ui, |ui| {
+            for heading in ["", "A\u{2081}", "\u{03c6} (\u{00b0})", "THD (%)", "DC"] {
+                ui.strong(heading);
+            }
+            ui.end_row();
+            for channel in &s.channels {
+                ui.label(&channel.name);
+                ui.label(format!("{:.4}", channel.fundamental));
+                ui.label(format!("{:.2}", channel.phase));
+                ui.label(format!("{:.2}", channel.thd));
+                ui.label(format!("{:.4}", channel.dc));
+                ui.end_row();
+            }
+        });
+    ui.label(format!("f\u{2081} = {:.3} Hz", window.f_1));
+}
+
 /// Renders the plot body for a `PlotNode` using `egui_plot`.
 ///
 /// Collects signal data from connected input pins' remote output nodes and
-/// plots each as a line series.
+/// plots each as a line series. After a sweep the traces of every run are
+/// overlaid instead, with a legend keyed by the parameter value, and after a
+/// Monte Carlo study each trace's envelope: dashed min/max, the percentile
+/// band and the solid median.
 fn show_plot_inline(
-    _node_id: NodeId,
+    node_id: NodeId,
     inputs: &[InPin],
     ui: &mut Ui,
     snarl: &Snarl<SimNode>,
     plot_height: f32,
 ) {
-    use egui_plot::{Line, PlotPoints};
+    use egui_plot::{Legend, Line, LineStyle, PlotPoints};
 
     // Collect plot data from connected remote nodes before rendering.
     // Each entry: (label, Vec of [x,y] points).
-    let mut lines: Vec<(String, Vec<[f64; 2]>)> = Vec::new();
-
-    for (i, input) in inputs.iter().enumerate() {
-        if let Some(remote) = input.remotes.first() {
-            let source_node = &snarl[remote.node];
-            match source_node.output_value(remote.output) {
-                Some(PortValue::Signal(data)) => {
-                    lines.push((format!("signal_{i}"), data.clone()));
-                }
-                Some(PortValue::Vector(data)) => {
-                    for (phase, &name) in ["a", "b", "c"].iter().enumerate() {
-                        let pts: Vec<[f64; 2]> = data
-                            .iter()
-                            .map(|row| {
-                                // row is [t, a, b, c] — index 0 is time, 1..3 are phases
-                                [row[0], row.get(phase + 1).copied().unwrap_or(0.0)]
-                            })
-                            .collect();
-                        lines.push((format!("{name}_{i}"), pts));
-                    }
-                }
-                _ => {}
-            }
-        }
-    }
-
-    let plot = egui_plot::Plot::new(ui.id().with("plot_area"))
+    let (runs, envelopes) = match snarl.get_node(node_id) {
+        Some(SimNode::Plot(p)) => (p.runs.as_slice(), p.envelopes.as_slice()),
+        _ => (&[][..], &[][..]),
+    };
+    let lines: Vec<(String, Vec<[f64; 2]>)> = if !envelopes.is_empty() {
+        Vec::new()
+    } else if runs.is_empty() {
+        inputs
+            .iter()
+            .enumerate()
+            .filter_map(|(i, input)| {
+                input
+                    .remotes
+                    .first()
+                    .map(|&remote| plot_lines(snarl, remote, i))
+            })
+            .flatten()
+            .collect()
+    } else {
+        runs.iter()
+            .flat_map(|run| {
+                let single = run.lines.len() == 1;
+                run.lines.iter().map(move |(name, data)| {
+                    let label = if single {
+                        run.label.clone()
+                    } else {
+                        format!("{name} ({})", run.label)
+                    };
+                    (label, data.clone())
+                })
+            })
+            .collect()
+    };
+
+    let mut plot = egui_plot::Plot::new(ui.id().with("plot_area"))
         .height(plot_height)
         .allow_drag(false)
         .allow_scroll(false)
         .allow_zoom(false)
         .show_axes(true);
+    if !runs.is_empty() || !envelopes.is_empty() {
+        plot = plot.legend(Legend::default());
+    }
 
+    let palette = [
+        Color32::from_rgb(0x56, 0x9C, 0xD6),
+        Color32::from_rgb(0xD6, 0x8C, 0x45),
+        Color32::from_rgb(0x4E, 0xBA, 0x6F),
+        Color32::from_rgb(0xC0, 0x50, 0x50),
+    ];
     plot.show(ui, |plot_ui| {
         for (name, data) in &lines {
             let points: PlotPoints<'_> = data.iter().map(|&[t, v]| [t, v]).collect();
             plot_ui.line(Line::new(name.as_str(), points));
         }
+        for (env, &color) in envelopes.iter().zip(palette.iter().cycle()) {
+            let styles = [
+                (&env.min, LineStyle::dashed_dense(), 0.5),
+                (&env.max, LineStyle::dashed_dense(), 0.5),
+                (&env.low, LineStyle::Solid, 0.5),
+                (&env.high, LineStyle::Solid, 0.5),
+                (&env.median, LineStyle::Solid, 1.5),
+            ];
+            for (data, style, width) in styles {
+                let points: PlotPoints<'_> = data.iter().map(|&[t, v]| [t, v]).collect();
+                plot_ui.line(
+                    Line::new(env.name.as_str(), points)
+                        .color(color)
+                        .style(style)
+                        .width(width),
+                );
+            }
+        }
     });
 }
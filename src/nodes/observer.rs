@@ -0,0 +1,440 @@
+//! Sensorless position observer node — rotor angle and speed estimated from
+//! phase voltages and currents only.
+//!
+//! Both estimators work in the stationary αβ frame on the non-salient model
+//! with `L = L_q`, whose back-EMF (the extended EMF on a salient machine)
+//! stays aligned with the q axis:
+//!
+//! ```text
+//! v_αβ = R·i_αβ + L·di_αβ/dt + e_αβ,   e_αβ = ω_e·λ·[−sin θ_e, cos θ_e]
+//! ```
+//!
+//! - **Back-EMF observer**: Luenberger current observer with the EMF as a
+//!   slowly varying disturbance state; both error poles at `−ω_o`.
+//! - **Sliding-mode observer**: current observer driven by
+//!   `z = k·sat((î − i)/φ)`; the EMF is `z` low-pass filtered at `ω_c`.
+//!
+//! The angle is taken from the EMF estimate either directly by `atan2` or by
+//! a PLL with both poles at `−ω_pll`, and corrected for the known phase lag
+//! of the estimator at the estimated speed. The observer states are
+//! integrated by the solver after the machine solve.
+
+use std::f64::consts::{PI, TAU};
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// Electrical speed (rad/s) below which the PLL error is no longer
+/// normalized by the EMF magnitude.
+const OMEGA_FLOOR: f64 = 10.0;
+
+/// Back-EMF estimator.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum EmfEstimator {
+    /// Luenberger observer with an EMF disturbance state.
+    #[default]
+    BackEmf,
+    /// Sliding-mode current observer with a low-pass EMF filter.
+    SlidingMode,
+}
+
+impl EmfEstimator {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 2] = [Self::BackEmf, Self::SlidingMode];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::BackEmf => "Back-EMF observer",
+            Self::SlidingMode => "Sliding-mode observer",
+        }
+    }
+}
+
+/// Angle extraction from the estimated EMF.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum AngleTracker {
+    /// `atan2` of the EMF; speed from the EMF magnitude.
+    Arctangent,
+    /// Phase-locked loop on the EMF direction.
+    #[default]
+    Pll,
+}
+
+impl AngleTracker {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 2] = [Self::Arctangent, Self::Pll];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Arctangent => "Arctangent",
+            Self::Pll => "PLL",
+        }
+    }
+}
+
+/// Sensorless angle and speed observer.
+///
+/// Inputs: phase voltages `v_abc` and currents `i_abc` (Vector), and the true
+/// electrical angle `θ_e` (Signal, optional, for the error output).\
+/// Outputs: estimated electrical angle `θ_e`, estimated mechanical speed
+/// `ω_m`, angle error `θ_err` wrapped to `(−π, π]` (Signal), and the EMF
+/// estimate `[e_α, e_β, |e|]` (Vector).
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ObserverNode {
+    /// Back-EMF estimator.
+    pub estimator: EmfEstimator,
+    /// Angle extraction.
+    pub tracker: AngleTracker,
+    /// Model stator resistance (Ω).
+    pub r_s: f64,
+    /// Model inductance, `L_q` on a salient machine (H).
+    pub l_s: f64,
+    /// Model PM flux linkage (Wb).
+    pub lambda_m: f64,
+    /// Number of pole pairs.
+    pub n_p: f64,
+    /// Back-EMF observer bandwidth `ω_o` (rad/s).
+    pub bandwidth: f64,
+    /// Sliding-mode gain `k` (V); must exceed the largest EMF.
+    pub k_smo: f64,
+    /// Sliding-mode boundary layer `φ` (A).
+    pub boundary: f64,
+    /// Sliding-mode EMF filter cutoff `ω_c` (rad/s).
+    pub omega_c: f64,
+    /// PLL bandwidth `ω_pll` (rad/s).
+    pub pll_bandwidth: f64,
+    /// Estimated angle time-series produced after simulation.
+    #[serde(skip)]
+    pub output_theta_e: Option<PortValue>,
+    /// Estimated speed time-series produced after simulation.
+    #[serde(skip)]
+    pub output_omega_m: Option<PortValue>,
+    /// Angle error time-series produced after simulation.
+    #[serde(skip)]
+    pub output_error: Option<PortValue>,
+    /// EMF estimate time-series produced after simulation.
+    #[serde(skip)]
+    pub output_emf: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for ObserverNode {
+    /// Tuned for the default Electrical node.
+    fn default() -> Self {
+        Self {
+            estimator: EmfEstimator::BackEmf,
+            tracker: AngleTracker::Pll,
+            r_s: 1.2,
+            l_s: 0.008,
+            lambda_m: 0.175,
+            n_p: 4.0,
+            bandwidth: 2000.0,
+            k_smo: 100.0,
+            boundary: 0.2,
+            omega_c: 2000.0,
+            pll_bandwidth: 200.0,
+            output_theta_e: None,
+            output_omega_m: None,
+            output_error: None,
+            output_emf: None,
+            custom_size: None,
+        }
+    }
+}
+
+/// Observer outputs over the solver's time points.
+#[derive(Clone, Debug, Default)]
+pub struct ObserverSeries {
+    /// Estimated electrical angle.
+    pub theta_e: Vec<[f64; 2]>,
+    /// Estimated mechanical speed.
+    pub omega_m: Vec<[f64; 2]>,
+    /// EMF estimate `[t, e_α, e_β, |e|]`.
+    pub emf: Vec<[f64; 4]>,
+}
+
+/// Amplitude-invariant Clarke transform `[a, b, c] → [α, β]`.
+pub fn clarke([a, b, c]: [f64; 3]) -> [f64; 2] {
+    [(2.0 * a - b - c) / 3.0, (b - c) / 3_f64.sqrt()]
+}
+
+/// Wrap an angle to `(−π, π]`.
+fn wrap(angle: f64) -> f64 {
+    PI - (PI - angle).rem_euclid(TAU)
+}
+
+impl ObserverNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "Observer"
+    }
+
+    /// Input port descriptors: phase voltages, phase currents and the true
+    /// angle.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("v_abc", PortType::Vector),
+            ("i_abc", PortType::Vector),
+            ("θ_e", PortType::Signal),
+        ]
+    }
+
+    /// Output port descriptors: estimated angle and speed, angle error and
+    /// EMF estimate.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("θ_e", PortType::Signal),
+            ("ω_m", PortType::Signal),
+            ("θ_err", PortType::Signal),
+            ("e_αβ", PortType::Vector),
+        ]
+    }
+
+    /// Header colour (teal, shared by the measurement nodes).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x30, 0x80, 0x80)
+    }
+
+    /// Number of observer states: `[î_α, î_β, ê_α, ê_β]`, plus `[θ, ω_e]`
+    /// with the PLL.
+    pub fn n_states(&self) -> usize {
+        match self.tracker {
+            AngleTracker::Arctangent => 4,
+            AngleTracker::Pll => 6,
+        }
+    }
+
+    /// Initial state: current estimate at the measured currents `i_ab`, no
+    /// EMF, PLL at rest.
+    pub fn initial_state(&self, i_ab: [f64; 2]) -> Vec<f64> {
+        let mut x = vec![0.0; self.n_states()];
+        if let Some(currents) = x.first_chunk_mut::<2>() {
+            *currents = i_ab;
+        }
+        x
+    }
+
+    /// Back-EMF observer gains `(l_1, l_2)` placing both error poles at
+    /// `−ω_o`.
+    fn luenberger_gains(&self) -> (f64, f64) {
+        let l = self.l_s.max(f64::EPSILON);
+        (
+            2.0 * self.bandwidth - self.r_s / l,
+            l * self.bandwidth * self.bandwidth,
+        )
+    }
+
+    /// Phase lag (rad) and gain of the EMF estimate at electrical speed
+    /// `omega_e`.
+    fn lag(&self, omega_e: f64) -> (f64, f64) {
+        match self.estimator {
+            EmfEstimator::BackEmf => {
+                let x = omega_e.abs() / self.bandwidth.max(f64::EPSILON);
+                (2.0 * x.atan(), 1.0 / (1.0 + x * x))
+            }
+            EmfEstimator::SlidingMode => {
+                let x = omega_e.abs() / self.omega_c.max(f64::EPSILON);
+                (x.atan(), 1.0 / x.hypot(1.0))
+            }
+        }
+    }
+
+    /// Speed magnitude `|ω_e|` whose attenuated EMF has magnitude `e_mag`.
+    fn emf_speed(&self, e_mag: f64) -> f64 {
+        let lambda = self.lambda_m.max(f64::EPSILON);
+        (0..8).fold(e_mag / lambda, |omega, _| {
+            e_mag / (lambda * self.lag(omega).1)
+        })
+    }
+
+    /// Normalized PLL phase error `≈ sin(θ_EMF − θ)` for EMF `[e_α, e_β]`.
+    fn pll_error(&self, [e_a, e_b]: [f64; 2], theta: f64) -> f64 {
+        let scale = e_a
+            .hypot(e_b)
+            .max(self.lambda_m.abs() * OMEGA_FLOOR)
+            .max(f64::EPSILON);
+        (-e_a * theta.cos() - e_b * theta.sin()) / scale
+    }
+
+    /// Write the observer state derivatives `dx` for state `x`, measured
+    /// voltages `v` and currents `i` (αβ).
+    pub fn derivatives(&self, v: [f64; 2], i: [f64; 2], x: &[f64], dx: &mut [f64]) {
+        let (Some((&[i_a, i_b, e_a, e_b], pll)), Some((d, d_pll))) =
+            (x.split_first_chunk::<4>(), dx.split_first_chunk_mut::<4>())
+        else {
+            return;
+        };
+        let ([v_a, v_b], [m_a, m_b]) = (v, i);
+        let (r, l) = (self.r_s, self.l_s.max(f64::EPSILON));
+        *d = match self.estimator {
+            EmfEstimator::BackEmf => {
+                let (l_1, l_2) = self.luenberger_gains();
+                let (r_a, r_b) = (m_a - i_a, m_b - i_b);
+                [
+                    (v_a - r * i_a - e_a) / l + l_1 * r_a,
+                    (v_b - r * i_b - e_b) / l + l_1 * r_b,
+                    -l_2 * r_a,
+                    -l_2 * r_b,
+                ]
+            }
+            EmfEstimator::SlidingMode => {
+                let phi = self.boundary.max(f64::EPSILON);
+                let [z_a, z_b] =
+                    [i_a - m_a, i_b - m_b].map(|err| self.k_smo * (err / phi).clamp(-1.0, 1.0));
+                [
+                    (v_a - r * i_a - z_a) / l,
+                    (v_b - r * i_b - z_b) / l,
+                    self.omega_c * (z_a - e_a),
+                    self.omega_c * (z_b - e_b),
+                ]
+            }
+        };
+        if let (&[theta, omega], [d_theta, d_omega]) = (pll, d_pll) {
+            let error = self.pll_error([e_a, e_b], theta);
+            let w = self.pll_bandwidth;
+            *d_theta = omega + 2.0 * w * error;
+            *d_omega = w * w * error;
+        }
+    }
+
+    /// Angle, speed and EMF estimates from the observer states `states` at
+    /// times `ts`.
+    pub fn estimate(&self, ts: &[f64], states: &[Vec<f64>]) -> ObserverSeries {
+        let n_p = self.n_p.max(f64::EPSILON);
+        let mut series = ObserverSeries::default();
+        // Arctangent tracking: rotation direction and unwrapped angle so far
+        let mut direction = 1.0;
+        let mut previous: Option<([f64; 2], f64)> = None;
+        for (&t, x) in ts.iter().zip(states) {
+            let Some((&[_, _, e_a, e_b], pll)) = x.split_first_chunk::<4>() else {
+                continue;
+            };
+            let e_mag = e_a.hypot(e_b);
+            let (theta, omega_e) = if let &[theta, omega] = pll {
+                (theta + omega.signum() * self.lag(omega).0, omega)
+            } else {
+                if let Some(([p_a, p_b], _)) = previous {
+                    let cross = p_a * e_b - p_b * e_a;
+                    if cross != 0.0 {
+                        direction = cross.signum();
+                    }
+                }
+                let omega = direction * self.emf_speed(e_mag);
+                let raw = (-direction * e_a).atan2(direction * e_b) + direction * self.lag(omega).0;
+                let theta = previous.map_or(raw, |(_, prev)| prev + wrap(raw - prev));
+                (theta, omega)
+            };
+            previous = Some(([e_a, e_b], theta));
+            series.theta_e.push([t, theta]);
+            series.omega_m.push([t, omega_e / n_p]);
+            series.emf.push([t, e_a, e_b, e_mag]);
+        }
+        series
+    }
+
+    /// Angle error `θ̂ − θ` wrapped to `(−π, π]`, for the estimate `theta_e`
+    /// and the true angle `theta_at`.
+    pub fn angle_error(theta_e: &[[f64; 2]], theta_at: impl Fn(f64) -> f64) -> Vec<[f64; 2]> {
+        theta_e
+            .iter()
+            .map(|&[t, estimate]| [t, wrap(estimate - theta_at(t))])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AngleTracker, EmfEstimator, ObserverNode, clarke, wrap};
+
+    /// Integrate the observer with forward Euler on a machine turning at a
+    /// constant electrical speed `omega_e` with a steady q-axis current, and
+    /// return the final angle error and speed estimate.
+    fn run(observer: &ObserverNode, omega_e: f64) -> (f64, f64) {
+        let (i_q, dt, steps) = (2.0, 1e-6, 300_000);
+        let theta_at = |t: f64| omega_e * t + 0.3;
+        let machine = |t: f64| {
+            let theta = theta_at(t);
+            let (sin, cos) = theta.sin_cos();
+            let i = [-i_q * sin, i_q * cos];
+            let e = [
+                -omega_e * observer.lambda_m * sin,
+                omega_e * observer.lambda_m * cos,
+            ];
+            // Steady state: v = R·i + L·di/dt + e, with di/dt = ω_e·J·i
+            let v = [
+                observer.r_s * i[0] - observer.l_s * omega_e * i[1] + e[0],
+                observer.r_s * i[1] + observer.l_s * omega_e * i[0] + e[1],
+            ];
+            (v, i)
+        };
+        let mut x = observer.initial_state(machine(0.0).1);
+        let mut dx = vec![0.0; x.len()];
+        let mut ts = vec![0.0];
+        let mut states = vec![x.clone()];
+        for k in 0..steps {
+            let t = k as f64 * dt;
+            let (v, i) = machine(t);
+            observer.derivatives(v, i, &x, &mut dx);
+            for (x, d) in x.iter_mut().zip(&dx) {
+                *x += dt * d;
+            }
+            ts.push(t + dt);
+            states.push(x.clone());
+        }
+        let series = observer.estimate(&ts, &states);
+        let error = ObserverNode::angle_error(&series.theta_e, theta_at);
+        let (&[_, error], &[_, omega_m]) = (
+            error.last().expect("samples"),
+            series.omega_m.last().expect("samples"),
+        );
+        (error, omega_m * observer.n_p)
+    }
+
+    /// Every estimator and tracker locks onto the rotor angle and speed,
+    /// with the estimator lag compensated.
+    #[test]
+    fn observers_lock_onto_rotating_rotor() {
+        for estimator in EmfEstimator::ALL {
+            for tracker in AngleTracker::ALL {
+                let observer = ObserverNode {
+                    estimator,
+                    tracker,
+                    ..ObserverNode::default()
+                };
+                let omega_e = 300.0;
+                let (error, omega) = run(&observer, omega_e);
+                let label = format!("{} / {}", estimator.label(), tracker.label());
+                assert!(error.abs() < 0.03, "{label}: angle error {error}");
+                assert!(
+                    (omega - omega_e).abs() < 0.02 * omega_e,
+                    "{label}: speed {omega}"
+                );
+            }
+        }
+    }
+
+    /// The Clarke transform maps a balanced set onto a unit phasor and the
+    /// wrap keeps angles within `(−π, π]`.
+    #[test]
+    fn clarke_and_wrap() {
+        let theta: f64 = 0.7;
+        let abc = [0.0, -1.0, 1.0].map(|k: f64| (theta + k * std::f64::consts::TAU / 3.0).cos());
+        let [alpha, beta] = clarke(abc);
+        assert!((alpha - theta.cos()).abs() < 1e-12, "α = cos θ");
+        assert!((beta - theta.sin()).abs() < 1e-12, "β = sin θ");
+        assert!(
+            (wrap(3.5 * std::f64::consts::PI) + 0.5 * std::f64::consts::PI).abs() < 1e-12,
+            "wrapped"
+        );
+        assert!(
+            (wrap(std::f64::consts::PI) - std::f64::consts::PI).abs() < 1e-12,
+            "π stays π"
+        );
+    }
+}
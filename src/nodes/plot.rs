@@ -4,15 +4,49 @@ use egui::Color32;
 
 use crate::port::PortType;
 
+/// Traces of one run of a sweep, as seen by a Plot node.
+#[derive(Clone, Debug, Default)]
+pub struct PlotRun {
+    /// Legend label, e.g. `j = 0.0008`.
+    pub label: String,
+    /// `(name, points)` of each plotted line.
+    pub lines: Vec<(String, Vec<[f64; 2]>)>,
+}
+
+/// Spread of one trace over the runs of a Monte Carlo study.
+#[derive(Clone, Debug, Default)]
+pub struct PlotEnvelope {
+    /// Name of the trace.
+    pub name: String,
+    /// Smallest value over the runs.
+    pub min: Vec<[f64; 2]>,
+    /// Lower percentile.
+    pub low: Vec<[f64; 2]>,
+    /// Median.
+    pub median: Vec<[f64; 2]>,
+    /// Upper percentile.
+    pub high: Vec<[f64; 2]>,
+    /// Largest value over the runs.
+    pub max: Vec<[f64; 2]>,
+}
+
 /// A sink node that collects Signal inputs for plotting.
 ///
 /// The number of inputs is configurable at runtime (1–8).
 /// It has no outputs; it is a pure sink in the simulation graph.
+/// After a sweep it overlays the traces of every run instead, and after a
+/// Monte Carlo study it draws the envelope of each trace.
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
 #[serde(default)]
 pub struct PlotNode {
     /// Number of Signal input pins. Minimum 1, maximum 8.
     pub num_inputs: usize,
+    /// Traces of the last sweep, one entry per run.
+    #[serde(skip)]
+    pub runs: Vec<PlotRun>,
+    /// Envelopes of the last Monte Carlo study, one per trace.
+    #[serde(skip)]
+    pub envelopes: Vec<PlotEnvelope>,
     /// User-defined node size override (width, height).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub custom_size: Option<[f32; 2]>,
@@ -22,6 +56,8 @@ impl Default for PlotNode {
     fn default() -> Self {
         Self {
             num_inputs: 1,
+            runs: Vec::new(),
+            envelopes: Vec::new(),
             custom_size: None,
         }
     }
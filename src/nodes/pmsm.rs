@@ -0,0 +1,351 @@
+//! Rotor-frame PMSM equations shared by the machine solve and the EKF node.
+//!
+//! With current states `x = [i_d, i_q, ω_m, θ_e]`:
+//!
+//! ```text
+//! di_d/dt = (v_d − R_s·i_d + N_p·ω_m·(L_q·i_q + Δψ_q − Δψ_d')) / L_d
+//! di_q/dt = (v_q − R_s·i_q − N_p·ω_m·(L_d·i_d + λ_m + Δψ_d + Δψ_q')) / L_q
+//! dω_m/dt = (T_e − T_load − B·ω_m) / J
+//! dθ_e/dt = N_p·ω_m
+//! T_e     = (3/2)·N_p·(λ_m·i_q + (L_d − L_q)·i_d·i_q
+//!                      + Δψ_d·i_q − Δψ_q·i_d + Δψ_d'·i_d + Δψ_q'·i_q) + T_cog
+//! ```
+//!
+//! with `Δψ(θ_e)` the harmonic PM flux, `' = ∂/∂θ_e` and `T_cog(θ_e / N_p)`
+//! the cogging torque (see [`SpatialHarmonics`]). With a flux map the first
+//! two states are the flux linkages `[ψ_d, ψ_q]` instead:
+//!
+//! ```text
+//! dψ_d/dt = v_d − R_s·i_d + N_p·ω_m·ψ_q
+//! dψ_q/dt = v_q − R_s·i_q − N_p·ω_m·ψ_d
+//! T_e     = (3/2)·N_p·(ψ_d·i_q − ψ_q·i_d + Δψ_d'·i_d + Δψ_q'·i_q) + T_cog
+//! ```
+//!
+//! where the currents come from inverting the map. `T_load` gathers every
+//! other torque on the rotor (core loss, friction, the load); the caller
+//! supplies it and adds its derivatives to the Jacobian, as it does for the
+//! dependence of `v_dq` on the state.
+
+use super::flux_map::FluxPoint;
+use super::harmonics::SpatialHarmonics;
+
+/// Machine constants of the rotor-frame equations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PmsmParams {
+    /// Stator resistance (Ω).
+    pub r_s: f64,
+    /// d-axis inductance (H).
+    pub l_d: f64,
+    /// q-axis inductance (H).
+    pub l_q: f64,
+    /// PM flux linkage (Wb).
+    pub lambda_m: f64,
+    /// Number of pole pairs.
+    pub n_p: f64,
+    /// Rotor inertia (kg·m²).
+    pub j: f64,
+    /// Viscous friction (N·m·s/rad).
+    pub b: f64,
+    /// Pole pairs of the torque equation.
+    pub torque_n_p: f64,
+    /// PM flux linkage of the torque equation (Wb).
+    pub torque_lambda_m: f64,
+    /// d-axis inductance of the torque equation (H).
+    pub torque_l_d: f64,
+    /// q-axis inductance of the torque equation (H).
+    pub torque_l_q: f64,
+}
+
+/// Jacobian of the rotor-frame equations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RotorJacobian {
+    /// `∂f/∂x`, one row per equation.
+    pub dx: [[f64; 4]; 4],
+    /// `∂f_d/∂v_d` and `∂f_q/∂v_q`; the voltages enter no other equation.
+    pub dv: [f64; 2],
+}
+
+/// The rotor-frame equations of one machine.
+#[derive(Clone, Copy, Debug)]
+pub struct RotorFrame<'a> {
+    /// Machine constants.
+    pub params: PmsmParams,
+    /// Back-EMF harmonics and cogging.
+    pub harmonics: &'a SpatialHarmonics,
+}
+
+impl RotorFrame<'_> {
+    /// Cogging torque and `∂T_cog/∂θ_e` at electrical angle `theta_e`.
+    fn cogging(&self, theta_e: f64) -> (f64, f64) {
+        let (t_cog, dt_cog) = self.harmonics.cogging(theta_e / self.params.n_p);
+        (t_cog, dt_cog / self.params.n_p)
+    }
+
+    /// Electromagnetic torque `T_e` at current states `x`.
+    pub fn torque(&self, [i_d, i_q, _, theta_e]: [f64; 4]) -> f64 {
+        let p = &self.params;
+        let pm = self.harmonics.pm_flux(p.lambda_m, theta_e);
+        let (t_cog, _) = self.cogging(theta_e);
+        1.5 * p.torque_n_p
+            * (p.torque_lambda_m * i_q
+                + (p.torque_l_d - p.torque_l_q) * i_d * i_q
+                + pm.psi[0] * i_q
+                - pm.psi[1] * i_d
+                + pm.dpsi[0] * i_d
+                + pm.dpsi[1] * i_q)
+            + t_cog
+    }
+
+    /// State derivative at current states `x`, voltages `v` and load torque
+    /// `t_load`.
+    pub fn derivatives(&self, x: [f64; 4], [v_d, v_q]: [f64; 2], t_load: f64) -> [f64; 4] {
+        let p = &self.params;
+        let [i_d, i_q, omega_m, theta_e] = x;
+        let pm = self.harmonics.pm_flux(p.lambda_m, theta_e);
+        let omega_e = p.n_p * omega_m;
+        [
+            (v_d - p.r_s * i_d + omega_e * (p.l_q * i_q + pm.psi[1] - pm.dpsi[0])) / p.l_d,
+            (v_q - p.r_s * i_q - omega_e * (p.l_d * i_d + p.lambda_m + pm.psi[0] + pm.dpsi[1]))
+                / p.l_q,
+            (self.torque(x) - t_load - p.b * omega_m) / p.j,
+            omega_e,
+        ]
+    }
+
+    /// Jacobian at current states `x`.
+    pub fn jacobian(&self, [i_d, i_q, omega_m, theta_e]: [f64; 4]) -> RotorJacobian {
+        let p = &self.params;
+        let pm = self.harmonics.pm_flux(p.lambda_m, theta_e);
+        let (_, dt_cog) = self.cogging(theta_e);
+        let omega_e = p.n_p * omega_m;
+        let k_t = 1.5 * p.torque_n_p;
+        let saliency = p.torque_l_d - p.torque_l_q;
+
+        // ∂T_e/∂(i_d, i_q, θ_e)
+        let dt_e_did = k_t * (saliency * i_q - pm.psi[1] + pm.dpsi[0]);
+        let dt_e_diq = k_t * (p.torque_lambda_m + saliency * i_d + pm.psi[0] + pm.dpsi[1]);
+        let dt_e_dte = k_t
+            * (pm.dpsi[0] * i_q - pm.dpsi[1] * i_d + pm.d2psi[0] * i_d + pm.d2psi[1] * i_q)
+            + dt_cog;
+
+        RotorJacobian {
+            dx: [
+                [
+                    -p.r_s / p.l_d,
+                    omega_e * p.l_q / p.l_d,
+                    p.n_p * (p.l_q * i_q + pm.psi[1] - pm.dpsi[0]) / p.l_d,
+                    omega_e * (pm.dpsi[1] - pm.d2psi[0]) / p.l_d,
+                ],
+                [
+                    -omega_e * p.l_d / p.l_q,
+                    -p.r_s / p.l_q,
+                    -p.n_p * (p.l_d * i_d + p.lambda_m + pm.psi[0] + pm.dpsi[1]) / p.l_q,
+                    -omega_e * (pm.dpsi[0] + pm.d2psi[1]) / p.l_q,
+                ],
+                [dt_e_did / p.j, dt_e_diq / p.j, -p.b / p.j, dt_e_dte / p.j],
+                [0.0, 0.0, p.n_p, 0.0],
+            ],
+            dv: [1.0 / p.l_d, 1.0 / p.l_q],
+        }
+    }
+
+    /// Electromagnetic torque `T_e` at flux states `x` with currents `i`.
+    pub fn flux_torque(&self, [psi_d, psi_q, _, theta_e]: [f64; 4], [i_d, i_q]: [f64; 2]) -> f64 {
+        // Flux states already include the harmonic PM flux
+        let pm = self.harmonics.pm_flux(self.params.lambda_m, theta_e);
+        let (t_cog, _) = self.cogging(theta_e);
+        1.5 * self.params.torque_n_p
+            * (psi_d * i_q - psi_q * i_d + pm.dpsi[0] * i_d + pm.dpsi[1] * i_q)
+            + t_cog
+    }
+
+    /// State derivative at flux states `x` with currents `i`, voltages `v`
+    /// and load torque `t_load`.
+    pub fn flux_derivatives(
+        &self,
+        x: [f64; 4],
+        i: [f64; 2],
+        [v_d, v_q]: [f64; 2],
+        t_load: f64,
+    ) -> [f64; 4] {
+        let p = &self.params;
+        let [psi_d, psi_q, omega_m, _] = x;
+        let omega_e = p.n_p * omega_m;
+        [
+            v_d - p.r_s * i[0] + omega_e * psi_q,
+            v_q - p.r_s * i[1] - omega_e * psi_d,
+            (self.flux_torque(x, i) - t_load - p.b * omega_m) / p.j,
+            omega_e,
+        ]
+    }
+
+    /// Jacobian at flux states `x` with currents `i` and the map's
+    /// incremental inductances `l` at `i`.
+    pub fn flux_jacobian(
+        &self,
+        [psi_d, psi_q, omega_m, theta_e]: [f64; 4],
+        [i_d, i_q]: [f64; 2],
+        l: &FluxPoint,
+    ) -> RotorJacobian {
+        let p = &self.params;
+        let pm = self.harmonics.pm_flux(p.lambda_m, theta_e);
+        let (_, dt_cog) = self.cogging(theta_e);
+        let omega_e = p.n_p * omega_m;
+        let k_t = 1.5 * p.torque_n_p;
+
+        // ∂i/∂ψ is the inverse Γ of the incremental inductance matrix
+        let det = l.l_dd * l.l_qq - l.l_dq * l.l_qd;
+        let (g_dd, g_dq) = (l.l_qq / det, -l.l_dq / det);
+        let (g_qd, g_qq) = (-l.l_qd / det, l.l_dd / det);
+
+        // ∂i/∂θ_e = −Γ·∂Δψ/∂θ_e
+        let did_dte = -(g_dd * pm.dpsi[0] + g_dq * pm.dpsi[1]);
+        let diq_dte = -(g_qd * pm.dpsi[0] + g_qq * pm.dpsi[1]);
+
+        // ∂T_e/∂(ψ_d, ψ_q, θ_e)
+        let dt_e_dpd =
+            k_t * (i_q + psi_d * g_qd - psi_q * g_dd + pm.dpsi[0] * g_dd + pm.dpsi[1] * g_qd);
+        let dt_e_dpq =
+            k_t * (psi_d * g_qq - i_d - psi_q * g_dq + pm.dpsi[0] * g_dq + pm.dpsi[1] * g_qq);
+        let dt_e_dte = k_t
+            * (psi_d * diq_dte - psi_q * did_dte
+                + pm.d2psi[0] * i_d
+                + pm.d2psi[1] * i_q
+                + pm.dpsi[0] * did_dte
+                + pm.dpsi[1] * diq_dte)
+            + dt_cog;
+
+        RotorJacobian {
+            dx: [
+                [
+                    -p.r_s * g_dd,
+                    -p.r_s * g_dq + omega_e,
+                    p.n_p * psi_q,
+                    -p.r_s * did_dte,
+                ],
+                [
+                    -p.r_s * g_qd - omega_e,
+                    -p.r_s * g_qq,
+                    -p.n_p * psi_d,
+                    -p.r_s * diq_dte,
+                ],
+                [dt_e_dpd / p.j, dt_e_dpq / p.j, -p.b / p.j, dt_e_dte / p.j],
+                [0.0, 0.0, p.n_p, 0.0],
+            ],
+            dv: [1.0, 1.0],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PmsmParams, RotorFrame, RotorJacobian};
+    use crate::nodes::flux_map::FluxMap;
+    use crate::nodes::harmonics::{CoggingModel, Harmonic, SpatialHarmonics};
+
+    /// Salient machine with distinct torque constants.
+    const PARAMS: PmsmParams = PmsmParams {
+        r_s: 1.2,
+        l_d: 0.006,
+        l_q: 0.009,
+        lambda_m: 0.175,
+        n_p: 4.0,
+        j: 0.0008,
+        b: 0.001,
+        torque_n_p: 4.0,
+        torque_lambda_m: 0.17,
+        torque_l_d: 0.0055,
+        torque_l_q: 0.0095,
+    };
+
+    /// 5th/7th back-EMF harmonics and a Fourier cogging term.
+    fn harmonics() -> SpatialHarmonics {
+        let term = |order, amplitude, phase| Harmonic {
+            order,
+            amplitude,
+            phase,
+        };
+        SpatialHarmonics {
+            emf: vec![term(5, 0.04, 0.3), term(7, 0.02, -0.5)],
+            cogging: CoggingModel::Fourier,
+            cogging_fourier: vec![term(24, 0.05, 0.1)],
+            ..SpatialHarmonics::default()
+        }
+    }
+
+    /// Assert that `jac` matches central differences of `f` about `x`.
+    fn assert_jacobian(f: impl Fn([f64; 4]) -> [f64; 4], x: [f64; 4], jac: &RotorJacobian) {
+        for (col, h) in [1e-4, 1e-4, 1e-3, 1e-5].into_iter().enumerate() {
+            let shifted = |step: f64| {
+                let mut x = x;
+                if let Some(xk) = x.get_mut(col) {
+                    *xk += step;
+                }
+                f(x)
+            };
+            let (plus, minus) = (shifted(h), shifted(-h));
+            for (row, analytic) in jac.dx.iter().map(|r| r.get(col).copied()).enumerate() {
+                let (Some(a), Some(b), Some(analytic)) = (plus.get(row), minus.get(row), analytic)
+                else {
+                    panic!("4×4 Jacobian");
+                };
+                let numeric = (a - b) / (2.0 * h);
+                assert!(
+                    (numeric - analytic).abs() < 1e-5 * (1.0 + analytic.abs()),
+                    "∂f{row}/∂x{col}: analytic {analytic}, numeric {numeric}"
+                );
+            }
+        }
+    }
+
+    /// The analytic Jacobians of both state forms match finite differences,
+    /// and the flux form on a linear map reproduces the current form.
+    #[test]
+    fn jacobians_match_finite_differences() {
+        let harmonics = harmonics();
+        let model = RotorFrame {
+            params: PARAMS,
+            harmonics: &harmonics,
+        };
+        let x = [-1.5, 4.0, 120.0, 0.7];
+        let v = [-20.0, 80.0];
+        assert_jacobian(|x| model.derivatives(x, v, 0.3), x, &model.jacobian(x));
+
+        // Linear map of the same machine; the states are the total flux
+        let map = FluxMap::linear(PARAMS.l_d, PARAMS.l_q, PARAMS.lambda_m, 50.0, 41);
+        let currents = |[psi_d, psi_q, _, theta_e]: [f64; 4]| {
+            let pm = harmonics.pm_flux(PARAMS.lambda_m, theta_e);
+            let (psi_d, psi_q) = (psi_d - pm.psi[0], psi_q - pm.psi[1]);
+            map.currents(psi_d, psi_q, map.linear_currents(psi_d, psi_q))
+                .expect("linear map inverts")
+                .into()
+        };
+        let [i_d, i_q, omega_m, theta_e] = x;
+        let pm = harmonics.pm_flux(PARAMS.lambda_m, theta_e);
+        let flux = [
+            PARAMS.l_d * i_d + PARAMS.lambda_m + pm.psi[0],
+            PARAMS.l_q * i_q + pm.psi[1],
+            omega_m,
+            theta_e,
+        ];
+        let jac = model.flux_jacobian(flux, [i_d, i_q], &map.eval(i_d, i_q));
+        assert_jacobian(
+            |x| model.flux_derivatives(x, currents(x), v, 0.3),
+            flux,
+            &jac,
+        );
+
+        // Same torque when the torque constants are the machine's
+        let machine = RotorFrame {
+            params: PmsmParams {
+                torque_lambda_m: PARAMS.lambda_m,
+                torque_l_d: PARAMS.l_d,
+                torque_l_q: PARAMS.l_q,
+                ..PARAMS
+            },
+            harmonics: &harmonics,
+        };
+        let (t_e, t_flux) = (machine.torque(x), machine.flux_torque(flux, [i_d, i_q]));
+        assert!((t_e - t_flux).abs() < 1e-9, "T_e {t_e} vs {t_flux}");
+    }
+}
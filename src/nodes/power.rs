@@ -0,0 +1,331 @@
+//! Power-flow node — electrical input, losses, mechanical output, efficiency
+//! and the energy balance of the d/q PMSM.
+//!
+//! At every solver step the node takes
+//!
+//! ```text
+//! P_e    = (3/2)·(v_d·i_d + v_q·i_q)        electrical input (pmsm.md §9)
+//! P_cu   = (3/2)·R_s·(i_d² + i_q²)          copper loss
+//! P_fe   = T_fe·ω_m                         iron loss
+//! P_m    = T_e·ω_m                          mechanical output
+//! P_fric = (B·ω_m + T_f)·ω_m                viscous and dry friction loss
+//! ```
+//!
+//! and the efficiency `η = P_m / P_e` when motoring (`P_e / P_m` when
+//! generating). Integrated over the run, the input energy must equal the
+//! losses, the energy delivered to the load and the change of the stored
+//! energies:
+//!
+//! ```text
+//! E_in = E_cu + E_fe + E_fric + E_load + ΔW_mag + ΔW_kin + ΔW_cog
+//! ```
+//!
+//! with the magnetic energy `W_mag = (3/4)·(L_d·i_d² + L_q·i_q²)` (or
+//! `(3/2)·∫i·dψ` with a flux map), the kinetic energy `W_kin = J·ω_m²/2` and
+//! the cogging energy `ΔW_cog = −∫T_cog·ω_m dt`. The residual is a check on
+//! the solution: it stays within the solver tolerance of the energy
+//! throughput unless the step size is too coarse or the Torque node's
+//! parameters disagree with the Electrical node's.
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// Power flow of the machine at one solver step.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PowerSample {
+    /// Time (s).
+    pub t: f64,
+    /// Electrical input power `P_e` (W).
+    pub p_e: f64,
+    /// Copper loss `P_cu` (W).
+    pub p_cu: f64,
+    /// Iron loss `P_fe` (W).
+    pub p_fe: f64,
+    /// Mechanical output `T_e·ω_m` (W).
+    pub p_m: f64,
+    /// Friction loss `(B·ω_m + T_f)·ω_m` (W).
+    pub p_fric: f64,
+    /// Power delivered to the load or shaft (W).
+    pub p_load: f64,
+    /// Cogging power `T_cog·ω_m` (W).
+    pub p_cog: f64,
+    /// Stored magnetic energy `W_mag` (J).
+    pub w_mag: f64,
+    /// Kinetic energy of the rotor `W_kin` (J).
+    pub w_kin: f64,
+}
+
+impl PowerSample {
+    /// Efficiency `P_m / P_e` when motoring or `P_e / P_m` when generating
+    /// (%), zero while the flows disagree in sign.
+    pub fn efficiency(&self) -> f64 {
+        if self.p_e > 0.0 && self.p_m >= 0.0 {
+            100.0 * self.p_m / self.p_e
+        } else if self.p_e < 0.0 && self.p_m < 0.0 {
+            100.0 * self.p_e / self.p_m
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Energies over the run (J) and the residual of their balance.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EnergyBalance {
+    /// Electrical input energy `E_in`.
+    pub e_in: f64,
+    /// Copper loss energy `E_cu`.
+    pub e_cu: f64,
+    /// Iron loss energy `E_fe`.
+    pub e_fe: f64,
+    /// Friction loss energy `E_fric`.
+    pub e_fric: f64,
+    /// Energy delivered to the load `E_load`.
+    pub e_load: f64,
+    /// Mechanical output energy `∫T_e·ω_m dt`.
+    pub e_m: f64,
+    /// Change of the magnetic energy `ΔW_mag`.
+    pub d_w_mag: f64,
+    /// Change of the kinetic energy `ΔW_kin`.
+    pub d_w_kin: f64,
+    /// Change of the cogging energy `ΔW_cog`.
+    pub d_w_cog: f64,
+    /// Largest residual allowed by the solver tolerance.
+    pub tolerance: f64,
+}
+
+impl EnergyBalance {
+    /// Input energy not accounted for by the losses, the load and the
+    /// stored energies.
+    pub fn residual(&self) -> f64 {
+        self.e_in
+            - self.e_cu
+            - self.e_fe
+            - self.e_fric
+            - self.e_load
+            - self.d_w_mag
+            - self.d_w_kin
+            - self.d_w_cog
+    }
+
+    /// Whether the residual is within [`Self::tolerance`].
+    pub fn closes(&self) -> bool {
+        self.residual().abs() <= self.tolerance
+    }
+
+    /// Mean efficiency over the run `E_m / E_in` (%).
+    pub fn efficiency(&self) -> f64 {
+        if self.e_in == 0.0 {
+            0.0
+        } else {
+            100.0 * self.e_m / self.e_in
+        }
+    }
+}
+
+/// A node that reports the power flow and energy balance of the d/q PMSM.
+///
+/// Has no inputs: the flows are computed from the machine states.\
+/// Outputs: `P_e`, `P_cu`, `P_fe`, `P_m`, `P_fric`, efficiency `η` (%) and
+/// input energy `E_in` (all Signal).
+#[derive(Clone, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PowerFlowNode {
+    /// Energy balance of the last run.
+    #[serde(skip)]
+    pub balance: Option<EnergyBalance>,
+    /// Electrical input power time-series produced after simulation.
+    #[serde(skip)]
+    pub output_p_e: Option<PortValue>,
+    /// Copper loss time-series produced after simulation.
+    #[serde(skip)]
+    pub output_p_cu: Option<PortValue>,
+    /// Iron loss time-series produced after simulation.
+    #[serde(skip)]
+    pub output_p_fe: Option<PortValue>,
+    /// Mechanical output power time-series produced after simulation.
+    #[serde(skip)]
+    pub output_p_m: Option<PortValue>,
+    /// Friction loss time-series produced after simulation.
+    #[serde(skip)]
+    pub output_p_fric: Option<PortValue>,
+    /// Efficiency time-series produced after simulation.
+    #[serde(skip)]
+    pub output_eta: Option<PortValue>,
+    /// Input energy time-series produced after simulation.
+    #[serde(skip)]
+    pub output_e_in: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+/// Time-series of the power flow, one `[t, value]` per solver step.
+#[derive(Clone, Debug, Default)]
+pub struct PowerSeries {
+    /// Electrical input power.
+    pub p_e: Vec<[f64; 2]>,
+    /// Copper loss.
+    pub p_cu: Vec<[f64; 2]>,
+    /// Iron loss.
+    pub p_fe: Vec<[f64; 2]>,
+    /// Mechanical output power.
+    pub p_m: Vec<[f64; 2]>,
+    /// Friction loss.
+    pub p_fric: Vec<[f64; 2]>,
+    /// Efficiency (%).
+    pub eta: Vec<[f64; 2]>,
+    /// Cumulative input energy.
+    pub e_in: Vec<[f64; 2]>,
+}
+
+impl PowerFlowNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "Power Flow"
+    }
+
+    /// Input port descriptors (none: the flows come from the machine states).
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[]
+    }
+
+    /// Output port descriptors: powers, efficiency and input energy.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("P_e", PortType::Signal),
+            ("P_cu", PortType::Signal),
+            ("P_fe", PortType::Signal),
+            ("P_m", PortType::Signal),
+            ("P_fric", PortType::Signal),
+            ("η", PortType::Signal),
+            ("E_in", PortType::Signal),
+        ]
+    }
+
+    /// Header colour (same family as the d/q electrical node).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0xB0, 0x50, 0x30)
+    }
+
+    /// Series and energy balance of the solver steps `samples`; the balance
+    /// must close to `rtol` of the energy throughput.
+    ///
+    /// Each step is integrated over the parabola through its end points and
+    /// the previous (or, for the first step, the next) sample, so the
+    /// quadrature error stays below the solver's on the variable steps.
+    pub fn account(samples: &[PowerSample], rtol: f64) -> (PowerSeries, EnergyBalance) {
+        let mut series = PowerSeries::default();
+        let mut balance = EnergyBalance::default();
+        let mut e_in = 0.0;
+        for (k, s) in samples.iter().enumerate() {
+            let prev = k.checked_sub(1).and_then(|j| samples.get(j));
+            let third = k
+                .checked_sub(2)
+                .and_then(|j| samples.get(j))
+                .or_else(|| samples.get(k + 1));
+            if let Some(prev) = prev {
+                let weights = match third {
+                    Some(third) => step_weights([prev.t, s.t, third.t], prev.t, s.t),
+                    None => [0.5 * (s.t - prev.t), 0.5 * (s.t - prev.t), 0.0],
+                };
+                let area = |f: fn(&PowerSample) -> f64| {
+                    let mut sum = weights[0] * f(prev) + weights[1] * f(s);
+                    if let Some(third) = third {
+                        sum += weights[2] * f(third);
+                    }
+                    sum
+                };
+                e_in += area(|s| s.p_e);
+                balance.e_cu += area(|s| s.p_cu);
+                balance.e_fe += area(|s| s.p_fe);
+                balance.e_fric += area(|s| s.p_fric);
+                balance.e_load += area(|s| s.p_load);
+                balance.e_m += area(|s| s.p_m);
+                balance.d_w_cog -= area(|s| s.p_cog);
+            }
+            series.p_e.push([s.t, s.p_e]);
+            series.p_cu.push([s.t, s.p_cu]);
+            series.p_fe.push([s.t, s.p_fe]);
+            series.p_m.push([s.t, s.p_m]);
+            series.p_fric.push([s.t, s.p_fric]);
+            series.eta.push([s.t, s.efficiency()]);
+            series.e_in.push([s.t, e_in]);
+        }
+        balance.e_in = e_in;
+        if let (Some(first), Some(last)) = (samples.first(), samples.last()) {
+            balance.d_w_mag = last.w_mag - first.w_mag;
+            balance.d_w_kin = last.w_kin - first.w_kin;
+        }
+        let throughput = [
+            balance.e_in,
+            balance.e_cu,
+            balance.e_fe,
+            balance.e_fric,
+            balance.e_load,
+            balance.d_w_mag,
+            balance.d_w_kin,
+            balance.d_w_cog,
+        ]
+        .iter()
+        .map(|e| e.abs())
+        .sum::<f64>();
+        balance.tolerance = rtol * throughput;
+        (series, balance)
+    }
+}
+
+/// Weights of the samples at `nodes` in the integral over `[from, to]` of
+/// the parabola through them (Lagrange basis integrals).
+fn step_weights(nodes: [f64; 3], from: f64, to: f64) -> [f64; 3] {
+    // Times relative to `from` keep the short steps free of cancellation
+    let [a, b, c] = nodes.map(|t| t - from);
+    let h = to - from;
+    // ∫(t − x)(t − y) dt over [0, h]
+    let product = |x: f64, y: f64| h * h * h / 3.0 - (x + y) * h * h / 2.0 + x * y * h;
+    [
+        product(b, c) / ((a - b) * (a - c)),
+        product(a, c) / ((b - a) * (b - c)),
+        product(a, b) / ((c - a) * (c - b)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PowerFlowNode, PowerSample};
+
+    /// A rotor spun up at constant input power by a lossy winding stores
+    /// the rest as kinetic energy, and the balance closes.
+    #[test]
+    fn balance_closes_for_consistent_flows() {
+        let (p_in, r_loss) = (100.0, 0.2);
+        let samples: Vec<PowerSample> = (0..=100)
+            .map(|k| {
+                let t = k as f64 * 0.01;
+                // The rest of the input accelerates the rotor
+                PowerSample {
+                    t,
+                    p_e: p_in,
+                    p_cu: r_loss * p_in,
+                    p_m: (1.0 - r_loss) * p_in,
+                    w_kin: (1.0 - r_loss) * p_in * t,
+                    ..PowerSample::default()
+                }
+            })
+            .collect();
+        let (series, balance) = PowerFlowNode::account(&samples, 1e-9);
+        assert!((balance.e_in - 100.0).abs() < 1e-9, "E_in {}", balance.e_in);
+        assert!(balance.closes(), "residual {}", balance.residual());
+        assert!((balance.efficiency() - 80.0).abs() < 1e-9);
+        let [_, eta] = *series.eta.last().expect("samples");
+        assert!((eta - 80.0).abs() < 1e-9, "η {eta}");
+
+        let leaky: Vec<PowerSample> = samples
+            .iter()
+            .map(|s| PowerSample { p_cu: 0.0, ..*s })
+            .collect();
+        let (_, balance) = PowerFlowNode::account(&leaky, 1e-9);
+        assert!(!balance.closes(), "unaccounted copper loss");
+    }
+}
@@ -0,0 +1,396 @@
+//! Position/speed sensor node — what a controller sees of the rotor angle.
+//!
+//! The true electrical angle `θ_e` is delayed by the sensor latency and
+//! shifted by the mounting offset, then passed through one of three sensor
+//! models:
+//!
+//! - **Encoder**: quadrature incremental encoder with `N` lines per
+//!   mechanical revolution, counting `4·N` edges; the angle is quantized to
+//!   `2π / (4·N)` mechanical.
+//! - **Resolver**: sine and cosine envelopes sampled at the excitation peaks,
+//!   with a gain error on the cosine channel and a quadrature phase error,
+//!   demodulated by `atan2`.
+//! - **Hall**: three sensors 120° apart; the angle is the centre of the
+//!   current 60° sector.
+//!
+//! Encoder and resolver speed is the angle difference over a fixed window,
+//! updated once per window; Hall speed is 60° over the time between the last
+//! two edges.
+
+use std::f64::consts::{PI, TAU};
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// Width of one Hall sector (rad, electrical).
+const SECTOR: f64 = PI / 3.0;
+
+/// Sensor principle.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SensorModel {
+    /// Quadrature incremental encoder.
+    #[default]
+    Encoder,
+    /// Resolver with arctangent demodulation.
+    Resolver,
+    /// Three Hall-effect switches.
+    Hall,
+}
+
+impl SensorModel {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 3] = [Self::Encoder, Self::Resolver, Self::Hall];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Encoder => "Encoder",
+            Self::Resolver => "Resolver",
+            Self::Hall => "Hall sensors",
+        }
+    }
+}
+
+/// Position sensor on the rotor shaft.
+///
+/// Input: true electrical angle `θ_e` (Signal), usually from the Mechanical
+/// node.\
+/// Outputs: measured electrical angle `θ_e`, measured mechanical speed
+/// `ω_m`, 60° sector `1…6` of the measured angle (Signal), and the raw
+/// channels (Vector): encoder `[A, B, Z]`, resolver `[sin, cos, amplitude]`
+/// envelopes, or Hall `[H_a, H_b, H_c]`.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PositionSensorNode {
+    /// Sensor principle.
+    pub model: SensorModel,
+    /// Motor pole pairs, converting between electrical and mechanical angle.
+    pub n_p: f64,
+    /// Mounting offset added to the true angle (°, electrical).
+    pub offset: f64,
+    /// Delay between the shaft position and the measurement (s).
+    pub latency: f64,
+    /// Encoder lines per mechanical revolution.
+    pub lines: u32,
+    /// Speed measurement window of encoder and resolver (s).
+    pub speed_window: f64,
+    /// Resolver pole pairs (speed ratio to the shaft).
+    pub resolver_pp: f64,
+    /// Resolver excitation frequency (Hz).
+    pub f_excitation: f64,
+    /// Relative gain error of the resolver cosine channel.
+    pub amplitude_error: f64,
+    /// Quadrature phase error of the resolver cosine channel (°).
+    pub phase_error: f64,
+    /// Measured angle time-series produced after simulation.
+    #[serde(skip)]
+    pub output_theta_e: Option<PortValue>,
+    /// Measured speed time-series produced after simulation.
+    #[serde(skip)]
+    pub output_omega_m: Option<PortValue>,
+    /// Sector time-series produced after simulation.
+    #[serde(skip)]
+    pub output_sector: Option<PortValue>,
+    /// Raw channel time-series produced after simulation.
+    #[serde(skip)]
+    pub output_raw: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for PositionSensorNode {
+    fn default() -> Self {
+        Self {
+            model: SensorModel::Encoder,
+            n_p: 4.0,
+            offset: 0.0,
+            latency: 0.0,
+            lines: 1024,
+            speed_window: 0.001,
+            resolver_pp: 1.0,
+            f_excitation: 10_000.0,
+            amplitude_error: 0.0,
+            phase_error: 0.0,
+            output_theta_e: None,
+            output_omega_m: None,
+            output_sector: None,
+            output_raw: None,
+            custom_size: None,
+        }
+    }
+}
+
+/// Sensor outputs over a time grid.
+#[derive(Clone, Debug, Default)]
+pub struct SensorSeries {
+    /// Measured electrical angle.
+    pub theta_e: Vec<[f64; 2]>,
+    /// Measured mechanical speed.
+    pub omega_m: Vec<[f64; 2]>,
+    /// Sector `1…6` of the measured angle.
+    pub sector: Vec<[f64; 2]>,
+    /// Raw channels.
+    pub raw: Vec<[f64; 4]>,
+}
+
+/// `1.0` for a high logic level, `0.0` for low.
+fn level(high: bool) -> f64 {
+    f64::from(u8::from(high))
+}
+
+impl PositionSensorNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "Position Sensor"
+    }
+
+    /// Input port descriptors: true electrical angle.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[("θ_e", PortType::Signal)]
+    }
+
+    /// Output port descriptors: measured angle, speed, sector and raw
+    /// channels.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("θ_e", PortType::Signal),
+            ("ω_m", PortType::Signal),
+            ("sector", PortType::Signal),
+            ("raw", PortType::Vector),
+        ]
+    }
+
+    /// Header colour (teal, shared by the measurement nodes).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x30, 0x80, 0x80)
+    }
+
+    /// Angle at the sensor at time `t`: the true angle `theta_at` one
+    /// latency earlier (held at `t0` before the start), plus the offset.
+    fn sensed(&self, t: f64, t0: f64, theta_at: &impl Fn(f64) -> f64) -> f64 {
+        theta_at((t - self.latency).max(t0)) + self.offset.to_radians()
+    }
+
+    /// Measured electrical angle and raw channels at time `t`.
+    fn sample(&self, t: f64, t0: f64, theta_at: &impl Fn(f64) -> f64) -> (f64, [f64; 3]) {
+        let n_p = self.n_p.max(f64::EPSILON);
+        match self.model {
+            SensorModel::Encoder => {
+                let theta_m = self.sensed(t, t0, theta_at) / n_p;
+                let lines = f64::from(self.lines.max(1));
+                let step = TAU / (4.0 * lines);
+                let count = (theta_m / step).floor();
+                let phase = lines * theta_m;
+                let raw = [
+                    level(phase.cos() >= 0.0),
+                    level(phase.sin() >= 0.0),
+                    level(theta_m.rem_euclid(TAU) < step),
+                ];
+                (count * step * n_p, raw)
+            }
+            SensorModel::Resolver => {
+                // Envelopes are sampled at the excitation peaks and held
+                let period = 1.0 / self.f_excitation.max(f64::EPSILON);
+                let t_s = t0 + ((t - t0) / period).floor() * period;
+                let pp = self.resolver_pp.max(f64::EPSILON);
+                let theta_r = pp * self.sensed(t_s, t0, theta_at) / n_p;
+                let sin = theta_r.sin();
+                let cos =
+                    (1.0 + self.amplitude_error) * (theta_r + self.phase_error.to_radians()).cos();
+                // Unwrap the demodulated angle next to the shaft angle
+                let error = (sin.atan2(cos) - theta_r + PI).rem_euclid(TAU) - PI;
+                ((theta_r + error) * n_p / pp, [sin, cos, sin.hypot(cos)])
+            }
+            SensorModel::Hall => {
+                let theta = self.sensed(t, t0, theta_at);
+                let raw = std::array::from_fn(|k| {
+                    level((theta - TAU * k as f64 / 3.0).rem_euclid(TAU) < PI)
+                });
+                (((theta / SECTOR).floor() + 0.5) * SECTOR, raw)
+            }
+        }
+    }
+
+    /// Evaluate the sensor on the time grid `ts`, with `theta_at` the true
+    /// electrical angle at any time.
+    pub fn compute(&self, ts: &[f64], theta_at: impl Fn(f64) -> f64) -> SensorSeries {
+        let Some(&t0) = ts.first() else {
+            return SensorSeries::default();
+        };
+        let n_p = self.n_p.max(f64::EPSILON);
+        let window = self.speed_window.max(f64::EPSILON);
+        let mut series = SensorSeries::default();
+        // Hall edge tracking: last sector index, last edge time, last speed
+        let mut hall: Option<(f64, Option<f64>, f64)> = None;
+        let mut previous: Option<(f64, f64)> = None;
+
+        for &t in ts {
+            let (theta, [r_0, r_1, r_2]) = self.sample(t, t0, &theta_at);
+            let omega = match self.model {
+                SensorModel::Encoder | SensorModel::Resolver => {
+                    let t_k = t0 + ((t - t0) / window).floor() * window;
+                    let t_j = (t_k - window).max(t0);
+                    if t_k > t_j {
+                        let (theta_k, _) = self.sample(t_k, t0, &theta_at);
+                        let (theta_j, _) = self.sample(t_j, t0, &theta_at);
+                        (theta_k - theta_j) / (n_p * (t_k - t_j))
+                    } else {
+                        0.0
+                    }
+                }
+                SensorModel::Hall => {
+                    let sensed = self.sensed(t, t0, &theta_at);
+                    let index = (sensed / SECTOR).floor();
+                    let (last_index, last_edge, speed) = hall.unwrap_or((index, None, 0.0));
+                    let (last_edge, speed) = match previous {
+                        Some((t_prev, sensed_prev)) if index != last_index => {
+                            // Edge time by linear interpolation to the sector boundary
+                            let boundary = index.max(last_index) * SECTOR;
+                            let span = sensed - sensed_prev;
+                            let fraction = if span == 0.0 {
+                                1.0
+                            } else {
+                                ((boundary - sensed_prev) / span).clamp(0.0, 1.0)
+                            };
+                            let edge = t_prev + fraction * (t - t_prev);
+                            let speed = last_edge.filter(|&e| edge > e).map_or(0.0, |e| {
+                                (index - last_index) * SECTOR / (n_p * (edge - e))
+                            });
+                            (Some(edge), speed)
+                        }
+                        _ => (last_edge, speed),
+                    };
+                    hall = Some((index, last_edge, speed));
+                    previous = Some((t, sensed));
+                    // Without a new edge the speed can only have fallen
+                    match last_edge {
+                        Some(edge) if t > edge && speed != 0.0 => {
+                            let bound = SECTOR / (n_p * (t - edge));
+                            speed.clamp(-bound, bound)
+                        }
+                        _ => speed,
+                    }
+                }
+            };
+            series.theta_e.push([t, theta]);
+            series.omega_m.push([t, omega]);
+            series
+                .sector
+                .push([t, (theta.rem_euclid(TAU) / SECTOR).floor() + 1.0]);
+            series.raw.push([t, r_0, r_1, r_2]);
+        }
+        series
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::TAU;
+
+    use super::{PositionSensorNode, SensorModel};
+
+    /// Time grid of `n` points spaced `dt` apart.
+    fn grid(n: usize, dt: f64) -> Vec<f64> {
+        (0..n).map(|k| k as f64 * dt).collect()
+    }
+
+    /// The encoder quantizes to `2π / (4·N)` mechanical and recovers a
+    /// constant speed exactly up to one count per window.
+    #[test]
+    fn encoder_quantizes_angle_and_measures_speed() {
+        let sensor = PositionSensorNode {
+            lines: 256,
+            ..PositionSensorNode::default()
+        };
+        let omega_m = 50.0;
+        let theta = |t: f64| sensor.n_p * omega_m * t;
+        let out = sensor.compute(&grid(200, 1e-4), theta);
+        let step = TAU / (4.0 * 256.0) * sensor.n_p;
+        for (&[t, measured], &[_, omega]) in out.theta_e.iter().zip(&out.omega_m) {
+            let error = theta(t) - measured;
+            assert!(
+                (0.0..step).contains(&error),
+                "quantized below the true angle"
+            );
+            if t >= 2.0 * sensor.speed_window {
+                let tolerance = TAU / (4.0 * 256.0) / sensor.speed_window;
+                assert!((omega - omega_m).abs() <= tolerance, "speed {omega}");
+            }
+        }
+    }
+
+    /// Latency and offset shift the measured angle; resolver gain and phase
+    /// errors produce a bounded angle error.
+    #[test]
+    fn resolver_errors_and_latency() {
+        let ideal = PositionSensorNode {
+            model: SensorModel::Resolver,
+            f_excitation: 1e9,
+            ..PositionSensorNode::default()
+        };
+        let theta = |t: f64| 100.0 * t;
+        let ts = grid(100, 1e-3);
+        for (&t, &[_, measured]) in ts.iter().zip(&ideal.compute(&ts, theta).theta_e) {
+            assert!(
+                (measured - theta(t)).abs() < 1e-6,
+                "ideal resolver is exact"
+            );
+        }
+
+        let shifted = PositionSensorNode {
+            latency: 1e-3,
+            offset: 10.0,
+            ..ideal.clone()
+        };
+        let out = shifted.compute(&ts, theta);
+        let &[t, measured] = out.theta_e.last().expect("samples");
+        let expected = theta(t - 1e-3) + 10_f64.to_radians();
+        assert!((measured - expected).abs() < 1e-6, "latency and offset");
+
+        let faulty = PositionSensorNode {
+            amplitude_error: 0.02,
+            phase_error: 1.0,
+            ..ideal
+        };
+        let worst = faulty
+            .compute(&ts, theta)
+            .theta_e
+            .iter()
+            .zip(&ts)
+            .map(|(&[_, m], &t)| (m - theta(t)).abs())
+            .fold(0.0, f64::max);
+        // Resolver error up to ε/2 + δ, times N_p / resolver pole pairs
+        let bound = (0.01 + 1_f64.to_radians()) * faulty.n_p;
+        assert!(
+            worst > 0.0 && worst < bound,
+            "angle error {worst} within {bound}"
+        );
+    }
+
+    /// Hall sensors step through six sectors per electrical turn and measure
+    /// speed from the edge spacing.
+    #[test]
+    fn hall_sectors_and_speed() {
+        let sensor = PositionSensorNode {
+            model: SensorModel::Hall,
+            ..PositionSensorNode::default()
+        };
+        let omega_e = 200.0;
+        let theta = |t: f64| omega_e * t;
+        let out = sensor.compute(&grid(2000, 5e-5), theta);
+        let mut codes = Vec::new();
+        for (&[_, sector], &[_, a, b, c]) in out.sector.iter().zip(&out.raw) {
+            assert!((1.0..=6.0).contains(&sector), "sector {sector}");
+            let code = (a, b, c);
+            if !codes.contains(&code) {
+                codes.push(code);
+            }
+        }
+        assert_eq!(codes.len(), 6, "six distinct Hall codes");
+        let &[_, omega] = out.omega_m.last().expect("samples");
+        let expected = omega_e / sensor.n_p;
+        assert!((omega - expected).abs() < 1e-3 * expected, "speed {omega}");
+    }
+}
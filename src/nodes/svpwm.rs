@@ -0,0 +1,335 @@
+//! Space-vector PWM modulator node — converts a voltage reference into
+//! three-phase duty cycles and averaged phase voltages.
+//!
+//! The reference is given either in the stationary αβ frame or in the rotating
+//! dq frame (rotated by `θ_e`). Min-max zero-sequence injection centres the
+//! phase references inside the DC-link range, which is equivalent to
+//! conventional SVPWM with symmetric zero vectors.
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// `√3`, used by the Clarke transform and the linear modulation limit.
+const SQRT_3: f64 = 1.732_050_807_568_877_2;
+
+/// Frame in which the voltage reference inputs are expressed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ReferenceFrame {
+    /// Stationary frame: inputs are `v_α`, `v_β`; `θ_e` is ignored.
+    Stationary,
+    /// Rotor frame: inputs are `v_d`, `v_q`, rotated by `θ_e` into αβ.
+    #[default]
+    Rotating,
+}
+
+impl ReferenceFrame {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 2] = [Self::Rotating, Self::Stationary];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Stationary => "αβ",
+            Self::Rotating => "dq",
+        }
+    }
+}
+
+/// Strategy applied when the reference exceeds the linear modulation range.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum OvermodulationMode {
+    /// Limit the reference magnitude to the inscribed circle `V_dc/√3`.
+    #[default]
+    Linear,
+    /// Scale the reference onto the hexagon boundary, preserving its angle.
+    MinimumPhaseError,
+    /// Clamp each duty cycle to `[0, 1]`, preserving as much magnitude as possible.
+    MinimumMagnitudeError,
+}
+
+impl OvermodulationMode {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 3] = [
+        Self::Linear,
+        Self::MinimumPhaseError,
+        Self::MinimumMagnitudeError,
+    ];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Linear => "Linear",
+            Self::MinimumPhaseError => "Min. phase error",
+            Self::MinimumMagnitudeError => "Min. magnitude error",
+        }
+    }
+}
+
+/// Space-vector PWM modulator.
+///
+/// Inputs: reference components (dq or αβ), electrical angle `θ_e` and DC-link
+/// voltage `V_dc`.\
+/// Outputs: duty cycles `d_abc` and averaged phase-to-neutral voltages `v_abc`
+/// (both [`PortType::Vector`]).
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SvpwmNode {
+    /// Frame of the voltage reference inputs.
+    pub frame: ReferenceFrame,
+    /// Overmodulation strategy.
+    pub overmodulation: OvermodulationMode,
+    /// Switching frequency (Hz), used to convert the minimum pulse to a duty.
+    pub f_sw: f64,
+    /// Minimum pulse width (s); shorter pulses are dropped or saturated.
+    pub t_min: f64,
+    /// Duty-cycle time-series produced after simulation.
+    #[serde(skip)]
+    pub output_duty: Option<PortValue>,
+    /// Averaged phase voltage time-series produced after simulation.
+    #[serde(skip)]
+    pub output_v_abc: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for SvpwmNode {
+    fn default() -> Self {
+        Self {
+            frame: ReferenceFrame::default(),
+            overmodulation: OvermodulationMode::default(),
+            f_sw: 10_000.0,
+            t_min: 1e-6,
+            output_duty: None,
+            output_v_abc: None,
+            custom_size: None,
+        }
+    }
+}
+
+impl SvpwmNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "SVPWM"
+    }
+
+    /// Input port descriptors; the reference labels follow the selected frame.
+    pub fn input_ports(&self) -> Vec<(&'static str, PortType)> {
+        let (u1, u2) = match self.frame {
+            ReferenceFrame::Stationary => ("v_α", "v_β"),
+            ReferenceFrame::Rotating => ("v_d", "v_q"),
+        };
+        vec![
+            (u1, PortType::Signal),
+            (u2, PortType::Signal),
+            ("θ_e", PortType::Signal),
+            ("V_dc", PortType::Signal),
+        ]
+    }
+
+    /// Output port descriptors: duty cycles and averaged phase voltages.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[("d_abc", PortType::Vector), ("v_abc", PortType::Vector)]
+    }
+
+    /// Header colour (teal, distinguishing power-electronics nodes).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x20, 0x90, 0x90)
+    }
+
+    /// Modulate a single reference sample.
+    ///
+    /// # Arguments
+    /// * `u1`, `u2` — reference components (`v_d`/`v_q` or `v_α`/`v_β`)
+    /// * `theta_e`  — electrical angle (rad), used only in the rotating frame
+    /// * `v_dc`     — DC-link voltage (V)
+    ///
+    /// # Returns
+    /// `(duty, v_abc)`: per-phase duty cycles in `[0, 1]` and the averaged
+    /// phase-to-neutral voltages they produce.
+    pub fn modulate(&self, u1: f64, u2: f64, theta_e: f64, v_dc: f64) -> ([f64; 3], [f64; 3]) {
+        if v_dc <= 0.0 {
+            return ([0.5; 3], [0.0; 3]);
+        }
+
+        let (mut v_alpha, mut v_beta) = match self.frame {
+            ReferenceFrame::Stationary => (u1, u2),
+            ReferenceFrame::Rotating => {
+                let (sin, cos) = theta_e.sin_cos();
+                (u1 * cos - u2 * sin, u1 * sin + u2 * cos)
+            }
+        };
+
+        // Magnitude limit for the selected overmodulation strategy.
+        let magnitude = v_alpha.hypot(v_beta);
+        let limit = match self.overmodulation {
+            OvermodulationMode::Linear => Some(v_dc / SQRT_3),
+            OvermodulationMode::MinimumPhaseError => {
+                // Distance from the origin to the hexagon edge along the reference angle.
+                let sector_angle = std::f64::consts::FRAC_PI_3;
+                let phi = v_beta.atan2(v_alpha).rem_euclid(sector_angle);
+                Some(v_dc / SQRT_3 / (phi - sector_angle / 2.0).cos())
+            }
+            OvermodulationMode::MinimumMagnitudeError => None,
+        };
+        if let Some(limit) = limit
+            && magnitude > limit
+        {
+            let scale = limit / magnitude;
+            v_alpha *= scale;
+            v_beta *= scale;
+        }
+
+        // Inverse Clarke (amplitude-invariant) followed by min-max injection.
+        let v_ref = [
+            v_alpha,
+            -0.5 * v_alpha + 0.5 * SQRT_3 * v_beta,
+            -0.5 * v_alpha - 0.5 * SQRT_3 * v_beta,
+        ];
+        let v_max = v_ref.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let v_min = v_ref.iter().copied().fold(f64::INFINITY, f64::min);
+        let v_zero = -0.5 * (v_max + v_min);
+
+        let d_min = (self.t_min * self.f_sw).clamp(0.0, 0.5);
+        let duty = v_ref.map(|v| {
+            let d = (0.5 + (v + v_zero) / v_dc).clamp(0.0, 1.0);
+            if d < d_min {
+                0.0
+            } else if d > 1.0 - d_min {
+                1.0
+            } else {
+                d
+            }
+        });
+
+        let d_mean = (duty[0] + duty[1] + duty[2]) / 3.0;
+        let v_abc = duty.map(|d| v_dc * (d - d_mean));
+        (duty, v_abc)
+    }
+
+    /// Evaluate the modulator pointwise over the time series.
+    ///
+    /// All input slices share the same time vector and are zipped by index.
+    ///
+    /// # Returns
+    /// A tuple `(duty, v_abc)` of `[t, a, b, c]` entries, one per time step.
+    pub fn compute(
+        &self,
+        u1: &[[f64; 2]],
+        u2: &[[f64; 2]],
+        theta_e: &[[f64; 2]],
+        v_dc: &[[f64; 2]],
+    ) -> (Vec<[f64; 4]>, Vec<[f64; 4]>) {
+        u1.iter()
+            .zip(u2.iter())
+            .zip(theta_e.iter())
+            .zip(v_dc.iter())
+            .map(|((([t, a], [_, b]), [_, th]), [_, vdc])| {
+                let (duty, v_abc) = self.modulate(*a, *b, *th, *vdc);
+                (
+                    [*t, duty[0], duty[1], duty[2]],
+                    [*t, v_abc[0], v_abc[1], v_abc[2]],
+                )
+            })
+            .unzip()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OvermodulationMode, ReferenceFrame, SvpwmNode};
+
+    /// In the linear range the averaged voltages reproduce the reference.
+    #[test]
+    fn linear_range_reproduces_reference() {
+        let node = SvpwmNode {
+            frame: ReferenceFrame::Stationary,
+            t_min: 0.0,
+            ..SvpwmNode::default()
+        };
+        let (duty, v_abc) = node.modulate(100.0, -50.0, 0.0, 400.0);
+
+        let eps = 1e-9;
+        let [v_a, v_b, v_c] = v_abc;
+        assert!((v_a - 100.0).abs() < eps, "v_a = {v_a}");
+        assert!(
+            (v_a + v_b + v_c).abs() < eps,
+            "phase voltages must sum to zero"
+        );
+        let v_beta = (v_b - v_c) / 3.0_f64.sqrt();
+        assert!((v_beta + 50.0).abs() < eps, "v_β = {v_beta}");
+        assert!(
+            duty.iter().all(|d| (0.0..=1.0).contains(d)),
+            "duty out of range: {duty:?}"
+        );
+    }
+
+    /// A dq reference rotated by `θ_e` matches the equivalent αβ reference.
+    #[test]
+    fn rotating_frame_matches_stationary() {
+        let dq = SvpwmNode::default();
+        let ab = SvpwmNode {
+            frame: ReferenceFrame::Stationary,
+            ..SvpwmNode::default()
+        };
+        let theta: f64 = 0.7;
+        let (v_d, v_q) = (20.0, 80.0);
+        let v_alpha = v_d * theta.cos() - v_q * theta.sin();
+        let v_beta = v_d * theta.sin() + v_q * theta.cos();
+
+        let (duty_dq, _) = dq.modulate(v_d, v_q, theta, 300.0);
+        let (duty_ab, _) = ab.modulate(v_alpha, v_beta, 123.0, 300.0);
+        for (a, b) in duty_dq.iter().zip(duty_ab.iter()) {
+            assert!((a - b).abs() < 1e-12, "duty mismatch: {a} vs {b}");
+        }
+    }
+
+    /// Overmodulation modes bound the output and min-pulse clipping saturates.
+    #[test]
+    fn overmodulation_and_min_pulse() {
+        let v_dc = 100.0;
+        let linear = SvpwmNode {
+            frame: ReferenceFrame::Stationary,
+            t_min: 0.0,
+            ..SvpwmNode::default()
+        };
+        let (_, v_abc) = linear.modulate(1000.0, 0.0, 0.0, v_dc);
+        let eps = 1e-9;
+        assert!(
+            (v_abc[0] - v_dc / 3.0_f64.sqrt()).abs() < eps,
+            "linear mode should limit to V_dc/√3, got {}",
+            v_abc[0]
+        );
+
+        // Along a hexagon vertex (α axis) the hexagon limit is 2/3 V_dc.
+        let hexagon = SvpwmNode {
+            overmodulation: OvermodulationMode::MinimumPhaseError,
+            ..linear.clone()
+        };
+        let (_, v_abc) = hexagon.modulate(1000.0, 0.0, 0.0, v_dc);
+        assert!(
+            (v_abc[0] - 2.0 / 3.0 * v_dc).abs() < eps,
+            "hexagon limit along α should be 2/3·V_dc, got {}",
+            v_abc[0]
+        );
+
+        // A 2 µs minimum pulse at 10 kHz gives d_min = 0.02.
+        let clipped = SvpwmNode {
+            t_min: 2e-6,
+            ..linear
+        };
+        // Near full modulation at 30° the extreme duties are 0.0025 and 0.9975.
+        let m = 0.995 * v_dc / 3.0_f64.sqrt();
+        let angle = std::f64::consts::FRAC_PI_6;
+        let (duty, _) = clipped.modulate(m * angle.cos(), m * angle.sin(), 0.0, v_dc);
+        assert!(
+            duty.contains(&0.0),
+            "narrow low pulse should be dropped: {duty:?}"
+        );
+        assert!(
+            duty.contains(&1.0),
+            "narrow high pulse should saturate: {duty:?}"
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! Lumped-parameter thermal network node — heat capacities joined by thermal
+//! resistances, driven by the machine losses.
+//!
+//! Each body `k` is an ODE state with
+//!
+//! ```text
+//! C_k · dT_k/dt = s_cu,k·P_cu + s_fe,k·P_fe + Σ_links (T_other − T_k) / R
+//! ```
+//!
+//! where `s_cu,k` and `s_fe,k` are the shares of the copper and iron loss
+//! deposited in the body, and a link either joins two bodies or a body and
+//! the ambient. The winding body can set the stator resistance and the
+//! magnet body the PM flux of the PMSM Electrical node.
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// One heat capacity of the network.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ThermalBody {
+    /// Display name.
+    pub name: String,
+    /// Heat capacity `C` (J/K).
+    pub c: f64,
+    /// Initial temperature (°C).
+    pub t_0: f64,
+    /// Share of the copper loss heating this body.
+    pub cu_share: f64,
+    /// Share of the iron loss heating this body.
+    pub fe_share: f64,
+}
+
+impl Default for ThermalBody {
+    fn default() -> Self {
+        Self {
+            name: "Body".to_owned(),
+            c: 500.0,
+            t_0: 25.0,
+            cu_share: 0.0,
+            fe_share: 0.0,
+        }
+    }
+}
+
+/// Thermal resistance between a body and another body or the ambient.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ThermalLink {
+    /// Index of the first body.
+    pub from: usize,
+    /// Index of the second body; `None` is the ambient.
+    pub to: Option<usize>,
+    /// Thermal resistance `R` (K/W).
+    pub r: f64,
+}
+
+/// Thermal RC network with optional temperature feedback into the PMSM.
+///
+/// Inputs: copper loss `P_cu` and iron loss `P_fe` (Signal). Wired to the
+/// Electrical node's loss outputs, the losses are evaluated from the machine
+/// state during the solve.\
+/// Outputs: winding temperature `T_w`, magnet temperature `T_pm` (Signal),
+/// and the temperatures of the first three bodies `T` (Vector, padded with
+/// the ambient).
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ThermalNode {
+    /// Heat capacities, one ODE state each.
+    pub bodies: Vec<ThermalBody>,
+    /// Thermal resistances.
+    pub links: Vec<ThermalLink>,
+    /// Ambient (coolant) temperature (°C).
+    pub t_ambient: f64,
+    /// Body holding the winding temperature.
+    pub winding: usize,
+    /// Body holding the magnet temperature.
+    pub magnet: usize,
+    /// Scale `R_s` with the winding temperature.
+    pub feedback_r_s: bool,
+    /// Scale `λ_m` with the magnet temperature.
+    pub feedback_lambda_m: bool,
+    /// Winding temperature time-series produced after simulation.
+    #[serde(skip)]
+    pub output_t_w: Option<PortValue>,
+    /// Magnet temperature time-series produced after simulation.
+    #[serde(skip)]
+    pub output_t_pm: Option<PortValue>,
+    /// Temperatures of the first three bodies produced after simulation.
+    #[serde(skip)]
+    pub output_t: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for ThermalNode {
+    /// Winding, stator iron and magnet of a small servo motor; the winding
+    /// and magnet reach the housing through the stator iron.
+    fn default() -> Self {
+        Self {
+            bodies: vec![
+                ThermalBody {
+                    name: "Winding".to_owned(),
+                    c: 400.0,
+                    cu_share: 1.0,
+                    ..ThermalBody::default()
+                },
+                ThermalBody {
+                    name: "Stator iron".to_owned(),
+                    c: 1500.0,
+                    fe_share: 1.0,
+                    ..ThermalBody::default()
+                },
+                ThermalBody {
+                    name: "Magnet".to_owned(),
+                    c: 300.0,
+                    ..ThermalBody::default()
+                },
+            ],
+            links: vec![
+                ThermalLink {
+                    from: 0,
+                    to: Some(1),
+                    r: 0.3,
+                },
+                ThermalLink {
+                    from: 1,
+                    to: None,
+                    r: 0.5,
+                },
+                ThermalLink {
+                    from: 2,
+                    to: Some(1),
+                    r: 2.0,
+                },
+            ],
+            t_ambient: 25.0,
+            winding: 0,
+            magnet: 2,
+            feedback_r_s: true,
+            feedback_lambda_m: true,
+            output_t_w: None,
+            output_t_pm: None,
+            output_t: None,
+            custom_size: None,
+        }
+    }
+}
+
+impl ThermalNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "Thermal Network"
+    }
+
+    /// Input port descriptors: copper and iron loss.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[("P_cu", PortType::Signal), ("P_fe", PortType::Signal)]
+    }
+
+    /// Output port descriptors: winding and magnet temperature, and the first
+    /// three body temperatures.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("T_w", PortType::Signal),
+            ("T_pm", PortType::Signal),
+            ("T", PortType::Vector),
+        ]
+    }
+
+    /// Header colour.
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0xB0, 0x60, 0x30)
+    }
+
+    /// Check that every capacity and resistance is positive and every link
+    /// and role refers to an existing body.
+    ///
+    /// # Errors
+    ///
+    /// A description of the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        let n = self.bodies.len();
+        if n == 0 {
+            return Err("thermal network has no bodies".to_owned());
+        }
+        if let Some(body) = self.bodies.iter().find(|b| b.c.is_nan() || b.c <= 0.0) {
+            return Err(format!(
+                "thermal body '{}' needs a positive capacity",
+                body.name
+            ));
+        }
+        for link in &self.links {
+            if link.from >= n || link.to.is_some_and(|to| to >= n || to == link.from) {
+                return Err("thermal link refers to a missing body".to_owned());
+            }
+            if link.r.is_nan() || link.r <= 0.0 {
+                return Err("thermal links need a positive resistance".to_owned());
+            }
+        }
+        if self.winding >= n || self.magnet >= n {
+            return Err("winding or magnet body is missing".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Remove body `k` together with its links, renumbering the links and
+    /// roles that refer to later bodies.
+    pub fn remove_body(&mut self, k: usize) {
+        if k >= self.bodies.len() {
+            return;
+        }
+        self.bodies.remove(k);
+        self.links
+            .retain(|link| link.from != k && link.to != Some(k));
+        let shift = |i: usize| if i > k { i - 1 } else { i };
+        for link in &mut self.links {
+            link.from = shift(link.from);
+            link.to = link.to.map(shift);
+        }
+        self.winding = shift(self.winding);
+        self.magnet = shift(self.magnet);
+    }
+
+    /// Temperature derivatives `dT/dt` for body temperatures `temps` and
+    /// losses `[P_cu, P_fe]`.
+    pub fn derivatives(&self, temps: &[f64], losses: [f64; 2], d_temps: &mut [f64]) {
+        let [p_cu, p_fe] = losses;
+        for (d, body) in d_temps.iter_mut().zip(&self.bodies) {
+            *d = body.cu_share * p_cu + body.fe_share * p_fe;
+        }
+        self.add_conduction(temps, Some(self.t_ambient), d_temps);
+        for (d, body) in d_temps.iter_mut().zip(&self.bodies) {
+            *d /= body.c;
+        }
+    }
+
+    /// Directional derivative of `dT/dt` along temperatures `d_temps` and
+    /// losses `d_losses`.
+    pub fn jvp(&self, d_temps: &[f64], d_losses: [f64; 2], out: &mut [f64]) {
+        let [dp_cu, dp_fe] = d_losses;
+        for (o, body) in out.iter_mut().zip(&self.bodies) {
+            *o = body.cu_share * dp_cu + body.fe_share * dp_fe;
+        }
+        self.add_conduction(d_temps, None, out);
+        for (o, body) in out.iter_mut().zip(&self.bodies) {
+            *o /= body.c;
+        }
+    }
+
+    /// Add the heat flows through the links for temperatures `temps` to
+    /// `flows`; `ambient` is `None` when differentiating.
+    fn add_conduction(&self, temps: &[f64], ambient: Option<f64>, flows: &mut [f64]) {
+        for link in &self.links {
+            let Some(&t_from) = temps.get(link.from) else {
+                continue;
+            };
+            let t_to = match link.to {
+                Some(to) => temps.get(to).copied().unwrap_or(t_from),
+                None => ambient.unwrap_or(0.0),
+            };
+            let q = (t_to - t_from) / link.r;
+            if let Some(f) = flows.get_mut(link.from) {
+                *f += q;
+            }
+            if let Some(f) = link.to.and_then(|to| flows.get_mut(to)) {
+                *f -= q;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ThermalNode;
+
+    /// With constant losses the default network settles at the temperatures
+    /// of the equivalent resistor network.
+    #[test]
+    fn steady_state_matches_resistor_network() {
+        let network = ThermalNode::default();
+        let (p_cu, p_fe) = (60.0, 20.0);
+        // No heat enters the magnet, so it sits at the iron temperature
+        let t_fe = network.t_ambient + 0.5 * (p_cu + p_fe);
+        let expected = [t_fe + 0.3 * p_cu, t_fe, t_fe];
+
+        // Forward Euler well inside the fastest time constant (R·C ≈ 120 s)
+        let mut temps = [network.t_ambient; 3];
+        let mut d = [0.0; 3];
+        for _ in 0..20_000 {
+            network.derivatives(&temps, [p_cu, p_fe], &mut d);
+            for (t, d) in temps.iter_mut().zip(d) {
+                *t += 5.0 * d;
+            }
+        }
+        network.derivatives(&temps, [p_cu, p_fe], &mut d);
+        for ((t, e), d) in temps.iter().zip(expected).zip(d) {
+            assert!((t - e).abs() < 1e-3, "temperature {t} vs {e}");
+            assert!(d.abs() < 1e-6, "settled");
+        }
+    }
+
+    /// Invalid links and capacities are reported.
+    #[test]
+    fn validation_rejects_bad_networks() {
+        assert!(ThermalNode::default().validate().is_ok());
+        let mut bad = ThermalNode::default();
+        if let Some(link) = bad.links.first_mut() {
+            link.to = Some(7);
+        }
+        assert!(bad.validate().is_err(), "dangling link");
+        let mut bad = ThermalNode::default();
+        if let Some(body) = bad.bodies.first_mut() {
+            body.c = 0.0;
+        }
+        assert!(bad.validate().is_err(), "zero capacity");
+    }
+}
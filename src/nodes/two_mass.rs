@@ -0,0 +1,197 @@
+//! Two-mass drivetrain node — motor and load inertias joined by a compliant
+//! shaft behind a gearbox with backlash.
+//!
+//! With gear ratio `N` (motor turns per load turn), shaft twist `φ` measured
+//! at the load side and a dead zone of total width `α`:
+//!
+//! ```text
+//! dω_m/dt = (T_e − T_sh/N − B_m·ω_m) / J_m,   dθ_e/dt = N_p·ω_m
+//! dω_L/dt = (T_sh − T_L − B_L·ω_L) / J_L
+//! dφ/dt   = ω_m/N − ω_L
+//! T_sh    = k·(φ − sign(φ)·α/2) + c·dφ/dt   for |φ| > α/2, else 0
+//! ```
+
+use egui::Color32;
+
+use crate::port::{PortType, PortValue};
+
+/// Two-inertia drivetrain: motor rotor, gearbox with backlash, compliant
+/// shaft and load.
+///
+/// Inputs: electromagnetic torque `T_e` and load torque `T_L` (Signal), as on
+/// the Mechanical node.\
+/// Outputs: motor speed `ω_m`, electrical angle `θ_e`, load speed `ω_L` and
+/// shaft torque `T_sh` (Signal). The first two match the Mechanical node's
+/// outputs, so this node can replace it in a graph.
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct TwoMassNode {
+    /// Motor-side moment of inertia (kg·m²).
+    pub j_m: f64,
+    /// Motor-side viscous friction (N·m·s/rad).
+    pub b_m: f64,
+    /// Load-side moment of inertia (kg·m²).
+    pub j_l: f64,
+    /// Load-side viscous friction (N·m·s/rad).
+    pub b_l: f64,
+    /// Shaft torsional stiffness (N·m/rad).
+    pub k_s: f64,
+    /// Shaft torsional damping (N·m·s/rad).
+    pub c_s: f64,
+    /// Gear ratio: motor turns per load turn.
+    pub ratio: f64,
+    /// Total backlash dead zone at the load side (rad).
+    pub backlash: f64,
+    /// Number of pole pairs of the motor.
+    pub n_p: f64,
+    /// Initial motor speed (rad/s); the load starts at `ω_m₀ / N`.
+    pub omega_m_0: f64,
+    /// Initial shaft twist (rad); zero has the teeth centred in the backlash
+    /// gap.
+    pub twist_0: f64,
+    /// Initial electrical angle (rad).
+    pub theta_e_0: f64,
+    /// Motor speed time-series produced after simulation.
+    #[serde(skip)]
+    pub output_omega_m: Option<PortValue>,
+    /// Electrical angle time-series produced after simulation.
+    #[serde(skip)]
+    pub output_theta_e: Option<PortValue>,
+    /// Load speed time-series produced after simulation.
+    #[serde(skip)]
+    pub output_omega_l: Option<PortValue>,
+    /// Shaft torque time-series produced after simulation.
+    #[serde(skip)]
+    pub output_t_sh: Option<PortValue>,
+    /// User-defined node size override (width, height).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom_size: Option<[f32; 2]>,
+}
+
+impl Default for TwoMassNode {
+    fn default() -> Self {
+        Self {
+            j_m: 0.0008,
+            b_m: 0.001,
+            j_l: 0.002,
+            b_l: 0.001,
+            k_s: 50.0,
+            c_s: 0.05,
+            ratio: 1.0,
+            backlash: 0.0,
+            n_p: 4.0,
+            omega_m_0: 0.0,
+            twist_0: 0.0,
+            theta_e_0: 0.0,
+            output_omega_m: None,
+            output_theta_e: None,
+            output_omega_l: None,
+            output_t_sh: None,
+            custom_size: None,
+        }
+    }
+}
+
+/// Shaft torque and its partial derivatives.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShaftTorque {
+    /// Shaft torque at the load side (N·m).
+    pub torque: f64,
+    /// `∂T_sh/∂φ` (N·m/rad).
+    pub d_twist: f64,
+    /// `∂T_sh/∂(dφ/dt)` (N·m·s/rad).
+    pub d_rate: f64,
+}
+
+impl TwoMassNode {
+    /// Display title shown in the node header.
+    pub fn title() -> &'static str {
+        "Two-Mass Drivetrain"
+    }
+
+    /// Input port descriptors: electromagnetic and load torque.
+    pub fn input_ports() -> &'static [(&'static str, PortType)] {
+        &[("T_e", PortType::Signal), ("T_L", PortType::Signal)]
+    }
+
+    /// Output port descriptors: motor speed, electrical angle, load speed and
+    /// shaft torque.
+    pub fn output_ports() -> &'static [(&'static str, PortType)] {
+        &[
+            ("ω_m", PortType::Signal),
+            ("θ_e", PortType::Signal),
+            ("ω_L", PortType::Signal),
+            ("T_sh", PortType::Signal),
+        ]
+    }
+
+    /// Header colour (same family as the Mechanical node).
+    pub fn header_color() -> Color32 {
+        Color32::from_rgb(0x40, 0x60, 0xB0)
+    }
+
+    /// Twist rate `dφ/dt = ω_m/N − ω_L`.
+    pub fn twist_rate(&self, omega_m: f64, omega_l: f64) -> f64 {
+        omega_m / self.ratio - omega_l
+    }
+
+    /// Twist at which the shaft at rest carries `torque`: the inverse of
+    /// [`Self::shaft_torque`] at zero rate, with the teeth centred in the gap
+    /// for zero torque.
+    pub fn steady_twist(&self, torque: f64) -> f64 {
+        if torque == 0.0 || self.k_s <= 0.0 {
+            return 0.0;
+        }
+        torque / self.k_s + (0.5 * self.backlash.max(0.0)).copysign(torque)
+    }
+
+    /// Shaft torque for twist `twist` and twist rate `rate`; zero while the
+    /// gear teeth are inside the backlash gap.
+    pub fn shaft_torque(&self, twist: f64, rate: f64) -> ShaftTorque {
+        let half = 0.5 * self.backlash.max(0.0);
+        if twist.abs() <= half {
+            return ShaftTorque {
+                torque: 0.0,
+                d_twist: 0.0,
+                d_rate: 0.0,
+            };
+        }
+        ShaftTorque {
+            torque: self.k_s * (twist - half.copysign(twist)) + self.c_s * rate,
+            d_twist: self.k_s,
+            d_rate: self.c_s,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TwoMassNode;
+
+    /// The shaft transmits nothing inside the backlash gap and acts as a
+    /// spring-damper offset by half the gap outside it.
+    #[test]
+    fn backlash_opens_a_dead_zone() {
+        let shaft = TwoMassNode {
+            backlash: 0.02,
+            ..TwoMassNode::default()
+        };
+        let inside = shaft.shaft_torque(0.009, 3.0);
+        assert!(inside.torque == 0.0 && inside.d_twist == 0.0, "no contact");
+
+        for sign in [1.0, -1.0] {
+            let contact = shaft.shaft_torque(sign * 0.03, 0.0);
+            let expected = sign * shaft.k_s * 0.02;
+            assert!(
+                (contact.torque - expected).abs() < 1e-12,
+                "spring offset by the half gap: {} vs {expected}",
+                contact.torque
+            );
+        }
+        let damped = shaft.shaft_torque(0.03, 2.0);
+        assert!(
+            (damped.torque - (shaft.k_s * 0.02 + shaft.c_s * 2.0)).abs() < 1e-12,
+            "damping acts in contact"
+        );
+    }
+}
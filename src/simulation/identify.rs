@@ -0,0 +1,828 @@
+//! Parameter identification: machine parameters fitted to measured traces.
+//!
+//! Measured `i_d`, `i_q` and `ω_m` traces, imported as CSV, are compared with
+//! the simulated outputs of the Electrical and Mechanical nodes at the
+//! measured times, each channel scaled by its RMS so that currents and speed
+//! weigh alike. The chosen parameters are fitted by Levenberg–Marquardt on
+//! their logarithms — they are all positive — with a forward-difference
+//! Jacobian whose columns are solved in parallel like the runs of a sweep.
+//! The simulation runs from the configured start to the last sample and is
+//! sampled at least as finely as the measurement.
+//!
+//! At the optimum the covariance of the log-parameters is estimated as
+//! `s²·(JᵀJ)⁻¹` with `s² = ‖r‖² / (m − n)` for `m` residuals and `n`
+//! parameters, which gives each parameter a standard error and a 95 %
+//! confidence interval.
+
+use egui_snarl::{NodeId, Snarl};
+use nalgebra::{DMatrix, DVector};
+
+use super::solver::{interpolate_signal, run_simulation};
+use super::sweep::{ParamValue, get_param, map_parallel, set_param};
+use super::{SimConfig, SimError};
+use crate::nodes::SimNode;
+use crate::port::PortValue;
+
+/// Relative step of the forward differences (in the log-parameters).
+const JACOBIAN_STEP: f64 = 1e-3;
+
+/// Relative cost reduction below which the fit has converged.
+const COST_TOLERANCE: f64 = 1e-9;
+
+/// Largest damping tried before the fit gives up on a step.
+const MAX_DAMPING: f64 = 1e12;
+
+/// Two-sided 95 % quantile of the normal distribution.
+const Z_95: f64 = 1.96;
+
+/// A machine parameter that can be fitted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum FitParam {
+    /// Stator resistance `r_s` of the Electrical node.
+    Rs,
+    /// d-axis inductance `l_d` of the Electrical (and Torque) node.
+    Ld,
+    /// q-axis inductance `l_q` of the Electrical (and Torque) node.
+    Lq,
+    /// PM flux linkage `lambda_m` of the Electrical (and Torque) node.
+    LambdaM,
+    /// Rotor inertia of the Mechanical or two-mass node.
+    J,
+    /// Viscous friction of the Mechanical or two-mass node.
+    B,
+}
+
+impl FitParam {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 6] = [
+        Self::Rs,
+        Self::Ld,
+        Self::Lq,
+        Self::LambdaM,
+        Self::J,
+        Self::B,
+    ];
+
+    /// Short label for checkboxes and tables.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Rs => "R_s (Ω)",
+            Self::Ld => "L_d (H)",
+            Self::Lq => "L_q (H)",
+            Self::LambdaM => "λ_m (Wb)",
+            Self::J => "J (kg·m²)",
+            Self::B => "B (N·m·s/rad)",
+        }
+    }
+}
+
+/// A measured machine output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Channel {
+    /// d-axis current of the Electrical node.
+    Id,
+    /// q-axis current of the Electrical node.
+    Iq,
+    /// Rotor speed of the Mechanical node.
+    OmegaM,
+}
+
+impl Channel {
+    /// Short label for tables.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Id => "i_d (A)",
+            Self::Iq => "i_q (A)",
+            Self::OmegaM => "ω_m (rad/s)",
+        }
+    }
+
+    /// Channel named by a CSV header cell.
+    fn from_header(name: &str) -> Option<Self> {
+        match name {
+            "i_d" | "id" => Some(Self::Id),
+            "i_q" | "iq" => Some(Self::Iq),
+            "omega_m" | "ω_m" | "w_m" | "wm" => Some(Self::OmegaM),
+            _ => None,
+        }
+    }
+}
+
+/// Measured traces on common sample times.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Measurement {
+    /// Strictly increasing sample times (s).
+    pub times: Vec<f64>,
+    /// Measured values of each channel, one per sample time.
+    pub channels: Vec<(Channel, Vec<f64>)>,
+}
+
+impl Measurement {
+    /// Parse traces from CSV text whose header names the columns: `t` and
+    /// any of `i_d`, `i_q`, `omega_m` (or `ω_m`).
+    ///
+    /// Commas, semicolons, tabs or spaces separate columns; blank lines and
+    /// `#` comments are skipped.
+    ///
+    /// # Errors
+    ///
+    /// [`SimError::InvalidMeasurement`] for a missing or unknown column, a
+    /// malformed row, fewer than two samples or times that do not increase.
+    pub fn from_csv(text: &str) -> Result<Self, SimError> {
+        let invalid = |msg: String| SimError::InvalidMeasurement(msg);
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(n, line)| (n + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+        let cells = |line: &'_ str| -> Vec<String> {
+            line.split([',', ';', '\t', ' '])
+                .map(str::trim)
+                .filter(|cell| !cell.is_empty())
+                .map(str::to_owned)
+                .collect()
+        };
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| invalid("the measurement is empty".to_owned()))?;
+        let header = cells(header);
+        let mut time_column = None;
+        let mut channels: Vec<(usize, Channel)> = Vec::new();
+        for (k, name) in header.iter().enumerate() {
+            match name.as_str() {
+                "t" | "time" => time_column = Some(k),
+                name => {
+                    let channel = Channel::from_header(name).ok_or_else(|| {
+                        invalid(format!(
+                            "unknown column '{name}': expected t, i_d, i_q or omega_m"
+                        ))
+                    })?;
+                    channels.push((k, channel));
+                }
+            }
+        }
+        let time_column = time_column.ok_or_else(|| invalid("no time column 't'".to_owned()))?;
+        if channels.is_empty() {
+            return Err(invalid(
+                "no measured column: add i_d, i_q or omega_m".to_owned(),
+            ));
+        }
+
+        let mut measurement = Self {
+            times: Vec::new(),
+            channels: channels.iter().map(|&(_, c)| (c, Vec::new())).collect(),
+        };
+        for (n, line) in lines {
+            let values = cells(line)
+                .iter()
+                .map(|cell| cell.parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .map_err(|err| invalid(format!("line {n}: {err}")))?;
+            let column = |k: usize| {
+                values.get(k).copied().ok_or_else(|| {
+                    invalid(format!(
+                        "line {n}: expected {} columns, found {}",
+                        header.len(),
+                        values.len()
+                    ))
+                })
+            };
+            let t = column(time_column)?;
+            if measurement.times.last().is_some_and(|&last| t <= last) {
+                return Err(invalid(format!("line {n}: times must increase")));
+            }
+            measurement.times.push(t);
+            for (&(k, _), (_, trace)) in channels.iter().zip(&mut measurement.channels) {
+                trace.push(column(k)?);
+            }
+        }
+        if measurement.times.len() < 2 {
+            return Err(invalid("the measurement needs two samples".to_owned()));
+        }
+        Ok(measurement)
+    }
+
+    /// Smallest spacing of the sample times.
+    fn min_spacing(&self) -> f64 {
+        self.times
+            .windows(2)
+            .filter_map(|w| match w {
+                &[a, b] => Some(b - a),
+                _ => None,
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// RMS of each channel, or 1 for an all-zero channel.
+    fn scales(&self) -> Vec<f64> {
+        self.channels
+            .iter()
+            .map(|(_, values)| {
+                let rms = (values.iter().map(|v| v * v).sum::<f64>() / values.len() as f64).sqrt();
+                if rms > 0.0 { rms } else { 1.0 }
+            })
+            .collect()
+    }
+
+    /// Time span `(t_first, t_last)` of the samples.
+    pub fn span(&self) -> (f64, f64) {
+        (
+            self.times.first().copied().unwrap_or_default(),
+            self.times.last().copied().unwrap_or_default(),
+        )
+    }
+}
+
+/// Setup of a parameter fit.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct FitConfig {
+    /// Fitted parameters; the others keep their node values.
+    pub params: Vec<FitParam>,
+    /// Measured traces.
+    pub measurement: Option<Measurement>,
+    /// Largest number of Levenberg–Marquardt iterations.
+    pub max_iterations: usize,
+    /// CSV text being edited in the measurement import box.
+    #[serde(skip)]
+    pub csv: String,
+    /// Error from the last measurement import.
+    #[serde(skip)]
+    pub csv_error: Option<String>,
+}
+
+impl Default for FitConfig {
+    fn default() -> Self {
+        Self {
+            params: FitParam::ALL.to_vec(),
+            measurement: None,
+            max_iterations: 30,
+            csv: String::new(),
+            csv_error: None,
+        }
+    }
+}
+
+/// Nodes the fitted parameters and simulated channels belong to.
+#[derive(Clone, Copy, Debug)]
+struct Targets {
+    /// The PMSM Electrical node.
+    electrical: NodeId,
+    /// The Torque node, whose copies of `L_d`, `L_q`, `λ_m` follow the fit.
+    torque: Option<NodeId>,
+    /// The Mechanical or two-mass node.
+    mechanical: NodeId,
+    /// Whether `mechanical` is a two-mass drivetrain.
+    two_mass: bool,
+}
+
+impl Targets {
+    /// The first Electrical, Torque and Mechanical or two-mass nodes, as
+    /// the solver picks them.
+    fn locate(snarl: &Snarl<SimNode>) -> Result<Self, SimError> {
+        let find = |pick: fn(&SimNode) -> bool| {
+            snarl
+                .node_ids()
+                .find_map(|(id, node)| pick(node).then_some(id))
+        };
+        let electrical = find(|n| matches!(n, SimNode::Electrical(_))).ok_or_else(|| {
+            SimError::InvalidSetup("the fit needs the PMSM Electrical node".to_owned())
+        })?;
+        let mechanical = find(|n| matches!(n, SimNode::Mechanical(_) | SimNode::TwoMass(_)))
+            .ok_or_else(|| SimError::InvalidSetup("the fit needs a Mechanical node".to_owned()))?;
+        Ok(Self {
+            electrical,
+            torque: find(|n| matches!(n, SimNode::Torque(_))),
+            mechanical,
+            two_mass: matches!(snarl.get_node(mechanical), Some(SimNode::TwoMass(_))),
+        })
+    }
+
+    /// Node fields holding `param`; the first one is the reference value.
+    fn fields(&self, param: FitParam) -> Vec<(NodeId, &'static str)> {
+        let with_torque = |path| {
+            std::iter::once((self.electrical, path))
+                .chain(self.torque.map(|id| (id, path)))
+                .collect()
+        };
+        match param {
+            FitParam::Rs => vec![(self.electrical, "r_s")],
+            FitParam::Ld => with_torque("l_d"),
+            FitParam::Lq => with_torque("l_q"),
+            FitParam::LambdaM => with_torque("lambda_m"),
+            FitParam::J => vec![(self.mechanical, if self.two_mass { "j_m" } else { "j" })],
+            FitParam::B => vec![(self.mechanical, if self.two_mass { "b_m" } else { "b" })],
+        }
+    }
+
+    /// Node and output pin of the simulated `channel`.
+    fn output(&self, channel: Channel) -> (NodeId, usize) {
+        match channel {
+            Channel::Id => (self.electrical, 0),
+            Channel::Iq => (self.electrical, 1),
+            Channel::OmegaM => (self.mechanical, 0),
+        }
+    }
+}
+
+/// One fitted parameter with its uncertainty.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FittedParam {
+    /// Which parameter.
+    pub param: FitParam,
+    /// Node value before the fit.
+    pub initial: f64,
+    /// Fitted value.
+    pub value: f64,
+    /// Standard error of `ln(value)`; infinite when the measurement does not
+    /// determine the parameter.
+    pub log_std: f64,
+    /// Node fields written by [`FitResult::apply`].
+    fields: Vec<(NodeId, &'static str)>,
+}
+
+impl FittedParam {
+    /// Standard error of the value, `value·σ_ln`.
+    pub fn std_error(&self) -> f64 {
+        self.value * self.log_std
+    }
+
+    /// 95 % confidence interval, symmetric in `ln(value)`.
+    pub fn interval(&self) -> (f64, f64) {
+        let spread = (Z_95 * self.log_std).exp();
+        (self.value / spread, self.value * spread)
+    }
+}
+
+/// Outcome of a parameter fit.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FitResult {
+    /// Fitted parameters, in the order of [`FitConfig::params`].
+    pub params: Vec<FittedParam>,
+    /// RMS error of each measured channel at the fit, in its unit.
+    pub rms: Vec<(Channel, f64)>,
+    /// Levenberg–Marquardt iterations taken.
+    pub iterations: usize,
+    /// Whether the cost stopped decreasing before the iteration limit.
+    pub converged: bool,
+}
+
+impl FitResult {
+    /// Write the fitted values into the nodes.
+    ///
+    /// # Errors
+    ///
+    /// [`SimError::InvalidSetup`] if a fitted node no longer exists.
+    pub fn apply(&self, snarl: &mut Snarl<SimNode>) -> Result<(), SimError> {
+        for p in &self.params {
+            for &(id, path) in &p.fields {
+                let node = snarl.get_node_mut(id).ok_or_else(|| {
+                    SimError::InvalidSetup("a fitted node no longer exists".to_owned())
+                })?;
+                set_param(node, path, p.value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Residual evaluation of one fit: the graph, the measurement and the
+/// parameter fields.
+struct Problem<'a> {
+    snarl: &'a Snarl<SimNode>,
+    config: SimConfig,
+    targets: Targets,
+    measurement: &'a Measurement,
+    /// RMS of each measured channel, scaling its residuals.
+    scales: Vec<f64>,
+    fields: Vec<Vec<(NodeId, &'static str)>>,
+}
+
+impl Problem<'_> {
+    /// Scaled residuals `(simulated − measured) / scale` at the
+    /// log-parameters `theta`.
+    fn residuals(&self, theta: &DVector<f64>) -> Result<DVector<f64>, SimError> {
+        let settings: Vec<ParamValue> = self
+            .fields
+            .iter()
+            .zip(theta.iter())
+            .flat_map(|(fields, &log_value)| {
+                fields
+                    .iter()
+                    .map(move |&(id, path)| (id, path.to_owned(), log_value.exp()))
+            })
+            .collect();
+        let mut copy = self.snarl.clone();
+        for (id, path, value) in &settings {
+            let node = copy.get_node_mut(*id).ok_or_else(|| {
+                SimError::InvalidSetup("a fitted node no longer exists".to_owned())
+            })?;
+            set_param(node, path, *value)?;
+        }
+        run_simulation(&mut copy, &self.config)?;
+
+        let mut residuals = Vec::new();
+        for ((channel, measured), scale) in self.measurement.channels.iter().zip(&self.scales) {
+            let (id, output) = self.targets.output(*channel);
+            let Some(PortValue::Signal(simulated)) =
+                copy.get_node(id).and_then(|n| n.output_value(output))
+            else {
+                return Err(SimError::GraphError(format!(
+                    "no simulated {} to compare",
+                    channel.label()
+                )));
+            };
+            residuals.extend(
+                self.measurement
+                    .times
+                    .iter()
+                    .zip(measured)
+                    .map(|(&t, &y)| (interpolate_signal(simulated, t) - y) / scale),
+            );
+        }
+        Ok(DVector::from_vec(residuals))
+    }
+
+    /// Forward-difference Jacobian of the residuals `r` at `theta`, its
+    /// columns solved in parallel.
+    fn jacobian(&self, theta: &DVector<f64>, r: &DVector<f64>) -> Result<DMatrix<f64>, SimError> {
+        let columns: Vec<usize> = (0..theta.len()).collect();
+        let perturbed = map_parallel(&columns, |&k| {
+            let mut step = theta.clone();
+            if let Some(x) = step.get_mut(k) {
+                *x += JACOBIAN_STEP;
+            }
+            self.residuals(&step)
+        });
+        let mut jacobian = DMatrix::zeros(r.len(), theta.len());
+        for (k, column) in perturbed.into_iter().enumerate() {
+            jacobian.set_column(k, &((column? - r) / JACOBIAN_STEP));
+        }
+        Ok(jacobian)
+    }
+
+    /// Levenberg–Marquardt from the log-parameters `theta`.
+    fn minimise(
+        &self,
+        mut theta: DVector<f64>,
+        max_iterations: usize,
+    ) -> Result<Minimum, SimError> {
+        let mut r = self.residuals(&theta)?;
+        let mut jacobian = self.jacobian(&theta, &r)?;
+        let mut damping = 1e-3;
+        let mut iterations = 0;
+        let mut converged = false;
+        while iterations < max_iterations && !converged {
+            iterations += 1;
+            let normal = jacobian.transpose() * &jacobian;
+            let gradient = jacobian.transpose() * &r;
+            let diagonal = normal.diagonal();
+            let cost = r.norm_squared();
+            let mut accepted = None;
+            while damping < MAX_DAMPING {
+                let mut system = normal.clone();
+                system.set_diagonal(&(&diagonal + diagonal.add_scalar(f64::EPSILON) * damping));
+                let Some(step) = system.cholesky().map(|c| c.solve(&(-&gradient))) else {
+                    damping *= 10.0;
+                    continue;
+                };
+                let trial = &theta + &step;
+                // A failed simulation rejects the step like a worse fit
+                match self.residuals(&trial) {
+                    Ok(r_trial) if r_trial.norm_squared() < cost => {
+                        accepted = Some((trial, r_trial));
+                        damping = (damping / 10.0).max(1e-12);
+                        break;
+                    }
+                    _ => damping *= 10.0,
+                }
+            }
+            let Some((trial, r_trial)) = accepted else {
+                // No step reduces the cost: a minimum to the solver's precision
+                converged = true;
+                break;
+            };
+            converged = cost - r_trial.norm_squared() <= COST_TOLERANCE * cost;
+            theta = trial;
+            r = r_trial;
+            jacobian = self.jacobian(&theta, &r)?;
+        }
+        Ok(Minimum {
+            theta,
+            r,
+            jacobian,
+            iterations,
+            converged,
+        })
+    }
+}
+
+/// Where Levenberg–Marquardt stopped.
+struct Minimum {
+    /// Log-parameters.
+    theta: DVector<f64>,
+    /// Scaled residuals at `theta`.
+    r: DVector<f64>,
+    /// Jacobian of the residuals at `theta`.
+    jacobian: DMatrix<f64>,
+    iterations: usize,
+    converged: bool,
+}
+
+/// Fit the chosen parameters of the graph's machine to the measurement.
+///
+/// The graph itself is not changed; see [`FitResult::apply`].
+///
+/// # Errors
+///
+/// - [`SimError::InvalidSetup`] — no measurement or parameters, a parameter
+///   that is not positive, fewer residuals than parameters, or no
+///   Electrical or Mechanical node.
+/// - Any simulation error at the initial parameters or of the Jacobian.
+pub fn run_fit(
+    snarl: &Snarl<SimNode>,
+    config: &SimConfig,
+    fit: &FitConfig,
+) -> Result<FitResult, SimError> {
+    let measurement = fit
+        .measurement
+        .as_ref()
+        .ok_or_else(|| SimError::InvalidSetup("load a measurement to fit".to_owned()))?;
+    if fit.params.is_empty() {
+        return Err(SimError::InvalidSetup(
+            "choose the parameters to fit".to_owned(),
+        ));
+    }
+    let targets = Targets::locate(snarl)?;
+    let fields: Vec<Vec<(NodeId, &'static str)>> =
+        fit.params.iter().map(|&p| targets.fields(p)).collect();
+    let initial = fit
+        .params
+        .iter()
+        .zip(&fields)
+        .map(|(param, fields)| {
+            fields
+                .first()
+                .and_then(|&(id, path)| get_param(snarl.get_node(id)?, path))
+                .filter(|&value| value > 0.0)
+                .ok_or_else(|| {
+                    SimError::InvalidSetup(format!("{} must be positive to fit", param.label()))
+                })
+        })
+        .collect::<Result<Vec<f64>, SimError>>()?;
+
+    // Simulate up to the last sample, at least as finely as measured
+    let (first, t_end) = measurement.span();
+    let spacing = measurement.min_spacing().min(config.output_dt);
+    let scales = measurement.scales();
+    let problem = Problem {
+        snarl,
+        config: SimConfig {
+            t_start: config.t_start.min(first),
+            t_end,
+            output_dt: spacing,
+            ..config.clone()
+        },
+        targets,
+        measurement,
+        scales,
+        fields,
+    };
+    let m = measurement.times.len() * measurement.channels.len();
+    let n = initial.len();
+    if m <= n {
+        return Err(SimError::InvalidSetup(format!(
+            "{m} residuals cannot determine {n} parameters"
+        )));
+    }
+
+    let theta = DVector::from_iterator(n, initial.iter().map(|v| v.ln()));
+    let Minimum {
+        theta,
+        r,
+        jacobian,
+        iterations,
+        converged,
+    } = problem.minimise(theta, fit.max_iterations)?;
+
+    // Covariance of the log-parameters at the optimum
+    let variance = r.norm_squared() / (m - n) as f64;
+    let covariance = (jacobian.transpose() * &jacobian).try_inverse();
+    let params = fit
+        .params
+        .iter()
+        .zip(problem.fields)
+        .zip(initial)
+        .zip(theta.iter())
+        .enumerate()
+        .map(
+            |(k, (((&param, fields), initial), log_value))| FittedParam {
+                param,
+                initial,
+                value: log_value.exp(),
+                log_std: covariance
+                    .as_ref()
+                    .and_then(|c| c.get((k, k)))
+                    .map_or(f64::INFINITY, |c| (variance * c).max(0.0).sqrt()),
+                fields,
+            },
+        )
+        .collect();
+    let samples = measurement.times.len();
+    let rms = measurement
+        .channels
+        .iter()
+        .zip(&problem.scales)
+        .enumerate()
+        .map(|(k, ((channel, _), scale))| {
+            let square = r.rows(k * samples, samples).norm_squared() / samples as f64;
+            (*channel, scale * square.sqrt())
+        })
+        .collect();
+    Ok(FitResult {
+        params,
+        rms,
+        iterations,
+        converged,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_snarl::{InPinId, NodeId, OutPinId, Snarl};
+    use rand::rngs::StdRng;
+    use rand::{Rng as _, SeedableRng as _};
+    use rand_distr::StandardNormal;
+
+    use super::{Channel, FitConfig, FitParam, Measurement, run_fit};
+    use crate::nodes::SimNode;
+    use crate::nodes::constant::ConstantNode;
+    use crate::nodes::electrical::ElectricalNode;
+    use crate::nodes::mechanical::MechanicalNode;
+    use crate::nodes::torque::TorqueNode;
+    use crate::port::PortValue;
+    use crate::simulation::solver::{interpolate_signal, run_simulation};
+    use crate::simulation::sweep::get_param;
+    use crate::simulation::{SimConfig, SimError};
+
+    /// The header names the columns in any order; bad input is rejected.
+    #[test]
+    fn measurement_csv_parsing() {
+        let m = Measurement::from_csv("# bench run\nt, omega_m, i_q\n0, 0, 1\n0.1; 5; 0.5\n")
+            .expect("valid csv");
+        assert_eq!(m.times, [0.0, 0.1]);
+        assert_eq!(
+            m.channels,
+            [
+                (Channel::OmegaM, vec![0.0, 5.0]),
+                (Channel::Iq, vec![1.0, 0.5])
+            ]
+        );
+        for bad in [
+            "t,i_d\n0,1\n",
+            "t,torque\n0,1\n1,2\n",
+            "i_d,i_q\n0,1\n1,2\n",
+            "t,i_d\n0,1\n0,2\n",
+            "t,i_d\n0,1\n1,x\n",
+        ] {
+            assert!(
+                matches!(
+                    Measurement::from_csv(bad),
+                    Err(SimError::InvalidMeasurement(_))
+                ),
+                "rejected: {bad:?}"
+            );
+        }
+    }
+
+    /// Default PMSM with a `v_d` and a `v_q` step, a Torque node and the
+    /// Electrical and Mechanical node ids.
+    fn machine_graph() -> (Snarl<SimNode>, NodeId, NodeId, NodeId) {
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let [vd, vq] = [4.0, 24.0].map(|value| {
+            snarl.insert_node(
+                pos,
+                SimNode::Constant(ConstantNode {
+                    value,
+                    ..ConstantNode::default()
+                }),
+            )
+        });
+        let elec = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let torque = snarl.insert_node(pos, SimNode::Torque(TorqueNode::default()));
+        let mech = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        for (from, input) in [(vd, 0), (vq, 1)] {
+            snarl.connect(
+                OutPinId {
+                    node: from,
+                    output: 0,
+                },
+                InPinId { node: elec, input },
+            );
+        }
+        (snarl, elec, torque, mech)
+    }
+
+    /// Traces simulated with known parameters plus 0.5 % noise are fitted
+    /// from the defaults: the true values lie within four standard errors,
+    /// and applying the fit writes them into the nodes.
+    #[test]
+    fn fit_recovers_machine_parameters() {
+        let truth = [
+            (FitParam::Rs, 1.5),
+            (FitParam::Ld, 0.006),
+            (FitParam::Lq, 0.01),
+            (FitParam::LambdaM, 0.15),
+            (FitParam::J, 0.0012),
+            (FitParam::B, 0.002),
+        ];
+        let (mut snarl, elec, torque, mech) = machine_graph();
+        if let Some(SimNode::Electrical(e)) = snarl.get_node_mut(elec) {
+            (e.r_s, e.l_d, e.l_q, e.lambda_m) = (1.5, 0.006, 0.01, 0.15);
+        }
+        if let Some(SimNode::Torque(t)) = snarl.get_node_mut(torque) {
+            (t.l_d, t.l_q, t.lambda_m) = (0.006, 0.01, 0.15);
+        }
+        if let Some(SimNode::Mechanical(m)) = snarl.get_node_mut(mech) {
+            (m.j, m.b) = (0.0012, 0.002);
+        }
+        let config = SimConfig {
+            t_end: 0.1,
+            output_dt: 0.0005,
+            ..SimConfig::default()
+        };
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+        let signal = |id: NodeId, output: usize| match snarl
+            .get_node(id)
+            .and_then(|n| n.output_value(output))
+        {
+            Some(PortValue::Signal(s)) => s.clone(),
+            _ => panic!("expected a simulated signal"),
+        };
+        let (i_d, i_q, omega_m) = (signal(elec, 0), signal(elec, 1), signal(mech, 0));
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut noisy = |x: f64, scale: f64| {
+            let u: f64 = rng.sample(StandardNormal);
+            x + 0.005 * scale * u
+        };
+        let mut csv = String::from("t,i_d,i_q,omega_m\n");
+        for k in 1..=200 {
+            let t = k as f64 * 0.0005;
+            csv.push_str(&format!(
+                "{t},{},{},{}\n",
+                noisy(interpolate_signal(&i_d, t), 2.0),
+                noisy(interpolate_signal(&i_q, t), 2.0),
+                noisy(interpolate_signal(&omega_m, t), 30.0)
+            ));
+        }
+
+        let (mut snarl, elec, torque, _) = machine_graph();
+        let fit = FitConfig {
+            measurement: Some(Measurement::from_csv(&csv).expect("valid csv")),
+            ..FitConfig::default()
+        };
+        let result = run_fit(&snarl, &SimConfig::default(), &fit).expect("fit should succeed");
+        assert!(result.converged, "{} iterations", result.iterations);
+        for (p, (param, value)) in result.params.iter().zip(truth) {
+            assert_eq!(p.param, param);
+            assert!(
+                p.log_std.is_finite() && (p.value / value).ln().abs() < 4.0 * p.log_std,
+                "{}: true {value} beyond 4σ of {} ± {}",
+                param.label(),
+                p.value,
+                p.std_error()
+            );
+            assert!(
+                (p.value / value - 1.0).abs() < 0.05,
+                "{}: fitted {} vs {value}",
+                param.label(),
+                p.value
+            );
+        }
+        for (channel, rms) in &result.rms {
+            let noise = if *channel == Channel::OmegaM {
+                0.15
+            } else {
+                0.01
+            };
+            assert!(rms < &(2.0 * noise), "{}: rms {rms}", channel.label());
+        }
+
+        result.apply(&mut snarl).expect("nodes exist");
+        let fitted = |id: NodeId, path: &str| {
+            snarl
+                .get_node(id)
+                .and_then(|n| get_param(n, path))
+                .expect("numeric parameter")
+        };
+        let lambda = result
+            .params
+            .iter()
+            .find(|p| p.param == FitParam::LambdaM)
+            .expect("λ_m fitted")
+            .value;
+        assert_eq!(fitted(elec, "lambda_m"), lambda);
+        assert_eq!(fitted(torque, "lambda_m"), lambda, "Torque node follows");
+    }
+}
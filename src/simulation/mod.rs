@@ -4,7 +4,10 @@
 //! for a single simulation run. [`SimError`] enumerates all failure modes that
 //! [`solver::run_simulation`] can surface to the caller.
 
+pub mod identify;
+pub mod monte_carlo;
 pub mod solver;
+pub mod sweep;
 
 /// Configuration for a single PMSM simulation run.
 ///
@@ -24,6 +27,9 @@ pub struct SimConfig {
     /// Maximum time between output points (seconds). Controls plot smoothness.
     /// The solver output is resampled onto a uniform grid with this spacing.
     pub output_dt: f64,
+    /// Start the machine at its steady state for the inputs at `t_start`
+    /// (see [`solver::trim`]) instead of the nodes' initial conditions.
+    pub start_at_steady_state: bool,
 }
 
 impl Default for SimConfig {
@@ -34,6 +40,7 @@ impl Default for SimConfig {
             rtol: 1e-6,
             atol: 1e-8,
             output_dt: 0.001,
+            start_at_steady_state: false,
         }
     }
 }
@@ -41,8 +48,12 @@ impl Default for SimConfig {
 /// Errors that can occur when running a graph simulation.
 #[derive(Debug)]
 pub enum SimError {
-    /// No ODE nodes (both [`crate::nodes::electrical::ElectricalNode`] and
-    /// [`crate::nodes::mechanical::MechanicalNode`]) were found in the graph.
+    /// No ODE nodes (a [`crate::nodes::mechanical::MechanicalNode`] or
+    /// [`crate::nodes::two_mass::TwoMassNode`] plus a machine: [`crate::nodes::electrical::ElectricalNode`],
+    /// [`crate::nodes::abc_machine::AbcMachineNode`],
+    /// [`crate::nodes::induction::InductionMachineNode`],
+    /// [`crate::nodes::dc_motor::DcMotorNode`] or
+    /// [`crate::nodes::bldc::BldcNode`]) were found in the graph.
     NoOdeNodes,
     /// A required connection between nodes is absent.
     MissingConnection(String),
@@ -50,17 +61,24 @@ pub enum SimError {
     SolverFailed(String),
     /// The node graph has an inconsistent or unsupported topology.
     GraphError(String),
+    /// A parameter sweep, Monte Carlo study or parameter fit is set up
+    /// incompletely or names a missing parameter.
+    InvalidSetup(String),
+    /// A measured CSV trace cannot be read.
+    InvalidMeasurement(String),
 }
 
 impl std::fmt::Display for SimError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::NoOdeNodes => {
-                write!(f, "no ODE nodes (Electrical + Mechanical) found in graph")
+                write!(f, "no ODE nodes (a machine + Mechanical) found in graph")
             }
             Self::MissingConnection(msg) => write!(f, "missing connection: {msg}"),
             Self::SolverFailed(msg) => write!(f, "solver failed: {msg}"),
             Self::GraphError(msg) => write!(f, "graph topology error: {msg}"),
+            Self::InvalidSetup(msg) => write!(f, "invalid setup: {msg}"),
+            Self::InvalidMeasurement(msg) => write!(f, "invalid measurement: {msg}"),
         }
     }
 }
@@ -0,0 +1,431 @@
+//! Monte Carlo tolerance analysis: the graph solved for random parameter
+//! sets drawn around the nominal values.
+//!
+//! Each tolerance draws its parameter as `x = x₀·(1 + s·u)` with `u` uniform
+//! on `[−1, 1]` or standard normal, from a seeded generator so a study is
+//! reproducible. The runs are solved like the runs of a sweep, then every
+//! Plot node receives the envelope of each of its traces — min/max, a
+//! percentile band and the median — and the chosen metrics of every trace
+//! are summarized over the runs.
+
+use egui_snarl::{NodeId, Snarl};
+use rand::rngs::StdRng;
+use rand::{Rng as _, SeedableRng as _};
+use rand_distr::StandardNormal;
+
+use super::solver::{interpolate_signal, run_simulation};
+use super::sweep::{ParamValue, RunResult, get_param, solve_variants};
+use super::{SimConfig, SimError};
+use crate::nodes::SimNode;
+use crate::nodes::metrics::time_averages;
+use crate::nodes::plot::PlotEnvelope;
+
+/// Distribution of the relative deviation of a parameter.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Distribution {
+    /// Uniform on `±spread`.
+    #[default]
+    Uniform,
+    /// Normal with standard deviation `spread`.
+    Normal,
+}
+
+impl Distribution {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 2] = [Self::Uniform, Self::Normal];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Uniform => "Uniform",
+            Self::Normal => "Normal",
+        }
+    }
+}
+
+/// Random variation of one node parameter.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Tolerance {
+    /// Node holding the parameter.
+    pub node: Option<NodeId>,
+    /// Field path of the parameter inside the node, `.`-separated.
+    pub param: String,
+    /// Distribution of the relative deviation.
+    pub distribution: Distribution,
+    /// Relative spread: half-width (uniform) or standard deviation (normal).
+    pub spread: f64,
+}
+
+/// Scalar metric of one trace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TraceMetric {
+    /// Value at the end of the run.
+    Final,
+    /// Largest value.
+    Max,
+    /// Smallest value.
+    Min,
+    /// Time average.
+    Mean,
+    /// Root mean square over time.
+    Rms,
+}
+
+impl TraceMetric {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 5] = [Self::Final, Self::Max, Self::Min, Self::Mean, Self::Rms];
+
+    /// Short label for checkboxes and tables.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Final => "Final",
+            Self::Max => "Max",
+            Self::Min => "Min",
+            Self::Mean => "Mean",
+            Self::Rms => "RMS",
+        }
+    }
+
+    /// Evaluate the metric on a `[t, value]` trace; time averages use the
+    /// trapezoidal rule.
+    pub fn eval(self, trace: &[[f64; 2]]) -> f64 {
+        let values = trace.iter().map(|&[_, v]| v);
+        match self {
+            Self::Final => trace.last().map_or(0.0, |&[_, v]| v),
+            Self::Max => values.fold(f64::NEG_INFINITY, f64::max),
+            Self::Min => values.fold(f64::INFINITY, f64::min),
+            Self::Mean => time_averages(trace).map_or(0.0, |(mean, _)| mean),
+            Self::Rms => time_averages(trace).map_or(0.0, |(_, rms)| rms),
+        }
+    }
+}
+
+/// Setup of a Monte Carlo study.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MonteCarloConfig {
+    /// Varied parameters.
+    pub tolerances: Vec<Tolerance>,
+    /// Number of runs.
+    pub runs: usize,
+    /// Random generator seed; equal seeds give equal parameter sets.
+    pub seed: u64,
+    /// Lower percentile of the band (%); the upper one is `100 − p`.
+    pub percentile: f64,
+    /// Metrics summarized for every plotted trace.
+    pub metrics: Vec<TraceMetric>,
+}
+
+impl Default for MonteCarloConfig {
+    fn default() -> Self {
+        Self {
+            tolerances: vec![Tolerance {
+                spread: 0.1,
+                ..Tolerance::default()
+            }],
+            runs: 50,
+            seed: 0,
+            percentile: 5.0,
+            metrics: vec![TraceMetric::Final, TraceMetric::Max],
+        }
+    }
+}
+
+/// Statistics of one metric of one trace over all runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricSummary {
+    /// Trace name, e.g. `Plot #7 signal_0`.
+    pub trace: String,
+    /// Summarized metric.
+    pub metric: TraceMetric,
+    /// Mean over the runs.
+    pub mean: f64,
+    /// Sample standard deviation over the runs.
+    pub std: f64,
+    /// Smallest value.
+    pub min: f64,
+    /// Lower percentile.
+    pub p_low: f64,
+    /// Upper percentile.
+    pub p_high: f64,
+    /// Largest value.
+    pub max: f64,
+}
+
+/// Percentile `p` (%) of ascending `sorted` values, linearly interpolated.
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let Some(last) = sorted.len().checked_sub(1) else {
+        return 0.0;
+    };
+    let rank = (p / 100.0).clamp(0.0, 1.0) * last as f64;
+    let lower = rank.floor() as usize;
+    match (sorted.get(lower), sorted.get(lower + 1)) {
+        (Some(a), Some(b)) => a + (rank - lower as f64) * (b - a),
+        (Some(&a), None) => a,
+        _ => 0.0,
+    }
+}
+
+impl MonteCarloConfig {
+    /// Draw the parameter sets of every run around the nominal values of
+    /// `snarl`.
+    ///
+    /// # Errors
+    ///
+    /// [`SimError::InvalidSetup`] for a tolerance without a node or with a
+    /// parameter the node lacks, or no runs.
+    pub fn variants(&self, snarl: &Snarl<SimNode>) -> Result<Vec<Vec<ParamValue>>, SimError> {
+        if self.runs == 0 {
+            return Err(SimError::InvalidSetup("no Monte Carlo runs".to_owned()));
+        }
+        let nominal = self
+            .tolerances
+            .iter()
+            .map(|tol| {
+                let node = tol.node.ok_or_else(|| {
+                    SimError::InvalidSetup("choose a node for every tolerance".to_owned())
+                })?;
+                let value = snarl
+                    .get_node(node)
+                    .and_then(|n| get_param(n, &tol.param))
+                    .ok_or_else(|| {
+                        SimError::InvalidSetup(format!("no numeric parameter '{}'", tol.param))
+                    })?;
+                Ok((node, value))
+            })
+            .collect::<Result<Vec<_>, SimError>>()?;
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        Ok((0..self.runs)
+            .map(|_| {
+                self.tolerances
+                    .iter()
+                    .zip(&nominal)
+                    .map(|(tol, &(node, value))| {
+                        let u: f64 = match tol.distribution {
+                            Distribution::Uniform => rng.random_range(-1.0..=1.0),
+                            Distribution::Normal => rng.sample(StandardNormal),
+                        };
+                        (node, tol.param.clone(), value * (1.0 + tol.spread * u))
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+/// Envelope of the runs' versions of one trace, on the time points of the
+/// first run.
+fn envelope(name: &str, traces: &[&[[f64; 2]]], p_low: f64) -> PlotEnvelope {
+    let times: Vec<f64> = traces
+        .first()
+        .map(|trace| trace.iter().map(|&[t, _]| t).collect())
+        .unwrap_or_default();
+    let mut env = PlotEnvelope {
+        name: name.to_owned(),
+        ..PlotEnvelope::default()
+    };
+    for t in times {
+        let mut values: Vec<f64> = traces
+            .iter()
+            .map(|trace| interpolate_signal(trace, t))
+            .collect();
+        values.sort_by(f64::total_cmp);
+        env.min.push([t, percentile(&values, 0.0)]);
+        env.low.push([t, percentile(&values, p_low)]);
+        env.median.push([t, percentile(&values, 50.0)]);
+        env.high.push([t, percentile(&values, 100.0 - p_low)]);
+        env.max.push([t, percentile(&values, 100.0)]);
+    }
+    env
+}
+
+/// Summarize each metric of one trace over the runs.
+fn summarize(
+    trace: &str,
+    metrics: &[TraceMetric],
+    traces: &[&[[f64; 2]]],
+    p_low: f64,
+) -> Vec<MetricSummary> {
+    metrics
+        .iter()
+        .map(|&metric| {
+            let mut values: Vec<f64> = traces.iter().map(|t| metric.eval(t)).collect();
+            values.sort_by(f64::total_cmp);
+            let n = values.len() as f64;
+            let mean = values.iter().sum::<f64>() / n;
+            let var = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0).max(1.0);
+            MetricSummary {
+                trace: trace.to_owned(),
+                metric,
+                mean,
+                std: var.sqrt(),
+                min: percentile(&values, 0.0),
+                p_low: percentile(&values, p_low),
+                p_high: percentile(&values, 100.0 - p_low),
+                max: percentile(&values, 100.0),
+            }
+        })
+        .collect()
+}
+
+/// Run a Monte Carlo study: solve the graph for every drawn parameter set,
+/// then solve the nominal graph, hand every Plot node the envelopes of its
+/// traces and return the metric summaries.
+///
+/// # Errors
+///
+/// [`SimError::InvalidSetup`] for an incomplete setup, or the first
+/// simulation error of any run.
+pub fn run_monte_carlo(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    mc: &MonteCarloConfig,
+) -> Result<Vec<MetricSummary>, SimError> {
+    let variants = mc.variants(snarl)?;
+    let runs: Vec<RunResult> = solve_variants(snarl, config, &variants)?;
+    run_simulation(snarl, config)?;
+
+    let p_low = mc.percentile.clamp(0.0, 50.0);
+    let mut summaries = Vec::new();
+    let Some(first) = runs.first() else {
+        return Ok(summaries);
+    };
+    for (k, (plot, lines)) in first.traces.iter().enumerate() {
+        let mut envelopes = Vec::new();
+        for (j, (name, _)) in lines.iter().enumerate() {
+            let traces: Vec<&[[f64; 2]]> = runs
+                .iter()
+                .filter_map(|run| run.traces.get(k)?.1.get(j))
+                .map(|(_, points)| points.as_slice())
+                .collect();
+            envelopes.push(envelope(name, &traces, p_low));
+            let trace = format!("Plot #{} {name}", plot.0);
+            summaries.extend(summarize(&trace, &mc.metrics, &traces, p_low));
+        }
+        if let Some(SimNode::Plot(p)) = snarl.get_node_mut(*plot) {
+            p.envelopes = envelopes;
+        }
+    }
+    Ok(summaries)
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_snarl::{InPinId, OutPinId, Snarl};
+
+    use super::{
+        Distribution, MonteCarloConfig, Tolerance, TraceMetric, percentile, run_monte_carlo,
+    };
+    use crate::nodes::SimNode;
+    use crate::nodes::constant::ConstantNode;
+    use crate::nodes::electrical::ElectricalNode;
+    use crate::nodes::mechanical::MechanicalNode;
+    use crate::nodes::metrics::SignalMetrics;
+    use crate::nodes::plot::PlotNode;
+    use crate::simulation::SimConfig;
+
+    /// Percentiles interpolate between ranks and metrics integrate over time.
+    #[test]
+    fn percentiles_and_metrics() {
+        let sorted = [1.0, 2.0, 3.0, 4.0, 5.0];
+        assert!((percentile(&sorted, 50.0) - 3.0).abs() < 1e-12, "median");
+        assert!((percentile(&sorted, 10.0) - 1.4).abs() < 1e-12, "10th");
+        let ramp = [[0.0, 0.0], [1.0, 1.0], [2.0, 2.0]];
+        assert!((TraceMetric::Mean.eval(&ramp) - 1.0).abs() < 1e-12, "mean");
+        assert!(
+            (TraceMetric::Final.eval(&ramp) - 2.0).abs() < 1e-12,
+            "final"
+        );
+        let square = [[0.0, 3.0], [1.0, -3.0], [2.0, 3.0]];
+        let rms = TraceMetric::Rms.eval(&square);
+        assert!((rms - 3.0).abs() < 1e-9, "rms {rms}");
+        // A single sample averages to itself, as on the Metrics node
+        let single = [[0.0, -2.0]];
+        let metrics = SignalMetrics::of(&single, 2.0).expect("one sample");
+        assert_eq!(TraceMetric::Mean.eval(&single), metrics.mean);
+        assert_eq!(TraceMetric::Rms.eval(&single), metrics.rms);
+        assert_eq!(metrics.rms, 2.0);
+    }
+
+    /// Varying the PM flux spreads the final speed around the nominal run,
+    /// the envelope holds the nominal trace and the draws are reproducible.
+    #[test]
+    fn flux_tolerance_spreads_final_speed() {
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let [vd, vq, tl] = [0.0, 24.0, 0.0].map(|value| {
+            snarl.insert_node(
+                pos,
+                SimNode::Constant(ConstantNode {
+                    value,
+                    ..ConstantNode::default()
+                }),
+            )
+        });
+        let elec = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let plot = snarl.insert_node(pos, SimNode::Plot(PlotNode::default()));
+        for (from, output, to, input) in [
+            (vd, 0, elec, 0),
+            (vq, 0, elec, 1),
+            (tl, 0, mech, 1),
+            (mech, 0, plot, 0),
+        ] {
+            snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+        }
+
+        let mc = MonteCarloConfig {
+            tolerances: vec![Tolerance {
+                node: Some(elec),
+                param: "lambda_m".to_owned(),
+                distribution: Distribution::Normal,
+                spread: 0.1,
+            }],
+            runs: 12,
+            seed: 3,
+            ..MonteCarloConfig::default()
+        };
+        assert_eq!(
+            mc.variants(&snarl).expect("valid"),
+            mc.variants(&snarl).expect("valid"),
+            "same seed, same draws"
+        );
+        let config = SimConfig {
+            t_end: 0.2,
+            ..SimConfig::default()
+        };
+        let summaries = run_monte_carlo(&mut snarl, &config, &mc).expect("study should succeed");
+
+        let final_speed = summaries
+            .iter()
+            .find(|s| s.metric == TraceMetric::Final)
+            .expect("final speed summarized");
+        assert!(final_speed.std > 0.0, "speeds spread");
+        assert!(
+            final_speed.min <= final_speed.p_low && final_speed.p_high <= final_speed.max,
+            "ordered statistics"
+        );
+
+        let Some(SimNode::Plot(plot)) = snarl.get_node(plot) else {
+            panic!("expected plot node");
+        };
+        let env = plot.envelopes.first().expect("one envelope");
+        let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech) else {
+            panic!("expected mechanical node");
+        };
+        let Some(crate::port::PortValue::Signal(nominal)) = mech.output_omega_m.as_ref() else {
+            panic!("expected nominal speed");
+        };
+        let (&[_, lo], &[_, hi], &[_, omega]) = (
+            env.min.last().expect("samples"),
+            env.max.last().expect("samples"),
+            nominal.last().expect("samples"),
+        );
+        assert!(
+            lo < omega && omega < hi,
+            "nominal {omega} within [{lo}, {hi}]"
+        );
+    }
+}
@@ -2,17 +2,55 @@
 //!
 //! The entry point is [`run_simulation`].  It traverses the snarl graph,
 //! extracts motor parameters from node fields and connected `Constant` sources,
-//! assembles the 4-state ODE system `[i_d, i_q, ω_m, θ_e]`, solves it with
+//! assembles the 4-state ODE system `[i_d, i_q, ω_m, θ_e]` (plus the DC-link
+//! capacitor voltage when a DC Link node is present; with a flux map the
+//! first two states are the flux linkages `[ψ_d, ψ_q]`), solves it with
 //! `diffsol`'s BDF integrator, and distributes the resulting time-series signals
 //! back into the graph nodes so the UI can render them.
-
-use diffsol::{DenseMatrix as _, NalgebraLU, NalgebraMat, OdeBuilder, OdeSolverMethod as _};
-use egui_snarl::{InPinId, NodeId, Snarl};
-
+//!
+//! Graphs built around the abc-frame machine, the induction machine, the
+//! brushed DC motor or the BLDC motor instead of the Electrical node are
+//! solved by the [`abc`], [`induction`], [`dc_motor`] and [`bldc`]
+//! submodules. Nodes that only observe the solution, such as position
+//! sensors and observers, are evaluated afterwards by the [`post`] pass.
+
+use std::cell::Cell;
+use std::ops::Index;
+use std::rc::Rc;
+
+use diffsol::error::OdeSolverError;
+use diffsol::{
+    Context as _, DenseMatrix as _, DiffsolError, MatrixCommon as _, NalgebraContext, NalgebraLU,
+    NalgebraMat, NalgebraVec, NonLinearOp as _, OdeBuilder, OdeEquations, OdeSolverMethod,
+    OdeSolverStopReason, Vector as _, VectorHost as _, VectorViewMut as _,
+};
+use egui_snarl::{InPinId, NodeId, OutPinId, Snarl};
+use nalgebra::{DMatrix, DVector};
+
+use self::thermal::ThermalModel;
 use super::{SimConfig, SimError};
 use crate::nodes::SimNode;
+use crate::nodes::dc_link::DcLinkNode;
+use crate::nodes::electrical::IronLoss;
+use crate::nodes::flux_map::FluxMap;
+use crate::nodes::harmonics::SpatialHarmonics;
+use crate::nodes::inverter::{InverterNode, LegState};
+use crate::nodes::mechanical::MechanicalNode;
+use crate::nodes::pmsm::{PmsmParams, RotorFrame};
+use crate::nodes::power::{PowerFlowNode, PowerSample};
+use crate::nodes::svpwm::{ReferenceFrame, SvpwmNode};
 use crate::port::{PortType, PortValue};
 
+mod abc;
+mod bldc;
+mod dc_motor;
+mod efficiency_map;
+mod induction;
+mod linearize;
+mod post;
+mod thermal;
+mod trim;
+
 /// Concrete dense-matrix type driven through the BDF solver.
 type M = NalgebraMat<f64>;
 
@@ -20,14 +58,16 @@ type M = NalgebraMat<f64>;
 type Ls = NalgebraLU<f64>;
 
 // ── State-vector indices ──────────────────────────────────────────────────────
-/// State index: d-axis stator current `i_d`.
+/// State index: d-axis stator current `i_d` (flux linkage `ψ_d` with a flux map).
 const S_ID: usize = 0;
-/// State index: q-axis stator current `i_q`.
+/// State index: q-axis stator current `i_q` (flux linkage `ψ_q` with a flux map).
 const S_IQ: usize = 1;
 /// State index: mechanical angular speed `ω_m`.
 const S_WM: usize = 2;
 /// State index: electrical angle `θ_e`.
 const S_TE: usize = 3;
+/// State index: DC-link capacitor voltage `v_C` (present only with a DC Link node).
+const S_VC: usize = 4;
 
 // ── Parameter-vector indices ──────────────────────────────────────────────────
 /// Parameter index: stator resistance `R_s`.
@@ -52,8 +92,10 @@ const P_T_LAM: usize = 8;
 const P_T_LD: usize = 9;
 /// Parameter index: torque-equation q-axis inductance `L_q`.
 const P_T_LQ: usize = 10;
+/// Number of parameters.
+const N_PARAMS: usize = 11;
 
-/// Total number of states in the coupled PMSM ODE system.
+/// Number of motor states; a DC link appends `v_C` at [`S_VC`].
 const N_STATES: usize = 4;
 
 /// Return the value emitted by a `Constant` source connected to a given input pin.
@@ -105,7 +147,7 @@ fn get_vector_input(snarl: &Snarl<SimNode>, node: NodeId, input: usize) -> Optio
     clippy::indexing_slicing,
     reason = "indices are bounded by early-return guards: is_empty, len==1, boundary clamps, and partition_point"
 )]
-fn interpolate_signal(signal: &[[f64; 2]], t: f64) -> f64 {
+pub(crate) fn interpolate_signal(signal: &[[f64; 2]], t: f64) -> f64 {
     if signal.is_empty() {
         return 0.0;
     }
@@ -232,6 +274,167 @@ fn resolve_external_input(snarl: &Snarl<SimNode>, node: NodeId, input: usize) ->
     ExternalInput::Constant(0.0)
 }
 
+/// DC-link voltage seen by a modulator or inverter folded into the ODE.
+#[derive(Clone)]
+enum BusVoltage {
+    /// Pre-resolved constant or signal.
+    External(ExternalInput),
+    /// Instantaneous bus voltage of the DC Link node in the ODE.
+    Link,
+}
+
+impl BusVoltage {
+    /// Evaluate the bus voltage at time `t` for the given ODE state.
+    fn at(&self, t: f64, state: CoupledState) -> f64 {
+        match self {
+            Self::External(ext) => ext.at(t),
+            Self::Link => state.v_dc,
+        }
+    }
+}
+
+/// Resolve the `V_dc` pin of a modulator: the ODE DC link or an external input.
+fn resolve_bus_voltage(
+    snarl: &Snarl<SimNode>,
+    node: NodeId,
+    input: usize,
+    link: Option<NodeId>,
+) -> BusVoltage {
+    let from_link = snarl
+        .in_pin(InPinId { node, input })
+        .remotes
+        .first()
+        .is_some_and(|r| Some(r.node) == link && r.output == 0);
+    if from_link {
+        BusVoltage::Link
+    } else {
+        BusVoltage::External(resolve_external_input(snarl, node, input))
+    }
+}
+
+/// An SVPWM modulator whose `θ_e` input is the ODE angle state.
+///
+/// The reference and DC-link inputs are pre-resolved; the modulator is
+/// evaluated inline at the instantaneous `θ_e` during integration.
+#[derive(Clone)]
+struct SvpwmCoupling {
+    /// Modulator settings (frame, overmodulation, minimum pulse).
+    node: SvpwmNode,
+    /// First reference component (`v_d` or `v_α`).
+    u1: ExternalInput,
+    /// Second reference component (`v_q` or `v_β`).
+    u2: ExternalInput,
+    /// DC-link voltage.
+    v_dc: BusVoltage,
+}
+
+/// Duty cycles driving a switched inverter folded into the ODE.
+#[derive(Clone)]
+enum DutySource {
+    /// Pre-resolved duty series (constant or signal).
+    Series(Vec<[f64; 4]>),
+    /// SVPWM duty cycles evaluated at the instantaneous `θ_e`.
+    Svpwm(Box<SvpwmCoupling>),
+}
+
+impl DutySource {
+    /// Evaluate the duty cycles `[d_a, d_b, d_c]` at time `t`.
+    fn at(&self, t: f64, state: CoupledState) -> [f64; 3] {
+        match self {
+            Self::Series(duty) => interpolate_vector(duty, t),
+            Self::Svpwm(m) => {
+                let (duty, _v_abc) =
+                    m.node
+                        .modulate(m.u1.at(t), m.u2.at(t), state.theta_e, m.v_dc.at(t, state));
+                duty
+            }
+        }
+    }
+}
+
+/// A switched inverter whose output voltage feeds the ODE through Park.
+///
+/// The leg states are piecewise constant: the solver sets them at every
+/// switching edge and stops exactly there, see [`solve_switched`].
+#[derive(Clone)]
+struct InverterCoupling {
+    /// Inverter settings (carrier frequency, dead time, diode drop).
+    node: InverterNode,
+    /// Duty cycles, sampled once per carrier period.
+    duty: DutySource,
+    /// DC-link voltage.
+    v_dc: BusVoltage,
+    /// Leg states of the current switching interval, shared with the RHS.
+    legs: Rc<Cell<[LegState; 3]>>,
+}
+
+/// ODE state components needed to evaluate graph inputs folded into the RHS.
+#[derive(Clone, Copy)]
+struct CoupledState {
+    /// d-axis current `i_d`.
+    i_d: f64,
+    /// q-axis current `i_q`.
+    i_q: f64,
+    /// Electrical angle `θ_e`.
+    theta_e: f64,
+    /// DC-link bus voltage; only read by sources wired to the DC Link node.
+    v_dc: f64,
+}
+
+impl CoupledState {
+    /// Gather the coupled components from a current-based ODE state vector.
+    ///
+    /// The bus voltage is not a state; [`Supply::eval`] fills it in. With a
+    /// flux map use [`Supply::motor_state`] instead.
+    fn from_states<V: Index<usize, Output = f64>>(x: &V) -> Self {
+        Self {
+            i_d: x[S_ID],
+            i_q: x[S_IQ],
+            theta_e: x[S_TE],
+            v_dc: 0.0,
+        }
+    }
+
+    /// Phase currents `[i_a, i_b, i_c]` from the inverse Park transform.
+    fn i_abc(self) -> [f64; 3] {
+        let two_thirds_pi = std::f64::consts::TAU / 3.0;
+        [0.0, -two_thirds_pi, two_thirds_pi].map(|offset| {
+            let theta = self.theta_e + offset;
+            self.i_d * theta.cos() - self.i_q * theta.sin()
+        })
+    }
+}
+
+/// Three-phase voltage feeding a Park transform folded into the ODE.
+#[derive(Clone)]
+enum PhaseSource {
+    /// Pre-resolved 3-phase series (constant or signal).
+    Series(Vec<[f64; 4]>),
+    /// Averaged SVPWM output evaluated at the instantaneous `θ_e`.
+    Svpwm(Box<SvpwmCoupling>),
+    /// Switched inverter output for the current switching interval.
+    Inverter(Box<InverterCoupling>),
+}
+
+impl PhaseSource {
+    /// Evaluate the phase voltages `[v_a, v_b, v_c]` at time `t`.
+    fn at(&self, t: f64, state: CoupledState) -> [f64; 3] {
+        match self {
+            Self::Series(f_abc) => interpolate_vector(f_abc, t),
+            Self::Svpwm(m) => {
+                let (_duty, v_abc) =
+                    m.node
+                        .modulate(m.u1.at(t), m.u2.at(t), state.theta_e, m.v_dc.at(t, state));
+                v_abc
+            }
+            Self::Inverter(inv) => {
+                inv.node
+                    .phase_voltages(inv.legs.get(), inv.v_dc.at(t, state), state.i_abc())
+            }
+        }
+    }
+}
+
 /// Voltage source for the d/q ODE equations.
 ///
 /// When `v_d`/`v_q` come directly from graph wires (constants or pre-computed
@@ -246,19 +449,20 @@ enum VoltageSource {
         v_q: ExternalInput,
     },
     /// Park transform computed inline using `theta_e` from the ODE state vector.
-    /// `f_abc` is the 3-phase voltage source (pre-resolved, constant or signal).
-    ParkCoupled { f_abc: Vec<[f64; 4]> },
+    /// `phase` is the 3-phase voltage source feeding the Park input.
+    ParkCoupled { phase: PhaseSource },
 }
 
 impl VoltageSource {
-    /// Evaluate `(v_d, v_q)` at time `t` using the given `theta_e`.
+    /// Evaluate `(v_d, v_q)` at time `t` for the given ODE state.
     ///
-    /// For `Direct`, `theta_e` is ignored.
-    fn eval(&self, t: f64, theta_e: f64) -> (f64, f64) {
+    /// For `Direct`, the state is ignored.
+    fn eval(&self, t: f64, state: CoupledState) -> (f64, f64) {
         match self {
             Self::Direct { v_d, v_q } => (v_d.at(t), v_q.at(t)),
-            Self::ParkCoupled { f_abc } => {
-                let [v_a, v_b, v_c] = interpolate_vector(f_abc, t);
+            Self::ParkCoupled { phase } => {
+                let [v_a, v_b, v_c] = phase.at(t, state);
+                let theta_e = state.theta_e;
                 let two_thirds = 2.0 / 3.0;
                 let two_thirds_pi = std::f64::consts::TAU / 3.0;
 
@@ -276,1140 +480,4746 @@ impl VoltageSource {
         }
     }
 
-    /// Whether this source is a Park-coupled source (for Jacobian dispatch).
-    const fn is_park_coupled(&self) -> bool {
-        matches!(self, Self::ParkCoupled { .. })
+    /// Partial derivatives `(∂v_d/∂θ_e, ∂v_q/∂θ_e)` at time `t`.
+    ///
+    /// For fixed phase voltages the Park rotation gives `dv_d/dθ = v_q` and
+    /// `dv_q/dθ = -v_d`; this also holds for a switched inverter between edges.
+    /// A coupled modulator also depends on `θ_e` itself, so a central
+    /// difference is used instead.
+    fn dtheta(&self, t: f64, state: CoupledState) -> (f64, f64) {
+        match self {
+            Self::Direct { .. } => (0.0, 0.0),
+            Self::ParkCoupled {
+                phase: PhaseSource::Series(_) | PhaseSource::Inverter(_),
+            } => {
+                let (v_d, v_q) = self.eval(t, state);
+                (v_q, -v_d)
+            }
+            Self::ParkCoupled {
+                phase: PhaseSource::Svpwm(_),
+            } => {
+                const H: f64 = 1e-6;
+                let shifted = |dtheta: f64| CoupledState {
+                    theta_e: state.theta_e + dtheta,
+                    ..state
+                };
+                let (vd_plus, vq_plus) = self.eval(t, shifted(H));
+                let (vd_minus, vq_minus) = self.eval(t, shifted(-H));
+                (
+                    (vd_plus - vd_minus) / (2.0 * H),
+                    (vq_plus - vq_minus) / (2.0 * H),
+                )
+            }
+        }
+    }
+
+    /// The switched inverter folded into this source, if any.
+    fn inverter(&self) -> Option<&InverterCoupling> {
+        match self {
+            Self::ParkCoupled {
+                phase: PhaseSource::Inverter(inv),
+            } => Some(inv),
+            Self::Direct { .. } | Self::ParkCoupled { .. } => None,
+        }
     }
 }
 
-/// Traverse the graph, solve the PMSM ODE system, and write results into node outputs.
-///
-/// # Errors
+/// Motor supply evaluated at one instant.
+#[derive(Clone, Copy)]
+struct SupplyPoint {
+    /// Coupled state with the bus voltage filled in.
+    state: CoupledState,
+    /// d-axis voltage `v_d`.
+    v_d: f64,
+    /// q-axis voltage `v_q`.
+    v_q: f64,
+    /// Electrical input power `P_e = (3/2)·(v_d·i_d + v_q·i_q)`.
+    p_e: f64,
+}
+
+/// Error for flux linkages `[ψ_d, ψ_q]` the flux map could not invert.
+fn flux_inversion_error([psi_d, psi_q]: [f64; 2]) -> SimError {
+    SimError::SolverFailed(format!(
+        "flux map inversion did not converge at ψ_d = {psi_d:.4} Wb, ψ_q = {psi_q:.4} Wb"
+    ))
+}
+
+/// The motor voltage source together with the optional DC link feeding it.
 ///
-/// - [`SimError::NoOdeNodes`] — the graph contains no `ElectricalNode` or no
-///   `MechanicalNode`.
-/// - [`SimError::GraphError`] — parameter extraction fails due to unexpected graph
-///   state (should not occur if the graph was built through the normal UI).
-/// - [`SimError::SolverFailed`] — the diffsol BDF integrator encounters a
-///   numerical error (ill-conditioned system, step-size underflow, etc.).
-#[expect(
-    clippy::too_many_lines,
-    reason = "ODE assembly inherently requires a long function"
-)]
-pub fn run_simulation(snarl: &mut Snarl<SimNode>, config: &SimConfig) -> Result<(), SimError> {
-    // ── 1. Snapshot all node IDs before any mutable access ───────────────────
-    let all_ids: Vec<NodeId> = snarl.node_ids().map(|(id, _)| id).collect();
+/// With a DC link the bus voltage depends on the inverter power, which in turn
+/// depends on the bus voltage through the modulator. The loop is closed with
+/// one fixed-point pass starting from the capacitor voltage: exact for an
+/// averaged modulator in its linear range (power independent of `V_dc`) and
+/// accurate to `ESR·Δi` otherwise.
+#[derive(Clone)]
+struct Supply {
+    /// Source of `v_d`/`v_q`.
+    voltage: VoltageSource,
+    /// DC link whose capacitor voltage is the state [`S_VC`].
+    link: Option<DcLinkNode>,
+    /// Flux map when the electrical states are flux linkages; converts them
+    /// back to the currents the supply and torque depend on.
+    flux_map: Option<Rc<FluxMap>>,
+    /// Back-EMF harmonics and cogging of the machine.
+    harmonics: Rc<SpatialHarmonics>,
+    /// Fundamental PM flux `λ_m` that scales the back-EMF harmonics (Wb).
+    lambda_m: f64,
+    /// Currents of the last converged map inversion, the Newton start of the
+    /// next one.
+    flux_guess: Rc<Cell<Option<(f64, f64)>>>,
+    /// Flux linkages of the last map inversion if it failed to converge.
+    flux_failure: Rc<Cell<Option<[f64; 2]>>>,
+}
 
-    // ── 2. Clear outputs from any previous simulation run ───────────────────
-    for &id in &all_ids {
-        if let Some(node) = snarl.get_node_mut(id) {
-            match node {
-                SimNode::Electrical(e) => {
-                    e.output_i_d = None;
-                    e.output_i_q = None;
-                }
-                SimNode::Mechanical(m) => {
-                    m.output_omega_m = None;
-                    m.output_theta_e = None;
-                }
-                SimNode::Torque(t) => {
-                    t.output_t_e = None;
-                }
-                SimNode::InversePark(p) => {
-                    p.output_f_abc = None;
-                }
-                SimNode::Park(p) => {
-                    p.output_f_d = None;
-                    p.output_f_q = None;
-                }
-                SimNode::Constant(c) => {
-                    c.output_port_value = None;
-                }
-                SimNode::Plot(_) => {}
+impl Supply {
+    /// Number of ODE states including the optional DC-link state.
+    fn n_states(&self) -> usize {
+        N_STATES + usize::from(self.link.is_some())
+    }
+
+    /// Motor currents and angle from an ODE state vector (bus voltage unset).
+    fn motor_state<V: Index<usize, Output = f64>>(&self, x: &V) -> CoupledState {
+        let state = CoupledState::from_states(x);
+        match &self.flux_map {
+            Some(map) => {
+                // The map covers the fundamental; harmonic PM flux is added on top
+                let pm = self.harmonics.pm_flux(self.lambda_m, x[S_TE]);
+                let (psi_d, psi_q) = (x[S_ID] - pm.psi[0], x[S_IQ] - pm.psi[1]);
+                // Successive evaluations are close, so Newton starts from the
+                // last converged currents and falls back to the linearised map
+                let converged = self
+                    .flux_guess
+                    .get()
+                    .and_then(|guess| map.currents(psi_d, psi_q, guess))
+                    .or_else(|| map.currents(psi_d, psi_q, map.linear_currents(psi_d, psi_q)));
+                // An unconverged inversion poisons the step so the integrator
+                // rejects it; the failure is reported if the run then fails
+                self.flux_failure
+                    .set(converged.is_none().then_some([psi_d, psi_q]));
+                let (i_d, i_q) = converged.map_or((f64::NAN, f64::NAN), |currents| {
+                    self.flux_guess.set(Some(currents));
+                    currents
+                });
+                CoupledState { i_d, i_q, ..state }
             }
+            None => state,
         }
     }
 
-    // ── 3. Locate ODE and algebraic nodes (first occurrence wins) ───────────
-    let mut elec_id: Option<NodeId> = None;
-    let mut mech_id: Option<NodeId> = None;
-    let mut torque_id: Option<NodeId> = None;
-    let mut inv_park_id: Option<NodeId> = None;
-    let mut park_id: Option<NodeId> = None;
+    /// The error of a failed run: a flux map that could not be inverted in
+    /// the last evaluation, or `error` itself.
+    fn failure(&self, error: SimError) -> SimError {
+        self.flux_failure.get().map_or(error, flux_inversion_error)
+    }
 
-    for (id, node) in snarl.node_ids() {
-        match node {
-            SimNode::Electrical(_) if elec_id.is_none() => elec_id = Some(id),
-            SimNode::Mechanical(_) if mech_id.is_none() => mech_id = Some(id),
-            SimNode::Torque(_) if torque_id.is_none() => torque_id = Some(id),
-            SimNode::InversePark(_) if inv_park_id.is_none() => inv_park_id = Some(id),
-            SimNode::Park(_) if park_id.is_none() => park_id = Some(id),
-            _ => {}
-        }
+    /// Evaluate the supply from an ODE state vector.
+    fn at<V: Index<usize, Output = f64>>(&self, t: f64, x: &V) -> SupplyPoint {
+        let v_c = self.link.as_ref().map(|_| x[S_VC]);
+        self.eval(t, self.motor_state(x), v_c)
     }
 
-    let elec_id = elec_id.ok_or(SimError::NoOdeNodes)?;
-    let mech_id = mech_id.ok_or(SimError::NoOdeNodes)?;
+    /// Evaluate the supply for the given motor state and capacitor voltage.
+    fn eval(&self, t: f64, state: CoupledState, v_c: Option<f64>) -> SupplyPoint {
+        let point = |state: CoupledState| {
+            let (v_d, v_q) = self.voltage.eval(t, state);
+            SupplyPoint {
+                state,
+                v_d,
+                v_q,
+                p_e: 1.5 * (v_d * state.i_d + v_q * state.i_q),
+            }
+        };
+        match (&self.link, v_c) {
+            (Some(link), Some(v_c)) => {
+                let first = point(CoupledState { v_dc: v_c, ..state });
+                point(CoupledState {
+                    v_dc: link.bus_voltage(v_c, first.p_e),
+                    ..state
+                })
+            }
+            _ => point(state),
+        }
+    }
 
-    // ── 4. Extract parameters from the Electrical node ──────────────────────
-    let (r_s, l_d, l_q, lambda_m, n_p_elec, i_d_0, i_q_0) = {
-        let node = snarl
-            .get_node(elec_id)
-            .ok_or_else(|| SimError::GraphError("electrical node vanished".to_owned()))?;
-        let SimNode::Electrical(e) = node else {
-            return Err(SimError::GraphError("expected electrical node".to_owned()));
+    /// Directional derivative of `(v_d, v_q, dv_C/dt)` along the state direction `v`.
+    ///
+    /// Without a DC link only `θ_e` enters the supply; with one, the bus
+    /// couples every state, so a central difference is used.
+    fn jvp<V: Index<usize, Output = f64>>(&self, t: f64, x: &V, v: &V) -> (f64, f64, f64) {
+        let Some(link) = &self.link else {
+            let (dvd_dte, dvq_dte) = self.voltage.dtheta(t, self.motor_state(x));
+            return (dvd_dte * v[S_TE], dvq_dte * v[S_TE], 0.0);
+        };
+        let scale = [v[S_ID], v[S_IQ], v[S_TE], v[S_VC]]
+            .iter()
+            .fold(0.0_f64, |m, d| m.max(d.abs()));
+        if scale == 0.0 {
+            return (0.0, 0.0, 0.0);
+        }
+        let h = 1e-6 / scale;
+        let terms = |step: f64| {
+            let shifted: [f64; N_STATES + 1] = std::array::from_fn(|k| x[k] + step * v[k]);
+            let point = self.at(t, &shifted);
+            (
+                point.v_d,
+                point.v_q,
+                link.dv_c(shifted[S_VC], point.state.v_dc),
+            )
         };
-        (e.r_s, e.l_d, e.l_q, e.lambda_m, e.n_p, e.i_d_0, e.i_q_0)
+        let (vd_plus, vq_plus, vc_plus) = terms(h);
+        let (vd_minus, vq_minus, vc_minus) = terms(-h);
+        (
+            (vd_plus - vd_minus) / (2.0 * h),
+            (vq_plus - vq_minus) / (2.0 * h),
+            (vc_plus - vc_minus) / (2.0 * h),
+        )
+    }
+}
+
+/// Duty-cycle and averaged phase-voltage series produced by an SVPWM node.
+type SvpwmSeries = (Vec<[f64; 4]>, Vec<[f64; 4]>);
+
+/// Stator flux linkage `(ψ_d, ψ_q)` and its slopes `(∂ψ_d/∂x_d, ∂ψ_q/∂x_q)`.
+///
+/// With a flux map the electrical states are the flux linkages themselves;
+/// otherwise they are currents and `ψ_d = L_d·i_d + λ_m`, `ψ_q = L_q·i_q`.
+fn stator_flux<X, P>(x: &X, p: &P, flux_states: bool) -> ([f64; 2], [f64; 2])
+where
+    X: Index<usize, Output = f64>,
+    P: Index<usize, Output = f64>,
+{
+    if flux_states {
+        ([x[S_ID], x[S_IQ]], [1.0, 1.0])
+    } else {
+        (
+            [p[P_LD] * x[S_ID] + p[P_LAM], p[P_LQ] * x[S_IQ]],
+            [p[P_LD], p[P_LQ]],
+        )
+    }
+}
+
+/// The machine states `[x_d, x_q, ω_m, θ_e]` of an ODE state vector.
+fn rotor_states<X: Index<usize, Output = f64>>(x: &X) -> [f64; 4] {
+    [x[S_ID], x[S_IQ], x[S_WM], x[S_TE]]
+}
+
+/// Rotor-frame equations of the machine at parameter vector `p`.
+fn rotor_frame<'a, P: Index<usize, Output = f64>>(supply: &'a Supply, p: &P) -> RotorFrame<'a> {
+    RotorFrame {
+        params: PmsmParams {
+            r_s: p[P_RS],
+            l_d: p[P_LD],
+            l_q: p[P_LQ],
+            lambda_m: p[P_LAM],
+            n_p: p[P_NP],
+            j: p[P_J],
+            b: p[P_B],
+            torque_n_p: p[P_T_NP],
+            torque_lambda_m: p[P_T_LAM],
+            torque_l_d: p[P_T_LD],
+            torque_l_q: p[P_T_LQ],
+        },
+        harmonics: &supply.harmonics,
+    }
+}
+
+/// Electromagnetic torque `T_e` at an ODE state, including the harmonic PM flux
+/// and cogging.
+///
+/// `state` holds the currents (converted from flux linkages with a flux map).
+fn electromagnetic_torque<X, P>(supply: &Supply, x: &X, p: &P, state: CoupledState) -> f64
+where
+    X: Index<usize, Output = f64>,
+    P: Index<usize, Output = f64>,
+{
+    let model = rotor_frame(supply, p);
+    if supply.flux_map.is_some() {
+        model.flux_torque(rotor_states(x), [state.i_d, state.i_q])
+    } else {
+        model.torque(rotor_states(x))
+    }
+}
+
+/// Copper and iron loss `[P_cu, P_fe]` at an ODE state.
+///
+/// `P_cu = (3/2)·R_s·(i_d² + i_q²)` with `R_s` from `p`, so a parameter
+/// vector taken at the winding temperature gives the hot copper loss;
+/// `P_fe = T_fe·ω_m` (see [`IronLoss`]).
+fn pmsm_losses<X, P>(supply: &Supply, iron_loss: &IronLoss, x: &X, p: &P) -> [f64; 2]
+where
+    X: Index<usize, Output = f64>,
+    P: Index<usize, Output = f64>,
+{
+    let state = supply.motor_state(x);
+    let p_cu = 1.5 * p[P_RS] * (state.i_d * state.i_d + state.i_q * state.i_q);
+    let ([psi_d, psi_q], _) = stator_flux(x, p, supply.flux_map.is_some());
+    [p_cu, iron_loss.power(p[P_NP], x[S_WM], psi_d.hypot(psi_q))]
+}
+
+/// Parameter vector at an ODE state: `p` itself, or with `R_s` and `λ_m`
+/// at the thermal network's temperatures when it feeds them back.
+fn params_at<P, X>(p: &P, x: &X, thermal: Option<&ThermalModel>) -> [f64; N_PARAMS]
+where
+    P: Index<usize, Output = f64>,
+    X: Index<usize, Output = f64>,
+{
+    thermal.map_or_else(|| std::array::from_fn(|k| p[k]), |th| th.params(p, x))
+}
+
+/// Directional derivative of the PMSM losses along the state direction `v`,
+/// by central difference; `p` is the parameter vector before temperature
+/// scaling.
+fn loss_jvp<P, X>(
+    supply: &Supply,
+    iron_loss: &IronLoss,
+    thermal: &ThermalModel,
+    p: &P,
+    x: &X,
+    v: &X,
+    n_states: usize,
+) -> [f64; 2]
+where
+    P: Index<usize, Output = f64>,
+    X: Index<usize, Output = f64>,
+{
+    let scale = (0..n_states).fold(0.0_f64, |m, k| m.max(v[k].abs()));
+    if scale == 0.0 {
+        return [0.0; 2];
+    }
+    let h = 1e-6 / scale;
+    let losses = |step: f64| {
+        let shifted: Vec<f64> = (0..n_states).map(|k| x[k] + step * v[k]).collect();
+        pmsm_losses(supply, iron_loss, &shifted, &thermal.params(p, &shifted))
     };
+    let ([cu_plus, fe_plus], [cu_minus, fe_minus]) = (losses(h), losses(-h));
+    [
+        (cu_plus - cu_minus) / (2.0 * h),
+        (fe_plus - fe_minus) / (2.0 * h),
+    ]
+}
 
-    // ── 5. Extract parameters from the Mechanical node ──────────────────────
-    let (j, b, n_p_mech, omega_m_0, theta_e_0) = {
-        let node = snarl
-            .get_node(mech_id)
-            .ok_or_else(|| SimError::GraphError("mechanical node vanished".to_owned()))?;
-        let SimNode::Mechanical(m) = node else {
-            return Err(SimError::GraphError("expected mechanical node".to_owned()));
-        };
-        (m.j, m.b, m.n_p, m.omega_m_0, m.theta_e_0)
+/// Evaluate an SVPWM node, sampling each input pin on a common time grid.
+///
+/// The grid is that of the `θ_e` signal when one is connected, so that a
+/// downstream Park transform zips aligned samples; otherwise `ts` is used.
+/// Unconnected inputs read as zero (a zero DC-link voltage yields zero output).
+fn compute_svpwm(snarl: &Snarl<SimNode>, node: NodeId, ts: &[f64]) -> Option<SvpwmSeries> {
+    let SimNode::Svpwm(svpwm) = snarl.get_node(node)? else {
+        return None;
+    };
+    // SvpwmNode pin layout: 0 = u1, 1 = u2, 2 = θ_e, 3 = V_dc
+    let theta_ts: Option<Vec<f64>> =
+        get_signal_input(snarl, node, 2).map(|theta| theta.iter().map(|s| s[0]).collect());
+    let ts = theta_ts.as_deref().unwrap_or(ts);
+    let sample = |input: usize| -> Vec<[f64; 2]> {
+        let ext = resolve_external_input(snarl, node, input);
+        ts.iter().map(|&t| [t, ext.at(t)]).collect()
     };
+    Some(svpwm.compute(&sample(0), &sample(1), &sample(2), &sample(3)))
+}
 
-    // ── 6. Extract parameters from the Torque node (fall back to Electrical) ─
-    // The torque equation uses its own copy of (N_p, λ_m, L_d, L_q) so the
-    // user can tune it independently from the ODE parameters.
-    let (torque_n_p, torque_lambda_m, torque_l_d, torque_l_q) = if let Some(tid) = torque_id {
-        let node = snarl
-            .get_node(tid)
-            .ok_or_else(|| SimError::GraphError("torque node vanished".to_owned()))?;
-        let SimNode::Torque(t) = node else {
-            return Err(SimError::GraphError("expected torque node".to_owned()));
-        };
-        (t.n_p, t.lambda_m, t.l_d, t.l_q)
-    } else {
-        // No dedicated Torque node — share the Electrical node's parameters.
-        (n_p_elec, lambda_m, l_d, l_q)
+/// Detect an SVPWM modulator feeding `pin` whose `θ_e` is the ODE state.
+///
+/// Returns `None` unless `pin` is wired to the SVPWM output `output`
+/// (0 = `d_abc`, 1 = `v_abc`), the modulator works in the rotating frame, and
+/// its `θ_e` input comes from the Mechanical node.
+fn resolve_svpwm_coupling(
+    snarl: &Snarl<SimNode>,
+    pin: InPinId,
+    output: usize,
+    mech: NodeId,
+    link: Option<NodeId>,
+) -> Option<SvpwmCoupling> {
+    let remote = *snarl.in_pin(pin).remotes.first()?;
+    if remote.output != output {
+        return None;
+    }
+    let SimNode::Svpwm(svpwm) = snarl.get_node(remote.node)? else {
+        return None;
     };
+    if svpwm.frame != ReferenceFrame::Rotating {
+        return None;
+    }
+    let theta_pin = snarl.in_pin(InPinId {
+        node: remote.node,
+        input: 2,
+    });
+    if theta_pin.remotes.first().is_none_or(|r| r.node != mech) {
+        return None;
+    }
+    Some(SvpwmCoupling {
+        node: svpwm.clone(),
+        u1: resolve_external_input(snarl, remote.node, 0),
+        u2: resolve_external_input(snarl, remote.node, 1),
+        v_dc: resolve_bus_voltage(snarl, remote.node, 3, link),
+    })
+}
 
-    // ── 7. Pre-generate Constant Signal/Vector outputs ─────────────────────────
-    // Constants adapted to Signal or Vector mode need time-series data
-    // populated *before* we resolve ODE external inputs, so that
-    // `get_signal_input` can read from them (and by extension, the Park
-    // transform can consume them).
-    let n_pre = 1000_usize;
-    let ts_pre: Vec<f64> = (0..n_pre)
-        .map(|i| {
-            config.t_start + (config.t_end - config.t_start) * (i as f64) / (n_pre as f64 - 1.0)
+/// Detect a switched inverter feeding the Park node whose `θ_e` is the ODE state.
+///
+/// Returns `None` unless the Park `f_abc` input is wired to an Inverter
+/// output. The inverter duty cycles are folded in too when they come from an
+/// ODE-coupled SVPWM; phase currents are taken from the ODE states.
+fn resolve_inverter_coupling(
+    snarl: &Snarl<SimNode>,
+    park: NodeId,
+    mech: NodeId,
+    link: Option<NodeId>,
+) -> Option<InverterCoupling> {
+    let remote = *snarl
+        .in_pin(InPinId {
+            node: park,
+            input: 0,
         })
-        .collect();
-    for &id in &all_ids {
-        if let Some(SimNode::Constant(c)) = snarl.get_node(id) {
-            let port_value = match c.output_type {
-                PortType::Signal => {
-                    let series: Vec<[f64; 2]> = ts_pre.iter().map(|&t| [t, c.value]).collect();
-                    Some(PortValue::Signal(series))
-                }
-                PortType::Vector => {
-                    let (a, b_val, cv) = (c.value, c.value_b, c.value_c);
-                    let series: Vec<[f64; 4]> = ts_pre.iter().map(|&t| [t, a, b_val, cv]).collect();
-                    Some(PortValue::Vector(series))
-                }
-                PortType::Scalar => None,
-            };
-            if let Some(pv) = port_value
-                && let Some(SimNode::Constant(c)) = snarl.get_node_mut(id)
-            {
-                c.output_port_value = Some(pv);
-            }
-        }
-    }
+        .remotes
+        .first()?;
+    let SimNode::Inverter(inverter) = snarl.get_node(remote.node)? else {
+        return None;
+    };
+    // InverterNode pin layout: 0 = d_abc, 1 = V_dc, 2 = i_abc
+    let duty_pin = InPinId {
+        node: remote.node,
+        input: 0,
+    };
+    let duty = match resolve_svpwm_coupling(snarl, duty_pin, 0, mech, link) {
+        Some(coupling) => DutySource::Svpwm(Box::new(coupling)),
+        None => DutySource::Series(get_vector_input(snarl, remote.node, 0).unwrap_or_default()),
+    };
+    Some(InverterCoupling {
+        node: inverter.clone(),
+        duty,
+        v_dc: resolve_bus_voltage(snarl, remote.node, 1, link),
+        legs: Rc::new(Cell::new([LegState::Lower; 3])),
+    })
+}
 
-    // ── 7b. Pre-compute Park transform if inputs are available ─────────────
-    // This populates Park f_d / f_q so they can feed Electrical v_d / v_q.
-    if let Some(pid) = park_id {
-        let f_abc = get_vector_input(snarl, pid, 0);
-        let theta_e = get_signal_input(snarl, pid, 1);
+/// Evaluate an Inverter node over `[t_start, t_end]` from its wired inputs.
+///
+/// When `i_abc` is not wired, `currents` supplies the phase currents (e.g.
+/// from the solved motor states); without either, currents read as zero.
+fn compute_inverter(
+    snarl: &Snarl<SimNode>,
+    node: NodeId,
+    t_start: f64,
+    t_end: f64,
+    currents: Option<&dyn Fn(f64) -> [f64; 3]>,
+) -> Option<Vec<[f64; 4]>> {
+    let SimNode::Inverter(inverter) = snarl.get_node(node)? else {
+        return None;
+    };
+    let duty = get_vector_input(snarl, node, 0).unwrap_or_default();
+    let v_dc = resolve_external_input(snarl, node, 1);
+    let i_abc = get_vector_input(snarl, node, 2);
+    Some(inverter.compute(
+        t_start,
+        t_end,
+        |t| interpolate_vector(&duty, t),
+        |t| v_dc.at(t),
+        |t| match (&i_abc, currents) {
+            (Some(series), _) => interpolate_vector(series, t),
+            (None, Some(f)) => f(t),
+            (None, None) => [0.0; 3],
+        },
+    ))
+}
 
-        if let (Some(f_abc), Some(theta_e)) = (f_abc, theta_e) {
-            let (f_d_series, f_q_series) = crate::nodes::park::ParkNode::compute(&f_abc, &theta_e);
+/// Solution matrix, output times and the inverter leg states at each output.
+type SwitchedSolution = (M, Vec<f64>, Vec<[LegState; 3]>);
 
-            if let Some(SimNode::Park(park)) = snarl.get_node_mut(pid) {
-                park.output_f_d = Some(PortValue::Signal(f_d_series));
-                park.output_f_q = Some(PortValue::Signal(f_q_series));
+/// Integrate up to `t_end`, stopping exactly at every inverter switching edge.
+///
+/// Duty cycles are sampled at the start of each carrier period from the
+/// current solver state (`supply` provides the bus voltage). Between edges the leg states are constant; after each
+/// edge the state derivative is refreshed and the BDF history reset to first
+/// order, so no step straddles a discontinuity.
+///
+/// Returns the solution like [`OdeSolverMethod::solve`], plus the leg states
+/// in force at each output column.
+///
+/// # Errors
+///
+/// [`SimError::SolverFailed`] if a step fails or a stop time is rejected.
+fn solve_switched<'a, Eqn, S>(
+    solver: &mut S,
+    supply: &Supply,
+    inverter: &InverterCoupling,
+    t_end: f64,
+) -> Result<SwitchedSolution, SimError>
+where
+    Eqn: OdeEquations<T = f64, V = NalgebraVec<f64>, M = M, C = NalgebraContext> + 'a,
+    S: OdeSolverMethod<'a, Eqn>,
+{
+    let fail = |e: DiffsolError| SimError::SolverFailed(format!("{e:?}"));
+    let n_states = solver.state().y.len();
+    let mut ys = solver
+        .problem()
+        .context()
+        .dense_mat_zeros::<NalgebraVec<f64>>(n_states, 64);
+    let mut ts = Vec::new();
+    let mut legs_log = Vec::new();
+    let mut write_out = |solver: &S, ys: &mut M, ts: &mut Vec<f64>| {
+        let i = ts.len();
+        if i >= ys.ncols() {
+            ys.resize_cols(2 * ys.ncols());
+        }
+        ys.column_mut(i).copy_from(solver.state().y);
+        ts.push(solver.state().t);
+        legs_log.push(inverter.legs.get());
+    };
+    write_out(solver, &mut ys, &mut ts);
+
+    let period = inverter.node.period();
+    let mut t_period = solver.state().t;
+    while t_period < t_end {
+        let state = supply.at(t_period, solver.state().y).state;
+        let duty = inverter.duty.at(t_period, state);
+        for (_, seg_end, legs) in inverter.node.period_segments(t_period, duty) {
+            let seg_end = seg_end.min(t_end);
+            if seg_end <= solver.state().t {
+                continue;
+            }
+            if legs != inverter.legs.get() {
+                inverter.legs.set(legs);
+                let y = solver.state().y.clone();
+                let t = solver.state().t;
+                let mut dy = y.clone();
+                solver.problem().eqn.rhs().call_inplace(&y, t, &mut dy);
+                solver.state_mut().dy.copy_from(&dy);
             }
+            match solver.set_stop_time(seg_end) {
+                Ok(()) => {}
+                // Sliver segments within the solver's round-off of `t` are already reached.
+                Err(DiffsolError::OdeSolverError(OdeSolverError::StopTimeAtCurrentTime)) => {
+                    continue;
+                }
+                Err(e) => return Err(fail(e)),
+            }
+            while !matches!(
+                solver.step().map_err(fail)?,
+                OdeSolverStopReason::TstopReached
+            ) {
+                write_out(solver, &mut ys, &mut ts);
+            }
+            write_out(solver, &mut ys, &mut ts);
         }
+        t_period += period;
     }
+    ys.resize_cols(ts.len());
+    Ok((ys, ts, legs_log))
+}
 
-    // ── 7c. Resolve external ODE forcing inputs ─────────────────────────────
-    // Check if v_d/v_q come from a Park node whose theta_e is ODE-coupled
-    // (circular dependency: Electrical ← Park ← Mechanical theta_e).
-    // When detected, the Park transform is folded into the RHS closure so it
-    // evaluates at the instantaneous theta_e state during integration.
-    let voltage_source = 'voltage: {
-        if let Some(pid) = park_id {
-            let vd_pin = snarl.in_pin(InPinId {
-                node: elec_id,
-                input: 0,
-            });
-            let vd_from_park = vd_pin.remotes.first().is_some_and(|r| r.node == pid);
+/// Type-erased right-hand side `f(t, x, ẋ)` for [`solve_numeric`].
+type Rhs = Rc<dyn Fn(f64, &[f64], &mut [f64])>;
 
-            if vd_from_park {
-                let theta_pin = snarl.in_pin(InPinId {
-                    node: pid,
-                    input: 1,
-                });
-                let theta_from_mech = theta_pin.remotes.first().is_some_and(|r| r.node == mech_id);
-
-                if theta_from_mech && let Some(f_abc) = get_vector_input(snarl, pid, 0) {
-                    break 'voltage VoltageSource::ParkCoupled { f_abc };
-                }
-            }
-        }
-        // Default: resolve v_d, v_q independently from the graph.
-        VoltageSource::Direct {
-            v_d: resolve_external_input(snarl, elec_id, 0),
-            v_q: resolve_external_input(snarl, elec_id, 1),
-        }
-    };
-    // MechanicalNode pin layout: 0 = T_e (Signal), 1 = T_L (Signal)
-    let t_l_input = resolve_external_input(snarl, mech_id, 1);
-
-    // Use pole-pair count from the Mechanical node throughout the ODE.
-    let n_p = n_p_mech;
-
-    // ── 8. Assemble parameter vector ─────────────────────────────────────────
-    // Element order must match the P_* constants defined above.
-    // v_d, v_q, and T_L are no longer in the parameter vector — they are
-    // captured as `ExternalInput` values in the RHS closure.
-    let p = vec![
-        r_s,
-        l_d,
-        l_q,
-        lambda_m,
-        n_p, // P_RS … P_NP
-        j,
-        b, // P_J, P_B
-        torque_n_p,
-        torque_lambda_m,
-        torque_l_d,
-        torque_l_q, // P_T_NP … P_T_LQ
-    ];
+/// Discrete state (e.g. a commutation sector) of a piecewise-smooth
+/// right-hand side, held fixed within each step of [`solve_numeric`].
+trait Switching {
+    /// Whether the discrete state at `(t, x)` differs from the one in force.
+    fn pending(&self, t: f64, x: &[f64]) -> bool;
+    /// Adopt the discrete state at `(t, x)`.
+    fn commit(&self, t: f64, x: &[f64]);
+}
 
-    // ── 9. Build and solve the ODE ────────────────────────────────────────────
-    //
-    // State vector:  [i_d, i_q, ω_m, θ_e]
-    //
-    // ODE system (Park-frame PMSM + rigid-rotor mechanics):
-    //   di_d/dt = (1/L_d) * (v_d - R_s·i_d + N_p·ω_m·L_q·i_q)
-    //   di_q/dt = (1/L_q) * (v_q - R_s·i_q - N_p·ω_m·(L_d·i_d + λ_m))
-    //   dω_m/dt = (1/J)  * (T_e - T_L - B·ω_m)
-    //   dθ_e/dt = N_p · ω_m
-    //
-    // where  T_e = (3/2) · N_p · (λ_m·i_q + (L_d - L_q)·i_d·i_q)
+/// Integrate `ẋ = rhs(t, x)` from `(t0, y0)` to `t1`, with the Jacobian
+/// approximated by a forward difference of `rhs`.
+///
+/// With `switching`, a step that ends in a different discrete state is cut
+/// back to the switching instant (located by bisection on the step's
+/// interpolant), the new state is committed and the BDF history restarts
+/// there, so no step straddles a discontinuity.
+///
+/// Returns the sample times and the state at each of them.
+///
+/// # Errors
+///
+/// [`SimError::SolverFailed`] if the problem cannot be built or a step fails.
+fn solve_numeric(
+    rhs: &Rhs,
+    switching: Option<&dyn Switching>,
+    config: &SimConfig,
+    t0: f64,
+    t1: f64,
+    y0: Vec<f64>,
+) -> Result<(Vec<f64>, Vec<Vec<f64>>), SimError> {
+    let fail = |e: DiffsolError| SimError::SolverFailed(format!("{e:?}"));
+    let n = y0.len();
+    if let Some(switching) = switching {
+        switching.commit(t0, &y0);
+    }
+    let mut ts = vec![t0];
+    let mut states = vec![y0.clone()];
     let problem = OdeBuilder::<M>::new()
-        .t0(config.t_start)
+        .t0(t0)
         .rtol(config.rtol)
-        .atol([config.atol; N_STATES])
-        .p(p)
+        .atol(vec![config.atol; n])
         .rhs_implicit(
-            // ── RHS: compute time derivatives ──────────────────────────────
             {
-                let voltage_src = voltage_source.clone();
-                let t_l_ext = t_l_input.clone();
-                move |x, p, t, y| {
-                    let (v_d, v_q) = voltage_src.eval(t, x[S_TE]);
-                    let t_l = t_l_ext.at(t);
-
-                    // Electromagnetic torque (used in the mechanical equation below)
-                    let t_e = 1.5
-                        * p[P_T_NP]
-                        * (p[P_T_LAM] * x[S_IQ] + (p[P_T_LD] - p[P_T_LQ]) * x[S_ID] * x[S_IQ]);
-
-                    // d-axis current
-                    y[S_ID] = (1.0 / p[P_LD])
-                        * (v_d - p[P_RS] * x[S_ID] + p[P_NP] * x[S_WM] * p[P_LQ] * x[S_IQ]);
-
-                    // q-axis current
-                    y[S_IQ] = (1.0 / p[P_LQ])
-                        * (v_q
-                            - p[P_RS] * x[S_IQ]
-                            - p[P_NP] * x[S_WM] * p[P_LD] * x[S_ID]
-                            - p[P_NP] * x[S_WM] * p[P_LAM]);
-
-                    // Mechanical speed
-                    y[S_WM] = (1.0 / p[P_J]) * (t_e - t_l - p[P_B] * x[S_WM]);
-
-                    // Electrical angle
-                    y[S_TE] = p[P_NP] * x[S_WM];
-                }
+                let rhs = Rc::clone(rhs);
+                move |x, _p, t, y| rhs(t, x.as_slice(), y.as_mut_slice())
             },
-            // ── Jacobian-vector product J·v where J = ∂f/∂x ─────────────
             {
-                let voltage_src_jac = voltage_source.clone();
-                let park_coupled = voltage_source.is_park_coupled();
-                move |x, p, t, v, y| {
-                    // Partial derivatives of T_e w.r.t. state components
-                    let dt_e_did = 1.5 * p[P_T_NP] * (p[P_T_LD] - p[P_T_LQ]) * x[S_IQ];
-                    let dt_e_diq =
-                        1.5 * p[P_T_NP] * (p[P_T_LAM] + (p[P_T_LD] - p[P_T_LQ]) * x[S_ID]);
-
-                    // ∂(di_d/dt)/∂(i_d, i_q, ω_m)
-                    let did_did = -p[P_RS] / p[P_LD];
-                    let did_diq = p[P_NP] * x[S_WM] * p[P_LQ] / p[P_LD];
-                    let did_dwm = p[P_NP] * p[P_LQ] * x[S_IQ] / p[P_LD];
-
-                    // ∂(di_q/dt)/∂(i_d, i_q, ω_m)
-                    let diq_did = -p[P_NP] * x[S_WM] * p[P_LD] / p[P_LQ];
-                    let diq_diq = -p[P_RS] / p[P_LQ];
-                    let diq_dwm = (-p[P_NP] * p[P_LD] * x[S_ID] - p[P_NP] * p[P_LAM]) / p[P_LQ];
-
-                    // ∂(dω_m/dt)/∂(i_d, i_q, ω_m)
-                    let dwm_did = dt_e_did / p[P_J];
-                    let dwm_diq = dt_e_diq / p[P_J];
-                    let dwm_dwm = -p[P_B] / p[P_J];
-
-                    // ∂(dθ_e/dt)/∂ω_m  (only non-zero entry in the θ_e row)
-                    let dte_dwm = p[P_NP];
-
-                    // Park-coupled θ_e dependence: dv_d/dθ = v_q, dv_q/dθ = -v_d
-                    let (did_dte, diq_dte) = if park_coupled {
-                        let (v_d, v_q) = voltage_src_jac.eval(t, x[S_TE]);
-                        (
-                            v_q / p[P_LD],  // (1/L_d) · dv_d/dθ = (1/L_d) · v_q
-                            -v_d / p[P_LQ], // (1/L_q) · dv_q/dθ = (1/L_q) · (-v_d)
-                        )
-                    } else {
-                        (0.0, 0.0)
-                    };
-
-                    // J·v
-                    y[S_ID] = did_did * v[S_ID]
-                        + did_diq * v[S_IQ]
-                        + did_dwm * v[S_WM]
-                        + did_dte * v[S_TE];
-                    y[S_IQ] = diq_did * v[S_ID]
-                        + diq_diq * v[S_IQ]
-                        + diq_dwm * v[S_WM]
-                        + diq_dte * v[S_TE];
-                    y[S_WM] = dwm_did * v[S_ID] + dwm_diq * v[S_IQ] + dwm_dwm * v[S_WM];
-                    y[S_TE] = dte_dwm * v[S_WM];
+                let rhs = Rc::clone(rhs);
+                move |x, _p, t, v, y| {
+                    let (x, v) = (x.as_slice(), v.as_slice());
+                    let norm = |s: &[f64]| s.iter().map(|a| a * a).sum::<f64>().sqrt();
+                    let norm_v = norm(v);
+                    if norm_v == 0.0 {
+                        y.as_mut_slice().fill(0.0);
+                        return;
+                    }
+                    let eps = f64::EPSILON.sqrt() * (1.0 + norm(x)) / norm_v;
+                    let shifted: Vec<f64> = x.iter().zip(v).map(|(a, b)| a + eps * b).collect();
+                    let mut f0 = vec![0.0; x.len()];
+                    let mut f1 = vec![0.0; x.len()];
+                    rhs(t, x, &mut f0);
+                    rhs(t, &shifted, &mut f1);
+                    for ((out, hi), lo) in y.as_mut_slice().iter_mut().zip(&f1).zip(&f0) {
+                        *out = (hi - lo) / eps;
+                    }
                 }
             },
         )
-        .init(
-            move |_p, _t, y| {
-                y[S_ID] = i_d_0;
-                y[S_IQ] = i_q_0;
-                y[S_WM] = omega_m_0;
-                y[S_TE] = theta_e_0;
-            },
-            N_STATES,
-        )
+        .init(move |_p, _t, y| y.as_mut_slice().copy_from_slice(&y0), n)
         .build()
         .map_err(|e| SimError::SolverFailed(format!("{e:?}")))?;
+    let mut solver = problem.bdf::<Ls>().map_err(fail)?;
+    if t1 <= t0 {
+        return Ok((ts, states));
+    }
+    solver.set_stop_time(t1).map_err(fail)?;
+    // diffsol counts Newton failures over the whole solve; give every
+    // restart a fresh budget
+    let newton_budget = solver.config().maximum_newton_fails;
+    loop {
+        let t_prev = solver.state().t;
+        let reason = solver.step().map_err(fail)?;
+        let t_now = solver.state().t;
+        if let Some(switching) = switching
+            && switching.pending(t_now, solver.state().y.as_slice())
+        {
+            // Bisect for the first instant at which the discrete state changes
+            let (mut lo, mut hi) = (t_prev, t_now);
+            while hi - lo > 1e-12 * (1.0 + hi.abs()) {
+                let mid = 0.5 * (lo + hi);
+                if switching.pending(mid, solver.interpolate(mid).map_err(fail)?.as_slice()) {
+                    hi = mid;
+                } else {
+                    lo = mid;
+                }
+            }
+            let y = solver.interpolate(hi).map_err(fail)?;
+            switching.commit(hi, y.as_slice());
+            let mut dy = y.clone();
+            rhs(hi, y.as_slice(), dy.as_mut_slice());
+            let state = solver.state_mut();
+            state.y.copy_from(&y);
+            state.dy.copy_from(&dy);
+            *state.t = hi;
+            solver.config_mut().maximum_newton_fails =
+                solver.get_statistics().number_of_nonlinear_solver_fails + newton_budget;
+            ts.push(hi);
+            states.push(y.as_slice().to_vec());
+            if hi >= t1 {
+                break;
+            }
+            // The discarded step may have consumed the stop time
+            solver.set_stop_time(t1).map_err(fail)?;
+            continue;
+        }
+        ts.push(t_now);
+        states.push(solver.state().y.as_slice().to_vec());
+        if matches!(reason, OdeSolverStopReason::TstopReached) {
+            break;
+        }
+    }
+    Ok((ts, states))
+}
 
-    let mut solver = problem
-        .bdf::<Ls>()
-        .map_err(|e| SimError::SolverFailed(format!("{e:?}")))?;
-
-    // ys: DenseMatrix with N_STATES rows, ts.len() columns (one per accepted step).
-    let (ys, ts) = solver
-        .solve(config.t_end)
-        .map_err(|e| SimError::SolverFailed(format!("{e:?}")))?;
-
-    // ── 10. Extract time-series signals from the solution matrix ─────────────
-    let i_d_series: Vec<[f64; 2]> = ts
-        .iter()
-        .enumerate()
-        .map(|(i, &t)| [t, ys.column(i)[S_ID]])
-        .collect();
-
-    let i_q_series: Vec<[f64; 2]> = ts
-        .iter()
-        .enumerate()
-        .map(|(i, &t)| [t, ys.column(i)[S_IQ]])
-        .collect();
-
-    let omega_m_series: Vec<[f64; 2]> = ts
-        .iter()
-        .enumerate()
-        .map(|(i, &t)| [t, ys.column(i)[S_WM]])
-        .collect();
-
-    let theta_e_series: Vec<[f64; 2]> = ts
-        .iter()
-        .enumerate()
-        .map(|(i, &t)| [t, ys.column(i)[S_TE]])
-        .collect();
-
-    // ── 11. Compute algebraic post-processing signals ────────────────────────
-
-    // Electromagnetic torque  T_e = (3/2) · N_p · (λ_m · i_q + (L_d - L_q) · i_d · i_q)
-    let t_e_series: Vec<[f64; 2]> = ts
-        .iter()
-        .enumerate()
-        .map(|(i, &t)| {
-            let i_d = ys.column(i)[S_ID];
-            let i_q = ys.column(i)[S_IQ];
-            let t_e =
-                1.5 * torque_n_p * (torque_lambda_m * i_q + (torque_l_d - torque_l_q) * i_d * i_q);
-            [t, t_e]
-        })
-        .collect();
-
-    // Inverse Park transform if an InversePark node is present in the graph.
-    // f_abc = Park⁻¹(f_d, f_q, θ_e):
-    //   f_a = f_d·cos(θ_e)           − f_q·sin(θ_e)
-    //   f_b = f_d·cos(θ_e − 2π/3)   − f_q·sin(θ_e − 2π/3)
-    //   f_c = f_d·cos(θ_e + 2π/3)   − f_q·sin(θ_e + 2π/3)
-    if let Some(pid) = inv_park_id {
-        let two_thirds_pi = std::f64::consts::PI * 2.0 / 3.0;
-
-        let f_abc_series: Vec<[f64; 4]> = ts
-            .iter()
-            .enumerate()
-            .map(|(i, &t)| {
-                let i_d = ys.column(i)[S_ID];
-                let i_q = ys.column(i)[S_IQ];
-                let theta = ys.column(i)[S_TE];
-
-                let f_a = i_d * theta.cos() - i_q * theta.sin();
-                let f_b = i_d * (theta - two_thirds_pi).cos() - i_q * (theta - two_thirds_pi).sin();
-                let f_c = i_d * (theta + two_thirds_pi).cos() - i_q * (theta + two_thirds_pi).sin();
-                [t, f_a, f_b, f_c]
-            })
-            .collect();
+/// Resample `signal` onto the time stamps of a 3-phase series.
+///
+/// Used before zipping a Park transform's inputs, which may come from
+/// different time grids (e.g. switching edges vs. solver steps).
+fn align_signal(signal: &[[f64; 2]], grid: &[[f64; 4]]) -> Vec<[f64; 2]> {
+    grid.iter()
+        .map(|row| [row[0], interpolate_signal(signal, row[0])])
+        .collect()
+}
 
-        if let Some(SimNode::InversePark(park)) = snarl.get_node_mut(pid) {
-            let f_abc_series = resample_vector(&f_abc_series, config.t_start, config.t_end, config.output_dt);
-            park.output_f_abc = Some(PortValue::Vector(f_abc_series));
+/// Publish Signal/Vector constant outputs sampled on the time grid `ts`.
+///
+/// Scalar constants are left alone; they are read by `get_constant_input`.
+fn publish_constants(snarl: &mut Snarl<SimNode>, ids: &[NodeId], ts: &[f64]) {
+    for &id in ids {
+        if let Some(SimNode::Constant(c)) = snarl.get_node_mut(id) {
+            c.output_port_value = match c.output_type {
+                PortType::Signal => Some(PortValue::Signal(
+                    ts.iter().map(|&t| [t, c.value]).collect(),
+                )),
+                PortType::Vector => {
+                    let (a, b, cv) = (c.value, c.value_b, c.value_c);
+                    Some(PortValue::Vector(
+                        ts.iter().map(|&t| [t, a, b, cv]).collect(),
+                    ))
+                }
+                PortType::Scalar => None,
+            };
         }
     }
+}
 
-    // ── 11b. Resample all series onto a uniform output grid ──────────────
-    let dt = config.output_dt;
-    let t0 = config.t_start;
-    let t1 = config.t_end;
-    let i_d_series = resample_signal(&i_d_series, t0, t1, dt);
-    let i_q_series = resample_signal(&i_q_series, t0, t1, dt);
-    let omega_m_series = resample_signal(&omega_m_series, t0, t1, dt);
-    let theta_e_series = resample_signal(&theta_e_series, t0, t1, dt);
-    let t_e_series = resample_signal(&t_e_series, t0, t1, dt);
+/// Evaluate a Park node from its connected inputs and publish `f_d`, `f_q`.
+///
+/// With `grid = Some((t_start, t_end, dt))` the outputs are resampled onto
+/// the uniform output grid.
+fn update_park(snarl: &mut Snarl<SimNode>, node: NodeId, grid: Option<(f64, f64, f64)>) {
+    let f_abc = get_vector_input(snarl, node, 0);
+    let theta_e = get_signal_input(snarl, node, 1);
+
+    if let (Some(f_abc), Some(theta_e)) = (f_abc, theta_e) {
+        let theta_e = align_signal(&theta_e, &f_abc);
+        let (f_d_series, f_q_series) = crate::nodes::park::ParkNode::compute(&f_abc, &theta_e);
+        let (f_d_series, f_q_series) = match grid {
+            Some((t0, t1, dt)) => (
+                resample_signal(&f_d_series, t0, t1, dt),
+                resample_signal(&f_q_series, t0, t1, dt),
+            ),
+            None => (f_d_series, f_q_series),
+        };
 
-    // ── 12. Write time-series results back into the graph nodes ──────────────
-    if let Some(SimNode::Electrical(e)) = snarl.get_node_mut(elec_id) {
-        e.output_i_d = Some(PortValue::Signal(i_d_series));
-        e.output_i_q = Some(PortValue::Signal(i_q_series));
+        if let Some(SimNode::Park(park)) = snarl.get_node_mut(node) {
+            park.output_f_d = Some(PortValue::Signal(f_d_series));
+            park.output_f_q = Some(PortValue::Signal(f_q_series));
+        }
     }
+}
 
-    if let Some(SimNode::Mechanical(m)) = snarl.get_node_mut(mech_id) {
-        m.output_omega_m = Some(PortValue::Signal(omega_m_series));
-        m.output_theta_e = Some(PortValue::Signal(theta_e_series));
-    }
+/// Modulator, inverter and Park nodes evaluated around a machine solve that
+/// does not fold them into its right-hand side (abc-frame and induction
+/// machines).
+#[derive(Clone, Copy)]
+struct Sources {
+    /// First SVPWM node, if any.
+    svpwm: Option<NodeId>,
+    /// First inverter node, if any.
+    inverter: Option<NodeId>,
+    /// First Park node, if any.
+    park: Option<NodeId>,
+}
 
-    if let Some(tid) = torque_id
-        && let Some(SimNode::Torque(t)) = snarl.get_node_mut(tid)
-    {
-        t.output_t_e = Some(PortValue::Signal(t_e_series));
+impl Sources {
+    /// Publish constants and evaluate the sources on a coarse grid, so the
+    /// machine inputs can be resolved before the solve (steps 7–7b of
+    /// [`run_simulation`]).
+    fn precompute(self, snarl: &mut Snarl<SimNode>, all_ids: &[NodeId], config: &SimConfig) {
+        let (t0, t1) = (config.t_start, config.t_end);
+        let n_pre = 1000_usize;
+        let ts_pre: Vec<f64> = (0..n_pre)
+            .map(|i| t0 + (t1 - t0) * (i as f64) / (n_pre as f64 - 1.0))
+            .collect();
+        publish_constants(snarl, all_ids, &ts_pre);
+        if let Some(sid) = self.svpwm
+            && let Some((duty, v_abc)) = compute_svpwm(snarl, sid, &ts_pre)
+            && let Some(SimNode::Svpwm(svpwm)) = snarl.get_node_mut(sid)
+        {
+            svpwm.output_duty = Some(PortValue::Vector(duty));
+            svpwm.output_v_abc = Some(PortValue::Vector(v_abc));
+        }
+        if let Some(iid) = self.inverter
+            && let Some(v_abc) = compute_inverter(snarl, iid, t0, t1, None)
+            && let Some(SimNode::Inverter(inverter)) = snarl.get_node_mut(iid)
+        {
+            inverter.output_v_abc = Some(PortValue::Vector(v_abc));
+        }
+        if let Some(pid) = self.park {
+            update_park(snarl, pid, None);
+        }
     }
 
-    // ── 12b. Re-generate constant Signal/Vector outputs on the uniform output grid ─
-    // Step 7 pre-generated these on a synthetic grid (`ts_pre`). Now regenerate
-    // on the same uniform grid used for ODE outputs so all series align.
-    let n_out = ((t1 - t0) / dt).ceil() as usize + 1;
-    let ts_uniform: Vec<f64> = (0..n_out).map(|i| (t0 + dt * i as f64).min(t1)).collect();
-    #[expect(clippy::shadow_unrelated, reason = "`b` is local to each scope")]
-    for &id in &all_ids {
-        if let Some(SimNode::Constant(c)) = snarl.get_node(id) {
-            let port_value = match c.output_type {
-                PortType::Signal => {
-                    let series: Vec<[f64; 2]> = ts_uniform.iter().map(|&t| [t, c.value]).collect();
-                    Some(PortValue::Signal(series))
-                }
-                PortType::Vector => {
-                    let (a, b, cv) = (c.value, c.value_b, c.value_c);
-                    let series: Vec<[f64; 4]> = ts_uniform.iter().map(|&t| [t, a, b, cv]).collect();
-                    Some(PortValue::Vector(series))
-                }
-                PortType::Scalar => None, // handled by get_constant_input
-            };
-            if let Some(pv) = port_value
-                && let Some(SimNode::Constant(c)) = snarl.get_node_mut(id)
+    /// Re-evaluate the sources on the output grid once the machine's phase
+    /// currents `i_abc` are known (steps 12b–13 of [`run_simulation`]).
+    fn refresh(
+        self,
+        snarl: &mut Snarl<SimNode>,
+        all_ids: &[NodeId],
+        config: &SimConfig,
+        i_abc: &[[f64; 4]],
+    ) {
+        let (t0, t1, dt) = (config.t_start, config.t_end, config.output_dt);
+        let n_out = ((t1 - t0) / dt).ceil() as usize + 1;
+        let ts_uniform: Vec<f64> = (0..n_out).map(|i| (t0 + dt * i as f64).min(t1)).collect();
+        publish_constants(snarl, all_ids, &ts_uniform);
+        if let Some(sid) = self.svpwm
+            && let Some((duty, v_abc)) = compute_svpwm(snarl, sid, &ts_uniform)
+            && let Some(SimNode::Svpwm(svpwm)) = snarl.get_node_mut(sid)
+        {
+            svpwm.output_duty = Some(PortValue::Vector(duty));
+            svpwm.output_v_abc = Some(PortValue::Vector(v_abc));
+        }
+        if let Some(iid) = self.inverter {
+            let currents = |t: f64| interpolate_vector(i_abc, t);
+            if let Some(v_abc) = compute_inverter(snarl, iid, t0, t1, Some(&currents))
+                && let Some(SimNode::Inverter(inverter)) = snarl.get_node_mut(iid)
             {
-                c.output_port_value = Some(pv);
+                inverter.output_v_abc = Some(PortValue::Vector(v_abc));
             }
         }
+        if let Some(pid) = self.park {
+            update_park(snarl, pid, Some((t0, t1, dt)));
+        }
     }
+}
 
-    // ── 13. Re-compute Park transform at ODE time resolution ────────────────
-    // Step 7b pre-computed this for ODE input resolution. Re-run with
-    // actual data from connected nodes (which may now include ODE outputs).
-    if let Some(pid) = park_id {
-        let f_abc = get_vector_input(snarl, pid, 0);
-        let theta_e = get_signal_input(snarl, pid, 1);
+/// Traverse the graph, solve the PMSM ODE system, and write results into node outputs.
+///
+/// # Errors
+///
+/// - [`SimError::NoOdeNodes`] — the graph contains no `MechanicalNode` or
+///   `TwoMassNode`, or
+///   no `ElectricalNode`, `AbcMachineNode`, `InductionMachineNode`,
+///   `DcMotorNode` or `BldcNode`.
+/// - [`SimError::GraphError`] — parameter extraction fails due to unexpected graph
+///   state (should not occur if the graph was built through the normal UI), or
+///   a `TwoMassNode` or `ThermalNode` is paired with a machine other than the
+///   Electrical node, or an `EfficiencyMapNode` has no Electrical node to
+///   map. A `BodeNode` wired to an unsupported input or output shows the
+///   error itself instead.
+/// - [`SimError::SolverFailed`] — the diffsol BDF integrator encounters a
+///   numerical error (ill-conditioned system, step-size underflow, etc.) in
+///   the machine or an observer, or the thermal network is invalid.
+pub fn run_simulation(snarl: &mut Snarl<SimNode>, config: &SimConfig) -> Result<(), SimError> {
+    // ── 1. Snapshot all node IDs before any mutable access ───────────────────
+    let all_ids: Vec<NodeId> = snarl.node_ids().map(|(id, _)| id).collect();
+    // A steady start begins from the trimmed state; the nodes keep their own
+    // initial conditions
+    let start = if config.start_at_steady_state {
+        Some(steady_state(snarl, config, &all_ids)?.x)
+    } else {
+        None
+    };
+    linearize::run(snarl, config, &all_ids)?;
+    efficiency_map::run(snarl, &all_ids)?;
+    solve_machine(snarl, config, &all_ids, Task::Simulate(start.as_deref()))?;
+    // ── 14. Evaluate the post-processing nodes on the solved signals ────────
+    post::run(snarl, config, &all_ids)
+}
 
-        if let (Some(f_abc), Some(theta_e)) = (f_abc, theta_e) {
-            let (f_d_series, f_q_series) = crate::nodes::park::ParkNode::compute(&f_abc, &theta_e);
+/// Steady-state operating point of the PMSM found by [`trim`].
+#[derive(Clone, Copy, Debug)]
+pub struct OperatingPoint {
+    /// d-axis current `i_d` (A).
+    pub i_d: f64,
+    /// q-axis current `i_q` (A).
+    pub i_q: f64,
+    /// Mechanical speed `ω_m` (rad/s).
+    pub omega_m: f64,
+    /// Shaft twist `φ` of a two-mass drivetrain (rad).
+    pub twist: Option<f64>,
+    /// Electrical node the currents belong to.
+    electrical: NodeId,
+    /// Mechanical or two-mass node the speed belongs to.
+    mechanical: NodeId,
+}
 
-            if let Some(SimNode::Park(park)) = snarl.get_node_mut(pid) {
-                park.output_f_d = Some(PortValue::Signal(resample_signal(&f_d_series, t0, t1, dt)));
-                park.output_f_q = Some(PortValue::Signal(resample_signal(&f_q_series, t0, t1, dt)));
+impl OperatingPoint {
+    /// Write the operating point into the initial conditions `i_d_0`, `i_q_0`
+    /// and `omega_m_0`, and `twist_0` of a two-mass drivetrain, of the nodes
+    /// it was trimmed for.
+    pub fn apply(&self, snarl: &mut Snarl<SimNode>) {
+        if let Some(SimNode::Electrical(e)) = snarl.get_node_mut(self.electrical) {
+            e.i_d_0 = self.i_d;
+            e.i_q_0 = self.i_q;
+        }
+        match snarl.get_node_mut(self.mechanical) {
+            Some(SimNode::Mechanical(m)) => m.omega_m_0 = self.omega_m,
+            Some(SimNode::TwoMass(m)) => {
+                m.omega_m_0 = self.omega_m;
+                m.twist_0 = self.twist.unwrap_or(m.twist_0);
             }
+            _ => {}
         }
     }
+}
+
+/// Find the steady state of the PMSM for the inputs at `t_start`: the
+/// currents and speed at which `di_d/dt`, `di_q/dt` and `dω_m/dt` vanish.
+///
+/// A two-mass drivetrain is trimmed with the rotor, its shaft carrying the
+/// load torque. `θ_e` and the other appended states stay at their initial
+/// values. The graph outputs are cleared as for a simulation run but not
+/// filled.
+///
+/// # Errors
+///
+/// - [`SimError::GraphError`] — the graph has no PMSM Electrical node, or
+///   is driven by a switched inverter, which has no steady state.
+/// - [`SimError::SolverFailed`] — Newton's method does not converge.
+/// - Any error of [`run_simulation`] raised while assembling the equations.
+pub fn trim(snarl: &mut Snarl<SimNode>, config: &SimConfig) -> Result<OperatingPoint, SimError> {
+    let all_ids: Vec<NodeId> = snarl.node_ids().map(|(id, _)| id).collect();
+    let point = steady_state(snarl, config, &all_ids)?;
+    let output = |node, output| point.output(OutPinId { node, output }).unwrap_or(f64::NAN);
+    Ok(OperatingPoint {
+        i_d: output(point.electrical, 0),
+        i_q: output(point.electrical, 1),
+        omega_m: output(point.mechanical, 0),
+        twist: point.twist,
+        electrical: point.electrical,
+        mechanical: point.mechanical,
+    })
+}
 
-    Ok(())
+/// The machine equations evaluated at the steady state found by [`trim`].
+fn steady_state(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    all_ids: &[NodeId],
+) -> Result<Evaluation, SimError> {
+    solve_machine(snarl, config, all_ids, Task::Trim)?.ok_or_else(|| {
+        SimError::GraphError("steady-state trim produced no operating point".to_owned())
+    })
 }
 
-#[cfg(test)]
-mod tests {
-    use egui_snarl::{InPinId, OutPinId, Snarl};
+/// What [`solve_machine`] does with the assembled machine equations.
+#[derive(Clone, Copy)]
+enum Task<'a> {
+    /// Integrate over the time span and write the node outputs, starting
+    /// from the given state or else the nodes' initial conditions.
+    Simulate(Option<&'a [f64]>),
+    /// Stop after assembly and evaluate the equations at the steady state.
+    Trim,
+    /// Stop after assembly and evaluate the equations at the given state.
+    Evaluate(&'a [f64]),
+}
+
+/// The PMSM equations evaluated at one state by [`Task::Trim`] or
+/// [`Task::Evaluate`], at `t_start`.
+struct Evaluation {
+    /// State vector `x`.
+    x: Vec<f64>,
+    /// State names, e.g. `i_d` or `ψ_d`.
+    names: Vec<String>,
+    /// Derivatives `f(x)`.
+    f: DVector<f64>,
+    /// Jacobian `∂f/∂x` from the analytic JVP.
+    a: DMatrix<f64>,
+    /// Output pins of the machine nodes, in row order of `y` and `c`.
+    pins: Vec<OutPinId>,
+    /// Outputs `y(x)`.
+    y: Vec<f64>,
+    /// Output Jacobian `∂y/∂x`.
+    c: DMatrix<f64>,
+    /// Shaft twist `φ` of a two-mass drivetrain.
+    twist: Option<f64>,
+    /// Electrical node of the machine.
+    electrical: NodeId,
+    /// Mechanical or two-mass node of the machine.
+    mechanical: NodeId,
+}
+
+impl Evaluation {
+    /// Value of the output pin `pin`, or `None` if it is not a machine
+    /// output.
+    fn output(&self, pin: OutPinId) -> Option<f64> {
+        let k = self.pins.iter().position(|&p| p == pin)?;
+        self.y.get(k).copied()
+    }
+}
+
+/// Steps 2–13 of [`run_simulation`]: clear the outputs, solve the machine
+/// ODE and write the results into the node outputs. For [`Task::Trim`] and
+/// [`Task::Evaluate`] the solve is replaced by an evaluation of the
+/// equations, which is returned.
+///
+/// # Errors
+///
+/// See [`run_simulation`] and [`trim`].
+#[expect(
+    clippy::too_many_lines,
+    reason = "ODE assembly inherently requires a long function"
+)]
+fn solve_machine(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    all_ids: &[NodeId],
+    task: Task<'_>,
+) -> Result<Option<Evaluation>, SimError> {
+    // ── 2. Clear outputs from any previous simulation run ───────────────────
+    for &id in all_ids {
+        if let Some(node) = snarl.get_node_mut(id) {
+            match node {
+                SimNode::Electrical(e) => {
+                    e.output_i_d = None;
+                    e.output_i_q = None;
+                    e.output_p_cu = None;
+                    e.output_p_fe = None;
+                }
+                SimNode::Mechanical(m) => {
+                    m.output_omega_m = None;
+                    m.output_theta_e = None;
+                }
+                SimNode::TwoMass(m) => {
+                    m.output_omega_m = None;
+                    m.output_theta_e = None;
+                    m.output_omega_l = None;
+                    m.output_t_sh = None;
+                }
+                SimNode::Torque(t) => {
+                    t.output_t_e = None;
+                }
+                SimNode::InversePark(p) => {
+                    p.output_f_abc = None;
+                }
+                SimNode::Park(p) => {
+                    p.output_f_d = None;
+                    p.output_f_q = None;
+                }
+                SimNode::Constant(c) => {
+                    c.output_port_value = None;
+                }
+                SimNode::Svpwm(s) => {
+                    s.output_duty = None;
+                    s.output_v_abc = None;
+                }
+                SimNode::Inverter(inv) => {
+                    inv.output_v_abc = None;
+                }
+                SimNode::DcLink(d) => {
+                    d.output_v_dc = None;
+                    d.output_i_bat = None;
+                    d.output_i_dc = None;
+                }
+                SimNode::AbcMachine(m) => {
+                    m.output_i_abc = None;
+                    m.output_t_e = None;
+                    m.output_i_f = None;
+                }
+                SimNode::Induction(m) => {
+                    m.output_i_sd = None;
+                    m.output_i_sq = None;
+                    m.output_psi_rd = None;
+                    m.output_psi_rq = None;
+                    m.output_t_e = None;
+                }
+                SimNode::DcMotor(m) => {
+                    m.output_i_a = None;
+                    m.output_t_e = None;
+                }
+                SimNode::Bldc(m) => {
+                    m.output_i_abc = None;
+                    m.output_hall = None;
+                    m.output_t_e = None;
+                }
+                SimNode::Thermal(th) => {
+                    th.output_t_w = None;
+                    th.output_t_pm = None;
+                    th.output_t = None;
+                }
+                SimNode::Sensor(s) => {
+                    s.output_theta_e = None;
+                    s.output_omega_m = None;
+                    s.output_sector = None;
+                    s.output_raw = None;
+                }
+                SimNode::Measurement(m) => {
+                    m.output_value = None;
+                }
+                SimNode::Observer(o) => {
+                    o.output_theta_e = None;
+                    o.output_omega_m = None;
+                    o.output_error = None;
+                    o.output_emf = None;
+                }
+                SimNode::Ekf(e) => {
+                    e.output_i_d = None;
+                    e.output_i_q = None;
+                    e.output_omega_m = None;
+                    e.output_theta_e = None;
+                    e.output_param = None;
+                    e.output_error = None;
+                    e.output_sigma_omega = None;
+                    e.output_sigma_theta = None;
+                    e.output_sigma_param = None;
+                }
+                SimNode::Plot(p) => {
+                    p.runs.clear();
+                    p.envelopes.clear();
+                }
+                // The small-signal model and the map are refreshed ahead of
+                // the solve
+                SimNode::Bode(_) | SimNode::EfficiencyMap(_) => {}
+                SimNode::Spectrum(s) => {
+                    s.analysis = None;
+                    s.channels.clear();
+                }
+                SimNode::Metrics(m) => m.metrics = None,
+                SimNode::PowerFlow(pf) => {
+                    pf.balance = None;
+                    pf.output_p_e = None;
+                    pf.output_p_cu = None;
+                    pf.output_p_fe = None;
+                    pf.output_p_m = None;
+                    pf.output_p_fric = None;
+                    pf.output_eta = None;
+                    pf.output_e_in = None;
+                }
+            }
+        }
+    }
+
+    // ── 3. Locate ODE and algebraic nodes (first occurrence wins) ───────────
+    let mut elec_id: Option<NodeId> = None;
+    let mut mech_id: Option<NodeId> = None;
+    let mut torque_id: Option<NodeId> = None;
+    let mut inv_park_id: Option<NodeId> = None;
+    let mut park_id: Option<NodeId> = None;
+    let mut svpwm_id: Option<NodeId> = None;
+    let mut inverter_id: Option<NodeId> = None;
+    let mut dc_link_id: Option<NodeId> = None;
+    let mut abc_id: Option<NodeId> = None;
+    let mut induction_id: Option<NodeId> = None;
+    let mut dc_motor_id: Option<NodeId> = None;
+    let mut bldc_id: Option<NodeId> = None;
+    let mut thermal_id: Option<NodeId> = None;
+    let mut power_id: Option<NodeId> = None;
+
+    for (id, node) in snarl.node_ids() {
+        match node {
+            SimNode::Electrical(_) if elec_id.is_none() => elec_id = Some(id),
+            SimNode::Mechanical(_) | SimNode::TwoMass(_) if mech_id.is_none() => {
+                mech_id = Some(id);
+            }
+            SimNode::Torque(_) if torque_id.is_none() => torque_id = Some(id),
+            SimNode::InversePark(_) if inv_park_id.is_none() => inv_park_id = Some(id),
+            SimNode::Park(_) if park_id.is_none() => park_id = Some(id),
+            SimNode::Svpwm(_) if svpwm_id.is_none() => svpwm_id = Some(id),
+            SimNode::Inverter(_) if inverter_id.is_none() => inverter_id = Some(id),
+            SimNode::DcLink(_) if dc_link_id.is_none() => dc_link_id = Some(id),
+            SimNode::AbcMachine(_) if abc_id.is_none() => abc_id = Some(id),
+            SimNode::Induction(_) if induction_id.is_none() => induction_id = Some(id),
+            SimNode::DcMotor(_) if dc_motor_id.is_none() => dc_motor_id = Some(id),
+            SimNode::Bldc(_) if bldc_id.is_none() => bldc_id = Some(id),
+            SimNode::Thermal(_) if thermal_id.is_none() => thermal_id = Some(id),
+            SimNode::PowerFlow(_) if power_id.is_none() => power_id = Some(id),
+            _ => {}
+        }
+    }
+
+    let mech_id = mech_id.ok_or(SimError::NoOdeNodes)?;
+    // Without a d/q Electrical node, the first alternative machine found
+    // (abc-frame, induction, DC, BLDC) drives the solve.
+    let sources = Sources {
+        svpwm: svpwm_id,
+        inverter: inverter_id,
+        park: park_id,
+    };
+    let Some(elec_id) = elec_id else {
+        if !matches!(task, Task::Simulate(_)) {
+            return Err(SimError::GraphError(
+                "the steady-state trim requires the PMSM Electrical node".to_owned(),
+            ));
+        }
+        if let Some(SimNode::TwoMass(_)) = snarl.get_node(mech_id) {
+            return Err(SimError::GraphError(
+                "the two-mass drivetrain requires the PMSM Electrical node".to_owned(),
+            ));
+        }
+        if thermal_id.is_some() {
+            return Err(SimError::GraphError(
+                "the thermal network requires the PMSM Electrical node".to_owned(),
+            ));
+        }
+        if power_id.is_some() {
+            return Err(SimError::GraphError(
+                "the power-flow node requires the PMSM Electrical node".to_owned(),
+            ));
+        }
+        if let Some(machine) = abc_id {
+            return abc::run(snarl, config, all_ids, machine, mech_id, sources).map(|()| None);
+        }
+        if let Some(machine) = induction_id {
+            return induction::run(snarl, config, all_ids, machine, mech_id, sources)
+                .map(|()| None);
+        }
+        if let Some(motor) = dc_motor_id {
+            return dc_motor::run(snarl, config, all_ids, motor, mech_id, sources).map(|()| None);
+        }
+        if let Some(motor) = bldc_id {
+            return bldc::run(snarl, config, all_ids, motor, mech_id, sources).map(|()| None);
+        }
+        return Err(SimError::NoOdeNodes);
+    };
+
+    // ── 4. Extract parameters from the Electrical node ──────────────────────
+    let (r_s, l_d, l_q, lambda_m, n_p_elec, i_d_0, i_q_0, flux_map, iron_loss, harmonics) = {
+        let node = snarl
+            .get_node(elec_id)
+            .ok_or_else(|| SimError::GraphError("electrical node vanished".to_owned()))?;
+        let SimNode::Electrical(e) = node else {
+            return Err(SimError::GraphError("expected electrical node".to_owned()));
+        };
+        (
+            e.r_s_at(e.t_winding),
+            e.l_d,
+            e.l_q,
+            e.lambda_m,
+            e.n_p,
+            e.i_d_0,
+            e.i_q_0,
+            e.flux_map.as_deref().cloned().map(Rc::new),
+            e.iron_loss,
+            Rc::new((*e.harmonics).clone()),
+        )
+    };
+
+    // ── 5. Extract parameters from the Mechanical node ──────────────────────
+    // A two-mass drivetrain supplies the motor-side parameters and keeps the
+    // shaft and load for the extra states. Dry friction and the built-in
+    // load models, with the load's inertia, belong to the rigid rotor only.
+    let (j, b, n_p_mech, omega_m_0, theta_e_0, rotor, shaft) = {
+        let node = snarl
+            .get_node(mech_id)
+            .ok_or_else(|| SimError::GraphError("mechanical node vanished".to_owned()))?;
+        match node {
+            SimNode::Mechanical(m) => (
+                m.inertia(),
+                m.b,
+                m.n_p,
+                m.omega_m_0,
+                m.theta_e_0,
+                Rc::new(m.clone()),
+                None,
+            ),
+            SimNode::TwoMass(m) => (
+                m.j_m,
+                m.b_m,
+                m.n_p,
+                m.omega_m_0,
+                m.theta_e_0,
+                // A rotor without dry friction or built-in load
+                Rc::new(MechanicalNode::default()),
+                Some(Rc::new(m.clone())),
+            ),
+            _ => return Err(SimError::GraphError("expected mechanical node".to_owned())),
+        }
+    };
+
+    // ── 6. Extract parameters from the Torque node (fall back to Electrical) ─
+    // The torque equation uses its own copy of (N_p, λ_m, L_d, L_q) so the
+    // user can tune it independently from the ODE parameters.
+    let (torque_n_p, torque_lambda_m, torque_l_d, torque_l_q) = if let Some(tid) = torque_id {
+        let node = snarl
+            .get_node(tid)
+            .ok_or_else(|| SimError::GraphError("torque node vanished".to_owned()))?;
+        let SimNode::Torque(t) = node else {
+            return Err(SimError::GraphError("expected torque node".to_owned()));
+        };
+        (t.n_p, t.lambda_m, t.l_d, t.l_q)
+    } else {
+        // No dedicated Torque node — share the Electrical node's parameters.
+        (n_p_elec, lambda_m, l_d, l_q)
+    };
+
+    // ── 7. Pre-generate Constant Signal/Vector outputs ─────────────────────────
+    // Constants adapted to Signal or Vector mode need time-series data
+    // populated *before* we resolve ODE external inputs, so that
+    // `get_signal_input` can read from them (and by extension, the Park
+    // transform can consume them).
+    let n_pre = 1000_usize;
+    let ts_pre: Vec<f64> = (0..n_pre)
+        .map(|i| {
+            config.t_start + (config.t_end - config.t_start) * (i as f64) / (n_pre as f64 - 1.0)
+        })
+        .collect();
+    publish_constants(snarl, all_ids, &ts_pre);
+
+    // The DC-link bus starts at the battery open-circuit voltage; publish that
+    // so modulators evaluated before the solve see a sensible V_dc.
+    let dc_link = dc_link_id.and_then(|id| match snarl.get_node(id) {
+        Some(SimNode::DcLink(d)) => Some(d.clone()),
+        _ => None,
+    });
+    if let Some(lid) = dc_link_id
+        && let Some(SimNode::DcLink(d)) = snarl.get_node_mut(lid)
+    {
+        let series: Vec<[f64; 2]> = ts_pre.iter().map(|&t| [t, d.v_oc]).collect();
+        d.output_v_dc = Some(PortValue::Signal(series));
+    }
+
+    // ── 7a. Pre-compute SVPWM outputs ───────────────────────────────────────
+    // When θ_e comes from the Mechanical node it reads as zero here; that case
+    // is folded into the RHS closure below and recomputed after the ODE solve.
+    if let Some(sid) = svpwm_id
+        && let Some((duty, v_abc)) = compute_svpwm(snarl, sid, &ts_pre)
+        && let Some(SimNode::Svpwm(svpwm)) = snarl.get_node_mut(sid)
+    {
+        svpwm.output_duty = Some(PortValue::Vector(duty));
+        svpwm.output_v_abc = Some(PortValue::Vector(v_abc));
+    }
+    if let Some(iid) = inverter_id
+        && let Some(v_abc) = compute_inverter(snarl, iid, config.t_start, config.t_end, None)
+        && let Some(SimNode::Inverter(inverter)) = snarl.get_node_mut(iid)
+    {
+        inverter.output_v_abc = Some(PortValue::Vector(v_abc));
+    }
+
+    // ── 7b. Pre-compute Park transform if inputs are available ─────────────
+    // This populates Park f_d / f_q so they can feed Electrical v_d / v_q.
+    if let Some(pid) = park_id {
+        update_park(snarl, pid, None);
+    }
+
+    // ── 7c. Resolve external ODE forcing inputs ─────────────────────────────
+    // Check if v_d/v_q come from a Park node whose theta_e is ODE-coupled
+    // (circular dependency: Electrical ← Park ← Mechanical theta_e).
+    // When detected, the Park transform is folded into the RHS closure so it
+    // evaluates at the instantaneous theta_e state during integration.
+    let voltage_source = 'voltage: {
+        if let Some(pid) = park_id {
+            let vd_pin = snarl.in_pin(InPinId {
+                node: elec_id,
+                input: 0,
+            });
+            let vd_from_park = vd_pin.remotes.first().is_some_and(|r| r.node == pid);
+
+            if vd_from_park {
+                let theta_pin = snarl.in_pin(InPinId {
+                    node: pid,
+                    input: 1,
+                });
+                let theta_from_mech = theta_pin.remotes.first().is_some_and(|r| r.node == mech_id);
+
+                let f_abc_pin = InPinId {
+                    node: pid,
+                    input: 0,
+                };
+                if theta_from_mech
+                    && let Some(coupling) =
+                        resolve_svpwm_coupling(snarl, f_abc_pin, 1, mech_id, dc_link_id)
+                {
+                    break 'voltage VoltageSource::ParkCoupled {
+                        phase: PhaseSource::Svpwm(Box::new(coupling)),
+                    };
+                }
+                if theta_from_mech
+                    && let Some(coupling) =
+                        resolve_inverter_coupling(snarl, pid, mech_id, dc_link_id)
+                {
+                    break 'voltage VoltageSource::ParkCoupled {
+                        phase: PhaseSource::Inverter(Box::new(coupling)),
+                    };
+                }
+                if theta_from_mech && let Some(f_abc) = get_vector_input(snarl, pid, 0) {
+                    break 'voltage VoltageSource::ParkCoupled {
+                        phase: PhaseSource::Series(f_abc),
+                    };
+                }
+            }
+        }
+        // Default: resolve v_d, v_q independently from the graph.
+        VoltageSource::Direct {
+            v_d: resolve_external_input(snarl, elec_id, 0),
+            v_q: resolve_external_input(snarl, elec_id, 1),
+        }
+    };
+    let supply = Supply {
+        voltage: voltage_source,
+        link: dc_link,
+        flux_map,
+        harmonics,
+        lambda_m,
+        flux_guess: Rc::new(Cell::new(None)),
+        flux_failure: Rc::new(Cell::new(None)),
+    };
+    // A two-mass drivetrain appends the load speed ω_L and shaft twist φ
+    let s_wl = supply.n_states();
+    let s_tw = s_wl + 1;
+    let n_states = supply.n_states() + if shaft.is_some() { 2 } else { 0 };
+    // A thermal network appends one temperature per body
+    let thermal = thermal_id
+        .map(|id| ThermalModel::resolve(snarl, id, elec_id, n_states).map(Rc::new))
+        .transpose()?;
+    let n_states = n_states + thermal.as_ref().map_or(0, |th| th.n_states());
+    let v_c_0 = supply.link.as_ref().map_or(0.0, |link| link.v_oc);
+    let start = match task {
+        Task::Simulate(Some(x)) if x.len() != n_states => {
+            return Err(SimError::GraphError(
+                "steady start does not match the machine states".to_owned(),
+            ));
+        }
+        Task::Simulate(start) => start.map(<[f64]>::to_vec),
+        Task::Trim | Task::Evaluate(_) => None,
+    };
+    // With a flux map the electrical states are the flux linkages.
+    let (x_d_0, x_q_0) = supply.flux_map.as_ref().map_or((i_d_0, i_q_0), |map| {
+        let point = map.eval(i_d_0, i_q_0);
+        let pm = supply.harmonics.pm_flux(lambda_m, theta_e_0);
+        (point.psi_d + pm.psi[0], point.psi_q + pm.psi[1])
+    });
+    // MechanicalNode pin layout: 0 = T_e (Signal), 1 = T_L (Signal)
+    let t_l_input = resolve_external_input(snarl, mech_id, 1);
+
+    // Use pole-pair count from the Mechanical node throughout the ODE.
+    let n_p = n_p_mech;
+
+    // ── 8. Assemble parameter vector ─────────────────────────────────────────
+    // Element order must match the P_* constants defined above.
+    // v_d, v_q, and T_L are no longer in the parameter vector — they are
+    // captured as `ExternalInput` values in the RHS closure.
+    let p = vec![
+        r_s,
+        l_d,
+        l_q,
+        lambda_m,
+        n_p, // P_RS … P_NP
+        j,
+        b, // P_J, P_B
+        torque_n_p,
+        torque_lambda_m,
+        torque_l_d,
+        torque_l_q, // P_T_NP … P_T_LQ
+    ];
+
+    // ── 9. Build and solve the ODE ────────────────────────────────────────────
+    //
+    // State vector:  [i_d, i_q, ω_m, θ_e]  (+ v_C with a DC link)
+    //                                      (+ ω_L, φ with a two-mass drivetrain)
+    //                                      (+ body temperatures with a thermal network)
+    //
+    // ODE system (Park-frame PMSM + rigid-rotor mechanics, see `RotorFrame`):
+    //   di_d/dt = (1/L_d) * (v_d - R_s·i_d + N_p·ω_m·(L_q·i_q + Δψ_q - Δψ_d'))
+    //   di_q/dt = (1/L_q) * (v_q - R_s·i_q - N_p·ω_m·(L_d·i_d + λ_m + Δψ_d + Δψ_q'))
+    //   dω_m/dt = (1/J)  * (T_e - T_fe - T_L - T_f - T_load - B·ω_m)
+    //   dθ_e/dt = N_p · ω_m
+    //   dv_C/dt = (V_dc - v_C) / (ESR · C)
+    //
+    // where  T_e = (3/2) · N_p · (λ_m·i_q + (L_d - L_q)·i_d·i_q
+    //                               + Δψ_d·i_q - Δψ_q·i_d + Δψ_d'·i_d + Δψ_q'·i_q) + T_cog
+    // with Δψ(θ_e) the harmonic PM flux, ' = ∂/∂θ_e, and T_cog(θ_e / N_p) cogging.
+    // and V_dc solves the bus current balance for the load P_e (see DcLinkNode).
+    // T_fe is the core-loss drag, a function of ω_m and |ψ| (see IronLoss), and
+    // R_s is taken at the winding temperature. T_f and T_load are the
+    // Mechanical node's dry friction and built-in load, functions of ω_m.
+    //
+    // A two-mass drivetrain replaces T_L in the ω_m equation with T_sh / N and
+    // adds the load and shaft equations documented in `TwoMassNode`.
+    //
+    // A thermal network adds the body temperatures documented in `ThermalNode`,
+    // heated by P_cu and P_fe. Where fed back, R_s follows the winding and λ_m
+    // the magnet temperature at every evaluation.
+    //
+    // With a flux map the first two states are [ψ_d, ψ_q] instead:
+    //   dψ_d/dt = v_d - R_s·i_d + N_p·ω_m·ψ_q
+    //   dψ_q/dt = v_q - R_s·i_q - N_p·ω_m·ψ_d
+    //   T_e     = (3/2) · N_p · (ψ_d·i_q - ψ_q·i_d + Δψ_d'·i_d + Δψ_q'·i_q) + T_cog
+    //   (i_d, i_q) = ψ⁻¹(ψ_d - Δψ_d, ψ_q - Δψ_q)
+    let problem = OdeBuilder::<M>::new()
+        .t0(config.t_start)
+        .rtol(config.rtol)
+        .atol(vec![config.atol; n_states])
+        .p(p.clone())
+        .rhs_implicit(
+            // ── RHS: compute time derivatives ──────────────────────────────
+            {
+                let supply = supply.clone();
+                let t_l_ext = t_l_input.clone();
+                let rotor = rotor.clone();
+                let shaft = shaft.clone();
+                let thermal = thermal.clone();
+                move |x, p, t, y| {
+                    let p = &params_at(p, x, thermal.as_deref());
+                    let SupplyPoint {
+                        v_d, v_q, state, ..
+                    } = supply.at(t, x);
+                    let t_l = t_l_ext.at(t);
+
+                    // Core-loss drag torque
+                    let ([psi_d, psi_q], _) = stator_flux(x, p, supply.flux_map.is_some());
+                    let t_fe = iron_loss
+                        .torque(p[P_NP], x[S_WM], psi_d.hypot(psi_q))
+                        .torque;
+
+                    // Load seen by the motor: T_L, or the shaft torque through the gearbox
+                    let t_load = match &shaft {
+                        Some(shaft) => {
+                            let rate = shaft.twist_rate(x[S_WM], x[s_wl]);
+                            let t_sh = shaft.shaft_torque(x[s_tw], rate).torque;
+                            y[s_wl] = (t_sh - t_l - shaft.b_l * x[s_wl]) / shaft.j_l;
+                            y[s_tw] = rate;
+                            t_sh / shaft.ratio
+                        }
+                        None => t_l,
+                    };
+                    // Dry friction and built-in speed-dependent load
+                    let t_speed = rotor.speed_torque(x[S_WM]).torque;
+
+                    // Stator, speed and angle equations of the machine; with a
+                    // flux map the currents are taken from the map
+                    let model = rotor_frame(&supply, p);
+                    let drag = t_fe + t_load + t_speed;
+                    let f = if supply.flux_map.is_some() {
+                        let i = [state.i_d, state.i_q];
+                        model.flux_derivatives(rotor_states(x), i, [v_d, v_q], drag)
+                    } else {
+                        model.derivatives(rotor_states(x), [v_d, v_q], drag)
+                    };
+                    let [f_d, f_q, f_wm, f_te] = f;
+                    y[S_ID] = f_d;
+                    y[S_IQ] = f_q;
+                    y[S_WM] = f_wm;
+                    y[S_TE] = f_te;
+
+                    // DC-link capacitor voltage
+                    if let Some(link) = &supply.link {
+                        y[S_VC] = link.dv_c(x[S_VC], state.v_dc);
+                    }
+
+                    // Body temperatures of the thermal network
+                    if let Some(thermal) = &thermal {
+                        thermal.derivatives(t, x, pmsm_losses(&supply, &iron_loss, x, p), y);
+                    }
+                }
+            },
+            // ── Jacobian-vector product J·v where J = ∂f/∂x ─────────────
+            {
+                let supply_jac = supply.clone();
+                let rotor_jac = rotor.clone();
+                let shaft = shaft.clone();
+                let thermal = thermal.clone();
+                move |x, p, t, v, y| {
+                    let p_base = p;
+                    let p = &params_at(p_base, x, thermal.as_deref());
+
+                    // Supply dependence on the state: θ_e through a coupled Park
+                    // transform, every state through the DC-link bus (zero for
+                    // direct voltage inputs without a DC link)
+                    let (dvd, dvq, dvc) = supply_jac.jvp(t, x, v);
+
+                    // Stator, speed and angle equations of the machine
+                    let model = rotor_frame(&supply_jac, p);
+                    let jac = match &supply_jac.flux_map {
+                        Some(map) => {
+                            let state = supply_jac.motor_state(x);
+                            let l = map.eval(state.i_d, state.i_q);
+                            model.flux_jacobian(rotor_states(x), [state.i_d, state.i_q], &l)
+                        }
+                        None => model.jacobian(rotor_states(x)),
+                    };
+                    let v_rotor = rotor_states(v);
+                    let [row_d, row_q, row_wm, row_te] = jac
+                        .dx
+                        .map(|row| row.iter().zip(&v_rotor).map(|(a, b)| a * b).sum::<f64>());
+                    y[S_ID] = row_d + jac.dv[0] * dvd;
+                    y[S_IQ] = row_q + jac.dv[1] * dvq;
+                    y[S_WM] = row_wm;
+
+                    // Core-loss drag through ω_m and |ψ|
+                    let ([psi_d, psi_q], [k_d, k_q]) =
+                        stator_flux(x, p, supply_jac.flux_map.is_some());
+                    let psi = psi_d.hypot(psi_q);
+                    let fe = iron_loss.torque(p[P_NP], x[S_WM], psi);
+                    let dpsi = if psi > 0.0 {
+                        (psi_d * k_d * v[S_ID] + psi_q * k_q * v[S_IQ]) / psi
+                    } else {
+                        0.0
+                    };
+                    y[S_WM] -= (fe.d_omega * v[S_WM] + fe.d_psi * dpsi) / p[P_J];
+                    let speed = rotor_jac.speed_torque(x[S_WM]);
+                    y[S_WM] -= speed.d_omega * v[S_WM] / p[P_J];
+
+                    // Shaft coupling between the motor, load and twist states
+                    if let Some(shaft) = &shaft {
+                        let rate = shaft.twist_rate(x[S_WM], x[s_wl]);
+                        let sh = shaft.shaft_torque(x[s_tw], rate);
+                        let d_rate = v[S_WM] / shaft.ratio - v[s_wl];
+                        let dt_sh = sh.d_twist * v[s_tw] + sh.d_rate * d_rate;
+                        y[S_WM] -= dt_sh / (shaft.ratio * p[P_J]);
+                        y[s_wl] = (dt_sh - shaft.b_l * v[s_wl]) / shaft.j_l;
+                        y[s_tw] = d_rate;
+                    }
+
+                    y[S_TE] = row_te;
+                    if supply_jac.link.is_some() {
+                        y[S_VC] = dvc;
+                    }
+
+                    // Temperature feedback through R_s and the fundamental λ_m,
+                    // and the thermal network rows
+                    if let Some(thermal) = &thermal {
+                        let (dr_dt, dlam_dt) = thermal.slopes();
+                        let dr = dr_dt * v[thermal.s_winding()];
+                        let dlam = dlam_dt * v[thermal.s_magnet()];
+                        if supply_jac.flux_map.is_some() {
+                            let state = supply_jac.motor_state(x);
+                            y[S_ID] -= dr * state.i_d;
+                            y[S_IQ] -= dr * state.i_q;
+                        } else {
+                            let omega_e = p[P_NP] * x[S_WM];
+                            y[S_ID] -= dr * x[S_ID] / p[P_LD];
+                            y[S_IQ] -= (dr * x[S_IQ] + omega_e * p_base[P_LAM] * dlam) / p[P_LQ];
+                            y[S_WM] += 1.5 * p[P_T_NP] * p_base[P_T_LAM] * dlam * x[S_IQ] / p[P_J];
+                        }
+                        let d_losses =
+                            loss_jvp(&supply_jac, &iron_loss, thermal, p_base, x, v, n_states);
+                        thermal.jvp(v, d_losses, y);
+                    }
+                }
+            },
+        )
+        .init(
+            {
+                let has_link = supply.link.is_some();
+                let load_0 = shaft
+                    .as_ref()
+                    .map(|shaft| (omega_m_0 / shaft.ratio, shaft.twist_0));
+                let thermal = thermal.clone();
+                move |_p, _t, y| {
+                    if let Some(start) = &start {
+                        y.as_mut_slice().copy_from_slice(start);
+                        return;
+                    }
+                    y[S_ID] = x_d_0;
+                    y[S_IQ] = x_q_0;
+                    y[S_WM] = omega_m_0;
+                    y[S_TE] = theta_e_0;
+                    if has_link {
+                        y[S_VC] = v_c_0;
+                    }
+                    // The load starts in step with the motor
+                    if let Some((omega_l_0, twist_0)) = load_0 {
+                        y[s_wl] = omega_l_0;
+                        y[s_tw] = twist_0;
+                    }
+                    if let Some(thermal) = &thermal {
+                        thermal.init(y);
+                    }
+                }
+            },
+            n_states,
+        )
+        .build()
+        .map_err(|e| SimError::SolverFailed(format!("{e:?}")))?;
+
+    // ── 9a. Evaluate the equations at the steady state or a given state ─────
+    if !matches!(task, Task::Simulate(_)) && supply.voltage.inverter().is_some() {
+        return Err(SimError::GraphError(
+            "a switched inverter has no steady state; trim with SVPWM instead".to_owned(),
+        ));
+    }
+    let x = match task {
+        Task::Simulate(_) => None,
+        Task::Trim => {
+            // The drivetrain follows the rotor: the load turns at ω_m/N and
+            // the shaft is twisted to carry T_L + B_L·ω_L
+            let t_l_0 = t_l_input.at(config.t_start);
+            let coupled = |x: &mut NalgebraVec<f64>| {
+                if let Some(shaft) = &shaft {
+                    let omega_l = x[S_WM] / shaft.ratio;
+                    x[s_wl] = omega_l;
+                    x[s_tw] = shaft.steady_twist(t_l_0 + shaft.b_l * omega_l);
+                }
+            };
+            let x = trim::equilibrium(&problem, config, coupled).map_err(|e| supply.failure(e))?;
+            Some(x)
+        }
+        Task::Evaluate(x) if x.len() == n_states => {
+            Some(NalgebraVec::from_slice(x, *problem.context()))
+        }
+        Task::Evaluate(_) => {
+            return Err(SimError::GraphError(
+                "operating point does not match the machine states".to_owned(),
+            ));
+        }
+    };
+    if let Some(x) = x {
+        // Every Signal output of the machine nodes that is a function of the
+        // state at t_start, as written by steps 10–12 of a simulation
+        let t = config.t_start;
+        let pin = |node, output| OutPinId { node, output };
+        let outputs = |x: &NalgebraVec<f64>| {
+            let state = supply.motor_state(x);
+            let p = params_at(&p, x, thermal.as_deref());
+            let [p_cu, p_fe] = pmsm_losses(&supply, &iron_loss, x, &p);
+            let t_e = electromagnetic_torque(&supply, x, &p, state);
+            let omega = x[S_WM];
+            let mut y = vec![
+                (pin(elec_id, 0), state.i_d),
+                (pin(elec_id, 1), state.i_q),
+                (pin(elec_id, 2), p_cu),
+                (pin(elec_id, 3), p_fe),
+                (pin(mech_id, 0), omega),
+                (pin(mech_id, 1), x[S_TE]),
+            ];
+            if let Some(shaft) = &shaft {
+                let rate = shaft.twist_rate(omega, x[s_wl]);
+                y.push((pin(mech_id, 2), x[s_wl]));
+                y.push((pin(mech_id, 3), shaft.shaft_torque(x[s_tw], rate).torque));
+            }
+            if let Some(tid) = torque_id {
+                y.push((pin(tid, 0), t_e));
+            }
+            if let Some(lid) = dc_link_id
+                && let Some(link) = &supply.link
+            {
+                let point = supply.at(t, x);
+                let currents = link.currents(x[S_VC], point.state.v_dc, point.p_e);
+                y.push((pin(lid, 0), point.state.v_dc));
+                y.push((pin(lid, 1), currents.i_bat));
+                y.push((pin(lid, 2), currents.i_dc));
+            }
+            if let Some(tid) = thermal_id
+                && let Some(thermal) = &thermal
+            {
+                let (t_w, t_pm, _) = thermal.outputs(x);
+                y.push((pin(tid, 0), t_w));
+                y.push((pin(tid, 1), t_pm));
+            }
+            if let Some(pid) = power_id {
+                let p_fric = (p[P_B] * omega + rotor.friction.torque(omega).torque) * omega;
+                y.push((pin(pid, 0), supply.at(t, x).p_e));
+                y.push((pin(pid, 1), p_cu));
+                y.push((pin(pid, 2), p_fe));
+                y.push((pin(pid, 3), t_e * omega));
+                y.push((pin(pid, 4), p_fric));
+            }
+            y
+        };
+        let values = |x: &NalgebraVec<f64>| outputs(x).into_iter().map(|(_, y)| y).collect();
+        let (f, a) = trim::jacobian(&problem, t, &x);
+        let c = trim::output_jacobian(&x, values);
+        let (pins, y) = outputs(&x).into_iter().unzip();
+        let mut names: Vec<String> = if supply.flux_map.is_some() {
+            vec!["ψ_d".to_owned(), "ψ_q".to_owned()]
+        } else {
+            vec!["i_d".to_owned(), "i_q".to_owned()]
+        };
+        names.extend(["ω_m", "θ_e"].map(str::to_owned));
+        if supply.link.is_some() {
+            names.push("v_C".to_owned());
+        }
+        if shaft.is_some() {
+            names.extend(["ω_L", "φ"].map(str::to_owned));
+        }
+        // Thermal network bodies
+        let n_named = names.len();
+        names.extend((n_named..n_states).map(|k| format!("T_{}", k + 1 - n_named)));
+        return Ok(Some(Evaluation {
+            x: x.as_slice().to_vec(),
+            names,
+            f,
+            a,
+            pins,
+            y,
+            c,
+            twist: shaft.as_ref().map(|_| x[s_tw]),
+            electrical: elec_id,
+            mechanical: mech_id,
+        }));
+    }
+
+    let mut solver = problem
+        .bdf::<Ls>()
+        .map_err(|e| SimError::SolverFailed(format!("{e:?}")))?;
+
+    // ys: DenseMatrix with n_states rows, ts.len() columns (one per accepted step).
+    // A switched inverter needs every edge hit exactly, so it is stepped by hand.
+    let (ys, ts, legs_log) = if let Some(inverter) = supply.voltage.inverter() {
+        solve_switched(&mut solver, &supply, inverter, config.t_end)
+    } else {
+        solver
+            .solve(config.t_end)
+            .map(|(ys, ts)| (ys, ts, Vec::new()))
+            .map_err(|e| SimError::SolverFailed(format!("{e:?}")))
+    }
+    .map_err(|e| supply.failure(e))?;
+
+    // ── 10. Extract time-series signals from the solution matrix ─────────────
+    // Motor currents per sample (converted from flux linkages with a flux map);
+    // rejected trial steps may have failed to invert, accepted ones must not
+    let motor_states: Vec<CoupledState> = (0..ts.len())
+        .map(|i| {
+            let state = supply.motor_state(&ys.column(i));
+            supply
+                .flux_failure
+                .get()
+                .map_or(Ok(state), |psi| Err(flux_inversion_error(psi)))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let i_d_series: Vec<[f64; 2]> = ts
+        .iter()
+        .zip(&motor_states)
+        .map(|(&t, state)| [t, state.i_d])
+        .collect();
+
+    let i_q_series: Vec<[f64; 2]> = ts
+        .iter()
+        .zip(&motor_states)
+        .map(|(&t, state)| [t, state.i_q])
+        .collect();
+
+    let omega_m_series: Vec<[f64; 2]> = ts
+        .iter()
+        .enumerate()
+        .map(|(i, &t)| [t, ys.column(i)[S_WM]])
+        .collect();
+
+    let theta_e_series: Vec<[f64; 2]> = ts
+        .iter()
+        .enumerate()
+        .map(|(i, &t)| [t, ys.column(i)[S_TE]])
+        .collect();
+
+    // Load speed and shaft torque of a two-mass drivetrain
+    let shaft_series = shaft.as_ref().map(|shaft| {
+        let mut omega_l_series = Vec::with_capacity(ts.len());
+        let mut t_sh_series = Vec::with_capacity(ts.len());
+        for (i, &t) in ts.iter().enumerate() {
+            let column = ys.column(i);
+            let rate = shaft.twist_rate(column[S_WM], column[s_wl]);
+            omega_l_series.push([t, column[s_wl]]);
+            t_sh_series.push([t, shaft.shaft_torque(column[s_tw], rate).torque]);
+        }
+        [omega_l_series, t_sh_series]
+    });
+
+    // ── 11. Compute algebraic post-processing signals ────────────────────────
+
+    // Electromagnetic torque  T_e = (3/2) · N_p · (λ_m · i_q + (L_d - L_q) · i_d · i_q),
+    // or (3/2) · N_p · (ψ_d · i_q − ψ_q · i_d) with a flux map, plus harmonics and cogging
+    let t_e_series: Vec<[f64; 2]> = ts
+        .iter()
+        .zip(&motor_states)
+        .enumerate()
+        .map(|(i, (&t, &state))| {
+            let column = ys.column(i);
+            let p = params_at(&p, &column, thermal.as_deref());
+            [t, electromagnetic_torque(&supply, &column, &p, state)]
+        })
+        .collect();
+
+    // Copper loss  P_cu = (3/2) · R_s · (i_d² + i_q²) and iron loss  P_fe = T_fe · ω_m
+    let (p_cu_series, p_fe_series): (Vec<[f64; 2]>, Vec<[f64; 2]>) = ts
+        .iter()
+        .enumerate()
+        .map(|(i, &t)| {
+            let column = ys.column(i);
+            let p = params_at(&p, &column, thermal.as_deref());
+            let [p_cu, p_fe] = pmsm_losses(&supply, &iron_loss, &column, &p);
+            ([t, p_cu], [t, p_fe])
+        })
+        .unzip();
+
+    // Power flow and energy balance, at the solver steps so the integrals
+    // see the full solution
+    let power_flow = power_id.map(|_| {
+        let mut samples = Vec::with_capacity(ts.len());
+        let mut w_mag = 0.0;
+        let mut previous: Option<(CoupledState, [f64; 2])> = None;
+        for (i, (&t, &state)) in ts.iter().zip(&motor_states).enumerate() {
+            if let (Some(inverter), Some(&legs)) = (supply.voltage.inverter(), legs_log.get(i)) {
+                inverter.legs.set(legs);
+            }
+            let column = ys.column(i);
+            let p = params_at(&p, &column, thermal.as_deref());
+            let omega = column[S_WM];
+            let [p_cu, p_fe] = pmsm_losses(&supply, &iron_loss, &column, &p);
+            let (t_cog, _) = supply.harmonics.cogging(column[S_TE] / p[P_NP]);
+            let t_load = match &shaft {
+                Some(shaft) => {
+                    let rate = shaft.twist_rate(omega, column[s_wl]);
+                    shaft.shaft_torque(column[s_tw], rate).torque / shaft.ratio
+                }
+                None => t_l_input.at(t),
+            };
+            // Magnetic energy: closed form, or (3/2)·∫i·dψ with flux states
+            let psi = [column[S_ID], column[S_IQ]];
+            w_mag = if supply.flux_map.is_some() {
+                previous.map_or(0.0, |(prev, psi_prev)| {
+                    w_mag
+                        + 0.75
+                            * ((prev.i_d + state.i_d) * (psi[0] - psi_prev[0])
+                                + (prev.i_q + state.i_q) * (psi[1] - psi_prev[1]))
+                })
+            } else {
+                0.75 * (p[P_LD] * state.i_d * state.i_d + p[P_LQ] * state.i_q * state.i_q)
+            };
+            previous = Some((state, psi));
+            samples.push(PowerSample {
+                t,
+                p_e: supply.at(t, &column).p_e,
+                p_cu,
+                p_fe,
+                p_m: electromagnetic_torque(&supply, &column, &p, state) * omega,
+                p_fric: (p[P_B] * omega + rotor.friction.torque(omega).torque) * omega,
+                p_load: (t_load + rotor.load.torque(omega).torque) * omega,
+                p_cog: t_cog * omega,
+                w_mag,
+                w_kin: 0.5 * p[P_J] * omega * omega,
+            });
+        }
+        PowerFlowNode::account(&samples, config.rtol)
+    });
+
+    // Winding, magnet and body temperatures of a thermal network
+    let thermal_series = thermal.as_ref().map(|thermal| {
+        let mut t_w_series = Vec::with_capacity(ts.len());
+        let mut t_pm_series = Vec::with_capacity(ts.len());
+        let mut t_series = Vec::with_capacity(ts.len());
+        for (i, &t) in ts.iter().enumerate() {
+            let (t_w, t_pm, [t_1, t_2, t_3]) = thermal.outputs(&ys.column(i));
+            t_w_series.push([t, t_w]);
+            t_pm_series.push([t, t_pm]);
+            t_series.push([t, t_1, t_2, t_3]);
+        }
+        (t_w_series, t_pm_series, t_series)
+    });
+
+    // DC-link bus voltage and branch currents, re-evaluated from the supply
+    // with the inverter leg states that were in force at each sample.
+    let dc_link_series = supply.link.as_ref().map(|link| {
+        let mut v_dc_series = Vec::with_capacity(ts.len());
+        let mut i_bat_series = Vec::with_capacity(ts.len());
+        let mut i_dc_series = Vec::with_capacity(ts.len());
+        for (i, &t) in ts.iter().enumerate() {
+            if let (Some(inverter), Some(&legs)) = (supply.voltage.inverter(), legs_log.get(i)) {
+                inverter.legs.set(legs);
+            }
+            let column = ys.column(i);
+            let point = supply.at(t, &column);
+            let currents = link.currents(column[S_VC], point.state.v_dc, point.p_e);
+            v_dc_series.push([t, point.state.v_dc]);
+            i_bat_series.push([t, currents.i_bat]);
+            i_dc_series.push([t, currents.i_dc]);
+        }
+        [v_dc_series, i_bat_series, i_dc_series]
+    });
+
+    // Inverse Park transform if an InversePark node is present in the graph.
+    // f_abc = Park⁻¹(f_d, f_q, θ_e):
+    //   f_a = f_d·cos(θ_e)           − f_q·sin(θ_e)
+    //   f_b = f_d·cos(θ_e − 2π/3)   − f_q·sin(θ_e − 2π/3)
+    //   f_c = f_d·cos(θ_e + 2π/3)   − f_q·sin(θ_e + 2π/3)
+    if let Some(pid) = inv_park_id {
+        let two_thirds_pi = std::f64::consts::PI * 2.0 / 3.0;
+
+        let f_abc_series: Vec<[f64; 4]> = ts
+            .iter()
+            .zip(&motor_states)
+            .map(|(&t, state)| {
+                let i_d = state.i_d;
+                let i_q = state.i_q;
+                let theta = state.theta_e;
+
+                let f_a = i_d * theta.cos() - i_q * theta.sin();
+                let f_b = i_d * (theta - two_thirds_pi).cos() - i_q * (theta - two_thirds_pi).sin();
+                let f_c = i_d * (theta + two_thirds_pi).cos() - i_q * (theta + two_thirds_pi).sin();
+                [t, f_a, f_b, f_c]
+            })
+            .collect();
+
+        if let Some(SimNode::InversePark(park)) = snarl.get_node_mut(pid) {
+            let f_abc_series = resample_vector(&f_abc_series, config.t_start, config.t_end, config.output_dt);
+            park.output_f_abc = Some(PortValue::Vector(f_abc_series));
+        }
+    }
+
+    // ── 11b. Resample all series onto a uniform output grid ──────────────
+    let dt = config.output_dt;
+    let t0 = config.t_start;
+    let t1 = config.t_end;
+    let i_d_series = resample_signal(&i_d_series, t0, t1, dt);
+    let i_q_series = resample_signal(&i_q_series, t0, t1, dt);
+    let omega_m_series = resample_signal(&omega_m_series, t0, t1, dt);
+    let theta_e_series = resample_signal(&theta_e_series, t0, t1, dt);
+    let t_e_series = resample_signal(&t_e_series, t0, t1, dt);
+    let p_cu_series = resample_signal(&p_cu_series, t0, t1, dt);
+    let p_fe_series = resample_signal(&p_fe_series, t0, t1, dt);
+    let dc_link_series =
+        dc_link_series.map(|series| series.map(|s| resample_signal(&s, t0, t1, dt)));
+    let shaft_series = shaft_series.map(|series| series.map(|s| resample_signal(&s, t0, t1, dt)));
+    let power_flow = power_flow.map(|(series, balance)| {
+        let resample = |s: &[[f64; 2]]| resample_signal(s, t0, t1, dt);
+        let series = [
+            &series.p_e,
+            &series.p_cu,
+            &series.p_fe,
+            &series.p_m,
+            &series.p_fric,
+            &series.eta,
+            &series.e_in,
+        ]
+        .map(|s| resample(s));
+        (series, balance)
+    });
+    let thermal_series = thermal_series.map(|(t_w, t_pm, t)| {
+        (
+            resample_signal(&t_w, t0, t1, dt),
+            resample_signal(&t_pm, t0, t1, dt),
+            resample_vector(&t, t0, t1, dt),
+        )
+    });
+
+    // ── 12. Write time-series results back into the graph nodes ──────────────
+    if let Some(SimNode::Electrical(e)) = snarl.get_node_mut(elec_id) {
+        e.output_i_d = Some(PortValue::Signal(i_d_series));
+        e.output_i_q = Some(PortValue::Signal(i_q_series));
+        e.output_p_cu = Some(PortValue::Signal(p_cu_series));
+        e.output_p_fe = Some(PortValue::Signal(p_fe_series));
+    }
+
+    match snarl.get_node_mut(mech_id) {
+        Some(SimNode::Mechanical(m)) => {
+            m.output_omega_m = Some(PortValue::Signal(omega_m_series));
+            m.output_theta_e = Some(PortValue::Signal(theta_e_series));
+        }
+        Some(SimNode::TwoMass(m)) => {
+            m.output_omega_m = Some(PortValue::Signal(omega_m_series));
+            m.output_theta_e = Some(PortValue::Signal(theta_e_series));
+            if let Some([omega_l, t_sh]) = shaft_series {
+                m.output_omega_l = Some(PortValue::Signal(omega_l));
+                m.output_t_sh = Some(PortValue::Signal(t_sh));
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(tid) = torque_id
+        && let Some(SimNode::Torque(t)) = snarl.get_node_mut(tid)
+    {
+        t.output_t_e = Some(PortValue::Signal(t_e_series));
+    }
+
+    if let Some(tid) = thermal_id
+        && let Some((t_w, t_pm, t)) = thermal_series
+        && let Some(SimNode::Thermal(th)) = snarl.get_node_mut(tid)
+    {
+        th.output_t_w = Some(PortValue::Signal(t_w));
+        th.output_t_pm = Some(PortValue::Signal(t_pm));
+        th.output_t = Some(PortValue::Vector(t));
+    }
+
+    if let Some(pid) = power_id
+        && let Some(([p_e, p_cu, p_fe, p_m, p_fric, eta, e_in], balance)) = power_flow
+        && let Some(SimNode::PowerFlow(pf)) = snarl.get_node_mut(pid)
+    {
+        pf.balance = Some(balance);
+        pf.output_p_e = Some(PortValue::Signal(p_e));
+        pf.output_p_cu = Some(PortValue::Signal(p_cu));
+        pf.output_p_fe = Some(PortValue::Signal(p_fe));
+        pf.output_p_m = Some(PortValue::Signal(p_m));
+        pf.output_p_fric = Some(PortValue::Signal(p_fric));
+        pf.output_eta = Some(PortValue::Signal(eta));
+        pf.output_e_in = Some(PortValue::Signal(e_in));
+    }
+
+    if let Some(lid) = dc_link_id
+        && let Some([v_dc, i_bat, i_dc]) = dc_link_series
+        && let Some(SimNode::DcLink(d)) = snarl.get_node_mut(lid)
+    {
+        d.output_v_dc = Some(PortValue::Signal(v_dc));
+        d.output_i_bat = Some(PortValue::Signal(i_bat));
+        d.output_i_dc = Some(PortValue::Signal(i_dc));
+    }
+
+    // ── 12b. Re-generate constant Signal/Vector outputs on the uniform output grid ─
+    // Step 7 pre-generated these on a synthetic grid (`ts_pre`). Now regenerate
+    // on the same uniform grid used for ODE outputs so all series align.
+    let n_out = ((t1 - t0) / dt).ceil() as usize + 1;
+    let ts_uniform: Vec<f64> = (0..n_out).map(|i| (t0 + dt * i as f64).min(t1)).collect();
+    publish_constants(snarl, all_ids, &ts_uniform);
+
+    // ── 12c. Re-compute SVPWM outputs with the solved θ_e ────────────────────
+    if let Some(sid) = svpwm_id
+        && let Some((duty, v_abc)) = compute_svpwm(snarl, sid, &ts_uniform)
+        && let Some(SimNode::Svpwm(svpwm)) = snarl.get_node_mut(sid)
+    {
+        svpwm.output_duty = Some(PortValue::Vector(duty));
+        svpwm.output_v_abc = Some(PortValue::Vector(v_abc));
+    }
+
+    // ── 12d. Re-compute inverter output with the solved phase currents ──────
+    // Kept at switching-edge resolution: resampling would smear the pulses.
+    if let Some(iid) = inverter_id {
+        let i_abc_series: Vec<[f64; 4]> = ts
+            .iter()
+            .zip(&motor_states)
+            .map(|(&t, state)| {
+                let [i_a, i_b, i_c] = state.i_abc();
+                [t, i_a, i_b, i_c]
+            })
+            .collect();
+        let currents = |t: f64| interpolate_vector(&i_abc_series, t);
+        if let Some(v_abc) = compute_inverter(snarl, iid, t0, t1, Some(&currents))
+            && let Some(SimNode::Inverter(inverter)) = snarl.get_node_mut(iid)
+        {
+            inverter.output_v_abc = Some(PortValue::Vector(v_abc));
+        }
+    }
+
+    // ── 13. Re-compute Park transform at ODE time resolution ────────────────
+    // Step 7b pre-computed this for ODE input resolution. Re-run with
+    // actual data from connected nodes (which may now include ODE outputs).
+    if let Some(pid) = park_id {
+        update_park(snarl, pid, Some((t0, t1, dt)));
+    }
+
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_snarl::{InPinId, OutPinId, Snarl};
+
+    use crate::nodes::SimNode;
+    use crate::nodes::constant::ConstantNode;
+    use crate::nodes::electrical::ElectricalNode;
+    use crate::nodes::mechanical::MechanicalNode;
+    use crate::nodes::plot::PlotNode;
+    use crate::nodes::torque::TorqueNode;
+    use crate::port::PortValue;
+    use crate::simulation::SimConfig;
+
+    use super::run_simulation;
+
+    /// Build a minimal PMSM graph and run the solver, verifying that outputs
+    /// are populated and physically plausible.
+    #[expect(
+        clippy::too_many_lines,
+        reason = "integration test exercises full simulation pipeline"
+    )]
+    #[test]
+    fn pmsm_simulation_produces_plausible_results() {
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+
+        let pos = egui::pos2(0.0, 0.0);
+
+        // Create nodes
+        let vd_node = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 0.0,
+                ..ConstantNode::default()
+            }),
+        );
+        let vq_node = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 24.0,
+                ..ConstantNode::default()
+            }),
+        );
+        let tl_node = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 0.0,
+                ..ConstantNode::default()
+            }),
+        );
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let torque_node = snarl.insert_node(pos, SimNode::Torque(TorqueNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let _plot_node = snarl.insert_node(pos, SimNode::Plot(PlotNode::default()));
+
+        // Wire: v_d -> Electrical input 0
+        snarl.connect(
+            OutPinId {
+                node: vd_node,
+                output: 0,
+            },
+            InPinId {
+                node: elec_node,
+                input: 0,
+            },
+        );
+        // Wire: v_q -> Electrical input 1
+        snarl.connect(
+            OutPinId {
+                node: vq_node,
+                output: 0,
+            },
+            InPinId {
+                node: elec_node,
+                input: 1,
+            },
+        );
+        // Wire: T_L -> Mechanical input 1
+        snarl.connect(
+            OutPinId {
+                node: tl_node,
+                output: 0,
+            },
+            InPinId {
+                node: mech_node,
+                input: 1,
+            },
+        );
+
+        // Note: Electrical ω_m input (pin 2) and Mechanical T_e input (pin 0)
+        // are Signal type, coming from other ODE/algebraic nodes. The solver
+        // handles the coupling internally via the state vector, not via graph wires.
+
+        let config = SimConfig {
+            t_start: 0.0,
+            t_end: 0.5,
+            rtol: 1e-6,
+            atol: 1e-8,
+            ..SimConfig::default()
+        };
+
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        // Verify Electrical node has output signals
+        let SimNode::Electrical(elec) = snarl.get_node(elec_node).expect("elec node") else {
+            panic!("expected electrical node");
+        };
+        let PortValue::Signal(i_d) = elec.output_i_d.as_ref().expect("i_d output") else {
+            panic!("expected Signal");
+        };
+        let PortValue::Signal(i_q) = elec.output_i_q.as_ref().expect("i_q output") else {
+            panic!("expected Signal");
+        };
+
+        // Should have multiple time steps
+        assert!(
+            i_d.len() > 10,
+            "expected many time steps, got {}",
+            i_d.len()
+        );
+        assert_eq!(i_d.len(), i_q.len());
+
+        // Initial conditions should be zero
+        let epsilon = 1e-10;
+        assert!(
+            i_d.first().expect("non-empty")[1].abs() < epsilon,
+            "i_d(0) should be ~0"
+        );
+        assert!(
+            i_q.first().expect("non-empty")[1].abs() < epsilon,
+            "i_q(0) should be ~0"
+        );
+
+        // With v_q=24V applied and no load, q-axis current should become non-zero
+        let last_i_q = i_q.last().expect("non-empty")[1];
+        assert!(
+            last_i_q.abs() > 0.01,
+            "i_q should be non-zero at end: {last_i_q}"
+        );
+
+        // Verify Mechanical node has output signals
+        let SimNode::Mechanical(mech) = snarl.get_node(mech_node).expect("mech node") else {
+            panic!("expected mechanical node");
+        };
+        let PortValue::Signal(omega) = mech.output_omega_m.as_ref().expect("omega output") else {
+            panic!("expected Signal");
+        };
+
+        // Motor should be spinning after 0.5s with v_q=24V
+        let last_omega = omega.last().expect("non-empty")[1];
+        assert!(
+            last_omega > 1.0,
+            "motor should be spinning: omega={last_omega}"
+        );
+
+        // Verify torque node output
+        let SimNode::Torque(torque) = snarl.get_node(torque_node).expect("torque node") else {
+            panic!("expected torque node");
+        };
+        assert!(torque.output_t_e.is_some(), "torque should have output");
+    }
+
+    /// Verify that a Constant node adapted to Vector mode produces a constant
+    /// 3-phase time series that the Park transform can consume, yielding
+    /// the expected d/q decomposition.
+    #[expect(
+        clippy::too_many_lines,
+        reason = "integration test wiring requires verbose graph setup"
+    )]
+    #[test]
+    fn constant_adapted_to_vector_feeds_park_transform() {
+        use crate::nodes::park::ParkNode;
+        use crate::port::PortType;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+
+        // Minimal ODE graph so the solver runs (it requires Electrical + Mechanical).
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+
+        // Constant adapted to Vector for Park's f_abc input.
+        let const_abc = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 1.0,   // phase a
+                value_b: 2.0, // phase b
+                value_c: 3.0, // phase c
+                output_type: PortType::Vector,
+                ..ConstantNode::default()
+            }),
+        );
+
+        // Constant adapted to Signal for Park's θ_e input (zero angle).
+        let const_theta = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 0.0,
+                output_type: PortType::Signal,
+                ..ConstantNode::default()
+            }),
+        );
+
+        let park_node = snarl.insert_node(pos, SimNode::Park(ParkNode::default()));
+
+        // Wire: const_abc -> Park input 0 (f_abc)
+        snarl.connect(
+            OutPinId {
+                node: const_abc,
+                output: 0,
+            },
+            InPinId {
+                node: park_node,
+                input: 0,
+            },
+        );
+        // Wire: const_theta -> Park input 1 (θ_e)
+        snarl.connect(
+            OutPinId {
+                node: const_theta,
+                output: 0,
+            },
+            InPinId {
+                node: park_node,
+                input: 1,
+            },
+        );
+
+        // Wire v_d/v_q constants to Electrical so solver doesn't error.
+        let vd = snarl.insert_node(pos, SimNode::Constant(ConstantNode::default()));
+        let vq = snarl.insert_node(pos, SimNode::Constant(ConstantNode::default()));
+        let tl = snarl.insert_node(pos, SimNode::Constant(ConstantNode::default()));
+        snarl.connect(
+            OutPinId {
+                node: vd,
+                output: 0,
+            },
+            InPinId {
+                node: elec_node,
+                input: 0,
+            },
+        );
+        snarl.connect(
+            OutPinId {
+                node: vq,
+                output: 0,
+            },
+            InPinId {
+                node: elec_node,
+                input: 1,
+            },
+        );
+        snarl.connect(
+            OutPinId {
+                node: tl,
+                output: 0,
+            },
+            InPinId {
+                node: mech_node,
+                input: 1,
+            },
+        );
+
+        let config = SimConfig {
+            t_start: 0.0,
+            t_end: 0.1,
+            rtol: 1e-6,
+            atol: 1e-8,
+            ..SimConfig::default()
+        };
+
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        // Verify the Constant's output_port_value was generated.
+        let SimNode::Constant(c_abc) = snarl.get_node(const_abc).expect("const node") else {
+            panic!("expected constant node");
+        };
+        let Some(PortValue::Vector(vec_data)) = &c_abc.output_port_value else {
+            panic!("expected Vector output from adapted constant");
+        };
+        assert!(!vec_data.is_empty(), "vector series should have entries");
+        // Every row should carry the constant phase values.
+        for row in vec_data {
+            let epsilon = 1e-12;
+            assert!(
+                (row[1] - 1.0).abs() < epsilon,
+                "phase a should be 1.0, got {}",
+                row[1]
+            );
+            assert!(
+                (row[2] - 2.0).abs() < epsilon,
+                "phase b should be 2.0, got {}",
+                row[2]
+            );
+            assert!(
+                (row[3] - 3.0).abs() < epsilon,
+                "phase c should be 3.0, got {}",
+                row[3]
+            );
+        }
+
+        // Verify Park transform produced outputs from the constant Vector.
+        let SimNode::Park(park) = snarl.get_node(park_node).expect("park node") else {
+            panic!("expected park node");
+        };
+        assert!(park.output_f_d.is_some(), "Park f_d should be populated");
+        assert!(park.output_f_q.is_some(), "Park f_q should be populated");
+
+        // With θ_e = 0, Park transform of [1, 2, 3] should give:
+        //   f_d = 1·cos(0) + 2·cos(-2π/3) + 3·cos(2π/3) = 1 - 1 - 1.5 = -1.5
+        //   f_q = -1·sin(0) - 2·sin(-2π/3) - 3·sin(2π/3)
+        // But the exact values depend on the Park scaling convention.
+        // Just verify non-trivial output exists.
+        let PortValue::Signal(f_d) = park.output_f_d.as_ref().expect("f_d") else {
+            panic!("expected Signal");
+        };
+        assert!(!f_d.is_empty(), "f_d series should be non-empty");
+    }
+
+    /// Verify that `ExternalInput::Signal` feeds the ODE correctly.
+    /// Constants adapted to `PortType::Signal` should produce time-series
+    /// that `resolve_external_input` picks up and interpolates during integration.
+    #[test]
+    fn signal_inputs_feed_ode() {
+        use crate::port::PortType;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+
+        // Constants adapted to Signal for v_d, v_q, T_L
+        let vd_node = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 0.0,
+                output_type: PortType::Signal,
+                ..ConstantNode::default()
+            }),
+        );
+        let vq_node = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 24.0,
+                output_type: PortType::Signal,
+                ..ConstantNode::default()
+            }),
+        );
+        let tl_node = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 0.0,
+                output_type: PortType::Signal,
+                ..ConstantNode::default()
+            }),
+        );
+
+        // Wire: v_d -> Electrical input 0
+        snarl.connect(
+            OutPinId {
+                node: vd_node,
+                output: 0,
+            },
+            InPinId {
+                node: elec_node,
+                input: 0,
+            },
+        );
+        // Wire: v_q -> Electrical input 1
+        snarl.connect(
+            OutPinId {
+                node: vq_node,
+                output: 0,
+            },
+            InPinId {
+                node: elec_node,
+                input: 1,
+            },
+        );
+        // Wire: T_L -> Mechanical input 1
+        snarl.connect(
+            OutPinId {
+                node: tl_node,
+                output: 0,
+            },
+            InPinId {
+                node: mech_node,
+                input: 1,
+            },
+        );
+
+        let config = SimConfig {
+            t_start: 0.0,
+            t_end: 0.5,
+            rtol: 1e-6,
+            atol: 1e-8,
+            ..SimConfig::default()
+        };
+
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        // Verify outputs are populated
+        let SimNode::Electrical(elec) = snarl.get_node(elec_node).expect("elec node") else {
+            panic!("expected electrical node");
+        };
+        let PortValue::Signal(i_q) = elec.output_i_q.as_ref().expect("i_q output") else {
+            panic!("expected Signal");
+        };
+
+        // With v_q=24V via Signal, results should match the Scalar case
+        let last_i_q = i_q.last().expect("non-empty")[1];
+        assert!(
+            last_i_q.abs() > 0.01,
+            "i_q should be non-zero at end: {last_i_q}"
+        );
+
+        let SimNode::Mechanical(mech) = snarl.get_node(mech_node).expect("mech node") else {
+            panic!("expected mechanical node");
+        };
+        let PortValue::Signal(omega) = mech.output_omega_m.as_ref().expect("omega output") else {
+            panic!("expected Signal");
+        };
+        let last_omega = omega.last().expect("non-empty")[1];
+        assert!(
+            last_omega > 1.0,
+            "motor should be spinning: omega={last_omega}"
+        );
+
+        // The Signal-adapted constants should also have output_port_value populated
+        let SimNode::Constant(c_vq) = snarl.get_node(vq_node).expect("vq node") else {
+            panic!("expected constant node");
+        };
+        assert!(
+            c_vq.output_port_value.is_some(),
+            "Signal-adapted constant should have output"
+        );
+    }
+
+    #[test]
+    fn interpolate_signal_edge_cases() {
+        use super::interpolate_signal;
+
+        // Empty signal returns 0.0
+        assert_eq!(interpolate_signal(&[], 1.0), 0.0);
+
+        // Single point returns that value for any t
+        let single = [[5.0, 42.0]];
+        assert_eq!(interpolate_signal(&single, 0.0), 42.0);
+        assert_eq!(interpolate_signal(&single, 5.0), 42.0);
+        assert_eq!(interpolate_signal(&single, 100.0), 42.0);
+
+        // Clamps to boundaries
+        let series = [[0.0, 10.0], [1.0, 20.0], [2.0, 30.0]];
+        assert_eq!(interpolate_signal(&series, -1.0), 10.0);
+        assert_eq!(interpolate_signal(&series, 3.0), 30.0);
+
+        // Exact boundary values
+        let eps = 1e-12;
+        assert!((interpolate_signal(&series, 0.0) - 10.0).abs() < eps);
+        assert!((interpolate_signal(&series, 2.0) - 30.0).abs() < eps);
+
+        // Midpoint interpolation
+        assert!((interpolate_signal(&series, 0.5) - 15.0).abs() < eps);
+        assert!((interpolate_signal(&series, 1.5) - 25.0).abs() < eps);
+    }
+
+    #[test]
+    fn interpolate_vector_edge_cases() {
+        use super::interpolate_vector;
+
+        // Empty signal returns [0, 0, 0]
+        assert_eq!(interpolate_vector(&[], 1.0), [0.0; 3]);
+
+        // Single point returns those values for any t
+        let single = [[5.0, 1.0, 2.0, 3.0]];
+        assert_eq!(interpolate_vector(&single, 0.0), [1.0, 2.0, 3.0]);
+        assert_eq!(interpolate_vector(&single, 5.0), [1.0, 2.0, 3.0]);
+        assert_eq!(interpolate_vector(&single, 100.0), [1.0, 2.0, 3.0]);
+
+        // Clamps to boundaries
+        let series = [
+            [0.0, 10.0, 20.0, 30.0],
+            [1.0, 40.0, 50.0, 60.0],
+            [2.0, 70.0, 80.0, 90.0],
+        ];
+        assert_eq!(interpolate_vector(&series, -1.0), [10.0, 20.0, 30.0]);
+        assert_eq!(interpolate_vector(&series, 3.0), [70.0, 80.0, 90.0]);
+
+        // Midpoint interpolation
+        let eps = 1e-12;
+        let mid = interpolate_vector(&series, 0.5);
+        assert!((mid[0] - 25.0).abs() < eps, "a: {}", mid[0]);
+        assert!((mid[1] - 35.0).abs() < eps, "b: {}", mid[1]);
+        assert!((mid[2] - 45.0).abs() < eps, "c: {}", mid[2]);
+    }
+
+    /// Verify that a Park node wired between a 3-phase constant and the Electrical
+    /// node, with `theta_e` from the Mechanical node (circular ODE dependency),
+    /// produces non-zero motor currents and speed.
+    #[expect(
+        clippy::too_many_lines,
+        reason = "integration test exercises full Park-coupled simulation pipeline"
+    )]
+    #[test]
+    fn park_coupled_to_ode_produces_nonzero_output() {
+        use crate::nodes::park::ParkNode;
+        use crate::port::PortType;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+
+        // -- ODE nodes --
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+
+        // -- Park node --
+        let park_node = snarl.insert_node(pos, SimNode::Park(ParkNode::default()));
+
+        // -- 3-phase voltage source (Constant in Vector mode) --
+        // v_a=0, v_b=100, v_c=-100 produces v_q ≈ 115 V at θ_e=0 via Park,
+        // which drives q-axis current and generates torque to spin the motor.
+        let const_abc = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 0.0,
+                value_b: 100.0,
+                value_c: -100.0,
+                output_type: PortType::Vector,
+                ..ConstantNode::default()
+            }),
+        );
+
+        // -- Load torque = 0 --
+        let tl_node = snarl.insert_node(pos, SimNode::Constant(ConstantNode::default()));
+
+        // Wire: const_abc -> Park input 0 (f_abc)
+        snarl.connect(
+            OutPinId {
+                node: const_abc,
+                output: 0,
+            },
+            InPinId {
+                node: park_node,
+                input: 0,
+            },
+        );
+        // Wire: Mechanical theta_e (output 1) -> Park input 1 (theta_e)
+        snarl.connect(
+            OutPinId {
+                node: mech_node,
+                output: 1,
+            },
+            InPinId {
+                node: park_node,
+                input: 1,
+            },
+        );
+        // Wire: Park f_d (output 0) -> Electrical input 0 (v_d)
+        snarl.connect(
+            OutPinId {
+                node: park_node,
+                output: 0,
+            },
+            InPinId {
+                node: elec_node,
+                input: 0,
+            },
+        );
+        // Wire: Park f_q (output 1) -> Electrical input 1 (v_q)
+        snarl.connect(
+            OutPinId {
+                node: park_node,
+                output: 1,
+            },
+            InPinId {
+                node: elec_node,
+                input: 1,
+            },
+        );
+        // Wire: T_L -> Mechanical input 1
+        snarl.connect(
+            OutPinId {
+                node: tl_node,
+                output: 0,
+            },
+            InPinId {
+                node: mech_node,
+                input: 1,
+            },
+        );
+
+        let config = SimConfig {
+            t_start: 0.0,
+            t_end: 0.5,
+            rtol: 1e-6,
+            atol: 1e-8,
+            ..SimConfig::default()
+        };
+
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        // Verify Electrical node has non-zero outputs
+        let SimNode::Electrical(elec) = snarl.get_node(elec_node).expect("elec node") else {
+            panic!("expected electrical node");
+        };
+        let PortValue::Signal(i_d) = elec.output_i_d.as_ref().expect("i_d output") else {
+            panic!("expected Signal");
+        };
+        let PortValue::Signal(i_q) = elec.output_i_q.as_ref().expect("i_q output") else {
+            panic!("expected Signal");
+        };
+
+        assert!(
+            i_d.len() > 10,
+            "expected many time steps, got {}",
+            i_d.len()
+        );
+
+        // With a 3-phase voltage applied via Park-coupled path, currents must be non-zero.
+        // This is the key assertion: the old code would produce all zeros here.
+        let last_i_d = i_d.last().expect("non-empty")[1];
+        let last_i_q = i_q.last().expect("non-empty")[1];
+        assert!(
+            last_i_d.abs() > 0.01 || last_i_q.abs() > 0.01,
+            "Park-coupled currents should be non-zero: i_d={last_i_d}, i_q={last_i_q}"
+        );
+
+        // Verify Mechanical node shows rotation
+        let SimNode::Mechanical(mech) = snarl.get_node(mech_node).expect("mech node") else {
+            panic!("expected mechanical node");
+        };
+        let PortValue::Signal(omega) = mech.output_omega_m.as_ref().expect("omega output") else {
+            panic!("expected Signal");
+        };
+        let last_omega = omega.last().expect("non-empty")[1];
+        assert!(
+            last_omega.abs() > 0.01,
+            "motor should be spinning: omega={last_omega}"
+        );
+
+        // Verify Park outputs are populated post-ODE (step 13 re-compute)
+        let SimNode::Park(park) = snarl.get_node(park_node).expect("park node") else {
+            panic!("expected park node");
+        };
+        assert!(
+            park.output_f_d.is_some(),
+            "Park f_d should be populated post-ODE"
+        );
+        assert!(
+            park.output_f_q.is_some(),
+            "Park f_q should be populated post-ODE"
+        );
+    }
+
+    /// Final speed of the default PMSM with `v_q` = 24 V applied directly.
+    fn direct_dq_final_speed(config: &SimConfig) -> f64 {
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let vq = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 24.0,
+                ..ConstantNode::default()
+            }),
+        );
+        let elec = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        snarl.connect(
+            OutPinId {
+                node: vq,
+                output: 0,
+            },
+            InPinId {
+                node: elec,
+                input: 1,
+            },
+        );
+        run_simulation(&mut snarl, config).expect("dq simulation should succeed");
+        let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+            panic!("expected mechanical node");
+        };
+        let Some(PortValue::Signal(omega)) = mech.output_omega_m.as_ref() else {
+            panic!("expected omega signal");
+        };
+        omega.last().expect("non-empty")[1]
+    }
+
+    /// A dq-frame SVPWM driven by the ODE angle feeds the motor through Park:
+    /// in the linear range the averaged voltages reproduce the dq reference.
+    #[test]
+    fn svpwm_coupled_to_ode_tracks_reference() {
+        use crate::nodes::park::ParkNode;
+        use crate::nodes::svpwm::SvpwmNode;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let park_node = snarl.insert_node(pos, SimNode::Park(ParkNode::default()));
+        let svpwm_node = snarl.insert_node(
+            pos,
+            SimNode::Svpwm(SvpwmNode {
+                t_min: 0.0,
+                ..SvpwmNode::default()
+            }),
+        );
+        let vd = snarl.insert_node(pos, SimNode::Constant(ConstantNode::default()));
+        let vq = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 24.0,
+                ..ConstantNode::default()
+            }),
+        );
+        let vdc = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 100.0,
+                ..ConstantNode::default()
+            }),
+        );
+
+        let wires = [
+            (vd, 0, svpwm_node, 0),
+            (vq, 0, svpwm_node, 1),
+            (mech_node, 1, svpwm_node, 2),
+            (vdc, 0, svpwm_node, 3),
+            (svpwm_node, 1, park_node, 0),
+            (mech_node, 1, park_node, 1),
+            (park_node, 0, elec_node, 0),
+            (park_node, 1, elec_node, 1),
+        ];
+        for (from, output, to, input) in wires {
+            snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+        }
+
+        let config = SimConfig {
+            t_start: 0.0,
+            t_end: 0.5,
+            ..SimConfig::default()
+        };
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        // The motor should reach the same speed as with v_q = 24 V applied directly.
+        let SimNode::Mechanical(mech) = snarl.get_node(mech_node).expect("mech node") else {
+            panic!("expected mechanical node");
+        };
+        let PortValue::Signal(omega) = mech.output_omega_m.as_ref().expect("omega output") else {
+            panic!("expected Signal");
+        };
+        let last_omega = omega.last().expect("non-empty")[1];
+
+        let direct_omega = direct_dq_final_speed(&config);
+        assert!(
+            direct_omega > 1.0,
+            "motor should be spinning: omega={direct_omega}"
+        );
+        assert!(
+            (last_omega - direct_omega).abs() < 1e-3 * direct_omega,
+            "SVPWM-fed and directly driven speeds agree: {last_omega} vs {direct_omega}"
+        );
+
+        // Park of the averaged SVPWM output should recover v_q ≈ 24 V.
+        let SimNode::Park(park) = snarl.get_node(park_node).expect("park node") else {
+            panic!("expected park node");
+        };
+        let PortValue::Signal(f_q) = park.output_f_q.as_ref().expect("f_q") else {
+            panic!("expected Signal");
+        };
+        let last_v_q = f_q.last().expect("non-empty")[1];
+        assert!(
+            (last_v_q - 24.0).abs() < 1e-6,
+            "Park(v_abc) should recover v_q: {last_v_q}"
+        );
+
+        let SimNode::Svpwm(svpwm) = snarl.get_node(svpwm_node).expect("svpwm node") else {
+            panic!("expected svpwm node");
+        };
+        assert!(
+            svpwm.output_duty.is_some(),
+            "duty cycles should be populated"
+        );
+    }
+
+    /// A back-EMF observer fed the SVPWM phase voltages and the phase
+    /// currents tracks the solved rotor angle and speed.
+    #[test]
+    fn observer_tracks_svpwm_driven_pmsm() {
+        use crate::nodes::observer::ObserverNode;
+        use crate::nodes::park::{InverseParkNode, ParkNode};
+        use crate::nodes::svpwm::SvpwmNode;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let park_node = snarl.insert_node(pos, SimNode::Park(ParkNode::default()));
+        let inv_park_node =
+            snarl.insert_node(pos, SimNode::InversePark(InverseParkNode::default()));
+        let observer_node = snarl.insert_node(pos, SimNode::Observer(ObserverNode::default()));
+        let svpwm_node = snarl.insert_node(
+            pos,
+            SimNode::Svpwm(SvpwmNode {
+                t_min: 0.0,
+                ..SvpwmNode::default()
+            }),
+        );
+        let [vd, vq, vdc] = [0.0, 24.0, 100.0].map(|value| {
+            snarl.insert_node(
+                pos,
+                SimNode::Constant(ConstantNode {
+                    value,
+                    ..ConstantNode::default()
+                }),
+            )
+        });
+        let wires = [
+            (vd, 0, svpwm_node, 0),
+            (vq, 0, svpwm_node, 1),
+            (mech_node, 1, svpwm_node, 2),
+            (vdc, 0, svpwm_node, 3),
+            (svpwm_node, 1, park_node, 0),
+            (mech_node, 1, park_node, 1),
+            (park_node, 0, elec_node, 0),
+            (park_node, 1, elec_node, 1),
+            (elec_node, 0, inv_park_node, 0),
+            (elec_node, 1, inv_park_node, 1),
+            (mech_node, 1, inv_park_node, 2),
+            (svpwm_node, 1, observer_node, 0),
+            (inv_park_node, 0, observer_node, 1),
+            (mech_node, 1, observer_node, 2),
+        ];
+        for (from, output, to, input) in wires {
+            snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+        }
+
+        let config = SimConfig {
+            t_end: 0.5,
+            output_dt: 1e-4,
+            ..SimConfig::default()
+        };
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+            panic!("expected mechanical node");
+        };
+        let Some(PortValue::Signal(omega)) = mech.output_omega_m.as_ref() else {
+            panic!("expected omega signal");
+        };
+        let omega = omega.last().expect("non-empty")[1];
+        let Some(SimNode::Observer(observer)) = snarl.get_node(observer_node) else {
+            panic!("expected observer node");
+        };
+        let (Some(PortValue::Signal(error)), Some(PortValue::Signal(omega_hat))) = (
+            observer.output_error.as_ref(),
+            observer.output_omega_m.as_ref(),
+        ) else {
+            panic!("expected observer outputs");
+        };
+        let (error, omega_hat) = (
+            error.last().expect("non-empty")[1],
+            omega_hat.last().expect("non-empty")[1],
+        );
+        assert!(error.abs() < 0.05, "angle error {error}");
+        assert!(
+            (omega_hat - omega).abs() < 0.02 * omega,
+            "estimated speed {omega_hat} vs {omega}"
+        );
+    }
+
+    /// An EKF fed the SVPWM phase voltages and the phase currents tracks
+    /// the solved rotor angle and speed through the run-up.
+    #[test]
+    fn ekf_tracks_svpwm_driven_pmsm() {
+        use crate::nodes::ekf::EkfNode;
+        use crate::nodes::park::{InverseParkNode, ParkNode};
+        use crate::nodes::svpwm::SvpwmNode;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let park_node = snarl.insert_node(pos, SimNode::Park(ParkNode::default()));
+        let inv_park_node =
+            snarl.insert_node(pos, SimNode::InversePark(InverseParkNode::default()));
+        let ekf_node = snarl.insert_node(pos, SimNode::Ekf(EkfNode::default()));
+        let svpwm_node = snarl.insert_node(
+            pos,
+            SimNode::Svpwm(SvpwmNode {
+                t_min: 0.0,
+                ..SvpwmNode::default()
+            }),
+        );
+        let [vd, vq, vdc] = [0.0, 24.0, 100.0].map(|value| {
+            snarl.insert_node(
+                pos,
+                SimNode::Constant(ConstantNode {
+                    value,
+                    ..ConstantNode::default()
+                }),
+            )
+        });
+        let wires = [
+            (vd, 0, svpwm_node, 0),
+            (vq, 0, svpwm_node, 1),
+            (mech_node, 1, svpwm_node, 2),
+            (vdc, 0, svpwm_node, 3),
+            (svpwm_node, 1, park_node, 0),
+            (mech_node, 1, park_node, 1),
+            (park_node, 0, elec_node, 0),
+            (park_node, 1, elec_node, 1),
+            (elec_node, 0, inv_park_node, 0),
+            (elec_node, 1, inv_park_node, 1),
+            (mech_node, 1, inv_park_node, 2),
+            (svpwm_node, 1, ekf_node, 0),
+            (inv_park_node, 0, ekf_node, 1),
+            (mech_node, 1, ekf_node, 2),
+        ];
+        for (from, output, to, input) in wires {
+            snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+        }
+
+        let config = SimConfig {
+            t_end: 0.5,
+            output_dt: 1e-4,
+            ..SimConfig::default()
+        };
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+            panic!("expected mechanical node");
+        };
+        let Some(PortValue::Signal(omega)) = mech.output_omega_m.as_ref() else {
+            panic!("expected omega signal");
+        };
+        let omega = omega.last().expect("non-empty")[1];
+        let Some(SimNode::Ekf(ekf)) = snarl.get_node(ekf_node) else {
+            panic!("expected EKF node");
+        };
+        let (Some(PortValue::Signal(error)), Some(PortValue::Signal(omega_hat))) =
+            (ekf.output_error.as_ref(), ekf.output_omega_m.as_ref())
+        else {
+            panic!("expected EKF outputs");
+        };
+        let (error, omega_hat) = (
+            error.last().expect("non-empty")[1],
+            omega_hat.last().expect("non-empty")[1],
+        );
+        assert!(error.abs() < 0.05, "angle error {error}");
+        assert!(
+            (omega_hat - omega).abs() < 0.02 * omega,
+            "estimated speed {omega_hat} vs {omega}"
+        );
+    }
+
+    /// An SVPWM-driven switched inverter lands the solver on every edge and
+    /// tracks the averaged model closely when the switching ripple is small.
+    #[test]
+    fn switched_inverter_tracks_averaged_model() {
+        use crate::nodes::inverter::InverterNode;
+        use crate::nodes::park::ParkNode;
+        use crate::nodes::svpwm::SvpwmNode;
+
+        let t_end = 0.02;
+        let final_speed = |switched: bool| {
+            let mut snarl: Snarl<SimNode> = Snarl::new();
+            let pos = egui::pos2(0.0, 0.0);
+            let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+            let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+            let park_node = snarl.insert_node(pos, SimNode::Park(ParkNode::default()));
+            let svpwm_node = snarl.insert_node(
+                pos,
+                SimNode::Svpwm(SvpwmNode {
+                    t_min: 0.0,
+                    ..SvpwmNode::default()
+                }),
+            );
+            let inverter_node = snarl.insert_node(
+                pos,
+                SimNode::Inverter(InverterNode {
+                    t_dead: 0.0,
+                    ..InverterNode::default()
+                }),
+            );
+            let constant = |snarl: &mut Snarl<SimNode>, value| {
+                snarl.insert_node(
+                    pos,
+                    SimNode::Constant(ConstantNode {
+                        value,
+                        ..ConstantNode::default()
+                    }),
+                )
+            };
+            let vd = constant(&mut snarl, 0.0);
+            let vq = constant(&mut snarl, 24.0);
+            let vdc = constant(&mut snarl, 100.0);
+
+            let mut wires = vec![
+                (vd, 0, svpwm_node, 0),
+                (vq, 0, svpwm_node, 1),
+                (mech_node, 1, svpwm_node, 2),
+                (vdc, 0, svpwm_node, 3),
+                (mech_node, 1, park_node, 1),
+                (park_node, 0, elec_node, 0),
+                (park_node, 1, elec_node, 1),
+            ];
+            if switched {
+                wires.extend([
+                    (svpwm_node, 0, inverter_node, 0),
+                    (vdc, 0, inverter_node, 1),
+                    (inverter_node, 0, park_node, 0),
+                ]);
+            } else {
+                wires.push((svpwm_node, 1, park_node, 0));
+            }
+            for (from, output, to, input) in wires {
+                snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+            }
+
+            let config = SimConfig {
+                t_start: 0.0,
+                t_end,
+                ..SimConfig::default()
+            };
+            run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+            if switched {
+                let SimNode::Inverter(inv) = snarl.get_node(inverter_node).expect("inverter")
+                else {
+                    panic!("expected inverter node");
+                };
+                let PortValue::Vector(v_abc) = inv.output_v_abc.as_ref().expect("v_abc") else {
+                    panic!("expected Vector");
+                };
+                let levels_ok = v_abc.iter().all(|row| {
+                    row.iter().skip(1).all(|v| {
+                        [0.0, 100.0 / 3.0, 200.0 / 3.0]
+                            .iter()
+                            .any(|level| (v.abs() - level).abs() < 1e-9)
+                    })
+                });
+                assert!(levels_ok, "phase voltages should take two-level values");
+            }
+
+            let SimNode::Mechanical(mech) = snarl.get_node(mech_node).expect("mech node") else {
+                panic!("expected mechanical node");
+            };
+            let PortValue::Signal(omega) = mech.output_omega_m.as_ref().expect("omega output")
+            else {
+                panic!("expected Signal");
+            };
+            omega.last().expect("non-empty")[1]
+        };
+
+        let averaged = final_speed(false);
+        let switched = final_speed(true);
+        assert!(averaged > 1.0, "motor should be spinning: omega={averaged}");
+        assert!(
+            (switched - averaged).abs() < 0.02 * averaged,
+            "switched {switched} vs averaged {averaged}"
+        );
+    }
+
+    /// An SVPWM fed from the DC link draws the motor power from the battery:
+    /// the bus sags while the motor accelerates and the battery current
+    /// carries the inverter load once the capacitor has settled.
+    #[test]
+    fn dc_link_sags_under_motor_load() {
+        use crate::nodes::dc_link::DcLinkNode;
+        use crate::nodes::park::ParkNode;
+        use crate::nodes::svpwm::SvpwmNode;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let park_node = snarl.insert_node(pos, SimNode::Park(ParkNode::default()));
+        let svpwm_node = snarl.insert_node(
+            pos,
+            SimNode::Svpwm(SvpwmNode {
+                t_min: 0.0,
+                ..SvpwmNode::default()
+            }),
+        );
+        let link_node = snarl.insert_node(
+            pos,
+            SimNode::DcLink(DcLinkNode {
+                r_bat: 0.2,
+                ..DcLinkNode::default()
+            }),
+        );
+        let vd = snarl.insert_node(pos, SimNode::Constant(ConstantNode::default()));
+        let vq = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 24.0,
+                ..ConstantNode::default()
+            }),
+        );
+
+        let wires = [
+            (vd, 0, svpwm_node, 0),
+            (vq, 0, svpwm_node, 1),
+            (mech_node, 1, svpwm_node, 2),
+            (link_node, 0, svpwm_node, 3),
+            (svpwm_node, 1, park_node, 0),
+            (mech_node, 1, park_node, 1),
+            (park_node, 0, elec_node, 0),
+            (park_node, 1, elec_node, 1),
+        ];
+        for (from, output, to, input) in wires {
+            snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+        }
+
+        let config = SimConfig {
+            t_start: 0.0,
+            t_end: 0.5,
+            ..SimConfig::default()
+        };
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        let SimNode::DcLink(link) = snarl.get_node(link_node).expect("link node") else {
+            panic!("expected DC link node");
+        };
+        let signal = |value: Option<&PortValue>| match value {
+            Some(PortValue::Signal(data)) => data.clone(),
+            _ => panic!("expected Signal"),
+        };
+        let v_dc = signal(link.output_v_dc.as_ref());
+        let i_bat = signal(link.output_i_bat.as_ref());
+        let i_dc = signal(link.output_i_dc.as_ref());
+
+        let v_min = v_dc.iter().map(|s| s[1]).fold(f64::INFINITY, f64::min);
+        assert!(
+            v_min < link.v_oc - 0.5,
+            "bus should sag during acceleration: min {v_min}"
+        );
+
+        let last_bat = i_bat.last().expect("non-empty")[1];
+        let last_dc = i_dc.last().expect("non-empty")[1];
+        assert!(last_dc > 0.0, "inverter should draw current: {last_dc}");
+        assert!(
+            (last_bat - last_dc).abs() < 1e-3 * last_dc.abs().max(1.0),
+            "battery should carry the load once settled: {last_bat} vs {last_dc}"
+        );
+    }
+
+    /// A flux map built from the linear inductances reproduces the
+    /// current-state model, exercising the flux-state RHS and Jacobian.
+    #[test]
+    fn linear_flux_map_matches_current_model() {
+        use crate::nodes::flux_map::FluxMap;
+
+        let run = |flux_map: Option<FluxMap>| {
+            let mut snarl: Snarl<SimNode> = Snarl::new();
+            let pos = egui::pos2(0.0, 0.0);
+            let vq_node = snarl.insert_node(
+                pos,
+                SimNode::Constant(ConstantNode {
+                    value: 24.0,
+                    ..ConstantNode::default()
+                }),
+            );
+            let elec_node = snarl.insert_node(
+                pos,
+                SimNode::Electrical(ElectricalNode {
+                    flux_map: flux_map.map(Box::new),
+                    ..ElectricalNode::default()
+                }),
+            );
+            snarl.insert_node(pos, SimNode::Torque(TorqueNode::default()));
+            let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+            snarl.connect(
+                OutPinId {
+                    node: vq_node,
+                    output: 0,
+                },
+                InPinId {
+                    node: elec_node,
+                    input: 1,
+                },
+            );
+            let config = SimConfig {
+                t_end: 0.2,
+                rtol: 1e-6,
+                atol: 1e-8,
+                ..SimConfig::default()
+            };
+            run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+            let Some(SimNode::Electrical(elec)) = snarl.get_node(elec_node) else {
+                panic!("expected electrical node");
+            };
+            let Some(PortValue::Signal(i_q)) = elec.output_i_q.as_ref() else {
+                panic!("expected i_q signal");
+            };
+            let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+                panic!("expected mechanical node");
+            };
+            let Some(PortValue::Signal(omega)) = mech.output_omega_m.as_ref() else {
+                panic!("expected omega signal");
+            };
+            (
+                i_q.last().expect("non-empty")[1],
+                omega.last().expect("non-empty")[1],
+            )
+        };
+
+        let e = ElectricalNode::default();
+        let (i_q_lin, omega_lin) = run(None);
+        let (i_q_map, omega_map) = run(Some(FluxMap::linear(e.l_d, e.l_q, e.lambda_m, 50.0, 11)));
+        assert!(
+            (omega_map - omega_lin).abs() < 1e-3 * omega_lin.abs().max(1.0),
+            "ω_m: map {omega_map} vs linear {omega_lin}"
+        );
+        assert!(
+            (i_q_map - i_q_lin).abs() < 1e-2 * i_q_lin.abs().max(1.0),
+            "i_q: map {i_q_map} vs linear {i_q_lin}"
+        );
+    }
+
+    /// Iron loss slows the rotor and is reported together with the copper loss.
+    #[test]
+    fn iron_loss_brakes_rotor_and_reports_losses() {
+        use crate::nodes::electrical::{IronLoss, IronLossModel};
+
+        let run = |iron_loss: IronLoss| {
+            let mut snarl: Snarl<SimNode> = Snarl::new();
+            let pos = egui::pos2(0.0, 0.0);
+            let vq_node = snarl.insert_node(
+                pos,
+                SimNode::Constant(ConstantNode {
+                    value: 24.0,
+                    ..ConstantNode::default()
+                }),
+            );
+            let elec_node = snarl.insert_node(
+                pos,
+                SimNode::Electrical(ElectricalNode {
+                    iron_loss,
+                    ..ElectricalNode::default()
+                }),
+            );
+            let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+            snarl.connect(
+                OutPinId {
+                    node: vq_node,
+                    output: 0,
+                },
+                InPinId {
+                    node: elec_node,
+                    input: 1,
+                },
+            );
+            let config = SimConfig {
+                t_end: 0.5,
+                ..SimConfig::default()
+            };
+            run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+            let Some(SimNode::Electrical(elec)) = snarl.get_node(elec_node) else {
+                panic!("expected electrical node");
+            };
+            let last = |value: Option<&PortValue>| {
+                let Some(PortValue::Signal(series)) = value else {
+                    panic!("expected signal output");
+                };
+                series.last().expect("non-empty")[1]
+            };
+            let p_cu = last(elec.output_p_cu.as_ref());
+            let p_fe = last(elec.output_p_fe.as_ref());
+            let i_q = last(elec.output_i_q.as_ref());
+            let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+                panic!("expected mechanical node");
+            };
+            (last(mech.output_omega_m.as_ref()), p_cu, p_fe, i_q)
+        };
+
+        let (omega_free, _, p_fe_free, _) = run(IronLoss::default());
+        let (omega_fe, p_cu, p_fe, i_q) = run(IronLoss {
+            model: IronLossModel::Resistance,
+            r_fe: 20.0,
+            ..IronLoss::default()
+        });
+        assert!(
+            p_fe_free.abs() < 1e-12,
+            "no iron loss by default: {p_fe_free}"
+        );
+        assert!(p_fe > 0.0, "iron loss should be positive: {p_fe}");
+        assert!(
+            omega_fe < omega_free,
+            "iron loss should slow the rotor: {omega_fe} vs {omega_free}"
+        );
+        let e = ElectricalNode::default();
+        assert!(
+            p_cu >= 1.5 * e.r_s * i_q * i_q - 1e-9,
+            "copper loss covers the q-axis current: {p_cu}"
+        );
+    }
+
+    /// A thermal network heated by the copper loss settles at
+    /// `T_amb + R·P_cu`, with `P_cu` taken at the hot resistance, and the
+    /// weakened magnet needs more current for the same load.
+    #[test]
+    #[expect(
+        clippy::too_many_lines,
+        reason = "integration test wiring requires verbose graph setup"
+    )]
+    fn thermal_network_heats_winding_and_weakens_magnet() {
+        use crate::nodes::thermal::{ThermalBody, ThermalLink, ThermalNode};
+
+        let run = |feedback: bool| {
+            let mut snarl: Snarl<SimNode> = Snarl::new();
+            let pos = egui::pos2(0.0, 0.0);
+            let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+            let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+            // Fast bodies so the temperatures settle within the run
+            let network = ThermalNode {
+                bodies: vec![
+                    ThermalBody {
+                        name: "Winding".to_owned(),
+                        c: 0.001,
+                        cu_share: 1.0,
+                        ..ThermalBody::default()
+                    },
+                    ThermalBody {
+                        name: "Magnet".to_owned(),
+                        c: 0.001,
+                        ..ThermalBody::default()
+                    },
+                ],
+                links: vec![
+                    ThermalLink {
+                        from: 0,
+                        to: None,
+                        r: 5.0,
+                    },
+                    ThermalLink {
+                        from: 1,
+                        to: Some(0),
+                        r: 1.0,
+                    },
+                ],
+                winding: 0,
+                magnet: 1,
+                feedback_r_s: feedback,
+                feedback_lambda_m: feedback,
+                ..ThermalNode::default()
+            };
+            let t_ambient = network.t_ambient;
+            let thermal_node = snarl.insert_node(pos, SimNode::Thermal(network));
+            for (value, to, input) in [(24.0, elec_node, 1), (0.5, mech_node, 1)] {
+                let source = snarl.insert_node(
+                    pos,
+                    SimNode::Constant(ConstantNode {
+                        value,
+                        ..ConstantNode::default()
+                    }),
+                );
+                snarl.connect(
+                    OutPinId {
+                        node: source,
+                        output: 0,
+                    },
+                    InPinId { node: to, input },
+                );
+            }
+            for (output, input) in [(2, 0), (3, 1)] {
+                snarl.connect(
+                    OutPinId {
+                        node: elec_node,
+                        output,
+                    },
+                    InPinId {
+                        node: thermal_node,
+                        input,
+                    },
+                );
+            }
+            let config = SimConfig {
+                t_end: 1.5,
+                ..SimConfig::default()
+            };
+            run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+            let last = |value: Option<&PortValue>| {
+                let Some(PortValue::Signal(series)) = value else {
+                    panic!("expected signal output");
+                };
+                series.last().expect("non-empty")[1]
+            };
+            let Some(SimNode::Electrical(elec)) = snarl.get_node(elec_node) else {
+                panic!("expected electrical node");
+            };
+            let (i_d, i_q) = (
+                last(elec.output_i_d.as_ref()),
+                last(elec.output_i_q.as_ref()),
+            );
+            let p_cu = last(elec.output_p_cu.as_ref());
+            let Some(SimNode::Thermal(th)) = snarl.get_node(thermal_node) else {
+                panic!("expected thermal node");
+            };
+            let (t_w, t_pm) = (last(th.output_t_w.as_ref()), last(th.output_t_pm.as_ref()));
+            assert!(
+                (t_w - (t_ambient + 5.0 * p_cu)).abs() < 1e-2 * (t_w - t_ambient),
+                "winding at T_amb + R·P_cu: {t_w} vs {p_cu} W"
+            );
+            assert!((t_pm - t_w).abs() < 1e-2, "magnet follows the winding");
+            (t_w, i_d, i_q, p_cu)
+        };
+
+        let (_, _, i_q_cold, _) = run(false);
+        let (t_w, i_d, i_q, p_cu) = run(true);
+        let e = ElectricalNode::default();
+        let expected = 1.5 * e.r_s_at(t_w) * (i_d * i_d + i_q * i_q);
+        assert!(
+            (p_cu - expected).abs() < 1e-6 * expected,
+            "copper loss at the hot resistance: {p_cu} vs {expected}"
+        );
+        assert!(
+            i_q > 1.01 * i_q_cold,
+            "hot magnet needs more current: {i_q} vs {i_q_cold}"
+        );
+    }
+
+    /// Back-EMF harmonics and cogging show up as torque ripple at steady speed.
+    #[test]
+    fn spatial_harmonics_produce_torque_ripple() {
+        use crate::nodes::harmonics::{CoggingModel, Harmonic, SpatialHarmonics};
+
+        let ripple = |harmonics: SpatialHarmonics| {
+            let mut snarl: Snarl<SimNode> = Snarl::new();
+            let pos = egui::pos2(0.0, 0.0);
+            let vq_node = snarl.insert_node(
+                pos,
+                SimNode::Constant(ConstantNode {
+                    value: 24.0,
+                    ..ConstantNode::default()
+                }),
+            );
+            let elec_node = snarl.insert_node(
+                pos,
+                SimNode::Electrical(ElectricalNode {
+                    harmonics: Box::new(harmonics),
+                    ..ElectricalNode::default()
+                }),
+            );
+            let torque_node = snarl.insert_node(pos, SimNode::Torque(TorqueNode::default()));
+            snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+            snarl.connect(
+                OutPinId {
+                    node: vq_node,
+                    output: 0,
+                },
+                InPinId {
+                    node: elec_node,
+                    input: 1,
+                },
+            );
+            let config = SimConfig {
+                t_end: 0.5,
+                output_dt: 1e-4,
+                ..SimConfig::default()
+            };
+            run_simulation(&mut snarl, &config).expect("simulation should succeed");
 
-    use crate::nodes::SimNode;
-    use crate::nodes::constant::ConstantNode;
-    use crate::nodes::electrical::ElectricalNode;
-    use crate::nodes::mechanical::MechanicalNode;
-    use crate::nodes::plot::PlotNode;
-    use crate::nodes::torque::TorqueNode;
-    use crate::port::PortValue;
-    use crate::simulation::SimConfig;
+            let Some(SimNode::Torque(torque)) = snarl.get_node(torque_node) else {
+                panic!("expected torque node");
+            };
+            let Some(PortValue::Signal(t_e)) = torque.output_t_e.as_ref() else {
+                panic!("expected T_e signal");
+            };
+            let tail = t_e.iter().filter(|s| s[0] >= 0.45).map(|s| s[1]);
+            let (lo, hi) = tail.fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| {
+                (lo.min(v), hi.max(v))
+            });
+            hi - lo
+        };
 
-    use super::run_simulation;
+        let ideal = ripple(SpatialHarmonics::default());
+        let emf = ripple(SpatialHarmonics {
+            emf: vec![
+                Harmonic {
+                    order: 5,
+                    amplitude: 0.05,
+                    phase: 0.0,
+                },
+                Harmonic {
+                    order: 7,
+                    amplitude: 0.03,
+                    phase: 0.0,
+                },
+            ],
+            ..SpatialHarmonics::default()
+        });
+        let cogging = ripple(SpatialHarmonics {
+            cogging: CoggingModel::Fourier,
+            cogging_fourier: vec![Harmonic {
+                order: 24,
+                amplitude: 0.05,
+                phase: 0.0,
+            }],
+            ..SpatialHarmonics::default()
+        });
+        assert!(ideal < 1e-3, "ideal machine has no ripple: {ideal}");
+        assert!(
+            emf > 0.01,
+            "back-EMF harmonics should ripple the torque: {emf}"
+        );
+        assert!(
+            cogging > 0.05,
+            "cogging should ripple the torque by at least its amplitude: {cogging}"
+        );
+    }
 
-    /// Build a minimal PMSM graph and run the solver, verifying that outputs
-    /// are populated and physically plausible.
-    #[expect(
-        clippy::too_many_lines,
-        reason = "integration test exercises full simulation pipeline"
-    )]
-    #[test]
-    fn pmsm_simulation_produces_plausible_results() {
-        let mut snarl: Snarl<SimNode> = Snarl::new();
+    /// Build an SVPWM-fed abc machine (`v_q` = 24 V in the rotor frame) and
+    /// return the graph with the machine and mechanical node ids.
+    fn abc_machine_graph(
+        machine: crate::nodes::abc_machine::AbcMachineNode,
+    ) -> (Snarl<SimNode>, egui_snarl::NodeId, egui_snarl::NodeId) {
+        use crate::nodes::svpwm::SvpwmNode;
 
+        let mut snarl: Snarl<SimNode> = Snarl::new();
         let pos = egui::pos2(0.0, 0.0);
-
-        // Create nodes
-        let vd_node = snarl.insert_node(
+        let machine_node = snarl.insert_node(pos, SimNode::AbcMachine(Box::new(machine)));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let svpwm_node = snarl.insert_node(
             pos,
-            SimNode::Constant(ConstantNode {
-                value: 0.0,
-                ..ConstantNode::default()
+            SimNode::Svpwm(SvpwmNode {
+                t_min: 0.0,
+                ..SvpwmNode::default()
             }),
         );
-        let vq_node = snarl.insert_node(
+        let vq = snarl.insert_node(
             pos,
             SimNode::Constant(ConstantNode {
                 value: 24.0,
                 ..ConstantNode::default()
             }),
         );
-        let tl_node = snarl.insert_node(
+        let vdc = snarl.insert_node(
             pos,
             SimNode::Constant(ConstantNode {
-                value: 0.0,
+                value: 100.0,
                 ..ConstantNode::default()
             }),
         );
-        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
-        let torque_node = snarl.insert_node(pos, SimNode::Torque(TorqueNode::default()));
-        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
-        let _plot_node = snarl.insert_node(pos, SimNode::Plot(PlotNode::default()));
-
-        // Wire: v_d -> Electrical input 0
-        snarl.connect(
-            OutPinId {
-                node: vd_node,
-                output: 0,
-            },
-            InPinId {
-                node: elec_node,
-                input: 0,
-            },
-        );
-        // Wire: v_q -> Electrical input 1
-        snarl.connect(
-            OutPinId {
-                node: vq_node,
-                output: 0,
-            },
-            InPinId {
-                node: elec_node,
-                input: 1,
-            },
-        );
-        // Wire: T_L -> Mechanical input 1
-        snarl.connect(
-            OutPinId {
-                node: tl_node,
-                output: 0,
-            },
-            InPinId {
-                node: mech_node,
-                input: 1,
-            },
-        );
+        let wires = [
+            (vq, 0, svpwm_node, 1),
+            (mech_node, 1, svpwm_node, 2),
+            (vdc, 0, svpwm_node, 3),
+            (svpwm_node, 1, machine_node, 0),
+        ];
+        for (from, output, to, input) in wires {
+            snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+        }
+        (snarl, machine_node, mech_node)
+    }
 
-        // Note: Electrical ω_m input (pin 2) and Mechanical T_e input (pin 0)
-        // are Signal type, coming from other ODE/algebraic nodes. The solver
-        // handles the coupling internally via the state vector, not via graph wires.
+    /// A healthy abc machine with a floating star point reproduces the d/q
+    /// model fed with the same rotor-frame voltage.
+    #[test]
+    fn healthy_abc_machine_matches_dq_model() {
+        use crate::nodes::abc_machine::AbcMachineNode;
 
         let config = SimConfig {
-            t_start: 0.0,
-            t_end: 0.5,
-            rtol: 1e-6,
-            atol: 1e-8,
+            t_end: 0.3,
             ..SimConfig::default()
         };
-
-        run_simulation(&mut snarl, &config).expect("simulation should succeed");
-
-        // Verify Electrical node has output signals
-        let SimNode::Electrical(elec) = snarl.get_node(elec_node).expect("elec node") else {
-            panic!("expected electrical node");
+        let (mut snarl, machine_node, mech_node) = abc_machine_graph(AbcMachineNode {
+            l_ls: 0.0004,
+            r_line: 0.0,
+            ..AbcMachineNode::default()
+        });
+        run_simulation(&mut snarl, &config).expect("abc simulation should succeed");
+        let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+            panic!("expected mechanical node");
         };
-        let PortValue::Signal(i_d) = elec.output_i_d.as_ref().expect("i_d output") else {
-            panic!("expected Signal");
+        let Some(PortValue::Signal(omega)) = mech.output_omega_m.as_ref() else {
+            panic!("expected omega signal");
         };
-        let PortValue::Signal(i_q) = elec.output_i_q.as_ref().expect("i_q output") else {
-            panic!("expected Signal");
+        let omega_abc = omega.last().expect("non-empty")[1];
+        let Some(SimNode::AbcMachine(machine)) = snarl.get_node(machine_node) else {
+            panic!("expected abc machine node");
         };
-
-        // Should have multiple time steps
+        let Some(PortValue::Vector(i_abc)) = machine.output_i_abc.as_ref() else {
+            panic!("expected i_abc vector");
+        };
+        let unbalance = i_abc
+            .iter()
+            .map(|s| (s[1] + s[2] + s[3]).abs())
+            .fold(0.0, f64::max);
         assert!(
-            i_d.len() > 10,
-            "expected many time steps, got {}",
-            i_d.len()
+            unbalance < 1e-6,
+            "floating star point: currents sum to zero ({unbalance})"
         );
-        assert_eq!(i_d.len(), i_q.len());
 
-        // Initial conditions should be zero
-        let epsilon = 1e-10;
-        assert!(
-            i_d.first().expect("non-empty")[1].abs() < epsilon,
-            "i_d(0) should be ~0"
-        );
+        let omega_dq = direct_dq_final_speed(&config);
+        assert!(omega_dq > 1.0, "dq motor should be spinning: {omega_dq}");
         assert!(
-            i_q.first().expect("non-empty")[1].abs() < epsilon,
-            "i_q(0) should be ~0"
+            (omega_abc - omega_dq).abs() < 1e-3 * omega_dq,
+            "abc and dq speeds agree: {omega_abc} vs {omega_dq}"
         );
+    }
 
-        // With v_q=24V applied and no load, q-axis current should become non-zero
-        let last_i_q = i_q.last().expect("non-empty")[1];
-        assert!(
-            last_i_q.abs() > 0.01,
-            "i_q should be non-zero at end: {last_i_q}"
-        );
+    /// An open phase stops its line current; an inter-turn short drives a
+    /// circulating current through the fault branch and ripples the torque.
+    #[test]
+    fn abc_machine_faults_change_behaviour() {
+        use crate::nodes::abc_machine::{AbcMachineNode, Fault, FaultKind};
 
-        // Verify Mechanical node has output signals
-        let SimNode::Mechanical(mech) = snarl.get_node(mech_node).expect("mech node") else {
-            panic!("expected mechanical node");
+        let config = SimConfig {
+            t_end: 0.3,
+            output_dt: 1e-4,
+            ..SimConfig::default()
         };
-        let PortValue::Signal(omega) = mech.output_omega_m.as_ref().expect("omega output") else {
-            panic!("expected Signal");
+        let run = |kind: FaultKind| {
+            let (mut snarl, machine_node, mech_node) = abc_machine_graph(AbcMachineNode {
+                fault: Fault {
+                    kind,
+                    t_fault: 0.2,
+                    ..Fault::default()
+                },
+                ..AbcMachineNode::default()
+            });
+            let t_l = snarl.insert_node(
+                egui::pos2(0.0, 0.0),
+                SimNode::Constant(ConstantNode {
+                    value: 0.3,
+                    ..ConstantNode::default()
+                }),
+            );
+            snarl.connect(
+                OutPinId {
+                    node: t_l,
+                    output: 0,
+                },
+                InPinId {
+                    node: mech_node,
+                    input: 1,
+                },
+            );
+            run_simulation(&mut snarl, &config).expect("simulation should succeed");
+            let Some(SimNode::AbcMachine(machine)) = snarl.get_node(machine_node) else {
+                panic!("expected abc machine node");
+            };
+            let (Some(PortValue::Vector(i_abc)), Some(PortValue::Signal(t_e))) =
+                (machine.output_i_abc.clone(), machine.output_t_e.clone())
+            else {
+                panic!("expected i_abc and T_e outputs");
+            };
+            let Some(PortValue::Signal(i_f)) = machine.output_i_f.clone() else {
+                panic!("expected i_f output");
+            };
+            (i_abc, t_e, i_f)
+        };
+        let after = |t: f64| t > 0.25;
+        let peak =
+            |values: &mut dyn Iterator<Item = f64>| values.fold(0.0_f64, |m, v| m.max(v.abs()));
+        let spread = |series: &[[f64; 2]]| {
+            let (lo, hi) = series
+                .iter()
+                .filter(|s| after(s[0]))
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), s| {
+                    (lo.min(s[1]), hi.max(s[1]))
+                });
+            hi - lo
         };
 
-        // Motor should be spinning after 0.5s with v_q=24V
-        let last_omega = omega.last().expect("non-empty")[1];
+        let (healthy_i, healthy_t, healthy_f) = run(FaultKind::None);
+        let healthy_a = peak(&mut healthy_i.iter().filter(|s| after(s[0])).map(|s| s[1]));
+        assert!(healthy_a > 0.1, "phase a conducts: {healthy_a}");
+        let healthy_if = peak(&mut healthy_f.iter().map(|s| s[1]));
+        assert!(healthy_if < 1e-12, "no fault current: {healthy_if}");
+
+        let (open_i, _, _) = run(FaultKind::OpenPhase);
+        let open_a = peak(&mut open_i.iter().filter(|s| after(s[0])).map(|s| s[1]));
+        assert!(open_a < 1e-9, "open phase carries no current: {open_a}");
+        let open_b = peak(&mut open_i.iter().filter(|s| after(s[0])).map(|s| s[2]));
+        assert!(open_b > 0.1, "phases b and c still conduct: {open_b}");
+
+        let (_, short_t, short_f) = run(FaultKind::InterTurn);
+        let short_if = peak(&mut short_f.iter().filter(|s| after(s[0])).map(|s| s[1]));
         assert!(
-            last_omega > 1.0,
-            "motor should be spinning: omega={last_omega}"
+            short_if > healthy_a,
+            "shorted turns circulate a large current: {short_if}"
+        );
+        assert!(
+            spread(&short_t) > 10.0 * spread(&healthy_t),
+            "inter-turn short ripples the torque: {} vs {}",
+            spread(&short_t),
+            spread(&healthy_t)
         );
-
-        // Verify torque node output
-        let SimNode::Torque(torque) = snarl.get_node(torque_node).expect("torque node") else {
-            panic!("expected torque node");
-        };
-        assert!(torque.output_t_e.is_some(), "torque should have output");
     }
 
-    /// Verify that a Constant node adapted to Vector mode produces a constant
-    /// 3-phase time series that the Park transform can consume, yielding
-    /// the expected d/q decomposition.
-    #[expect(
-        clippy::too_many_lines,
-        reason = "integration test wiring requires verbose graph setup"
-    )]
+    /// An induction machine fed in the synchronous frame accelerates to just
+    /// below synchronous speed, where its torque balances the load.
     #[test]
-    fn constant_adapted_to_vector_feeds_park_transform() {
-        use crate::nodes::park::ParkNode;
-        use crate::port::PortType;
+    fn induction_machine_runs_below_synchronous_speed() {
+        use crate::nodes::induction::InductionMachineNode;
 
         let mut snarl: Snarl<SimNode> = Snarl::new();
         let pos = egui::pos2(0.0, 0.0);
-
-        // Minimal ODE graph so the solver runs (it requires Electrical + Mechanical).
-        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let machine = InductionMachineNode::default();
+        let omega_sync = std::f64::consts::TAU * machine.f_sync / machine.n_p;
+        let machine_node = snarl.insert_node(pos, SimNode::Induction(machine));
         let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
-
-        // Constant adapted to Vector for Park's f_abc input.
-        let const_abc = snarl.insert_node(
+        let vq = snarl.insert_node(
             pos,
             SimNode::Constant(ConstantNode {
-                value: 1.0,   // phase a
-                value_b: 2.0, // phase b
-                value_c: 3.0, // phase c
-                output_type: PortType::Vector,
+                value: 325.0,
                 ..ConstantNode::default()
             }),
         );
-
-        // Constant adapted to Signal for Park's θ_e input (zero angle).
-        let const_theta = snarl.insert_node(
+        let t_l = snarl.insert_node(
             pos,
             SimNode::Constant(ConstantNode {
-                value: 0.0,
-                output_type: PortType::Signal,
+                value: 5.0,
                 ..ConstantNode::default()
             }),
         );
+        let wires = [(vq, 0, machine_node, 1), (t_l, 0, mech_node, 1)];
+        for (from, output, to, input) in wires {
+            snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+        }
 
-        let park_node = snarl.insert_node(pos, SimNode::Park(ParkNode::default()));
+        let config = SimConfig {
+            t_end: 1.0,
+            ..SimConfig::default()
+        };
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
 
-        // Wire: const_abc -> Park input 0 (f_abc)
-        snarl.connect(
-            OutPinId {
-                node: const_abc,
-                output: 0,
-            },
-            InPinId {
-                node: park_node,
-                input: 0,
-            },
+        let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+            panic!("expected mechanical node");
+        };
+        let Some(PortValue::Signal(omega)) = mech.output_omega_m.as_ref() else {
+            panic!("expected omega signal");
+        };
+        let omega = omega.last().expect("non-empty")[1];
+        let slip = (omega_sync - omega) / omega_sync;
+        assert!(
+            slip > 0.0 && slip < 0.1,
+            "runs with a small positive slip: ω_m = {omega}, slip = {slip}"
+        );
+
+        let Some(SimNode::Induction(machine)) = snarl.get_node(machine_node) else {
+            panic!("expected induction machine node");
+        };
+        let Some(PortValue::Signal(t_e)) = machine.output_t_e.as_ref() else {
+            panic!("expected T_e signal");
+        };
+        let t_e = t_e.last().expect("non-empty")[1];
+        let load = 5.0 + MechanicalNode::default().b * omega;
+        assert!(
+            (t_e - load).abs() < 1e-3,
+            "torque balances the load at steady state: {t_e} vs {load}"
+        );
+        assert!(
+            machine.output_psi_rd.is_some() && machine.output_i_sq.is_some(),
+            "flux and current outputs populated"
+        );
+    }
+    /// A PMSM driving a load through a geared, backlashed shaft settles with
+    /// the load at `ω_m / N` and the shaft carrying the load torque.
+    #[test]
+    fn two_mass_drivetrain_settles_through_gearbox() {
+        use crate::nodes::two_mass::TwoMassNode;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let drivetrain = TwoMassNode {
+            ratio: 2.0,
+            backlash: 0.01,
+            ..TwoMassNode::default()
+        };
+        let (ratio, b_l) = (drivetrain.ratio, drivetrain.b_l);
+        let load = 0.5;
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::TwoMass(drivetrain));
+        for (value, to, input) in [(24.0, elec_node, 1), (load, mech_node, 1)] {
+            let source = snarl.insert_node(
+                pos,
+                SimNode::Constant(ConstantNode {
+                    value,
+                    ..ConstantNode::default()
+                }),
+            );
+            snarl.connect(
+                OutPinId {
+                    node: source,
+                    output: 0,
+                },
+                InPinId { node: to, input },
+            );
+        }
+
+        let config = SimConfig {
+            t_end: 1.0,
+            ..SimConfig::default()
+        };
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        let Some(SimNode::TwoMass(m)) = snarl.get_node(mech_node) else {
+            panic!("expected two-mass node");
+        };
+        let last = |value: Option<&PortValue>| match value {
+            Some(PortValue::Signal(s)) => s.last().expect("non-empty")[1],
+            _ => panic!("expected signal output"),
+        };
+        let omega_m = last(m.output_omega_m.as_ref());
+        let omega_l = last(m.output_omega_l.as_ref());
+        let t_sh = last(m.output_t_sh.as_ref());
+        assert!(omega_m > 1.0, "motor turns: {omega_m}");
+        assert!(
+            (omega_l * ratio - omega_m).abs() < 1e-3 * omega_m,
+            "load follows through the gearbox: ω_L = {omega_l}, ω_m = {omega_m}"
+        );
+        let expected = load + b_l * omega_l;
+        assert!(
+            (t_sh - expected).abs() < 1e-3 * expected,
+            "shaft carries the load: {t_sh} vs {expected}"
+        );
+        assert!(m.output_theta_e.is_some(), "θ_e output populated");
+
+        // A steady start has the shaft carrying the load from the first step
+        let steady = SimConfig {
+            t_end: 0.05,
+            start_at_steady_state: true,
+            ..SimConfig::default()
+        };
+        run_simulation(&mut snarl, &steady).expect("steady-start simulation should succeed");
+        let Some(SimNode::TwoMass(m)) = snarl.get_node(mech_node) else {
+            panic!("expected two-mass node");
+        };
+        let Some(PortValue::Signal(t_sh)) = m.output_t_sh.as_ref() else {
+            panic!("expected shaft torque signal");
+        };
+        assert!(
+            t_sh.iter()
+                .all(|&[_, t_sh]| (t_sh - expected).abs() < 1e-3 * expected),
+            "shaft torque holds at {expected} without a transient"
+        );
+
+        // Trimmed initial conditions written into the nodes do the same
+        let point = super::trim(&mut snarl, &steady).expect("trim should converge");
+        point.apply(&mut snarl);
+        let from_nodes = SimConfig {
+            start_at_steady_state: false,
+            ..steady
+        };
+        run_simulation(&mut snarl, &from_nodes).expect("simulation should succeed");
+        let Some(SimNode::TwoMass(m)) = snarl.get_node(mech_node) else {
+            panic!("expected two-mass node");
+        };
+        assert_eq!(Some(m.twist_0), point.twist, "steady twist applied");
+        let Some(PortValue::Signal(t_sh)) = m.output_t_sh.as_ref() else {
+            panic!("expected shaft torque signal");
+        };
+        assert!(
+            t_sh.iter()
+                .all(|&[_, t_sh]| (t_sh - expected).abs() < 1e-3 * expected),
+            "applied trim holds the shaft torque at {expected}"
+        );
+    }
+
+    /// A brushed DC motor on a constant armature voltage settles where back-EMF
+    /// and torque balance: `ω = (V·k_t − R·T_L) / (k_e·k_t + R·B)`.
+    #[test]
+    fn dc_motor_reaches_analytic_steady_state() {
+        use crate::nodes::dc_motor::DcMotorNode;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let motor = DcMotorNode::default();
+        let mech = MechanicalNode::default();
+        let (v, load) = (12.0, 0.1);
+        let expected =
+            (v * motor.k_t - motor.r_a * load) / (motor.k_e * motor.k_t + motor.r_a * mech.b);
+        let motor_node = snarl.insert_node(pos, SimNode::DcMotor(motor));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(mech));
+        for (value, to, input) in [(v, motor_node, 0), (load, mech_node, 1)] {
+            let source = snarl.insert_node(
+                pos,
+                SimNode::Constant(ConstantNode {
+                    value,
+                    ..ConstantNode::default()
+                }),
+            );
+            snarl.connect(
+                OutPinId {
+                    node: source,
+                    output: 0,
+                },
+                InPinId { node: to, input },
+            );
+        }
+
+        let config = SimConfig {
+            t_end: 3.0,
+            ..SimConfig::default()
+        };
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+            panic!("expected mechanical node");
+        };
+        let Some(PortValue::Signal(omega)) = mech.output_omega_m.as_ref() else {
+            panic!("expected omega signal");
+        };
+        let omega = omega.last().expect("non-empty")[1];
+        assert!(
+            (omega - expected).abs() < 1e-3 * expected,
+            "steady-state speed {omega} vs {expected}"
         );
-        // Wire: const_theta -> Park input 1 (θ_e)
-        snarl.connect(
-            OutPinId {
-                node: const_theta,
-                output: 0,
-            },
-            InPinId {
-                node: park_node,
-                input: 1,
-            },
+        let Some(SimNode::DcMotor(motor)) = snarl.get_node(motor_node) else {
+            panic!("expected DC motor node");
+        };
+        assert!(
+            motor.output_i_a.is_some() && motor.output_t_e.is_some(),
+            "current and torque outputs populated"
         );
+    }
 
-        // Wire v_d/v_q constants to Electrical so solver doesn't error.
-        let vd = snarl.insert_node(pos, SimNode::Constant(ConstantNode::default()));
-        let vq = snarl.insert_node(pos, SimNode::Constant(ConstantNode::default()));
-        let tl = snarl.insert_node(pos, SimNode::Constant(ConstantNode::default()));
-        snarl.connect(
-            OutPinId {
-                node: vd,
-                output: 0,
+    /// A DC motor driving a fan through Coulomb friction settles where the
+    /// motor torque balances `T_C + k·ω² + B·ω`.
+    #[test]
+    fn dc_motor_settles_against_fan_load_and_coulomb_friction() {
+        use crate::nodes::dc_motor::DcMotorNode;
+        use crate::nodes::mechanical::{Friction, FrictionModel, Load, LoadModel};
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let motor = DcMotorNode::default();
+        let mech = MechanicalNode {
+            friction: Friction {
+                model: FrictionModel::Coulomb,
+                ..Friction::default()
             },
-            InPinId {
-                node: elec_node,
-                input: 0,
+            load: Load {
+                model: LoadModel::Fan,
+                ..Load::default()
             },
+            ..MechanicalNode::default()
+        };
+        let v = 12.0;
+        // k·ω² + (B + k_t·k_e/R)·ω + T_C − k_t·V/R = 0
+        let (qa, qb, qc) = (
+            mech.load.k_fan,
+            mech.b + motor.k_t * motor.k_e / motor.r_a,
+            mech.friction.t_c - motor.k_t * v / motor.r_a,
         );
-        snarl.connect(
-            OutPinId {
-                node: vq,
-                output: 0,
-            },
-            InPinId {
-                node: elec_node,
-                input: 1,
-            },
+        let expected = (-qb + (qb * qb - 4.0 * qa * qc).sqrt()) / (2.0 * qa);
+        let motor_node = snarl.insert_node(pos, SimNode::DcMotor(motor));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(mech));
+        let source = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: v,
+                ..ConstantNode::default()
+            }),
         );
         snarl.connect(
             OutPinId {
-                node: tl,
+                node: source,
                 output: 0,
             },
             InPinId {
-                node: mech_node,
-                input: 1,
+                node: motor_node,
+                input: 0,
             },
         );
 
         let config = SimConfig {
-            t_start: 0.0,
-            t_end: 0.1,
-            rtol: 1e-6,
-            atol: 1e-8,
+            t_end: 3.0,
             ..SimConfig::default()
         };
-
         run_simulation(&mut snarl, &config).expect("simulation should succeed");
 
-        // Verify the Constant's output_port_value was generated.
-        let SimNode::Constant(c_abc) = snarl.get_node(const_abc).expect("const node") else {
-            panic!("expected constant node");
+        let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+            panic!("expected mechanical node");
         };
-        let Some(PortValue::Vector(vec_data)) = &c_abc.output_port_value else {
-            panic!("expected Vector output from adapted constant");
+        let Some(PortValue::Signal(omega)) = mech.output_omega_m.as_ref() else {
+            panic!("expected omega signal");
         };
-        assert!(!vec_data.is_empty(), "vector series should have entries");
-        // Every row should carry the constant phase values.
-        for row in vec_data {
-            let epsilon = 1e-12;
-            assert!(
-                (row[1] - 1.0).abs() < epsilon,
-                "phase a should be 1.0, got {}",
-                row[1]
-            );
-            assert!(
-                (row[2] - 2.0).abs() < epsilon,
-                "phase b should be 2.0, got {}",
-                row[2]
-            );
-            assert!(
-                (row[3] - 3.0).abs() < epsilon,
-                "phase c should be 3.0, got {}",
-                row[3]
-            );
-        }
+        let omega = omega.last().expect("non-empty")[1];
+        assert!(
+            (omega - expected).abs() < 1e-3 * expected,
+            "steady-state speed {omega} vs {expected}"
+        );
+    }
 
-        // Verify Park transform produced outputs from the constant Vector.
-        let SimNode::Park(park) = snarl.get_node(park_node).expect("park node") else {
-            panic!("expected park node");
+    /// A vehicle rolling back down a grade from rest accelerates the
+    /// unpowered rotor at `F·(r/N) / J_eq` with `J_eq = J + m·(r/N)²`, for
+    /// the PMSM and the DC motor alike.
+    #[test]
+    fn vehicle_mass_adds_to_rotor_inertia() {
+        use crate::nodes::dc_motor::DcMotorNode;
+        use crate::nodes::mechanical::{Load, LoadModel};
+
+        let mech = MechanicalNode {
+            b: 0.0,
+            load: Load {
+                model: LoadModel::Vehicle,
+                grade: 10.0,
+                c_rr: 0.0,
+                c_d_a: 0.0,
+                ..Load::default()
+            },
+            ..MechanicalNode::default()
         };
-        assert!(park.output_f_d.is_some(), "Park f_d should be populated");
-        assert!(park.output_f_q.is_some(), "Park f_q should be populated");
+        let l = mech.load;
+        let lever = l.wheel_radius / l.ratio;
+        let force = l.mass * 9.81 * (l.grade / 100.0).atan().sin();
+        let expected = -force * lever / (mech.j + l.mass * lever * lever);
 
-        // With θ_e = 0, Park transform of [1, 2, 3] should give:
-        //   f_d = 1·cos(0) + 2·cos(-2π/3) + 3·cos(2π/3) = 1 - 1 - 1.5 = -1.5
-        //   f_q = -1·sin(0) - 2·sin(-2π/3) - 3·sin(2π/3)
-        // But the exact values depend on the Park scaling convention.
-        // Just verify non-trivial output exists.
-        let PortValue::Signal(f_d) = park.output_f_d.as_ref().expect("f_d") else {
-            panic!("expected Signal");
+        let config = SimConfig {
+            t_end: 0.01,
+            ..SimConfig::default()
         };
-        assert!(!f_d.is_empty(), "f_d series should be non-empty");
+        for machine in [
+            SimNode::Electrical(ElectricalNode::default()),
+            SimNode::DcMotor(DcMotorNode::default()),
+        ] {
+            let mut snarl: Snarl<SimNode> = Snarl::new();
+            let pos = egui::pos2(0.0, 0.0);
+            snarl.insert_node(pos, machine);
+            let mech_node = snarl.insert_node(pos, SimNode::Mechanical(mech.clone()));
+            run_simulation(&mut snarl, &config).expect("simulation should succeed");
+            let Some(SimNode::Mechanical(solved)) = snarl.get_node(mech_node) else {
+                panic!("expected mechanical node");
+            };
+            let Some(PortValue::Signal(omega)) = solved.output_omega_m.as_ref() else {
+                panic!("expected omega signal");
+            };
+            let &[t, omega] = omega.last().expect("non-empty");
+            let acceleration = omega / t;
+            // Back-EMF braking at these low speeds is well below 1 %
+            assert!(
+                (acceleration - expected).abs() < 1e-2 * expected.abs(),
+                "acceleration {acceleration} vs F/m_eq {expected}"
+            );
+        }
     }
 
-    /// Verify that `ExternalInput::Signal` feeds the ODE correctly.
-    /// Constants adapted to `PortType::Signal` should produce time-series
-    /// that `resolve_external_input` picks up and interpolates during integration.
+    /// An encoder on the shaft of a DC motor reports the solved angle within
+    /// one count and the settled speed within the window resolution.
     #[test]
-    fn signal_inputs_feed_ode() {
-        use crate::port::PortType;
+    fn encoder_tracks_dc_motor_shaft() {
+        use crate::nodes::dc_motor::DcMotorNode;
+        use crate::nodes::sensor::PositionSensorNode;
 
         let mut snarl: Snarl<SimNode> = Snarl::new();
         let pos = egui::pos2(0.0, 0.0);
-
-        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let motor_node = snarl.insert_node(pos, SimNode::DcMotor(DcMotorNode::default()));
         let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
-
-        // Constants adapted to Signal for v_d, v_q, T_L
-        let vd_node = snarl.insert_node(
+        let sensor = PositionSensorNode {
+            n_p: MechanicalNode::default().n_p,
+            ..PositionSensorNode::default()
+        };
+        let (n_p, lines, window) = (sensor.n_p, f64::from(sensor.lines), sensor.speed_window);
+        let sensor_node = snarl.insert_node(pos, SimNode::Sensor(sensor));
+        let source = snarl.insert_node(
             pos,
             SimNode::Constant(ConstantNode {
-                value: 0.0,
-                output_type: PortType::Signal,
+                value: 12.0,
                 ..ConstantNode::default()
             }),
         );
-        let vq_node = snarl.insert_node(
-            pos,
-            SimNode::Constant(ConstantNode {
-                value: 24.0,
-                output_type: PortType::Signal,
-                ..ConstantNode::default()
-            }),
+        for (from, output, to) in [(source, 0, motor_node), (mech_node, 1, sensor_node)] {
+            snarl.connect(
+                OutPinId { node: from, output },
+                InPinId { node: to, input: 0 },
+            );
+        }
+
+        let config = SimConfig {
+            t_end: 3.0,
+            ..SimConfig::default()
+        };
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+            panic!("expected mechanical node");
+        };
+        let (Some(PortValue::Signal(omega)), Some(PortValue::Signal(theta))) =
+            (mech.output_omega_m.as_ref(), mech.output_theta_e.as_ref())
+        else {
+            panic!("expected mechanical outputs");
+        };
+        let Some(SimNode::Sensor(sensor)) = snarl.get_node(sensor_node) else {
+            panic!("expected sensor node");
+        };
+        let (Some(PortValue::Signal(theta_hat)), Some(PortValue::Signal(omega_hat))) = (
+            sensor.output_theta_e.as_ref(),
+            sensor.output_omega_m.as_ref(),
+        ) else {
+            panic!("expected sensor outputs");
+        };
+        let count = std::f64::consts::TAU / (4.0 * lines) * n_p;
+        for (&[_, true_theta], &[_, measured]) in theta.iter().zip(theta_hat) {
+            assert!((true_theta - measured).abs() <= count, "within one count");
+        }
+        let (omega, omega_hat) = (
+            omega.last().expect("non-empty")[1],
+            omega_hat.last().expect("non-empty")[1],
         );
-        let tl_node = snarl.insert_node(
+        assert!(
+            (omega - omega_hat).abs() <= count / n_p / window,
+            "measured speed {omega_hat} vs {omega}"
+        );
+    }
+
+    /// A Hall-commutated BLDC motor on a fixed bus spins up to just below
+    /// its no-load speed `V_dc / (2·k_e)`, with the Hall outputs toggling.
+    #[test]
+    fn bldc_motor_spins_up_under_six_step_drive() {
+        use crate::nodes::bldc::BldcNode;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let motor = BldcNode::default();
+        let v_dc = 24.0;
+        let no_load = v_dc / (2.0 * motor.k_e);
+        let motor_node = snarl.insert_node(pos, SimNode::Bldc(motor));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let bus = snarl.insert_node(
             pos,
             SimNode::Constant(ConstantNode {
-                value: 0.0,
-                output_type: PortType::Signal,
+                value: v_dc,
                 ..ConstantNode::default()
             }),
         );
-
-        // Wire: v_d -> Electrical input 0
         snarl.connect(
             OutPinId {
-                node: vd_node,
+                node: bus,
                 output: 0,
             },
             InPinId {
-                node: elec_node,
+                node: motor_node,
                 input: 0,
             },
         );
-        // Wire: v_q -> Electrical input 1
-        snarl.connect(
-            OutPinId {
-                node: vq_node,
-                output: 0,
-            },
-            InPinId {
-                node: elec_node,
-                input: 1,
-            },
-        );
-        // Wire: T_L -> Mechanical input 1
-        snarl.connect(
-            OutPinId {
-                node: tl_node,
-                output: 0,
-            },
-            InPinId {
-                node: mech_node,
-                input: 1,
-            },
-        );
 
         let config = SimConfig {
-            t_start: 0.0,
             t_end: 0.5,
-            rtol: 1e-6,
-            atol: 1e-8,
             ..SimConfig::default()
         };
-
         run_simulation(&mut snarl, &config).expect("simulation should succeed");
 
-        // Verify outputs are populated
-        let SimNode::Electrical(elec) = snarl.get_node(elec_node).expect("elec node") else {
-            panic!("expected electrical node");
+        let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+            panic!("expected mechanical node");
         };
-        let PortValue::Signal(i_q) = elec.output_i_q.as_ref().expect("i_q output") else {
-            panic!("expected Signal");
+        let Some(PortValue::Signal(omega)) = mech.output_omega_m.as_ref() else {
+            panic!("expected omega signal");
         };
-
-        // With v_q=24V via Signal, results should match the Scalar case
-        let last_i_q = i_q.last().expect("non-empty")[1];
+        let omega = omega.last().expect("non-empty")[1];
         assert!(
-            last_i_q.abs() > 0.01,
-            "i_q should be non-zero at end: {last_i_q}"
+            omega > 0.8 * no_load && omega < no_load,
+            "runs just below no-load speed: {omega} vs {no_load}"
         );
 
-        let SimNode::Mechanical(mech) = snarl.get_node(mech_node).expect("mech node") else {
-            panic!("expected mechanical node");
+        let Some(SimNode::Bldc(motor)) = snarl.get_node(motor_node) else {
+            panic!("expected BLDC motor node");
         };
-        let PortValue::Signal(omega) = mech.output_omega_m.as_ref().expect("omega output") else {
-            panic!("expected Signal");
+        let Some(PortValue::Vector(hall)) = motor.output_hall.as_ref() else {
+            panic!("expected Hall vector");
         };
-        let last_omega = omega.last().expect("non-empty")[1];
         assert!(
-            last_omega > 1.0,
-            "motor should be spinning: omega={last_omega}"
+            hall.iter()
+                .all(|row| row.iter().skip(1).all(|&h| h == 0.0 || h == 1.0)),
+            "Hall states are binary"
         );
-
-        // The Signal-adapted constants should also have output_port_value populated
-        let SimNode::Constant(c_vq) = snarl.get_node(vq_node).expect("vq node") else {
-            panic!("expected constant node");
-        };
         assert!(
-            c_vq.output_port_value.is_some(),
-            "Signal-adapted constant should have output"
+            hall.iter()
+                .zip(hall.iter().skip(1))
+                .any(|(a, b)| a[1] != b[1]),
+            "Hall sensor A toggles"
         );
     }
 
+    /// The trimmed operating point balances the load torque, and a run that
+    /// starts from it stays there instead of passing through the start-up
+    /// transient, leaving the nodes' initial conditions untouched.
     #[test]
-    fn interpolate_signal_edge_cases() {
-        use super::interpolate_signal;
-
-        // Empty signal returns 0.0
-        assert_eq!(interpolate_signal(&[], 1.0), 0.0);
-
-        // Single point returns that value for any t
-        let single = [[5.0, 42.0]];
-        assert_eq!(interpolate_signal(&single, 0.0), 42.0);
-        assert_eq!(interpolate_signal(&single, 5.0), 42.0);
-        assert_eq!(interpolate_signal(&single, 100.0), 42.0);
-
-        // Clamps to boundaries
-        let series = [[0.0, 10.0], [1.0, 20.0], [2.0, 30.0]];
-        assert_eq!(interpolate_signal(&series, -1.0), 10.0);
-        assert_eq!(interpolate_signal(&series, 3.0), 30.0);
-
-        // Exact boundary values
-        let eps = 1e-12;
-        assert!((interpolate_signal(&series, 0.0) - 10.0).abs() < eps);
-        assert!((interpolate_signal(&series, 2.0) - 30.0).abs() < eps);
-
-        // Midpoint interpolation
-        assert!((interpolate_signal(&series, 0.5) - 15.0).abs() < eps);
-        assert!((interpolate_signal(&series, 1.5) - 25.0).abs() < eps);
-    }
-
-    #[test]
-    fn interpolate_vector_edge_cases() {
-        use super::interpolate_vector;
-
-        // Empty signal returns [0, 0, 0]
-        assert_eq!(interpolate_vector(&[], 1.0), [0.0; 3]);
-
-        // Single point returns those values for any t
-        let single = [[5.0, 1.0, 2.0, 3.0]];
-        assert_eq!(interpolate_vector(&single, 0.0), [1.0, 2.0, 3.0]);
-        assert_eq!(interpolate_vector(&single, 5.0), [1.0, 2.0, 3.0]);
-        assert_eq!(interpolate_vector(&single, 100.0), [1.0, 2.0, 3.0]);
-
-        // Clamps to boundaries
-        let series = [
-            [0.0, 10.0, 20.0, 30.0],
-            [1.0, 40.0, 50.0, 60.0],
-            [2.0, 70.0, 80.0, 90.0],
-        ];
-        assert_eq!(interpolate_vector(&series, -1.0), [10.0, 20.0, 30.0]);
-        assert_eq!(interpolate_vector(&series, 3.0), [70.0, 80.0, 90.0]);
+    fn trimmed_start_holds_steady_state() {
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let load = 0.3;
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        for (value, to, input) in [(24.0, elec_node, 1), (load, mech_node, 1)] {
+            let source = snarl.insert_node(
+                pos,
+                SimNode::Constant(ConstantNode {
+                    value,
+                    ..ConstantNode::default()
+                }),
+            );
+            snarl.connect(
+                OutPinId {
+                    node: source,
+                    output: 0,
+                },
+                InPinId { node: to, input },
+            );
+        }
 
-        // Midpoint interpolation
-        let eps = 1e-12;
-        let mid = interpolate_vector(&series, 0.5);
-        assert!((mid[0] - 25.0).abs() < eps, "a: {}", mid[0]);
-        assert!((mid[1] - 35.0).abs() < eps, "b: {}", mid[1]);
-        assert!((mid[2] - 45.0).abs() < eps, "c: {}", mid[2]);
+        let config = SimConfig {
+            t_end: 0.05,
+            start_at_steady_state: true,
+            ..SimConfig::default()
+        };
+        let point = super::trim(&mut snarl, &config).expect("trim should converge");
+        let (e, m) = (ElectricalNode::default(), MechanicalNode::default());
+        let t_e = 1.5 * e.n_p * e.lambda_m * point.i_q;
+        assert!(
+            (t_e - load - m.b * point.omega_m).abs() < 1e-6,
+            "torque balances the load: {t_e} at ω_m = {}",
+            point.omega_m
+        );
+        let v_q = e.r_s * point.i_q + e.n_p * point.omega_m * (e.l_d * point.i_d + e.lambda_m);
+        assert!((v_q - 24.0).abs() < 1e-6, "q-axis voltage balance: {v_q}");
+
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+        let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_node) else {
+            panic!("expected mechanical node");
+        };
+        assert_eq!(mech.omega_m_0, 0.0, "initial speed kept");
+        let Some(SimNode::Electrical(elec)) = snarl.get_node(elec_node) else {
+            panic!("expected electrical node");
+        };
+        assert_eq!(
+            (elec.i_d_0, elec.i_q_0),
+            (0.0, 0.0),
+            "initial currents kept"
+        );
+        let Some(PortValue::Signal(omega)) = mech.output_omega_m.as_ref() else {
+            panic!("expected omega signal");
+        };
+        assert!(
+            omega
+                .iter()
+                .all(|&[_, w]| (w - point.omega_m).abs() < 1e-4 * point.omega_m),
+            "speed stays at the operating point"
+        );
     }
 
-    /// Verify that a Park node wired between a 3-phase constant and the Electrical
-    /// node, with `theta_e` from the Mechanical node (circular ODE dependency),
-    /// produces non-zero motor currents and speed.
+    /// The Bode node's linear model from `v_q` to `ω_m` has `B = e_q/L_q`,
+    /// stable poles and a low-frequency gain equal to the slope of the
+    /// trimmed speed against `v_q`.
+    #[test]
     #[expect(
         clippy::too_many_lines,
-        reason = "integration test exercises full Park-coupled simulation pipeline"
+        reason = "integration test rewires the Bode node through several sources and outputs"
     )]
-    #[test]
-    fn park_coupled_to_ode_produces_nonzero_output() {
+    fn bode_node_linearises_about_steady_state() {
+        use egui_snarl::NodeId;
+
+        use crate::nodes::bode::BodeNode;
         use crate::nodes::park::ParkNode;
         use crate::port::PortType;
 
         let mut snarl: Snarl<SimNode> = Snarl::new();
         let pos = egui::pos2(0.0, 0.0);
-
-        // -- ODE nodes --
         let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
         let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
-
-        // -- Park node --
-        let park_node = snarl.insert_node(pos, SimNode::Park(ParkNode::default()));
-
-        // -- 3-phase voltage source (Constant in Vector mode) --
-        // v_a=0, v_b=100, v_c=-100 produces v_q ≈ 115 V at θ_e=0 via Park,
-        // which drives q-axis current and generates torque to spin the motor.
-        let const_abc = snarl.insert_node(
+        let bode_node = snarl.insert_node(
+            pos,
+            SimNode::Bode(BodeNode {
+                f_min: 0.001,
+                f_max: 1000.0,
+                ..BodeNode::default()
+            }),
+        );
+        let vq_node = snarl.insert_node(
             pos,
             SimNode::Constant(ConstantNode {
-                value: 0.0,
-                value_b: 100.0,
-                value_c: -100.0,
-                output_type: PortType::Vector,
+                value: 24.0,
                 ..ConstantNode::default()
             }),
         );
+        for (from, output, to, input) in [
+            (vq_node, 0, elec_node, 1),
+            (vq_node, 0, bode_node, 0),
+            (mech_node, 0, bode_node, 1),
+        ] {
+            snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+        }
 
-        // -- Load torque = 0 --
-        let tl_node = snarl.insert_node(pos, SimNode::Constant(ConstantNode::default()));
+        let config = SimConfig {
+            t_end: 0.01,
+            ..SimConfig::default()
+        };
+        let e = ElectricalNode::default();
+        let outputs_at = |snarl: &mut Snarl<SimNode>, v_q: f64| {
+            if let Some(SimNode::Constant(c)) = snarl.get_node_mut(vq_node) {
+                c.value = v_q;
+            }
+            let point = super::trim(snarl, &config).expect("trim");
+            let p_cu = 1.5 * e.r_s * (point.i_d * point.i_d + point.i_q * point.i_q);
+            [point.omega_m, p_cu]
+        };
+        let [omega_hi, p_cu_hi] = outputs_at(&mut snarl, 24.1);
+        let [omega_lo, p_cu_lo] = outputs_at(&mut snarl, 23.9);
+        let slope = (omega_hi - omega_lo) / 0.2;
+        let p_cu_slope = (p_cu_hi - p_cu_lo) / 0.2;
+        outputs_at(&mut snarl, 24.0);
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
 
-        // Wire: const_abc -> Park input 0 (f_abc)
-        snarl.connect(
-            OutPinId {
-                node: const_abc,
-                output: 0,
-            },
+        let Some(SimNode::Bode(bode)) = snarl.get_node(bode_node) else {
+            panic!("expected Bode node");
+        };
+        let model = bode.model.as_ref().expect("linear model");
+        assert_eq!(model.states, ["i_d", "i_q", "ω_m"]);
+        let &[b_d, b_q, b_w] = model.b.as_slice() else {
+            panic!("expected three states");
+        };
+        assert!(
+            (b_q - 1.0 / e.l_q).abs() < 1e-3 / e.l_q && b_d.abs() < 1e-6 && b_w.abs() < 1e-6,
+            "v_q drives the q-axis current: {}",
+            model.b
+        );
+        assert!(bode.poles.len() == 3 && bode.poles.iter().all(|p| p.re < 0.0));
+        let [_, gain_db] = *bode.series.magnitude.first().expect("non-empty");
+        let gain = 10.0_f64.powf(gain_db / 20.0);
+        assert!(
+            (gain - slope).abs() < 1e-3 * slope,
+            "DC gain {gain} matches the trim slope {slope}"
+        );
+
+        let b_constant = model.b.clone();
+
+        // y may be any machine output, here the copper loss
+        let (u_pin, y_pin) = (
             InPinId {
-                node: park_node,
+                node: bode_node,
                 input: 0,
             },
-        );
-        // Wire: Mechanical theta_e (output 1) -> Park input 1 (theta_e)
-        snarl.connect(
-            OutPinId {
-                node: mech_node,
-                output: 1,
-            },
             InPinId {
-                node: park_node,
+                node: bode_node,
                 input: 1,
             },
         );
-        // Wire: Park f_d (output 0) -> Electrical input 0 (v_d)
-        snarl.connect(
-            OutPinId {
-                node: park_node,
-                output: 0,
-            },
+        let rewire = |snarl: &mut Snarl<SimNode>, from: NodeId, output, to: InPinId| {
+            snarl.drop_inputs(to);
+            snarl.connect(OutPinId { node: from, output }, to);
+        };
+        rewire(&mut snarl, elec_node, 2, y_pin);
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+        let Some(SimNode::Bode(bode)) = snarl.get_node(bode_node) else {
+            panic!("expected Bode node");
+        };
+        let model = bode.model.as_ref().expect("linear model of P_cu");
+        let gain = model.response(0.0).expect("no pole at DC").re;
+        assert!(
+            (gain - p_cu_slope).abs() < 1e-3 * p_cu_slope.abs(),
+            "P_cu DC gain {gain} matches the trim slope {p_cu_slope}"
+        );
+
+        // A signal source is linearised about its value at t_start: v_q
+        // from the f_q of a Park transform of fixed phase voltages
+        let abc_node = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 20.0,
+                value_b: -10.0,
+                value_c: -10.0,
+                output_type: PortType::Vector,
+                ..ConstantNode::default()
+            }),
+        );
+        let theta_node = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: -std::f64::consts::FRAC_PI_2,
+                output_type: PortType::Signal,
+                ..ConstantNode::default()
+            }),
+        );
+        let park_node = snarl.insert_node(pos, SimNode::Park(ParkNode::default()));
+        let vq_pin = InPinId {
+            node: elec_node,
+            input: 1,
+        };
+        rewire(
+            &mut snarl,
+            abc_node,
+            0,
             InPinId {
-                node: elec_node,
+                node: park_node,
                 input: 0,
             },
         );
-        // Wire: Park f_q (output 1) -> Electrical input 1 (v_q)
-        snarl.connect(
-            OutPinId {
-                node: park_node,
-                output: 1,
-            },
+        rewire(
+            &mut snarl,
+            theta_node,
+            0,
             InPinId {
-                node: elec_node,
+                node: park_node,
                 input: 1,
             },
         );
-        // Wire: T_L -> Mechanical input 1
-        snarl.connect(
-            OutPinId {
-                node: tl_node,
-                output: 0,
-            },
-            InPinId {
-                node: mech_node,
-                input: 1,
-            },
+        rewire(&mut snarl, park_node, 1, vq_pin);
+        rewire(&mut snarl, park_node, 1, u_pin);
+        rewire(&mut snarl, mech_node, 0, y_pin);
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+        let Some(SimNode::Bode(bode)) = snarl.get_node(bode_node) else {
+            panic!("expected Bode node");
+        };
+        let model = bode.model.as_ref().expect("linear model from a signal");
+        assert!(
+            (&model.b - &b_constant).norm() < 1e-6 * b_constant.norm(),
+            "same input matrix as the Constant: {}",
+            model.b
+        );
+        assert!(
+            snarl.in_pin(vq_pin).remotes
+                == [OutPinId {
+                    node: park_node,
+                    output: 1
+                }],
+            "the perturbed source is rewired back"
         );
 
+        // A machine output on u depends on the state, and a source on y is
+        // not a machine output; both are reported on the node and the run
+        // goes on
+        for (u_from, y_from, message) in [
+            ((elec_node, 1), (mech_node, 0), "u must"),
+            ((vq_node, 0), (vq_node, 0), "y must"),
+        ] {
+            rewire(&mut snarl, u_from.0, u_from.1, u_pin);
+            rewire(&mut snarl, y_from.0, y_from.1, y_pin);
+            run_simulation(&mut snarl, &config).expect("simulation should succeed");
+            let Some(SimNode::Bode(bode)) = snarl.get_node(bode_node) else {
+                panic!("expected Bode node");
+            };
+            assert!(bode.model.is_none(), "no model for {message}");
+            assert!(
+                bode.error
+                    .as_deref()
+                    .is_some_and(|e| e.starts_with(message)),
+                "error names the wrong pin: {:?}",
+                bode.error
+            );
+        }
+    }
+
+    /// The phase currents of a healthy abc machine analysed over electrical
+    /// periods of its own `θ_e` are balanced sinusoids 120° apart.
+    #[test]
+    fn spectrum_node_resolves_balanced_phase_currents() {
+        use crate::nodes::abc_machine::AbcMachineNode;
+        use crate::nodes::spectrum::SpectrumNode;
+        use crate::port::PortType;
+
+        let (mut snarl, machine_node, mech_node) = abc_machine_graph(AbcMachineNode::default());
+        let spectrum_node = snarl.insert_node(
+            egui::pos2(0.0, 0.0),
+            SimNode::Spectrum(SpectrumNode {
+                port_type: PortType::Vector,
+                ..SpectrumNode::default()
+            }),
+        );
+        for (from, output, input) in [(machine_node, 0, 0), (mech_node, 1, 1)] {
+            snarl.connect(
+                OutPinId { node: from, output },
+                InPinId {
+                    node: spectrum_node,
+                    input,
+                },
+            );
+        }
         let config = SimConfig {
-            t_start: 0.0,
-            t_end: 0.5,
-            rtol: 1e-6,
-            atol: 1e-8,
+            t_end: 0.3,
             ..SimConfig::default()
         };
-
         run_simulation(&mut snarl, &config).expect("simulation should succeed");
 
-        // Verify Electrical node has non-zero outputs
-        let SimNode::Electrical(elec) = snarl.get_node(elec_node).expect("elec node") else {
-            panic!("expected electrical node");
-        };
-        let PortValue::Signal(i_d) = elec.output_i_d.as_ref().expect("i_d output") else {
-            panic!("expected Signal");
+        let Some(SimNode::Spectrum(spectrum)) = snarl.get_node(spectrum_node) else {
+            panic!("expected spectrum node");
         };
-        let PortValue::Signal(i_q) = elec.output_i_q.as_ref().expect("i_q output") else {
-            panic!("expected Signal");
+        let [a, b, c] = &spectrum.channels[..] else {
+            panic!("expected three phase spectra");
         };
-
         assert!(
-            i_d.len() > 10,
-            "expected many time steps, got {}",
-            i_d.len()
+            a.fundamental > 1e-3,
+            "phase current flows: {}",
+            a.fundamental
         );
-
-        // With a 3-phase voltage applied via Park-coupled path, currents must be non-zero.
-        // This is the key assertion: the old code would produce all zeros here.
-        let last_i_d = i_d.last().expect("non-empty")[1];
-        let last_i_q = i_q.last().expect("non-empty")[1];
+        for phase in [b, c] {
+            assert!(
+                (phase.fundamental - a.fundamental).abs() < 1e-2 * a.fundamental,
+                "balanced amplitudes: {} vs {}",
+                phase.fundamental,
+                a.fundamental
+            );
+            assert!(phase.thd < 1.0, "sinusoidal current: THD {} %", phase.thd);
+        }
+        let shift = |x: f64, y: f64| (x - y).rem_euclid(360.0);
         assert!(
-            last_i_d.abs() > 0.01 || last_i_q.abs() > 0.01,
-            "Park-coupled currents should be non-zero: i_d={last_i_d}, i_q={last_i_q}"
+            (shift(a.phase, b.phase) - 120.0).abs() < 1.0,
+            "b lags a by 120°: {} vs {}",
+            a.phase,
+            b.phase
         );
+        assert!(
+            (shift(b.phase, c.phase) - 120.0).abs() < 1.0,
+            "c lags b by 120°: {} vs {}",
+            b.phase,
+            c.phase
+        );
+    }
 
-        // Verify Mechanical node shows rotation
-        let SimNode::Mechanical(mech) = snarl.get_node(mech_node).expect("mech node") else {
-            panic!("expected mechanical node");
+    /// Spinning the default PMSM up against a load, the electrical input
+    /// energy splits into losses, load work and stored energy, and the
+    /// balance closes to the solver tolerance.
+    #[test]
+    fn power_flow_energy_balance_closes() {
+        use crate::nodes::power::PowerFlowNode;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let constant = |value| {
+            SimNode::Constant(ConstantNode {
+                value,
+                ..ConstantNode::default()
+            })
         };
-        let PortValue::Signal(omega) = mech.output_omega_m.as_ref().expect("omega output") else {
-            panic!("expected Signal");
+        let vd_node = snarl.insert_node(pos, constant(0.0));
+        let vq_node = snarl.insert_node(pos, constant(24.0));
+        let tl_node = snarl.insert_node(pos, constant(0.05));
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        snarl.insert_node(pos, SimNode::Torque(TorqueNode::default()));
+        let mech_node = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let power_node = snarl.insert_node(pos, SimNode::PowerFlow(PowerFlowNode::default()));
+        for (from, to, input) in [
+            (vd_node, elec_node, 0),
+            (vq_node, elec_node, 1),
+            (tl_node, mech_node, 1),
+        ] {
+            snarl.connect(
+                OutPinId {
+                    node: from,
+                    output: 0,
+                },
+                InPinId { node: to, input },
+            );
+        }
+        let config = SimConfig {
+            t_end: 0.5,
+            rtol: 1e-6,
+            atol: 1e-8,
+            ..SimConfig::default()
         };
-        let last_omega = omega.last().expect("non-empty")[1];
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+
+        let Some(SimNode::PowerFlow(pf)) = snarl.get_node(power_node) else {
+            panic!("expected power-flow node");
+        };
+        let balance = pf.balance.expect("energy balance");
         assert!(
-            last_omega.abs() > 0.01,
-            "motor should be spinning: omega={last_omega}"
+            balance.e_in > 0.0,
+            "motoring draws energy: {}",
+            balance.e_in
         );
-
-        // Verify Park outputs are populated post-ODE (step 13 re-compute)
-        let SimNode::Park(park) = snarl.get_node(park_node).expect("park node") else {
-            panic!("expected park node");
+        assert!(balance.e_cu > 0.0 && balance.e_load > 0.0 && balance.d_w_kin > 0.0);
+        assert!(
+            balance.closes(),
+            "residual {:e} J exceeds {:e} J",
+            balance.residual(),
+            balance.tolerance
+        );
+        assert!(
+            balance.efficiency() > 0.0 && balance.efficiency() < 100.0,
+            "η {}",
+            balance.efficiency()
+        );
+        let Some(PortValue::Signal(e_in)) = &pf.output_e_in else {
+            panic!("expected E_in signal");
         };
+        let &[_, last] = e_in.last().expect("samples");
         assert!(
-            park.output_f_d.is_some(),
-            "Park f_d should be populated post-ODE"
+            (last - balance.e_in).abs() < 1e-2 * balance.e_in,
+            "cumulative E_in {last} vs {}",
+            balance.e_in
+        );
+    }
+
+    /// The Efficiency Map node is filled from the Electrical node's voltage
+    /// equations and the Torque node's torque equation: a Torque node with
+    /// twice the PM flux doubles the torque available at low speed.
+    #[test]
+    fn efficiency_map_uses_torque_node_parameters() {
+        use crate::nodes::efficiency_map::EfficiencyMapNode;
+
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let vq_node = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 24.0,
+                ..ConstantNode::default()
+            }),
+        );
+        let elec_node = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let torque_node = snarl.insert_node(pos, SimNode::Torque(TorqueNode::default()));
+        snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let map_node = snarl.insert_node(
+            pos,
+            SimNode::EfficiencyMap(EfficiencyMapNode {
+                speed_points: 5,
+                torque_points: 5,
+                torque_max: 30.0,
+                ..EfficiencyMapNode::default()
+            }),
+        );
+        snarl.connect(
+            OutPinId {
+                node: vq_node,
+                output: 0,
+            },
+            InPinId {
+                node: elec_node,
+                input: 1,
+            },
         );
+        let config = SimConfig {
+            t_end: 0.01,
+            ..SimConfig::default()
+        };
+        let low_speed_torque = |snarl: &Snarl<SimNode>| {
+            let Some(SimNode::EfficiencyMap(m)) = snarl.get_node(map_node) else {
+                panic!("expected efficiency map node");
+            };
+            let map = m.map.as_ref().expect("map after the run");
+            let &[_, [_, torque], ..] = map.envelope.as_slice() else {
+                panic!("expected an envelope per speed");
+            };
+            torque
+        };
+
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+        let nominal = low_speed_torque(&snarl);
+        if let Some(SimNode::Torque(t)) = snarl.get_node_mut(torque_node) {
+            t.lambda_m *= 2.0;
+        }
+        run_simulation(&mut snarl, &config).expect("simulation should succeed");
+        let doubled = low_speed_torque(&snarl);
         assert!(
-            park.output_f_q.is_some(),
-            "Park f_q should be populated post-ODE"
+            nominal > 9.0 && (doubled / nominal - 2.0).abs() < 0.02,
+            "T_max {nominal} → {doubled} N·m"
         );
     }
 }
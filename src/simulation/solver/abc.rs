@@ -0,0 +1,491 @@
+//! Circuit-level solve for the abc-frame machine ([`AbcMachineNode`]).
+//!
+//! The winding network (see [`AbcMachineNode::network`]) is reduced to one
+//! ODE per independent coil current. With the coil incidence matrix `A`
+//! (+1 where a coil current leaves a node, −1 where it enters), the node
+//! potentials follow from the resistive KCL `G·φ = s − A·i`, where `s` holds
+//! the source voltages divided by their line resistance. Nodes that no
+//! resistor ties to a source form islands whose net coil current must vanish;
+//! those constraints define an orthonormal basis `T` with `i = T·z`, and
+//!
+//! ```text
+//! Tᵀ·L(θ_e)·T · dz/dt = Tᵀ·(Aᵀ·φ − R·i − ω_e·(∂L/∂θ_e·i + ∂ψ_pm/∂θ_e))
+//! T_e = N_p·(½·iᵀ·∂L/∂θ_e·i + iᵀ·∂ψ_pm/∂θ_e)
+//! dω_m/dt = (T_e − T_L − T_f(ω_m) − T_load(ω_m) − B·ω_m) / J,   dθ_e/dt = N_p·ω_m
+//! ```
+//!
+//! A fault switches the network at `t_fault`: the solve restarts there with
+//! the coil currents of each phase carried over and projected onto the
+//! faulted circuit's constraints. The Jacobian is a forward difference of
+//! the right-hand side.
+//!
+//! The phase voltages come from a 3-phase series, or from an SVPWM modulator
+//! evaluated at the instantaneous `θ_e` when its angle input is the
+//! Mechanical node. A switched inverter or DC link is not folded into this
+//! solve; their precomputed outputs are used as plain series.
+
+use std::rc::Rc;
+
+use egui_snarl::{InPinId, NodeId, Snarl};
+use nalgebra::{DMatrix, DVector, RowDVector};
+
+use super::{
+    CoupledState, ExternalInput, PhaseSource, Rhs, Sources, get_vector_input, resample_signal,
+    resample_vector, resolve_external_input, resolve_svpwm_coupling, solve_numeric,
+};
+use crate::nodes::SimNode;
+use crate::nodes::abc_machine::{AbcMachineNode, Far, FaultKind, Network};
+use crate::nodes::mechanical::MechanicalNode;
+use crate::port::PortValue;
+use crate::simulation::{SimConfig, SimError};
+
+/// Winding network of one solve segment with the matrices its ODE needs.
+struct Circuit {
+    /// Branches of the network.
+    network: Network,
+    /// Coil incidence `A` (nodes × coils).
+    incidence: DMatrix<f64>,
+    /// Inverse of the nodal conductance matrix, gauged on floating islands.
+    g_inv: DMatrix<f64>,
+    /// Orthonormal basis `T` (coils × states) of admissible coil currents.
+    basis: DMatrix<f64>,
+}
+
+impl Circuit {
+    /// Assemble the circuit matrices; `None` if the conductance matrix is
+    /// singular.
+    fn new(network: Network) -> Option<Self> {
+        let (n, m) = (network.n_nodes, network.coils.len());
+        let incidence = DMatrix::from_fn(n, m, |node, c| {
+            network.coils.get(c).map_or(0.0, |coil| {
+                if coil.from == node {
+                    1.0
+                } else if coil.to == node {
+                    -1.0
+                } else {
+                    0.0
+                }
+            })
+        });
+
+        // Label resistively connected nodes with the lowest node index
+        let mut label: Vec<usize> = (0..n).collect();
+        for r in &network.resistors {
+            if let Far::Node(k) = r.far
+                && let (Some(&a), Some(&b)) = (label.get(r.node), label.get(k))
+            {
+                let (lo, hi) = (a.min(b), a.max(b));
+                label.iter_mut().filter(|l| **l == hi).for_each(|l| *l = lo);
+            }
+        }
+        let anchored: Vec<usize> = network
+            .resistors
+            .iter()
+            .filter(|r| !matches!(r.far, Far::Node(_)))
+            .filter_map(|r| label.get(r.node).copied())
+            .collect();
+
+        // Each floating island fixes its potential at its first node, and
+        // constrains the net coil current into it to zero
+        let floating: Vec<usize> = (0..n)
+            .filter(|&k| label.get(k) == Some(&k) && !anchored.contains(&k))
+            .collect();
+        let g = DMatrix::from_fn(n, n, |p, q| {
+            let stamps: f64 = network
+                .resistors
+                .iter()
+                .map(|r| {
+                    let conductance = 1.0 / r.r;
+                    match r.far {
+                        Far::Node(k) if (p, q) == (r.node, k) || (p, q) == (k, r.node) => {
+                            -conductance
+                        }
+                        Far::Node(k) if p == q && (p == r.node || p == k) => conductance,
+                        Far::Source(_) | Far::Reference if p == q && p == r.node => conductance,
+                        Far::Node(_) | Far::Source(_) | Far::Reference => 0.0,
+                    }
+                })
+                .sum();
+            let gauge = if p == q && floating.contains(&p) {
+                1.0
+            } else {
+                0.0
+            };
+            stamps + gauge
+        });
+        let mut constraints = Vec::new();
+        for &island in &floating {
+            let row = label
+                .iter()
+                .zip(incidence.row_iter())
+                .filter(|(l, _)| **l == island)
+                .fold(RowDVector::zeros(m), |acc, (_, row)| acc + row);
+            constraints.push(row);
+        }
+        let basis = if constraints.is_empty() {
+            DMatrix::identity(m, m)
+        } else {
+            let c = DMatrix::from_rows(&constraints);
+            let projector = DMatrix::identity(m, m) - c.clone().pseudo_inverse(1e-12).ok()? * c;
+            let eigen = projector.symmetric_eigen();
+            let columns: Vec<DVector<f64>> = eigen
+                .eigenvalues
+                .iter()
+                .zip(eigen.eigenvectors.column_iter())
+                .filter(|(lambda, _)| **lambda > 0.5)
+                .map(|(_, v)| v.into_owned())
+                .collect();
+            if columns.is_empty() {
+                DMatrix::zeros(m, 0)
+            } else {
+                DMatrix::from_columns(&columns)
+            }
+        };
+
+        Some(Self {
+            network,
+            incidence,
+            g_inv: g.try_inverse()?,
+            basis,
+        })
+    }
+
+    /// Number of independent coil currents.
+    fn n_currents(&self) -> usize {
+        self.basis.ncols()
+    }
+
+    /// Node potentials for source voltages `v_abc` and coil currents `i`.
+    fn potentials(&self, v_abc: [f64; 3], i: &DVector<f64>) -> DVector<f64> {
+        let s = DVector::from_fn(self.network.n_nodes, |node, _| {
+            self.network
+                .resistors
+                .iter()
+                .filter(|r| r.node == node)
+                .map(|r| match r.far {
+                    Far::Source(k) => v_abc.get(k).copied().unwrap_or(0.0) / r.r,
+                    Far::Node(_) | Far::Reference => 0.0,
+                })
+                .sum::<f64>()
+        });
+        &self.g_inv * (s - &self.incidence * i)
+    }
+}
+
+/// Coil inductance, its angle derivative, and the PM flux derivative.
+struct CoilMatrices {
+    /// Coil inductance matrix `L(θ_e)`.
+    l: DMatrix<f64>,
+    /// `∂L/∂θ_e`.
+    dl: DMatrix<f64>,
+    /// `∂ψ_pm/∂θ_e` per coil.
+    dpsi: DVector<f64>,
+}
+
+/// The abc machine with its supply and load for one solve segment.
+struct AbcModel {
+    /// Machine parameters.
+    machine: AbcMachineNode,
+    /// Winding network in force.
+    circuit: Circuit,
+    /// Phase voltage source.
+    source: PhaseSource,
+    /// Load torque.
+    t_l: ExternalInput,
+    /// Rotor, friction and built-in load.
+    mech: MechanicalNode,
+}
+
+/// Machine quantities reported at one sample.
+struct Sample {
+    /// Line currents into terminals a, b, c.
+    i_abc: [f64; 3],
+    /// Electromagnetic torque.
+    t_e: f64,
+    /// Fault-branch current.
+    i_f: f64,
+}
+
+impl AbcModel {
+    /// Coil matrices at electrical angle `theta_e`.
+    ///
+    /// A partial coil links the magnetizing flux in proportion to its turns
+    /// and carries its own share of the leakage, so the series connection of
+    /// the parts of a split winding has the inductance of the whole.
+    fn coil_matrices(&self, theta_e: f64) -> CoilMatrices {
+        let (l, dl) = self.machine.inductance(theta_e);
+        let (_, dpsi) = self.machine.pm_flux(theta_e);
+        let coils = &self.circuit.network.coils;
+        let l_ls = self.machine.l_ls;
+        let entry = |m: &[[f64; 3]; 3], j: usize, k: usize| {
+            m.get(j).and_then(|row| row.get(k)).copied().unwrap_or(0.0)
+        };
+        let m = coils.len();
+        let l = DMatrix::from_fn(m, m, |p, q| match (coils.get(p), coils.get(q)) {
+            (Some(a), Some(b)) => {
+                let leak = if a.phase == b.phase { l_ls } else { 0.0 };
+                let own = if p == q { a.turns * l_ls } else { 0.0 };
+                a.turns * b.turns * (entry(&l, a.phase, b.phase) - leak) + own
+            }
+            _ => 0.0,
+        });
+        let dl = DMatrix::from_fn(m, m, |p, q| match (coils.get(p), coils.get(q)) {
+            (Some(a), Some(b)) => a.turns * b.turns * entry(&dl, a.phase, b.phase),
+            _ => 0.0,
+        });
+        let dpsi = DVector::from_iterator(
+            m,
+            coils
+                .iter()
+                .map(|c| c.turns * dpsi.get(c.phase).copied().unwrap_or(0.0)),
+        );
+        CoilMatrices { l, dl, dpsi }
+    }
+
+    /// Source voltages at time `t` and electrical angle `theta_e`.
+    fn voltages(&self, t: f64, theta_e: f64) -> [f64; 3] {
+        // Only the angle is read by the sources used in abc mode
+        let state = CoupledState {
+            i_d: 0.0,
+            i_q: 0.0,
+            theta_e,
+            v_dc: 0.0,
+        };
+        self.source.at(t, state)
+    }
+
+    /// Split a state vector into coil currents, `ω_m` and `θ_e`.
+    fn unpack(&self, x: &[f64]) -> (DVector<f64>, f64, f64) {
+        let (z, mech) = x.split_at(self.circuit.n_currents().min(x.len()));
+        let &[omega_m, theta_e] = mech else {
+            return (DVector::zeros(self.circuit.basis.nrows()), 0.0, 0.0);
+        };
+        let i = &self.circuit.basis * DVector::from_column_slice(z);
+        (i, omega_m, theta_e)
+    }
+
+    /// Electromagnetic torque from the co-energy derivative.
+    fn torque(&self, coil: &CoilMatrices, i: &DVector<f64>) -> f64 {
+        self.mech.n_p * (0.5 * i.dot(&(&coil.dl * i)) + i.dot(&coil.dpsi))
+    }
+
+    /// State derivatives `dx/dt` at time `t`.
+    fn derivatives(&self, t: f64, x: &[f64], y: &mut [f64]) {
+        let (i, omega_m, theta_e) = self.unpack(x);
+        let coil = self.coil_matrices(theta_e);
+        let omega_e = self.mech.n_p * omega_m;
+        let phi = self.potentials(t, theta_e, &i);
+        let r = DVector::from_iterator(
+            i.len(),
+            self.circuit
+                .network
+                .coils
+                .iter()
+                .map(|c| c.turns * self.machine.r_s),
+        );
+        let emf = self.circuit.incidence.tr_mul(&phi)
+            - r.component_mul(&i)
+            - (&coil.dl * &i + &coil.dpsi) * omega_e;
+        let t_basis = &self.circuit.basis;
+        let reduced = t_basis.tr_mul(&(&coil.l * t_basis));
+        let dz = reduced
+            .lu()
+            .solve(&t_basis.tr_mul(&emf))
+            .unwrap_or_else(|| DVector::from_element(t_basis.ncols(), f64::NAN));
+
+        let (dz_out, mech) = y.split_at_mut(dz.len().min(y.len()));
+        dz_out.copy_from_slice(dz.as_slice());
+        if let [d_omega, d_theta] = mech {
+            let t_e = self.torque(&coil, &i);
+            let mech = &self.mech;
+            let t_speed = mech.speed_torque(omega_m).torque;
+            *d_omega = (t_e - self.t_l.at(t) - t_speed - mech.b * omega_m) / mech.inertia();
+            *d_theta = omega_e;
+        }
+    }
+
+    /// Node potentials at time `t`.
+    fn potentials(&self, t: f64, theta_e: f64, i: &DVector<f64>) -> DVector<f64> {
+        self.circuit.potentials(self.voltages(t, theta_e), i)
+    }
+
+    /// Line, torque and fault quantities at time `t` and state `x`.
+    fn sample(&self, t: f64, x: &[f64]) -> Sample {
+        let (i, _, theta_e) = self.unpack(x);
+        let v_abc = self.voltages(t, theta_e);
+        let phi = self.circuit.potentials(v_abc, &i);
+        let potential = |node: usize| phi.get(node).copied().unwrap_or(0.0);
+        let resistors = &self.circuit.network.resistors;
+        let i_abc = std::array::from_fn(|k| {
+            resistors
+                .iter()
+                .find(|r| r.far == Far::Source(k))
+                .map_or(0.0, |r| {
+                    (v_abc.get(k).copied().unwrap_or(0.0) - potential(r.node)) / r.r
+                })
+        });
+        let i_f = self
+            .circuit
+            .network
+            .fault
+            .and_then(|f| resistors.get(f))
+            .map_or(0.0, |r| match r.far {
+                Far::Node(k) => (potential(r.node) - potential(k)) / r.r,
+                Far::Source(_) | Far::Reference => potential(r.node) / r.r,
+            });
+        Sample {
+            i_abc,
+            t_e: self.torque(&self.coil_matrices(theta_e), &i),
+            i_f,
+        }
+    }
+
+    /// Initial state of `next` continuing from state `x` of this model.
+    ///
+    /// Each coil of `next` takes the current of the same phase's winding;
+    /// the result is projected onto `next`'s admissible currents.
+    fn transfer(&self, x: &[f64], next: &Circuit) -> Vec<f64> {
+        let (i, omega_m, theta_e) = self.unpack(x);
+        let coils = &self.circuit.network.coils;
+        let carried = DVector::from_iterator(
+            next.network.coils.len(),
+            next.network.coils.iter().map(|c| {
+                coils
+                    .iter()
+                    .zip(i.iter())
+                    .find(|(old, _)| old.phase == c.phase)
+                    .map_or(0.0, |(_, &current)| current)
+            }),
+        );
+        let z = next.basis.tr_mul(&carried);
+        z.iter().copied().chain([omega_m, theta_e]).collect()
+    }
+}
+
+/// Raw output series of the abc solve, at solver time resolution.
+#[derive(Default)]
+struct AbcSeries {
+    /// Line currents.
+    i_abc: Vec<[f64; 4]>,
+    /// Electromagnetic torque.
+    t_e: Vec<[f64; 2]>,
+    /// Fault-branch current.
+    i_f: Vec<[f64; 2]>,
+    /// Mechanical speed.
+    omega_m: Vec<[f64; 2]>,
+    /// Electrical angle.
+    theta_e: Vec<[f64; 2]>,
+}
+
+/// Solve a graph whose motor is an abc-frame machine and publish its outputs.
+///
+/// # Errors
+///
+/// - [`SimError::GraphError`] — a node vanished or has an unexpected type.
+/// - [`SimError::SolverFailed`] — the inductances are inconsistent
+///   (`L_d`, `L_q` must exceed `L_ls > 0`), or the BDF integrator fails.
+pub(super) fn run(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    all_ids: &[NodeId],
+    machine_id: NodeId,
+    mech_id: NodeId,
+    sources: Sources,
+) -> Result<(), SimError> {
+    let Some(SimNode::AbcMachine(machine)) = snarl.get_node(machine_id) else {
+        return Err(SimError::GraphError("expected abc machine node".to_owned()));
+    };
+    let machine = (**machine).clone();
+    if !(machine.l_ls > 0.0 && machine.l_d > machine.l_ls && machine.l_q > machine.l_ls) {
+        return Err(SimError::SolverFailed(
+            "abc machine needs L_d, L_q > L_ls > 0".to_owned(),
+        ));
+    }
+    let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_id) else {
+        return Err(SimError::GraphError("expected mechanical node".to_owned()));
+    };
+    let mech = mech.clone();
+    let (t0, t1) = (config.t_start, config.t_end);
+
+    sources.precompute(snarl, all_ids, config);
+    let v_abc_pin = InPinId {
+        node: machine_id,
+        input: 0,
+    };
+    let source = resolve_svpwm_coupling(snarl, v_abc_pin, 1, mech_id, None).map_or_else(
+        || PhaseSource::Series(get_vector_input(snarl, machine_id, 0).unwrap_or_default()),
+        |coupling| PhaseSource::Svpwm(Box::new(coupling)),
+    );
+    // MechanicalNode pin layout: 0 = T_e (Signal), 1 = T_L (Signal)
+    let t_l = resolve_external_input(snarl, mech_id, 1);
+
+    let singular = || SimError::SolverFailed("singular winding circuit".to_owned());
+    let model = |network: Network| -> Result<Rc<AbcModel>, SimError> {
+        Ok(Rc::new(AbcModel {
+            machine: machine.clone(),
+            circuit: Circuit::new(network).ok_or_else(singular)?,
+            source: source.clone(),
+            t_l: t_l.clone(),
+            mech: mech.clone(),
+        }))
+    };
+
+    // Healthy segment up to the fault, faulted segment after it
+    let t_fault = machine.fault.t_fault;
+    let fault_in_span = machine.fault.kind != FaultKind::None && t_fault < t1;
+    let mut segments = Vec::new();
+    if !fault_in_span || t_fault > t0 {
+        let end = if fault_in_span { t_fault } else { t1 };
+        segments.push((model(machine.network(false))?, t0, end));
+    }
+    if fault_in_span {
+        segments.push((model(machine.network(true))?, t_fault.max(t0), t1));
+    }
+
+    let mut series = AbcSeries::default();
+    let mut carried: Option<(Rc<AbcModel>, Vec<f64>)> = None;
+    for (segment, start, end) in segments {
+        let y0 = match &carried {
+            Some((previous, x)) => previous.transfer(x, &segment.circuit),
+            None => vec![0.0; segment.circuit.n_currents()]
+                .into_iter()
+                .chain([mech.omega_m_0, mech.theta_e_0])
+                .collect(),
+        };
+        let rhs: Rhs = {
+            let model = Rc::clone(&segment);
+            Rc::new(move |t: f64, x: &[f64], y: &mut [f64]| model.derivatives(t, x, y))
+        };
+        let (ts, states) = solve_numeric(&rhs, None, config, start, end, y0)?;
+        for (&t, x) in ts.iter().zip(&states) {
+            let Sample { i_abc, t_e, i_f } = segment.sample(t, x);
+            let [i_a, i_b, i_c] = i_abc;
+            let (_, omega_m, theta_e) = segment.unpack(x);
+            series.i_abc.push([t, i_a, i_b, i_c]);
+            series.t_e.push([t, t_e]);
+            series.i_f.push([t, i_f]);
+            series.omega_m.push([t, omega_m]);
+            series.theta_e.push([t, theta_e]);
+        }
+        carried = states.last().cloned().map(|x| (segment, x));
+    }
+
+    let (t0, t1, dt) = (config.t_start, config.t_end, config.output_dt);
+    let signal = |series: &[[f64; 2]]| PortValue::Signal(resample_signal(series, t0, t1, dt));
+    if let Some(SimNode::AbcMachine(m)) = snarl.get_node_mut(machine_id) {
+        m.output_i_abc = Some(PortValue::Vector(resample_vector(
+            &series.i_abc,
+            t0,
+            t1,
+            dt,
+        )));
+        m.output_t_e = Some(signal(&series.t_e));
+        m.output_i_f = Some(signal(&series.i_f));
+    }
+    if let Some(SimNode::Mechanical(m)) = snarl.get_node_mut(mech_id) {
+        m.output_omega_m = Some(signal(&series.omega_m));
+        m.output_theta_e = Some(signal(&series.theta_e));
+    }
+    sources.refresh(snarl, all_ids, config, &series.i_abc);
+    Ok(())
+}
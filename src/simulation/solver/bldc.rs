@@ -0,0 +1,201 @@
+//! ODE solve for the BLDC motor ([`BldcNode`]).
+//!
+//! State vector: `[i_a, i_b, ω_m, θ_e]`; the star point floats, so
+//! `i_c = −i_a − i_b`. The phase equations are documented in
+//! [`crate::nodes::bldc`], and the rigid rotor is that of the Mechanical node:
+//!
+//! ```text
+//! dω_m/dt = (T_e − T_L − T_f(ω_m) − T_load(ω_m) − B·ω_m) / J,   dθ_e/dt = N_p·ω_m
+//! ```
+//!
+//! The leg states follow the Hall code of `θ_e`, with off legs split into
+//! floating and diode-clamped. They are held fixed within each solver step
+//! and switched at the Hall edges and diode transitions, where the
+//! integration restarts. A floating leg makes the system stiff; the
+//! Jacobian is a forward difference of the right-hand side.
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use egui_snarl::{InPinId, NodeId, Snarl};
+
+use super::{
+    ExternalInput, Rhs, Sources, Switching, resample_signal, resample_vector,
+    resolve_external_input, solve_numeric,
+};
+use crate::nodes::SimNode;
+use crate::nodes::bldc::{BldcNode, Leg};
+use crate::nodes::mechanical::MechanicalNode;
+use crate::port::PortValue;
+use crate::simulation::{SimConfig, SimError};
+
+/// BLDC motor, its drive inputs and the Mechanical node's rotor.
+struct BldcModel {
+    /// Motor parameters.
+    motor: BldcNode,
+    /// DC-link voltage.
+    v_dc: ExternalInput,
+    /// PWM duty of the high-side switch.
+    duty: ExternalInput,
+    /// Load torque.
+    t_l: ExternalInput,
+    /// Rotor, friction and built-in load.
+    mech: MechanicalNode,
+    /// Leg drive states in force.
+    legs: Cell<[Leg; 3]>,
+}
+
+impl BldcModel {
+    /// Phase currents `[i_a, i_b, i_c]` from the two current states.
+    fn currents(i_a: f64, i_b: f64) -> [f64; 3] {
+        [i_a, i_b, -i_a - i_b]
+    }
+
+    /// Electromagnetic torque (N·m) at angle `theta_e` for phase currents `i`.
+    fn torque(&self, theta_e: f64, i: [f64; 3]) -> f64 {
+        let shape = BldcNode::emf_shape(theta_e);
+        self.motor.k_e * shape.iter().zip(i).map(|(f, i)| f * i).sum::<f64>()
+    }
+
+    /// Evaluate the state derivatives `y` at time `t` and state `x`.
+    fn derivatives(&self, t: f64, x: &[f64], y: &mut [f64]) {
+        let (&[i_a, i_b, omega_m, theta_e], [d_ia, d_ib, d_wm, d_te]) = (x, y) else {
+            return;
+        };
+        let m = &self.motor;
+        let i = Self::currents(i_a, i_b);
+        let e = BldcNode::emf_shape(theta_e).map(|f| m.k_e * omega_m * f);
+        let v_dc = self.v_dc.at(t);
+        let v = BldcNode::leg_voltages(self.legs.get(), self.duty.at(t) * v_dc, v_dc, i);
+        let v_n = (v.iter().sum::<f64>() - e.iter().sum::<f64>()) / 3.0;
+        let ([v_a, v_b, _], [e_a, e_b, _]) = (v, e);
+        *d_ia = (v_a - v_n - m.r_s * i_a - e_a) / m.l_s;
+        *d_ib = (v_b - v_n - m.r_s * i_b - e_b) / m.l_s;
+        let mech = &self.mech;
+        let t_speed = mech.speed_torque(omega_m).torque;
+        *d_wm = (self.torque(theta_e, i) - self.t_l.at(t) - t_speed - mech.b * omega_m)
+            / mech.inertia();
+        *d_te = mech.n_p * omega_m;
+    }
+
+    /// Leg states at time `t` and state `x`: commanded by the Hall sensors,
+    /// with the off legs resolved into diode conduction.
+    fn commanded(&self, t: f64, x: &[f64]) -> [Leg; 3] {
+        let &[i_a, i_b, _, theta_e] = x else {
+            return [Leg::Off; 3];
+        };
+        let legs = BldcNode::commutation(self.motor.hall(theta_e));
+        BldcNode::conduction(legs, self.v_dc.at(t), Self::currents(i_a, i_b))
+    }
+}
+
+impl Switching for BldcModel {
+    fn pending(&self, t: f64, x: &[f64]) -> bool {
+        self.commanded(t, x) != self.legs.get()
+    }
+
+    fn commit(&self, t: f64, x: &[f64]) {
+        self.legs.set(self.commanded(t, x));
+    }
+}
+
+/// Solve a graph whose motor is a BLDC motor and publish its outputs.
+///
+/// # Errors
+///
+/// - [`SimError::GraphError`] — a node vanished or has an unexpected type.
+/// - [`SimError::SolverFailed`] — the phase inductance is not positive, or
+///   the BDF integrator fails.
+pub(super) fn run(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    all_ids: &[NodeId],
+    motor_id: NodeId,
+    mech_id: NodeId,
+    sources: Sources,
+) -> Result<(), SimError> {
+    let Some(SimNode::Bldc(motor)) = snarl.get_node(motor_id) else {
+        return Err(SimError::GraphError("expected BLDC motor node".to_owned()));
+    };
+    let motor = motor.clone();
+    if motor.l_s <= 0.0 {
+        return Err(SimError::SolverFailed(
+            "BLDC motor needs a positive phase inductance".to_owned(),
+        ));
+    }
+    let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_id) else {
+        return Err(SimError::GraphError("expected mechanical node".to_owned()));
+    };
+    let mech = mech.clone();
+    let (omega_m_0, theta_e_0) = (mech.omega_m_0, mech.theta_e_0);
+
+    sources.precompute(snarl, all_ids, config);
+    // BldcNode pin layout: 0 = V_dc, 1 = d (Signal)
+    let v_dc = resolve_external_input(snarl, motor_id, 0);
+    let duty_pin = InPinId {
+        node: motor_id,
+        input: 1,
+    };
+    let duty = if snarl.in_pin(duty_pin).remotes.is_empty() {
+        ExternalInput::Constant(1.0)
+    } else {
+        resolve_external_input(snarl, motor_id, 1)
+    };
+    // MechanicalNode pin layout: 0 = T_e (Signal), 1 = T_L (Signal)
+    let t_l = resolve_external_input(snarl, mech_id, 1);
+
+    let model = Rc::new(BldcModel {
+        motor,
+        v_dc,
+        duty,
+        t_l,
+        mech,
+        legs: Cell::new([Leg::Off; 3]),
+    });
+    let rhs: Rhs = {
+        let model = Rc::clone(&model);
+        Rc::new(move |t: f64, x: &[f64], y: &mut [f64]| model.derivatives(t, x, y))
+    };
+    let y0 = vec![0.0, 0.0, omega_m_0, theta_e_0];
+    let (ts, states) = solve_numeric(
+        &rhs,
+        Some(&*model),
+        config,
+        config.t_start,
+        config.t_end,
+        y0,
+    )?;
+
+    let mut i_abc = Vec::with_capacity(ts.len());
+    let mut hall = Vec::with_capacity(ts.len());
+    let mut t_e = Vec::with_capacity(ts.len());
+    let mut omega_m = Vec::with_capacity(ts.len());
+    let mut theta_e = Vec::with_capacity(ts.len());
+    for (&t, x) in ts.iter().zip(&states) {
+        let &[i_a, i_b, w, theta] = x.as_slice() else {
+            continue;
+        };
+        let i = BldcModel::currents(i_a, i_b);
+        let [h_a, h_b, h_c] = model.motor.hall(theta).map(f64::from);
+        let [i_a, i_b, i_c] = i;
+        i_abc.push([t, i_a, i_b, i_c]);
+        hall.push([t, h_a, h_b, h_c]);
+        t_e.push([t, model.torque(theta, i)]);
+        omega_m.push([t, w]);
+        theta_e.push([t, theta]);
+    }
+
+    let (t0, t1, dt) = (config.t_start, config.t_end, config.output_dt);
+    let signal = |series: &[[f64; 2]]| PortValue::Signal(resample_signal(series, t0, t1, dt));
+    if let Some(SimNode::Bldc(m)) = snarl.get_node_mut(motor_id) {
+        m.output_i_abc = Some(PortValue::Vector(resample_vector(&i_abc, t0, t1, dt)));
+        m.output_hall = Some(PortValue::Vector(resample_vector(&hall, t0, t1, dt)));
+        m.output_t_e = Some(signal(&t_e));
+    }
+    if let Some(SimNode::Mechanical(m)) = snarl.get_node_mut(mech_id) {
+        m.output_omega_m = Some(signal(&omega_m));
+        m.output_theta_e = Some(signal(&theta_e));
+    }
+    sources.refresh(snarl, all_ids, config, &i_abc);
+    Ok(())
+}
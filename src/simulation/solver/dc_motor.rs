@@ -0,0 +1,135 @@
+//! ODE solve for the brushed DC motor
+//! ([`DcMotorNode`](crate::nodes::dc_motor::DcMotorNode)).
+//!
+//! State vector: `[i_a, ω_m, θ_e]`, with the armature equation documented in
+//! [`crate::nodes::dc_motor`] and the rigid rotor of the Mechanical node:
+//!
+//! ```text
+//! dω_m/dt = (k_t·i_a − T_L − T_f(ω_m) − T_load(ω_m) − B·ω_m) / J,   dθ_e/dt = N_p·ω_m
+//! ```
+//!
+//! Without friction or built-in load the system is linear and the Jacobian
+//! constant. The angle only serves the Mechanical node's `θ_e` output.
+
+use diffsol::{DenseMatrix as _, OdeBuilder, OdeSolverMethod as _};
+use egui_snarl::{NodeId, Snarl};
+
+use super::{Ls, M, Sources, resample_signal, resolve_external_input};
+use crate::nodes::SimNode;
+use crate::port::PortValue;
+use crate::simulation::{SimConfig, SimError};
+
+/// State index: armature current `i_a`.
+const S_IA: usize = 0;
+/// State index: rotor mechanical speed `ω_m`.
+const S_WM: usize = 1;
+/// State index: rotor electrical angle `θ_e`.
+const S_TE: usize = 2;
+/// Number of states.
+const N_STATES: usize = 3;
+
+/// Solve a graph whose motor is a brushed DC motor and publish its outputs.
+///
+/// # Errors
+///
+/// - [`SimError::GraphError`] — a node vanished or has an unexpected type.
+/// - [`SimError::SolverFailed`] — the armature inductance is not positive,
+///   or the BDF integrator fails.
+pub(super) fn run(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    all_ids: &[NodeId],
+    motor_id: NodeId,
+    mech_id: NodeId,
+    sources: Sources,
+) -> Result<(), SimError> {
+    let Some(SimNode::DcMotor(motor)) = snarl.get_node(motor_id) else {
+        return Err(SimError::GraphError("expected DC motor node".to_owned()));
+    };
+    let (r_a, l_a, k_e, k_t, i_a_0) = (motor.r_a, motor.l_a, motor.k_e, motor.k_t, motor.i_a_0);
+    if l_a <= 0.0 {
+        return Err(SimError::SolverFailed(
+            "DC motor needs a positive armature inductance".to_owned(),
+        ));
+    }
+    let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_id) else {
+        return Err(SimError::GraphError("expected mechanical node".to_owned()));
+    };
+    let (j, b, n_p, omega_m_0, theta_e_0) = (
+        mech.inertia(),
+        mech.b,
+        mech.n_p,
+        mech.omega_m_0,
+        mech.theta_e_0,
+    );
+    let (mech_rhs, mech_jac) = (mech.clone(), mech.clone());
+
+    sources.precompute(snarl, all_ids, config);
+    // DcMotorNode pin layout: 0 = v_a (Signal)
+    let v_a = resolve_external_input(snarl, motor_id, 0);
+    // MechanicalNode pin layout: 0 = T_e (Signal), 1 = T_L (Signal)
+    let t_l = resolve_external_input(snarl, mech_id, 1);
+
+    let problem = OdeBuilder::<M>::new()
+        .t0(config.t_start)
+        .rtol(config.rtol)
+        .atol(vec![config.atol; N_STATES])
+        .rhs_implicit(
+            // ── RHS: compute time derivatives ──────────────────────────────
+            move |x, _p, t, y| {
+                y[S_IA] = (v_a.at(t) - r_a * x[S_IA] - k_e * x[S_WM]) / l_a;
+                let t_speed = mech_rhs.speed_torque(x[S_WM]).torque;
+                y[S_WM] = (k_t * x[S_IA] - t_l.at(t) - t_speed - b * x[S_WM]) / j;
+                y[S_TE] = n_p * x[S_WM];
+            },
+            // ── Jacobian-vector product J·v where J = ∂f/∂x ─────────────
+            move |x, _p, _t, v, y| {
+                let d_speed = mech_jac.speed_torque(x[S_WM]).d_omega;
+                y[S_IA] = (-r_a * v[S_IA] - k_e * v[S_WM]) / l_a;
+                y[S_WM] = (k_t * v[S_IA] - (b + d_speed) * v[S_WM]) / j;
+                y[S_TE] = n_p * v[S_WM];
+            },
+        )
+        .init(
+            move |_p, _t, y| {
+                y[S_IA] = i_a_0;
+                y[S_WM] = omega_m_0;
+                y[S_TE] = theta_e_0;
+            },
+            N_STATES,
+        )
+        .build()
+        .map_err(|e| SimError::SolverFailed(format!("{e:?}")))?;
+
+    let mut solver = problem
+        .bdf::<Ls>()
+        .map_err(|e| SimError::SolverFailed(format!("{e:?}")))?;
+    let (ys, ts) = solver
+        .solve(config.t_end)
+        .map_err(|e| SimError::SolverFailed(format!("{e:?}")))?;
+
+    let mut i_a = Vec::with_capacity(ts.len());
+    let mut t_e = Vec::with_capacity(ts.len());
+    let mut omega_m = Vec::with_capacity(ts.len());
+    let mut theta_e = Vec::with_capacity(ts.len());
+    for (i, &t) in ts.iter().enumerate() {
+        let column = ys.column(i);
+        i_a.push([t, column[S_IA]]);
+        t_e.push([t, k_t * column[S_IA]]);
+        omega_m.push([t, column[S_WM]]);
+        theta_e.push([t, column[S_TE]]);
+    }
+
+    let (t0, t1, dt) = (config.t_start, config.t_end, config.output_dt);
+    let signal = |series: &[[f64; 2]]| PortValue::Signal(resample_signal(series, t0, t1, dt));
+    if let Some(SimNode::DcMotor(m)) = snarl.get_node_mut(motor_id) {
+        m.output_i_a = Some(signal(&i_a));
+        m.output_t_e = Some(signal(&t_e));
+    }
+    if let Some(SimNode::Mechanical(m)) = snarl.get_node_mut(mech_id) {
+        m.output_omega_m = Some(signal(&omega_m));
+        m.output_theta_e = Some(signal(&theta_e));
+    }
+    sources.refresh(snarl, all_ids, config, &[]);
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! Efficiency maps of the d/q PMSM.
+//!
+//! Every Efficiency Map node is filled from the steady-state model of the
+//! first Electrical node, with the torque equation of the first Torque node
+//! and the friction of the first Mechanical or two-mass node, as in the
+//! solve itself.
+
+use egui_snarl::{NodeId, Snarl};
+
+use crate::nodes::SimNode;
+use crate::nodes::efficiency_map::SteadyStateModel;
+use crate::nodes::mechanical::Friction;
+use crate::simulation::SimError;
+
+/// Refresh the map of every Efficiency Map node.
+///
+/// # Errors
+///
+/// [`SimError::GraphError`] — a map node is present without the PMSM
+/// Electrical node.
+pub(super) fn run(snarl: &mut Snarl<SimNode>, all_ids: &[NodeId]) -> Result<(), SimError> {
+    let maps: Vec<NodeId> = all_ids
+        .iter()
+        .copied()
+        .filter(|&id| matches!(snarl.get_node(id), Some(SimNode::EfficiencyMap(_))))
+        .collect();
+    if maps.is_empty() {
+        return Ok(());
+    }
+    let nodes = || all_ids.iter().filter_map(|&id| snarl.get_node(id));
+    let electrical = nodes()
+        .find_map(|node| match node {
+            SimNode::Electrical(e) => Some(e),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            SimError::GraphError("the efficiency map needs the PMSM Electrical node".to_owned())
+        })?;
+    let torque = nodes().find_map(|node| match node {
+        SimNode::Torque(t) => Some(t),
+        _ => None,
+    });
+    let (b, friction) = nodes()
+        .find_map(|node| match node {
+            SimNode::Mechanical(m) => Some((m.b, m.friction)),
+            SimNode::TwoMass(m) => Some((m.b_m, Friction::default())),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let model = SteadyStateModel::new(electrical, torque, b, friction);
+    for id in maps {
+        if let Some(SimNode::EfficiencyMap(map)) = snarl.get_node_mut(id) {
+            map.evaluate(&model);
+        }
+    }
+    Ok(())
+}
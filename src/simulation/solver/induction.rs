@@ -0,0 +1,213 @@
+//! ODE solve for the induction machine
+//! ([`InductionMachineNode`](crate::nodes::induction::InductionMachineNode)).
+//!
+//! State vector: `[ψ_sd, ψ_sq, ψ_rd, ψ_rq, ω_m, θ_e]`, with the flux
+//! equations documented in [`crate::nodes::induction`] and the rigid rotor
+//! of the Mechanical node:
+//!
+//! ```text
+//! dω_m/dt = (T_e − T_L − T_f(ω_m) − T_load(ω_m) − B·ω_m) / J,   dθ_e/dt = N_p·ω_m
+//! T_e = (3/2)·N_p·(L_m / D)·(ψ_sq·ψ_rd − ψ_sd·ψ_rq),   D = L_s·L_r − L_m²
+//! ```
+//!
+//! The pole-pair count is the induction machine's own; the Mechanical node's
+//! `N_p` is not used. The voltages `v_d`, `v_q` are read as external inputs
+//! in the machine's frame.
+
+use std::rc::Rc;
+
+use diffsol::{DenseMatrix as _, OdeBuilder, OdeSolverMethod as _};
+use egui_snarl::{NodeId, Snarl};
+
+use super::{CoupledState, Ls, M, Sources, resample_signal, resolve_external_input};
+use crate::nodes::SimNode;
+use crate::nodes::induction::InductionFrame;
+use crate::port::PortValue;
+use crate::simulation::{SimConfig, SimError};
+
+/// State index: rotor mechanical speed `ω_m`.
+const S_WM: usize = 4;
+/// State index: rotor electrical angle `θ_e`.
+const S_TE: usize = 5;
+/// Number of states.
+const N_STATES: usize = 6;
+
+/// Flux linkages `[ψ_sd, ψ_sq, ψ_rd, ψ_rq]` of a state vector.
+fn fluxes<V: std::ops::Index<usize, Output = f64>>(x: &V) -> [f64; 4] {
+    [x[0], x[1], x[2], x[3]]
+}
+
+/// Solve a graph whose motor is an induction machine and publish its outputs.
+///
+/// # Errors
+///
+/// - [`SimError::GraphError`] — a node vanished or has an unexpected type.
+/// - [`SimError::SolverFailed`] — the inductances are not positive, or the
+///   BDF integrator fails.
+#[expect(
+    clippy::too_many_lines,
+    reason = "ODE assembly inherently requires a long function"
+)]
+pub(super) fn run(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    all_ids: &[NodeId],
+    machine_id: NodeId,
+    mech_id: NodeId,
+    sources: Sources,
+) -> Result<(), SimError> {
+    let Some(SimNode::Induction(machine)) = snarl.get_node(machine_id) else {
+        return Err(SimError::GraphError(
+            "expected induction machine node".to_owned(),
+        ));
+    };
+    let machine = Rc::new(machine.clone());
+    if !(machine.l_m > 0.0 && machine.l_ls > 0.0 && machine.l_lr > 0.0) {
+        return Err(SimError::SolverFailed(
+            "induction machine needs positive inductances".to_owned(),
+        ));
+    }
+    let Some(SimNode::Mechanical(mech)) = snarl.get_node(mech_id) else {
+        return Err(SimError::GraphError("expected mechanical node".to_owned()));
+    };
+    let (j, b, omega_m_0, theta_e_0) = (mech.inertia(), mech.b, mech.omega_m_0, mech.theta_e_0);
+    let mech = Rc::new(mech.clone());
+
+    sources.precompute(snarl, all_ids, config);
+    // InductionMachineNode pin layout: 0 = v_d, 1 = v_q (Signal)
+    let v_d = resolve_external_input(snarl, machine_id, 0);
+    let v_q = resolve_external_input(snarl, machine_id, 1);
+    // MechanicalNode pin layout: 0 = T_e (Signal), 1 = T_L (Signal)
+    let t_l = resolve_external_input(snarl, mech_id, 1);
+
+    let problem = OdeBuilder::<M>::new()
+        .t0(config.t_start)
+        .rtol(config.rtol)
+        .atol(vec![config.atol; N_STATES])
+        .rhs_implicit(
+            // ── RHS: compute time derivatives ──────────────────────────────
+            {
+                let (machine, mech) = (Rc::clone(&machine), Rc::clone(&mech));
+                move |x, _p, t, y| {
+                    let psi = fluxes(x);
+                    let omega_k = machine.frame_speed(x[S_WM]);
+                    let omega_r = machine.n_p * x[S_WM];
+                    let d_psi =
+                        machine.flux_derivatives(psi, [v_d.at(t), v_q.at(t)], omega_k, omega_r);
+                    for (k, d) in d_psi.into_iter().enumerate() {
+                        y[k] = d;
+                    }
+                    let t_speed = mech.speed_torque(x[S_WM]).torque;
+                    y[S_WM] = (machine.torque(psi) - t_l.at(t) - t_speed - b * x[S_WM]) / j;
+                    y[S_TE] = omega_r;
+                }
+            },
+            // ── Jacobian-vector product J·v where J = ∂f/∂x ─────────────
+            {
+                let machine = Rc::clone(&machine);
+                move |x, _p, _t, v, y| {
+                    let d_speed = mech.speed_torque(x[S_WM]).d_omega;
+                    let m = &machine;
+                    let [psi_sd, psi_sq, psi_rd, psi_rq] = fluxes(x);
+                    let (l_s, l_r) = (m.l_ls + m.l_m, m.l_lr + m.l_m);
+                    let det = l_s * l_r - m.l_m * m.l_m;
+                    // ∂i/∂ψ coefficients: i_s = a·ψ_s − c·ψ_r, i_r = e·ψ_r − c·ψ_s
+                    let (a, c, e) = (l_r / det, m.l_m / det, l_s / det);
+                    let omega_k = m.frame_speed(x[S_WM]);
+                    let slip = omega_k - m.n_p * x[S_WM];
+                    // ∂ω_k/∂ω_m: only the rotor frame moves with the rotor
+                    let dk = if m.frame == InductionFrame::Rotor {
+                        m.n_p
+                    } else {
+                        0.0
+                    };
+                    let dslip = dk - m.n_p;
+
+                    y[0] = -m.r_s * a * v[0]
+                        + omega_k * v[1]
+                        + m.r_s * c * v[2]
+                        + dk * psi_sq * v[S_WM];
+                    y[1] = -omega_k * v[0] - m.r_s * a * v[1] + m.r_s * c * v[3]
+                        - dk * psi_sd * v[S_WM];
+                    y[2] = m.r_r * c * v[0] - m.r_r * e * v[2]
+                        + slip * v[3]
+                        + dslip * psi_rq * v[S_WM];
+                    y[3] = m.r_r * c * v[1]
+                        - slip * v[2]
+                        - m.r_r * e * v[3]
+                        - dslip * psi_rd * v[S_WM];
+
+                    // T_e = k·(ψ_sq·ψ_rd − ψ_sd·ψ_rq)
+                    let k = 1.5 * m.n_p * c;
+                    let dt_e = k * (-psi_rq * v[0] + psi_rd * v[1] + psi_sq * v[2] - psi_sd * v[3]);
+                    y[S_WM] = (dt_e - (b + d_speed) * v[S_WM]) / j;
+                    y[S_TE] = m.n_p * v[S_WM];
+                }
+            },
+        )
+        .init(
+            move |_p, _t, y| {
+                for k in 0..S_WM {
+                    y[k] = 0.0;
+                }
+                y[S_WM] = omega_m_0;
+                y[S_TE] = theta_e_0;
+            },
+            N_STATES,
+        )
+        .build()
+        .map_err(|e| SimError::SolverFailed(format!("{e:?}")))?;
+
+    let mut solver = problem
+        .bdf::<Ls>()
+        .map_err(|e| SimError::SolverFailed(format!("{e:?}")))?;
+    let (ys, ts) = solver
+        .solve(config.t_end)
+        .map_err(|e| SimError::SolverFailed(format!("{e:?}")))?;
+
+    // Output series in the machine's frame, plus phase currents for the inverter
+    let mut i_sd = Vec::with_capacity(ts.len());
+    let mut i_sq = Vec::with_capacity(ts.len());
+    let mut psi_rd = Vec::with_capacity(ts.len());
+    let mut psi_rq = Vec::with_capacity(ts.len());
+    let mut t_e = Vec::with_capacity(ts.len());
+    let mut omega_m = Vec::with_capacity(ts.len());
+    let mut theta_e = Vec::with_capacity(ts.len());
+    let mut i_abc = Vec::with_capacity(ts.len());
+    for (i, &t) in ts.iter().enumerate() {
+        let column = ys.column(i);
+        let psi = fluxes(&column);
+        let [d, q, ..] = machine.currents(psi);
+        i_sd.push([t, d]);
+        i_sq.push([t, q]);
+        psi_rd.push([t, psi[2]]);
+        psi_rq.push([t, psi[3]]);
+        t_e.push([t, machine.torque(psi)]);
+        omega_m.push([t, column[S_WM]]);
+        theta_e.push([t, column[S_TE]]);
+        let stator = CoupledState {
+            i_d: d,
+            i_q: q,
+            theta_e: machine.frame_angle(t, column[S_TE]),
+            v_dc: 0.0,
+        };
+        let [i_a, i_b, i_c] = stator.i_abc();
+        i_abc.push([t, i_a, i_b, i_c]);
+    }
+
+    let (t0, t1, dt) = (config.t_start, config.t_end, config.output_dt);
+    let signal = |series: &[[f64; 2]]| PortValue::Signal(resample_signal(series, t0, t1, dt));
+    if let Some(SimNode::Induction(m)) = snarl.get_node_mut(machine_id) {
+        m.output_i_sd = Some(signal(&i_sd));
+        m.output_i_sq = Some(signal(&i_sq));
+        m.output_psi_rd = Some(signal(&psi_rd));
+        m.output_psi_rq = Some(signal(&psi_rq));
+        m.output_t_e = Some(signal(&t_e));
+    }
+    if let Some(SimNode::Mechanical(m)) = snarl.get_node_mut(mech_id) {
+        m.output_omega_m = Some(signal(&omega_m));
+        m.output_theta_e = Some(signal(&theta_e));
+    }
+    sources.refresh(snarl, all_ids, config, &i_abc);
+    Ok(())
+}
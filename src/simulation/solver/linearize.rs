@@ -0,0 +1,153 @@
+//! Small-signal models for the Bode nodes.
+//!
+//! Each wired Bode node is linearised about the steady state of the machine
+//! for the inputs at `t_start`. `A` and `C` are evaluated at the trimmed
+//! state; `B` and `D` by a central difference in the value at `t_start` of
+//! the source wired to `u`, with the state held at the trim. The source is
+//! perturbed by rewiring its consumers to a temporary Constant for the two
+//! evaluations.
+
+use egui_snarl::{InPinId, NodeId, OutPinId, Snarl};
+
+use super::{Evaluation, S_TE, Task, interpolate_signal, solve_machine};
+use crate::nodes::SimNode;
+use crate::nodes::bode::LinearModel;
+use crate::nodes::constant::ConstantNode;
+use crate::port::{PortType, PortValue};
+use crate::simulation::{SimConfig, SimError};
+
+/// Refresh the linear model of every Bode node; unwired nodes are cleared
+/// and wrongly wired ones keep the reason as their error.
+///
+/// # Errors
+///
+/// - [`SimError::GraphError`] — the machine cannot be trimmed.
+/// - [`SimError::SolverFailed`] — the steady-state trim does not converge.
+pub(super) fn run(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    all_ids: &[NodeId],
+) -> Result<(), SimError> {
+    for &id in all_ids {
+        if !matches!(snarl.get_node(id), Some(SimNode::Bode(_))) {
+            continue;
+        }
+        let (model, error) = match wiring(snarl, id) {
+            Some((u, y)) => match linear_model(snarl, config, all_ids, u, y)? {
+                Ok(model) => (Some(model), None),
+                Err(error) => (None, Some(error)),
+            },
+            None => (None, None),
+        };
+        if let Some(SimNode::Bode(bode)) = snarl.get_node_mut(id) {
+            bode.set_model(model);
+            bode.error = error;
+        }
+    }
+    Ok(())
+}
+
+/// The output pins wired to `u` and `y`, or `None` while either is unwired.
+fn wiring(snarl: &Snarl<SimNode>, id: NodeId) -> Option<(OutPinId, OutPinId)> {
+    // BodeNode pin layout: 0 = u (source), 1 = y (machine output)
+    let remote = |input| {
+        snarl
+            .in_pin(InPinId { node: id, input })
+            .remotes
+            .first()
+            .copied()
+    };
+    Some((remote(0)?, remote(1)?))
+}
+
+/// Value at `t_start` of the source output `u`, as left by the trim.
+fn source_value(snarl: &Snarl<SimNode>, u: OutPinId, t_start: f64) -> Option<f64> {
+    match snarl.get_node(u.node)? {
+        SimNode::Constant(c) if c.output_type != PortType::Vector => Some(c.value),
+        node => match node.output_value(u.output)? {
+            PortValue::Signal(data) => Some(interpolate_signal(data, t_start)),
+            _ => None,
+        },
+    }
+}
+
+/// Linear model from the source output `u` to the machine output `y`.
+///
+/// The outer error fails the run; the inner one is a message for the node
+/// when the wiring cannot be linearised.
+fn linear_model(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    all_ids: &[NodeId],
+    u: OutPinId,
+    y: OutPinId,
+) -> Result<Result<LinearModel, String>, SimError> {
+    let missing =
+        || SimError::GraphError("the Bode node needs the PMSM Electrical node".to_owned());
+    let nominal = solve_machine(snarl, config, all_ids, Task::Trim)?.ok_or_else(missing)?;
+    let Some(output) = nominal.pins.iter().position(|&pin| pin == y) else {
+        return Ok(Err(
+            "y must be a signal output of the PMSM, its drivetrain, DC link, thermal network or power flow"
+                .to_owned(),
+        ));
+    };
+    let Some(u_0) = source_value(snarl, u, config.t_start) else {
+        return Ok(Err(
+            "u must be a signal with a value at t_start that does not depend on the machine state"
+                .to_owned(),
+        ));
+    };
+
+    // Perturb the input about the trim through a temporary Constant that
+    // takes over the consumers of `u`; the wiring is restored even if a
+    // pass fails.
+    let consumers = snarl.out_pin(u).remotes;
+    let probe = snarl.insert_node(
+        egui::Pos2::ZERO,
+        SimNode::Constant(ConstantNode {
+            value: u_0,
+            output_type: PortType::Signal,
+            ..ConstantNode::default()
+        }),
+    );
+    let probe_pin = OutPinId {
+        node: probe,
+        output: 0,
+    };
+    for &pin in &consumers {
+        snarl.disconnect(u, pin);
+        snarl.connect(probe_pin, pin);
+    }
+    let ids: Vec<NodeId> = all_ids.iter().copied().chain([probe]).collect();
+    let h = 1e-6 * u_0.abs().max(1.0);
+    let mut evaluate = |value: f64| -> Result<Evaluation, SimError> {
+        if let Some(SimNode::Constant(c)) = snarl.get_node_mut(probe) {
+            c.value = value;
+        }
+        solve_machine(snarl, config, &ids, Task::Evaluate(&nominal.x))?.ok_or_else(missing)
+    };
+    let plus = evaluate(u_0 + h);
+    let minus = evaluate(u_0 - h);
+    snarl.remove_node(probe);
+    for &pin in &consumers {
+        snarl.connect(u, pin);
+    }
+    let (plus, minus) = (plus?, minus?);
+
+    // θ_e only integrates ω_m; dropping it removes the pole at the origin.
+    let keep: Vec<usize> = (0..nominal.x.len()).filter(|&k| k != S_TE).collect();
+    Ok(Ok(LinearModel {
+        states: keep
+            .iter()
+            .filter_map(|&k| nominal.names.get(k).cloned())
+            .collect(),
+        a: nominal.a.select_rows(&keep).select_columns(&keep),
+        b: ((plus.f - minus.f) / (2.0 * h)).select_rows(&keep),
+        c: nominal.c.row(output).select_columns(&keep),
+        d: plus
+            .y
+            .get(output)
+            .zip(minus.y.get(output))
+            .map_or(0.0, |(p, m)| (p - m) / (2.0 * h)),
+    }))
+}
@@ -0,0 +1,296 @@
+//! Post-processing pass over the solved graph.
+//!
+//! Nodes that only observe the machine — they read solved signals and feed
+//! nothing back into the ODE — are evaluated once the solve has written its
+//! outputs. A node is evaluated as soon as all its wired inputs hold data,
+//! so chains of such nodes resolve in dependency order. Observer states are
+//! integrated here with the same BDF solver as the machine, and Kalman
+//! filters run at their own sampling rate.
+
+use std::rc::Rc;
+
+use egui_snarl::{InPinId, NodeId, Snarl};
+
+use super::{
+    Rhs, get_signal_input, get_vector_input, interpolate_signal, interpolate_vector,
+    resample_signal, resample_vector, solve_numeric,
+};
+use crate::nodes::SimNode;
+use crate::nodes::ekf::EkfNode;
+use crate::nodes::observer::{ObserverNode, clarke};
+use crate::nodes::spectrum::SpectrumNode;
+use crate::port::{PortType, PortValue};
+use crate::simulation::{SimConfig, SimError};
+
+/// Evaluate every post-processing node whose inputs are available,
+/// repeating until no further node can be evaluated.
+///
+/// # Errors
+///
+/// [`SimError::SolverFailed`] if an observer cannot be integrated.
+pub(super) fn run(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    all_ids: &[NodeId],
+) -> Result<(), SimError> {
+    let mut pending: Vec<NodeId> = all_ids
+        .iter()
+        .copied()
+        .filter(|&id| {
+            matches!(
+                snarl.get_node(id),
+                Some(
+                    SimNode::Sensor(_)
+                        | SimNode::Measurement(_)
+                        | SimNode::Observer(_)
+                        | SimNode::Ekf(_)
+                        | SimNode::Spectrum(_)
+                        | SimNode::Metrics(_)
+                )
+            )
+        })
+        .collect();
+    loop {
+        let before = pending.len();
+        let mut remaining = Vec::with_capacity(before);
+        for id in pending {
+            if !evaluate(snarl, config, id)? {
+                remaining.push(id);
+            }
+        }
+        pending = remaining;
+        if pending.is_empty() || pending.len() == before {
+            return Ok(());
+        }
+    }
+}
+
+/// Evaluate node `id` and publish its outputs; `false` while an input is
+/// still missing.
+fn evaluate(snarl: &mut Snarl<SimNode>, config: &SimConfig, id: NodeId) -> Result<bool, SimError> {
+    match snarl.get_node(id) {
+        Some(SimNode::Sensor(sensor)) => {
+            let sensor = sensor.clone();
+            // PositionSensorNode pin layout: 0 = θ_e (Signal)
+            let Some(theta_e) = get_signal_input(snarl, id, 0) else {
+                return Ok(false);
+            };
+            let ts: Vec<f64> = theta_e.iter().map(|&[t, _]| t).collect();
+            let series = sensor.compute(&ts, |t| interpolate_signal(&theta_e, t));
+            if let Some(SimNode::Sensor(s)) = snarl.get_node_mut(id) {
+                s.output_theta_e = Some(PortValue::Signal(series.theta_e));
+                s.output_omega_m = Some(PortValue::Signal(series.omega_m));
+                s.output_sector = Some(PortValue::Signal(series.sector));
+                s.output_raw = Some(PortValue::Vector(series.raw));
+            }
+            Ok(true)
+        }
+        Some(SimNode::Measurement(measurement)) => {
+            let measurement = measurement.clone();
+            // MeasurementNode pin layout: 0 = x (Signal or Vector)
+            let measured = if measurement.port_type == PortType::Vector {
+                get_vector_input(snarl, id, 0)
+                    .map(|x| PortValue::Vector(measurement.compute_vector(&x)))
+            } else {
+                get_signal_input(snarl, id, 0)
+                    .map(|x| PortValue::Signal(measurement.compute_signal(&x)))
+            };
+            let Some(measured) = measured else {
+                return Ok(false);
+            };
+            if let Some(SimNode::Measurement(m)) = snarl.get_node_mut(id) {
+                m.output_value = Some(measured);
+            }
+            Ok(true)
+        }
+        Some(SimNode::Observer(observer)) => update_observer(snarl, config, id, observer.clone()),
+        Some(SimNode::Ekf(ekf)) => Ok(update_ekf(snarl, config, id, &ekf.clone())),
+        Some(SimNode::Spectrum(spectrum)) => Ok(update_spectrum(snarl, id, &spectrum.clone())),
+        Some(SimNode::Metrics(_)) => {
+            // MetricsNode pin layout: 0 = x (Signal)
+            let Some(x) = get_signal_input(snarl, id, 0) else {
+                return Ok(false);
+            };
+            if let Some(SimNode::Metrics(m)) = snarl.get_node_mut(id) {
+                m.metrics = m.evaluate(&x);
+            }
+            Ok(true)
+        }
+        _ => Ok(true),
+    }
+}
+
+/// Integrate an observer over the simulated interval and publish its
+/// outputs; `false` while an input is still missing.
+///
+/// # Errors
+///
+/// [`SimError::SolverFailed`] if the observer ODE cannot be integrated.
+fn update_observer(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    id: NodeId,
+    observer: ObserverNode,
+) -> Result<bool, SimError> {
+    // ObserverNode pin layout: 0 = v_abc, 1 = i_abc (Vector), 2 = θ_e (Signal)
+    let (Some(v_abc), Some(i_abc)) = (
+        get_vector_input(snarl, id, 0),
+        get_vector_input(snarl, id, 1),
+    ) else {
+        return Ok(false);
+    };
+    let theta_wired = !snarl
+        .in_pin(InPinId { node: id, input: 2 })
+        .remotes
+        .is_empty();
+    let theta_e = get_signal_input(snarl, id, 2);
+    if theta_wired && theta_e.is_none() {
+        return Ok(false);
+    }
+
+    let (t0, t1, dt) = (config.t_start, config.t_end, config.output_dt);
+    let y0 = observer.initial_state(clarke(interpolate_vector(&i_abc, t0)));
+    let observer = Rc::new(observer);
+    let rhs: Rhs = {
+        let observer = Rc::clone(&observer);
+        Rc::new(move |t: f64, x: &[f64], y: &mut [f64]| {
+            let v = clarke(interpolate_vector(&v_abc, t));
+            let i = clarke(interpolate_vector(&i_abc, t));
+            observer.derivatives(v, i, x, y);
+        })
+    };
+    let (ts, states) = solve_numeric(&rhs, None, config, t0, t1, y0)?;
+    let series = observer.estimate(&ts, &states);
+    let theta_hat = resample_signal(&series.theta_e, t0, t1, dt);
+    let error = theta_e.map(|theta_e| {
+        PortValue::Signal(ObserverNode::angle_error(&theta_hat, |t| {
+            interpolate_signal(&theta_e, t)
+        }))
+    });
+
+    if let Some(SimNode::Observer(o)) = snarl.get_node_mut(id) {
+        o.output_theta_e = Some(PortValue::Signal(theta_hat));
+        o.output_omega_m = Some(PortValue::Signal(resample_signal(
+            &series.omega_m,
+            t0,
+            t1,
+            dt,
+        )));
+        o.output_error = error;
+        o.output_emf = Some(PortValue::Vector(resample_vector(&series.emf, t0, t1, dt)));
+    }
+    Ok(true)
+}
+
+/// Run an EKF over the simulated interval and publish its outputs; `false`
+/// while an input is still missing.
+fn update_ekf(snarl: &mut Snarl<SimNode>, config: &SimConfig, id: NodeId, ekf: &EkfNode) -> bool {
+    // EkfNode pin layout: 0 = v_abc, 1 = i_abc (Vector), 2 = θ_e (Signal)
+    let (Some(v_abc), Some(i_abc)) = (
+        get_vector_input(snarl, id, 0),
+        get_vector_input(snarl, id, 1),
+    ) else {
+        return false;
+    };
+    let theta_wired = !snarl
+        .in_pin(InPinId { node: id, input: 2 })
+        .remotes
+        .is_empty();
+    let theta_e = get_signal_input(snarl, id, 2);
+    if theta_wired && theta_e.is_none() {
+        return false;
+    }
+
+    let (t0, t1, dt) = (config.t_start, config.t_end, config.output_dt);
+    let series = ekf.estimate(
+        t0,
+        t1,
+        |t| clarke(interpolate_vector(&v_abc, t)),
+        |t| clarke(interpolate_vector(&i_abc, t)),
+    );
+    let signal = |s: &[[f64; 2]]| Some(PortValue::Signal(resample_signal(s, t0, t1, dt)));
+    let error = theta_e.map(|theta_e| {
+        PortValue::Signal(ObserverNode::angle_error(&series.theta_e, |t| {
+            interpolate_signal(&theta_e, t)
+        }))
+    });
+
+    if let Some(SimNode::Ekf(e)) = snarl.get_node_mut(id) {
+        e.output_i_d = signal(&series.i_d);
+        e.output_i_q = signal(&series.i_q);
+        e.output_omega_m = signal(&series.omega_m);
+        e.output_theta_e = signal(&series.theta_e);
+        e.output_param = signal(&series.param);
+        e.output_error = error;
+        e.output_sigma_omega = signal(&series.sigma_omega);
+        e.output_sigma_theta = signal(&series.sigma_theta);
+        e.output_sigma_param = signal(&series.sigma_param);
+    }
+    true
+}
+
+/// Analyse the last periods of a Signal or Vector and store its spectrum;
+/// `false` while an input is still missing.
+fn update_spectrum(snarl: &mut Snarl<SimNode>, id: NodeId, spectrum: &SpectrumNode) -> bool {
+    // SpectrumNode pin layout: 0 = x (Signal or Vector), 1 = θ_e (Signal)
+    let theta_wired = !snarl
+        .in_pin(InPinId { node: id, input: 1 })
+        .remotes
+        .is_empty();
+    let theta_e = get_signal_input(snarl, id, 1);
+    if theta_wired && theta_e.is_none() {
+        return false;
+    }
+    // Sample each channel at the window times over the span of the input
+    let (signal, phases) = if spectrum.port_type == PortType::Vector {
+        let Some(x) = get_vector_input(snarl, id, 0) else {
+            return false;
+        };
+        (None, Some(x))
+    } else {
+        let Some(x) = get_signal_input(snarl, id, 0) else {
+            return false;
+        };
+        (Some(x), None)
+    };
+    let span = match (&signal, &phases) {
+        (Some(x), _) => x.first().zip(x.last()).map(|(a, b)| (a[0], b[0])),
+        (_, Some(x)) => x.first().zip(x.last()).map(|(a, b)| (a[0], b[0])),
+        _ => None,
+    };
+    let window = span.and_then(|(t0, t1)| spectrum.window(t0, t1, theta_e.as_deref()));
+    let spectra = match (&window, &signal, &phases) {
+        (Some(window), Some(x), _) => {
+            let values: Vec<f64> = window
+                .times
+                .iter()
+                .map(|&t| interpolate_signal(x, t))
+                .collect();
+            vec![spectrum.spectrum("x", window, &values)]
+        }
+        (Some(window), _, Some(x)) => {
+            let samples: Vec<[f64; 3]> = window
+                .times
+                .iter()
+                .map(|&t| interpolate_vector(x, t))
+                .collect();
+            ["a", "b", "c"]
+                .iter()
+                .enumerate()
+                .map(|(k, name)| {
+                    let values: Vec<f64> = samples
+                        .iter()
+                        .map(|v| v.get(k).copied().unwrap_or(0.0))
+                        .collect();
+                    spectrum.spectrum(name, window, &values)
+                })
+                .collect()
+        }
+        _ => Vec::new(),
+    };
+    if let Some(SimNode::Spectrum(s)) = snarl.get_node_mut(id) {
+        s.analysis = window;
+        s.channels = spectra;
+    }
+    true
+}
@@ -0,0 +1,233 @@
+//! Thermal-network states appended to the PMSM ODE
+//! ([`ThermalNode`](crate::nodes::thermal::ThermalNode)).
+//!
+//! One temperature state per body follows the network equation documented in
+//! [`crate::nodes::thermal`]. Losses wired from the Electrical node are
+//! evaluated from the machine state, so the temperatures rise with the load
+//! actually drawn; with feedback enabled the winding temperature sets `R_s`
+//! and the magnet temperature scales `λ_m` at every RHS evaluation.
+
+use std::ops::{Index, IndexMut};
+
+use egui_snarl::{InPinId, NodeId, Snarl};
+
+use super::{ExternalInput, N_PARAMS, P_LAM, P_RS, P_T_LAM, resolve_external_input};
+use crate::nodes::SimNode;
+use crate::nodes::thermal::ThermalNode;
+use crate::simulation::SimError;
+
+/// Source of one loss input of the network.
+enum LossInput {
+    /// The matching loss of the PMSM, evaluated from the ODE state.
+    Machine,
+    /// Any other signal, resolved before the solve.
+    External(ExternalInput),
+}
+
+impl LossInput {
+    /// Loss at time `t`, given the machine's loss `machine` at the state.
+    fn at(&self, t: f64, machine: f64) -> f64 {
+        match self {
+            Self::Machine => machine,
+            Self::External(input) => input.at(t),
+        }
+    }
+
+    /// Directional derivative given the machine's `d_machine`; external
+    /// losses do not depend on the state.
+    fn slope(&self, d_machine: f64) -> f64 {
+        match self {
+            Self::Machine => d_machine,
+            Self::External(_) => 0.0,
+        }
+    }
+}
+
+/// Linear temperature dependence `value·(1 + α·(T − T_ref))`.
+#[derive(Clone, Copy)]
+struct Coefficient {
+    /// Value at `T_ref`.
+    value: f64,
+    /// Temperature coefficient (1/K).
+    alpha: f64,
+    /// Reference temperature (°C).
+    t_ref: f64,
+}
+
+impl Coefficient {
+    /// Value at temperature `temp`.
+    fn at(self, temp: f64) -> f64 {
+        self.value * (1.0 + self.alpha * (temp - self.t_ref))
+    }
+}
+
+/// The thermal network with its loss sources and feedback paths.
+pub(super) struct ThermalModel {
+    /// Network parameters.
+    network: ThermalNode,
+    /// State index of the first body temperature.
+    offset: usize,
+    /// Sources of `[P_cu, P_fe]`.
+    losses: [LossInput; 2],
+    /// `R_s` versus winding temperature, when fed back.
+    r_s: Option<Coefficient>,
+    /// Relative PM flux versus magnet temperature, when fed back.
+    lambda_m: Option<Coefficient>,
+}
+
+impl ThermalModel {
+    /// Build the model for the thermal node `thermal_id`, with its states
+    /// starting at `offset`.
+    ///
+    /// # Errors
+    ///
+    /// - [`SimError::GraphError`] — a node vanished or has an unexpected type.
+    /// - [`SimError::SolverFailed`] — the network is invalid (see
+    ///   [`ThermalNode::validate`]).
+    pub(super) fn resolve(
+        snarl: &Snarl<SimNode>,
+        thermal_id: NodeId,
+        elec_id: NodeId,
+        offset: usize,
+    ) -> Result<Self, SimError> {
+        let (Some(SimNode::Thermal(network)), Some(SimNode::Electrical(e))) =
+            (snarl.get_node(thermal_id), snarl.get_node(elec_id))
+        else {
+            return Err(SimError::GraphError("expected thermal node".to_owned()));
+        };
+        network.validate().map_err(SimError::SolverFailed)?;
+        // ThermalNode pin layout: 0 = P_cu, 1 = P_fe; ElectricalNode outputs 2, 3
+        let losses = [(0, 2), (1, 3)].map(|(input, elec_output)| {
+            let pin = snarl.in_pin(InPinId {
+                node: thermal_id,
+                input,
+            });
+            let from_machine = pin
+                .remotes
+                .first()
+                .is_some_and(|r| r.node == elec_id && r.output == elec_output);
+            if from_machine {
+                LossInput::Machine
+            } else {
+                LossInput::External(resolve_external_input(snarl, thermal_id, input))
+            }
+        });
+        Ok(Self {
+            network: network.clone(),
+            offset,
+            losses,
+            r_s: network.feedback_r_s.then_some(Coefficient {
+                value: e.r_s,
+                alpha: e.alpha_cu,
+                t_ref: e.t_ref,
+            }),
+            lambda_m: network.feedback_lambda_m.then_some(Coefficient {
+                value: 1.0,
+                alpha: e.alpha_pm,
+                t_ref: e.t_ref,
+            }),
+        })
+    }
+
+    /// Number of temperature states.
+    pub(super) fn n_states(&self) -> usize {
+        self.network.bodies.len()
+    }
+
+    /// State index of the winding temperature.
+    pub(super) fn s_winding(&self) -> usize {
+        self.offset + self.network.winding
+    }
+
+    /// State index of the magnet temperature.
+    pub(super) fn s_magnet(&self) -> usize {
+        self.offset + self.network.magnet
+    }
+
+    /// Body temperatures from an ODE state vector.
+    pub(super) fn temperatures<X: Index<usize, Output = f64>>(&self, x: &X) -> Vec<f64> {
+        (self.offset..self.offset + self.n_states())
+            .map(|k| x[k])
+            .collect()
+    }
+
+    /// Parameter vector `p` with `R_s` and `λ_m` (also the Torque node's)
+    /// taken at the temperatures in `x`, where fed back.
+    pub(super) fn params<P, X>(&self, p: &P, x: &X) -> [f64; N_PARAMS]
+    where
+        P: Index<usize, Output = f64>,
+        X: Index<usize, Output = f64>,
+    {
+        let mut scaled: [f64; N_PARAMS] = std::array::from_fn(|k| p[k]);
+        if let Some(r_s) = self.r_s {
+            scaled[P_RS] = r_s.at(x[self.s_winding()]);
+        }
+        if let Some(lambda_m) = self.lambda_m {
+            let factor = lambda_m.at(x[self.s_magnet()]);
+            scaled[P_LAM] *= factor;
+            scaled[P_T_LAM] *= factor;
+        }
+        scaled
+    }
+
+    /// Slopes `∂R_s/∂T_w` and `∂λ_m/∂T_pm` (relative) of the fed-back
+    /// parameters; zero without feedback.
+    pub(super) fn slopes(&self) -> (f64, f64) {
+        (
+            self.r_s.map_or(0.0, |c| c.value * c.alpha),
+            self.lambda_m.map_or(0.0, |c| c.value * c.alpha),
+        )
+    }
+
+    /// Write the temperature derivatives at time `t` and state `x` into `y`,
+    /// with `machine` the PMSM losses `[P_cu, P_fe]` at `x`.
+    pub(super) fn derivatives<X, Y>(&self, t: f64, x: &X, machine: [f64; 2], y: &mut Y)
+    where
+        X: Index<usize, Output = f64>,
+        Y: IndexMut<usize, Output = f64>,
+    {
+        let ([cu, fe], [p_cu, p_fe]) = (&self.losses, machine);
+        let losses = [cu.at(t, p_cu), fe.at(t, p_fe)];
+        let mut d = vec![0.0; self.n_states()];
+        self.network
+            .derivatives(&self.temperatures(x), losses, &mut d);
+        for (k, d) in d.into_iter().enumerate() {
+            y[self.offset + k] = d;
+        }
+    }
+
+    /// Write the temperature rows of `J·v` into `y`, with `machine` the
+    /// directional derivative of the PMSM losses along `v`.
+    pub(super) fn jvp<V, Y>(&self, v: &V, machine: [f64; 2], y: &mut Y)
+    where
+        V: Index<usize, Output = f64>,
+        Y: IndexMut<usize, Output = f64>,
+    {
+        let ([cu, fe], [dp_cu, dp_fe]) = (&self.losses, machine);
+        let d_losses = [cu.slope(dp_cu), fe.slope(dp_fe)];
+        let mut out = vec![0.0; self.n_states()];
+        self.network.jvp(&self.temperatures(v), d_losses, &mut out);
+        for (k, o) in out.into_iter().enumerate() {
+            y[self.offset + k] = o;
+        }
+    }
+
+    /// Write the initial body temperatures into `y`.
+    pub(super) fn init<Y: IndexMut<usize, Output = f64>>(&self, y: &mut Y) {
+        for (k, body) in self.network.bodies.iter().enumerate() {
+            y[self.offset + k] = body.t_0;
+        }
+    }
+
+    /// Winding temperature, magnet temperature and the first three body
+    /// temperatures (padded with the ambient) at state `x`.
+    pub(super) fn outputs<X: Index<usize, Output = f64>>(&self, x: &X) -> (f64, f64, [f64; 3]) {
+        let temps = self.temperatures(x);
+        let body = |k: usize| temps.get(k).copied().unwrap_or(self.network.t_ambient);
+        (
+            x[self.s_winding()],
+            x[self.s_magnet()],
+            [body(0), body(1), body(2)],
+        )
+    }
+}
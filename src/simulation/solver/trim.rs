@@ -0,0 +1,157 @@
+//! Steady-state trim and linearisation of the assembled machine equations.
+//!
+//! Newton's method on the ODE right-hand side finds the stator states and
+//! rotor speed at which `di_d/dt`, `di_q/dt` and `dω_m/dt` vanish for the
+//! inputs at `t_start`. The Jacobian is the same analytic one the BDF solver
+//! uses. States tied to the trimmed ones at equilibrium, like the load speed
+//! and shaft twist of a drivetrain, follow them through a caller-supplied
+//! coupling. `θ_e` has no equilibrium while the rotor turns, so it and the
+//! remaining appended states (`v_C`, temperatures) stay at their initial
+//! values.
+
+use diffsol::{
+    ConstantOp as _, NalgebraContext, NalgebraVec, NonLinearOp as _, NonLinearOpJacobian as _,
+    OdeEquationsImplicit, OdeSolverProblem, Vector as _, VectorHost as _,
+};
+use nalgebra::{DMatrix, DVector, Matrix3, Vector3};
+
+use super::{M, S_ID, S_IQ, S_WM};
+use crate::simulation::{SimConfig, SimError};
+
+/// States solved for by the trim, in Newton-vector order.
+const TRIMMED: [usize; 3] = [S_ID, S_IQ, S_WM];
+
+/// Newton iterations before the trim gives up.
+const MAX_ITERATIONS: usize = 100;
+
+/// Smallest fraction of a Newton step tried by the backtracking line search.
+const MIN_STEP: f64 = 1.0 / 1024.0;
+
+/// Solve `f(x, t_start) = 0` for the [`TRIMMED`] states, starting from the
+/// problem's initial state.
+///
+/// `coupled` sets the states that follow the trimmed ones at equilibrium; it
+/// is applied to every iterate, and the Jacobian differentiates through it.
+///
+/// The trim has converged once a full Newton step moves every trimmed state
+/// by less than `atol + rtol·|x|` of the simulation tolerances.
+///
+/// # Errors
+///
+/// [`SimError::SolverFailed`] if the Jacobian is singular or Newton's method
+/// does not converge.
+pub(super) fn equilibrium<Eqn>(
+    problem: &OdeSolverProblem<Eqn>,
+    config: &SimConfig,
+    coupled: impl Fn(&mut NalgebraVec<f64>),
+) -> Result<NalgebraVec<f64>, SimError>
+where
+    Eqn: OdeEquationsImplicit<T = f64, V = NalgebraVec<f64>, M = M, C = NalgebraContext>,
+{
+    let t = config.t_start;
+    let rhs = problem.eqn.rhs();
+    let residual = |x: &NalgebraVec<f64>| {
+        let dx = rhs.call(x, t);
+        Vector3::new(dx[S_ID], dx[S_IQ], dx[S_WM])
+    };
+    // Columns of ∂f/∂x restricted to the trimmed rows, each along the
+    // direction a trimmed state moves the full state through the coupling
+    let jacobian = |x: &NalgebraVec<f64>| {
+        let mut columns = [Vector3::zeros(); 3];
+        for (column, &s) in columns.iter_mut().zip(&TRIMMED) {
+            let h = 1e-7 * x[s].abs().max(1.0);
+            let mut shifted = x.clone();
+            shifted[s] += h;
+            coupled(&mut shifted);
+            let mut v = NalgebraVec::zeros(x.len(), *x.context());
+            for k in 0..x.len() {
+                v[k] = (shifted[k] - x[k]) / h;
+            }
+            v[s] = 1.0;
+            let jv = rhs.jac_mul(x, t, &v);
+            *column = Vector3::new(jv[S_ID], jv[S_IQ], jv[S_WM]);
+        }
+        Matrix3::from_columns(&columns)
+    };
+
+    let mut x = problem.eqn.init().call(t);
+    coupled(&mut x);
+    let mut r = residual(&x);
+    for _ in 0..MAX_ITERATIONS {
+        let step = jacobian(&x).lu().solve(&-r).ok_or_else(|| {
+            SimError::SolverFailed("steady-state trim: singular Jacobian".to_owned())
+        })?;
+        // Halve the step until the residual falls, so starts far from the
+        // operating point (e.g. at standstill) still converge.
+        let mut scale = 1.0;
+        let (x_next, r_next) = loop {
+            let mut x_next = x.clone();
+            for (&s, dx) in TRIMMED.iter().zip(step.iter()) {
+                x_next[s] += scale * dx;
+            }
+            coupled(&mut x_next);
+            let r_next = residual(&x_next);
+            if r_next.norm() < r.norm() || scale <= MIN_STEP {
+                break (x_next, r_next);
+            }
+            scale *= 0.5;
+        };
+        x = x_next;
+        r = r_next;
+        let converged = TRIMMED
+            .iter()
+            .zip(step.iter())
+            .all(|(&s, dx)| dx.abs() <= config.atol + config.rtol * x[s].abs());
+        if converged {
+            return Ok(x);
+        }
+    }
+    Err(SimError::SolverFailed(
+        "steady-state trim did not converge".to_owned(),
+    ))
+}
+
+/// Derivatives `f(x, t)` and the full Jacobian `∂f/∂x`, one analytic JVP per
+/// column.
+pub(super) fn jacobian<Eqn>(
+    problem: &OdeSolverProblem<Eqn>,
+    t: f64,
+    x: &NalgebraVec<f64>,
+) -> (DVector<f64>, DMatrix<f64>)
+where
+    Eqn: OdeEquationsImplicit<T = f64, V = NalgebraVec<f64>, M = M, C = NalgebraContext>,
+{
+    let rhs = problem.eqn.rhs();
+    let n = x.len();
+    let f = DVector::from_column_slice(rhs.call(x, t).as_slice());
+    let mut v = NalgebraVec::zeros(n, *x.context());
+    let mut a = DMatrix::zeros(n, n);
+    for k in 0..n {
+        v[k] = 1.0;
+        a.set_column(
+            k,
+            &DVector::from_column_slice(rhs.jac_mul(x, t, &v).as_slice()),
+        );
+        v[k] = 0.0;
+    }
+    (f, a)
+}
+
+/// Jacobian `∂y/∂x` of the outputs by central differences.
+pub(super) fn output_jacobian(
+    x: &NalgebraVec<f64>,
+    outputs: impl Fn(&NalgebraVec<f64>) -> Vec<f64>,
+) -> DMatrix<f64> {
+    let n = x.len();
+    let mut c = DMatrix::zeros(outputs(x).len(), n);
+    for k in 0..n {
+        let h = 1e-6 * x[k].abs().max(1.0);
+        let shifted = |step: f64| {
+            let mut x = x.clone();
+            x[k] += step;
+            DVector::from_column_slice(&outputs(&x))
+        };
+        c.set_column(k, &((shifted(h) - shifted(-h)) / (2.0 * h)));
+    }
+    c
+}
@@ -0,0 +1,565 @@
+//! Parameter sweeps: the graph solved once per value of one node parameter.
+//!
+//! A parameter is addressed by its field path inside the serialized node,
+//! e.g. `j` on the Mechanical node or `iron_loss.k_h` on the Electrical
+//! node, so every numeric field of every node can be swept without per-node
+//! code. Each run solves an independent copy of the graph — in parallel on
+//! native targets — and every Plot node collects the traces of all runs,
+//! labelled with the parameter value. The named scalars of the Metrics
+//! nodes are returned for every run.
+
+use egui_snarl::{InPinId, NodeId, Snarl};
+use serde_json::Value;
+
+use super::solver::run_simulation;
+use super::{SimConfig, SimError};
+use crate::nodes::plot::PlotRun;
+use crate::nodes::{SimNode, plot_lines};
+
+/// How the swept values are given.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum SweepMode {
+    /// Comma-separated list.
+    #[default]
+    List,
+    /// `count` values evenly spaced from `start` to `end`.
+    Linear,
+    /// `count` values geometrically spaced from `start` to `end`.
+    Logarithmic,
+}
+
+impl SweepMode {
+    /// All variants, in UI display order.
+    pub const ALL: [Self; 3] = [Self::List, Self::Linear, Self::Logarithmic];
+
+    /// Short label for combo boxes.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::List => "List",
+            Self::Linear => "Linear range",
+            Self::Logarithmic => "Log range",
+        }
+    }
+}
+
+/// Setup of a parameter sweep.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct SweepConfig {
+    /// Node holding the swept parameter.
+    pub node: Option<NodeId>,
+    /// Field path of the parameter inside the node, `.`-separated.
+    pub param: String,
+    /// How the values are given.
+    pub mode: SweepMode,
+    /// Comma-separated values for [`SweepMode::List`].
+    pub list: String,
+    /// First value of a range.
+    pub start: f64,
+    /// Last value of a range.
+    pub end: f64,
+    /// Number of values in a range.
+    pub count: usize,
+}
+
+impl Default for SweepConfig {
+    fn default() -> Self {
+        Self {
+            node: None,
+            param: String::new(),
+            mode: SweepMode::List,
+            list: String::new(),
+            start: 1.0,
+            end: 2.0,
+            count: 5,
+        }
+    }
+}
+
+impl SweepConfig {
+    /// The swept values.
+    ///
+    /// # Errors
+    ///
+    /// [`SimError::InvalidSetup`] for an unparsable or empty list, or a
+    /// logarithmic range that does not keep one sign.
+    pub fn values(&self) -> Result<Vec<f64>, SimError> {
+        let values = match self.mode {
+            SweepMode::List => self
+                .list
+                .split([',', ';'])
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| {
+                    item.parse::<f64>()
+                        .map_err(|_err| SimError::InvalidSetup(format!("'{item}' is not a number")))
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            SweepMode::Linear => {
+                let steps = self.count.saturating_sub(1).max(1) as f64;
+                (0..self.count)
+                    .map(|k| self.start + (self.end - self.start) * k as f64 / steps)
+                    .collect()
+            }
+            SweepMode::Logarithmic => {
+                if self.start * self.end <= 0.0 {
+                    return Err(SimError::InvalidSetup(
+                        "a log range needs start and end of one sign".to_owned(),
+                    ));
+                }
+                let steps = self.count.saturating_sub(1).max(1) as f64;
+                let ratio = self.end / self.start;
+                (0..self.count)
+                    .map(|k| self.start * ratio.powf(k as f64 / steps))
+                    .collect()
+            }
+        };
+        if values.is_empty() {
+            return Err(SimError::InvalidSetup("no values to sweep".to_owned()));
+        }
+        Ok(values)
+    }
+}
+
+/// One parameter setting: node, field path and value.
+pub type ParamValue = (NodeId, String, f64);
+
+/// Plot traces of one run: every Plot node with its `(name, points)` lines.
+pub type RunTraces = Vec<(NodeId, Vec<(String, Vec<[f64; 2]>)>)>;
+
+/// Named scalars of one run, as `(name, value)`.
+pub type RunScalars = Vec<(String, f64)>;
+
+/// Results of one solved copy of the graph.
+#[derive(Clone, Debug, Default)]
+pub struct RunResult {
+    /// Traces of every Plot node.
+    pub traces: RunTraces,
+    /// Scalars of every Metrics node.
+    pub scalars: RunScalars,
+}
+
+/// The fields of `node` as a JSON object, without the variant tag.
+fn fields(node: &SimNode) -> Option<Value> {
+    serde_json::to_value(node)
+        .ok()?
+        .as_object_mut()?
+        .values_mut()
+        .next()
+        .map(Value::take)
+}
+
+/// Collect the paths of the floating-point leaves below `value`.
+fn collect_paths(value: &Value, prefix: &str, paths: &mut Vec<String>) {
+    let join = |key: &str| {
+        if prefix.is_empty() {
+            key.to_owned()
+        } else {
+            format!("{prefix}.{key}")
+        }
+    };
+    match value {
+        Value::Number(n) if n.is_f64() => paths.push(prefix.to_owned()),
+        Value::Object(map) => {
+            for (key, child) in map {
+                if key != "custom_size" {
+                    collect_paths(child, &join(key), paths);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for (k, child) in items.iter().enumerate() {
+                collect_paths(child, &join(&k.to_string()), paths);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Walk `path` below `value`.
+fn lookup_mut<'a>(value: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    path.split('.').try_fold(value, |value, key| match value {
+        Value::Object(map) => map.get_mut(key),
+        Value::Array(items) => items.get_mut(key.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// Paths of the numeric parameters of `node`, in alphabetical field order.
+pub fn numeric_params(node: &SimNode) -> Vec<String> {
+    let mut paths = Vec::new();
+    if let Some(fields) = fields(node) {
+        collect_paths(&fields, "", &mut paths);
+    }
+    paths
+}
+
+/// Value of parameter `path` of `node`, if it is numeric.
+pub fn get_param(node: &SimNode, path: &str) -> Option<f64> {
+    let mut fields = fields(node)?;
+    lookup_mut(&mut fields, path)?.as_f64()
+}
+
+/// Set parameter `path` of `node` to `value`.
+///
+/// Simulation outputs of the node are cleared.
+///
+/// # Errors
+///
+/// [`SimError::InvalidSetup`] if `path` does not name a numeric parameter of
+/// the node or `value` is not finite.
+pub fn set_param(node: &mut SimNode, path: &str, value: f64) -> Result<(), SimError> {
+    let invalid = || SimError::InvalidSetup(format!("{} has no parameter '{path}'", node.title()));
+    let mut tagged = serde_json::to_value(&*node).map_err(|_err| invalid())?;
+    let leaf = tagged
+        .as_object_mut()
+        .and_then(|map| map.values_mut().next())
+        .and_then(|fields| lookup_mut(fields, path))
+        .filter(|leaf| leaf.is_f64())
+        .ok_or_else(invalid)?;
+    *leaf = serde_json::Number::from_f64(value)
+        .map(Value::Number)
+        .ok_or_else(|| SimError::InvalidSetup(format!("{value} is not a finite value")))?;
+    *node = serde_json::from_value(tagged).map_err(|_err| invalid())?;
+    Ok(())
+}
+
+/// Traces of every Plot node of a solved graph.
+fn plot_traces(snarl: &Snarl<SimNode>) -> RunTraces {
+    snarl
+        .node_ids()
+        .filter_map(|(id, node)| match node {
+            SimNode::Plot(plot) => Some((id, plot.num_inputs)),
+            _ => None,
+        })
+        .map(|(id, num_inputs)| {
+            let lines = (0..num_inputs)
+                .filter_map(|input| {
+                    let pin = snarl.in_pin(InPinId { node: id, input });
+                    pin.remotes
+                        .first()
+                        .map(|&remote| plot_lines(snarl, remote, input))
+                })
+                .flatten()
+                .collect();
+            (id, lines)
+        })
+        .collect()
+}
+
+/// Named scalars `<name>.<metric>` of every Metrics node of a solved graph.
+pub fn named_scalars(snarl: &Snarl<SimNode>) -> RunScalars {
+    snarl
+        .nodes()
+        .filter_map(|node| match node {
+            SimNode::Metrics(m) => Some(m.scalars()),
+            _ => None,
+        })
+        .flatten()
+        .collect()
+}
+
+/// Map `f` over `items`, in parallel on native targets.
+pub(super) fn map_parallel<T: Sync, R: Send>(
+    items: &[T],
+    f: impl Fn(&T) -> R + Sync + Send,
+) -> Vec<R> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use rayon::iter::{IntoParallelRefIterator as _, ParallelIterator as _};
+        items.par_iter().map(f).collect()
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        items.iter().map(f).collect()
+    }
+}
+
+/// Solve a copy of the graph for each set of parameter values and return the
+/// Plot traces and named scalars of every run.
+///
+/// # Errors
+///
+/// The first error of any run, or [`SimError::InvalidSetup`] for a parameter
+/// that does not exist.
+pub fn solve_variants(
+    snarl: &Snarl<SimNode>,
+    config: &SimConfig,
+    variants: &[Vec<ParamValue>],
+) -> Result<Vec<RunResult>, SimError> {
+    map_parallel(variants, |settings| {
+        let mut copy = snarl.clone();
+        for (node, path, value) in settings {
+            let target = copy
+                .get_node_mut(*node)
+                .ok_or_else(|| SimError::InvalidSetup("swept node no longer exists".to_owned()))?;
+            set_param(target, path, *value)?;
+        }
+        run_simulation(&mut copy, config)?;
+        Ok(RunResult {
+            traces: plot_traces(&copy),
+            scalars: named_scalars(&copy),
+        })
+    })
+    .into_iter()
+    .collect()
+}
+
+/// Run a parameter sweep: solve the graph once per value, then solve the
+/// unchanged graph and hand every Plot node the traces of all runs.
+///
+/// Returns the named scalars of each run with its swept value.
+///
+/// # Errors
+///
+/// [`SimError::InvalidSetup`] for an incomplete setup, or the first
+/// simulation error of any run.
+pub fn run_sweep(
+    snarl: &mut Snarl<SimNode>,
+    config: &SimConfig,
+    sweep: &SweepConfig,
+) -> Result<Vec<(f64, RunScalars)>, SimError> {
+    let node = sweep
+        .node
+        .filter(|&id| snarl.get_node(id).is_some())
+        .ok_or_else(|| SimError::InvalidSetup("choose a node to sweep".to_owned()))?;
+    let values = sweep.values()?;
+    let variants: Vec<Vec<ParamValue>> = values
+        .iter()
+        .map(|&value| vec![(node, sweep.param.clone(), value)])
+        .collect();
+    let runs = solve_variants(snarl, config, &variants)?;
+
+    run_simulation(snarl, config)?;
+    let mut scalars = Vec::with_capacity(runs.len());
+    for (&value, run) in values.iter().zip(runs) {
+        for (plot, lines) in run.traces {
+            if let Some(SimNode::Plot(p)) = snarl.get_node_mut(plot) {
+                p.runs.push(PlotRun {
+                    label: format!("{} = {value}", sweep.param),
+                    lines,
+                });
+            }
+        }
+        scalars.push((value, run.scalars));
+    }
+    Ok(scalars)
+}
+
+#[cfg(test)]
+mod tests {
+    use egui_snarl::{InPinId, OutPinId, Snarl};
+
+    use super::{
+        SweepConfig, SweepMode, get_param, named_scalars, numeric_params, run_sweep, set_param,
+    };
+    use crate::nodes::SimNode;
+    use crate::nodes::constant::ConstantNode;
+    use crate::nodes::electrical::ElectricalNode;
+    use crate::nodes::mechanical::MechanicalNode;
+    use crate::nodes::metrics::MetricsNode;
+    use crate::nodes::plot::PlotNode;
+    use crate::port::PortValue;
+    use crate::simulation::SimConfig;
+
+    /// Numeric fields, nested ones included, can be listed, read and set.
+    #[test]
+    fn parameters_are_addressed_by_path() {
+        let mut node = SimNode::Mechanical(MechanicalNode::default());
+        let params = numeric_params(&node);
+        assert!(params.iter().any(|p| p == "j"), "inertia listed");
+        assert!(
+            params.iter().any(|p| p == "friction.t_c"),
+            "nested field listed: {params:?}"
+        );
+        set_param(&mut node, "j", 0.002).expect("inertia is numeric");
+        assert_eq!(get_param(&node, "j"), Some(0.002), "value written");
+        assert!(
+            set_param(&mut node, "missing", 1.0).is_err(),
+            "unknown path"
+        );
+    }
+
+    /// Ranges are evenly spaced and lists parse with either separator.
+    #[test]
+    fn sweep_values() {
+        let sweep = SweepConfig {
+            mode: SweepMode::Logarithmic,
+            start: 1.0,
+            end: 100.0,
+            count: 3,
+            ..SweepConfig::default()
+        };
+        let values = sweep.values().expect("valid range");
+        assert!(
+            values
+                .iter()
+                .zip([1.0, 10.0, 100.0])
+                .all(|(v, e)| (v - e).abs() < 1e-9),
+            "{values:?}"
+        );
+        let sweep = SweepConfig {
+            list: "0.1, 0.2; 0.4".to_owned(),
+            ..SweepConfig::default()
+        };
+        assert_eq!(sweep.values().expect("valid list"), [0.1, 0.2, 0.4]);
+        let sweep = SweepConfig {
+            list: "0.1, x".to_owned(),
+            ..SweepConfig::default()
+        };
+        assert!(sweep.values().is_err(), "bad entry rejected");
+    }
+
+    /// Sweeping the inertia gives one plot trace per value, and a heavier
+    /// rotor is slower early in the run-up.
+    #[test]
+    fn inertia_sweep_overlays_speed_traces() {
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let [vd, vq, tl] = [0.0, 24.0, 0.0].map(|value| {
+            snarl.insert_node(
+                pos,
+                SimNode::Constant(ConstantNode {
+                    value,
+                    ..ConstantNode::default()
+                }),
+            )
+        });
+        let elec = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let plot = snarl.insert_node(pos, SimNode::Plot(PlotNode::default()));
+        for (from, output, to, input) in [
+            (vd, 0, elec, 0),
+            (vq, 0, elec, 1),
+            (tl, 0, mech, 1),
+            (mech, 0, plot, 0),
+        ] {
+            snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+        }
+
+        let sweep = SweepConfig {
+            node: Some(mech),
+            param: "j".to_owned(),
+            list: "0.0008, 0.0016".to_owned(),
+            ..SweepConfig::default()
+        };
+        let config = SimConfig {
+            t_end: 0.1,
+            ..SimConfig::default()
+        };
+        run_sweep(&mut snarl, &config, &sweep).expect("sweep should succeed");
+
+        let Some(SimNode::Plot(plot)) = snarl.get_node(plot) else {
+            panic!("expected plot node");
+        };
+        let labels: Vec<&str> = plot.runs.iter().map(|run| run.label.as_str()).collect();
+        assert_eq!(labels, ["j = 0.0008", "j = 0.0016"]);
+        let speed_at = |k: usize| {
+            let run = plot.runs.get(k).expect("run");
+            let (_, points) = run.lines.first().expect("one line");
+            points
+                .iter()
+                .find(|&&[t, _]| t >= 0.01)
+                .map(|&[_, omega]| omega)
+                .expect("sample at 10 ms")
+        };
+        assert!(
+            speed_at(1) < speed_at(0),
+            "heavier rotor accelerates slower"
+        );
+    }
+
+    /// A Metrics node on the speed publishes its figures for every run; a
+    /// heavier rotor rises more slowly.
+    #[test]
+    fn sweep_reports_metrics_scalars() {
+        let mut snarl: Snarl<SimNode> = Snarl::new();
+        let pos = egui::pos2(0.0, 0.0);
+        let vq = snarl.insert_node(
+            pos,
+            SimNode::Constant(ConstantNode {
+                value: 24.0,
+                ..ConstantNode::default()
+            }),
+        );
+        let elec = snarl.insert_node(pos, SimNode::Electrical(ElectricalNode::default()));
+        let mech = snarl.insert_node(pos, SimNode::Mechanical(MechanicalNode::default()));
+        let metrics = snarl.insert_node(
+            pos,
+            SimNode::Metrics(MetricsNode {
+                name: "speed".to_owned(),
+                ..MetricsNode::default()
+            }),
+        );
+        for (from, output, to, input) in [(vq, 0, elec, 1), (mech, 0, metrics, 0)] {
+            snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+        }
+
+        let sweep = SweepConfig {
+            node: Some(mech),
+            param: "j".to_owned(),
+            list: "0.0008, 0.0016".to_owned(),
+            ..SweepConfig::default()
+        };
+        let config = SimConfig {
+            t_end: 1.0,
+            ..SimConfig::default()
+        };
+        let runs = run_sweep(&mut snarl, &config, &sweep).expect("sweep should succeed");
+        let scalar = |k: usize, name: &str| {
+            let (_, scalars) = runs.get(k).expect("run");
+            scalars
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|&(_, v)| v)
+                .unwrap_or_else(|| panic!("{name} published: {scalars:?}"))
+        };
+        let [(light, _), (heavy, _)] = runs.as_slice() else {
+            panic!("expected two runs");
+        };
+        assert_eq!([*light, *heavy], [0.0008, 0.0016]);
+        assert!(
+            scalar(1, "speed.rise_time") > scalar(0, "speed.rise_time"),
+            "heavier rotor rises slower"
+        );
+        let settled = scalar(0, "speed.settling_time");
+        assert!(
+            settled > 0.0 && settled < 1.0,
+            "settles in the run: {settled}"
+        );
+        // The nominal graph is solved last and keeps its own figures
+        let nominal = named_scalars(&snarl);
+        let names: Vec<&str> = nominal.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "speed.rms",
+                "speed.mean",
+                "speed.peak_to_peak",
+                "speed.ripple",
+                "speed.rise_time",
+                "speed.overshoot",
+                "speed.settling_time",
+            ]
+        );
+        let Some(SimNode::Mechanical(m)) = snarl.get_node(mech) else {
+            panic!("expected mechanical node");
+        };
+        let Some(PortValue::Signal(omega)) = m.output_omega_m.as_ref() else {
+            panic!("expected omega signal");
+        };
+        let value = |key: &str| {
+            nominal
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|&(_, v)| v)
+                .unwrap_or_else(|| panic!("{key} published"))
+        };
+        let (rms, mean) = (value("speed.rms"), value("speed.mean"));
+        let peak = omega.iter().map(|&[_, w]| w.abs()).fold(0.0, f64::max);
+        assert!(
+            mean > 0.0 && rms > mean && rms < peak,
+            "mean {mean} < RMS {rms} < peak {peak} of a rising speed"
+        );
+    }
+}